@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::config::CredentialConfig;
+use crate::recording;
+use crate::stats::CacheCounter;
+
+/// Tokens are renewed this long before they expire.
+const EXPIRY_MARGIN: Duration = Duration::from_secs(300);
+
+/// Azure Instance Metadata Service token endpoint used for managed identity.
+const IMDS_TOKEN_URL: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// Acquires and caches Azure AD access tokens for the REST APIs that need
+/// them (Log Analytics, Application Insights, Kusto, Cost Management, Key
+/// Vault, ...), one per resource.
+pub struct TokenProvider {
+    http: reqwest::Client,
+    credential: CredentialConfig,
+    tokens: Mutex<HashMap<String, (String, Instant)>>,
+    cache: CacheCounter,
+}
+
+impl TokenProvider {
+    pub fn new(credential: &CredentialConfig) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            credential: credential.clone(),
+            tokens: Mutex::default(),
+            cache: CacheCounter::default(),
+        }
+    }
+
+    /// How often [`token`](Self::token) was served from the cache.
+    pub fn cache(&self) -> &CacheCounter {
+        &self.cache
+    }
+
+    /// A bearer token for `resource` (e.g. `https://api.loganalytics.io`),
+    /// from the cache while it is still valid.
+    pub async fn token(&self, resource: &str) -> Result<String> {
+        let mut tokens = self.tokens.lock().await;
+        if let Some((token, expires)) = tokens.get(resource) {
+            if Instant::now() + EXPIRY_MARGIN < *expires {
+                self.cache.hit();
+                return Ok(token.clone());
+            }
+        }
+        self.cache.miss();
+        let (token, lifetime) = self
+            .acquire(resource)
+            .await
+            .with_context(|| format!("Failed to get an Azure AD token for {resource}"))?;
+        tokens.insert(
+            resource.to_string(),
+            (token.clone(), Instant::now() + lifetime),
+        );
+        Ok(token)
+    }
+
+    /// Token requests are neither recorded nor replayed: recordings would
+    /// hold the credential and the tokens, and replayed requests need none.
+    async fn acquire(&self, resource: &str) -> Result<(String, Duration)> {
+        if recording::replaying() {
+            return Ok(("replayed".to_string(), Duration::from_secs(3600)));
+        }
+        let request = match &self.credential {
+            CredentialConfig::ClientSecret {
+                authority_host,
+                tenant_id,
+                client_id,
+                client_secret,
+            } => self
+                .http
+                .post(format!(
+                    "{}/{tenant_id}/oauth2/v2.0/token",
+                    authority_host.trim_end_matches('/')
+                ))
+                .form(&[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", client_id),
+                    ("client_secret", client_secret),
+                    ("scope", &format!("{resource}/.default")),
+                ]),
+            CredentialConfig::ManagedIdentity { client_id } => {
+                let mut query = vec![("api-version", "2018-02-01"), ("resource", resource)];
+                if let Some(client_id) = client_id {
+                    query.push(("client_id", client_id));
+                }
+                self.http
+                    .get(IMDS_TOKEN_URL)
+                    .query(&query)
+                    .header("metadata", "true")
+            }
+        };
+
+        let response = request.send().await.context("Token request failed")?;
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .context("Token endpoint returned invalid JSON")?;
+        if !status.is_success() {
+            let message = body["error_description"]
+                .as_str()
+                .or(body["error"].as_str())
+                .unwrap_or_default();
+            bail!("Token endpoint returned {status}: {message}");
+        }
+        parse_token(&body)
+    }
+}
+
+/// The access token and its lifetime from a token response.  Azure AD
+/// returns `expires_in` as a number, managed identity as a string.
+fn parse_token(body: &Value) -> Result<(String, Duration)> {
+    let token = body["access_token"]
+        .as_str()
+        .context("Token response has no access_token")?;
+    let expires_in = match &body["expires_in"] {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+    .unwrap_or(3_600);
+    Ok((token.to_string(), Duration::from_secs(expires_in)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_token_accepts_both_expiry_forms() {
+        let (token, lifetime) =
+            parse_token(&serde_json::json!({ "access_token": "a", "expires_in": 3599 })).unwrap();
+        assert_eq!(token, "a");
+        assert_eq!(lifetime, Duration::from_secs(3599));
+        let (_, lifetime) =
+            parse_token(&serde_json::json!({ "access_token": "b", "expires_in": "86400" }))
+                .unwrap();
+        assert_eq!(lifetime, Duration::from_secs(86400));
+        assert!(parse_token(&serde_json::json!({ "error": "invalid_client" })).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::Method;
+use serde_json::{Map, Value};
+
+use crate::blob::{self, BlobEncoding};
+use crate::storage::{encode_path, Service, StorageClient};
+
+/// Paths returned per `adls_list_paths` page when the caller gives no
+/// `max_results`.
+pub const DEFAULT_MAX_RESULTS: u32 = 500;
+
+/// Upper bound on `max_results`; the service never returns more per request.
+pub const MAX_RESULTS: u32 = 5_000;
+
+/// Convert a path of a List Paths response to
+/// `{name, is_directory, size, last_modified, owner, group, permissions}`.
+/// The service returns flags and sizes as strings.
+fn path_entry(path: &Value) -> Value {
+    let is_directory = path["isDirectory"].as_str() == Some("true");
+    serde_json::json!({
+        "name": path["name"],
+        "is_directory": is_directory,
+        "size": (!is_directory)
+            .then(|| path["contentLength"].as_str()?.parse::<u64>().ok())
+            .flatten(),
+        "last_modified": path["lastModified"],
+        "owner": path["owner"],
+        "group": path["group"],
+        "permissions": path["permissions"],
+    })
+}
+
+/// List the paths of `filesystem` below `directory` (the root when `None`),
+/// recursively when asked, up to `max_results` per page, continuing from
+/// `continuation` when given.
+///
+/// Returns the paths and, when more remain, the continuation token of the
+/// next page.
+pub async fn list_paths(
+    client: &StorageClient,
+    filesystem: &str,
+    directory: Option<&str>,
+    recursive: bool,
+    max_results: u32,
+    continuation: Option<&str>,
+) -> Result<(Vec<Value>, Option<String>)> {
+    let max_results = max_results.clamp(1, MAX_RESULTS).to_string();
+    let mut query = vec![
+        ("resource", "filesystem"),
+        ("recursive", if recursive { "true" } else { "false" }),
+        ("maxResults", max_results.as_str()),
+    ];
+    if let Some(directory) = directory.map(|d| d.trim_matches('/')) {
+        if !directory.is_empty() {
+            query.push(("directory", directory));
+        }
+    }
+    if let Some(continuation) = continuation {
+        query.push(("continuation", continuation));
+    }
+
+    let response = client
+        .send(
+            Service::Dfs,
+            Method::GET,
+            &urlencoding::encode(filesystem),
+            &query,
+            &[],
+            Vec::new(),
+        )
+        .await
+        .with_context(|| format!("Failed to list paths of filesystem {filesystem}"))?;
+    let body: Value = serde_json::from_slice(&response.body)
+        .context("Data Lake service returned invalid JSON")?;
+    let paths = body["paths"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(path_entry)
+        .collect();
+    let next = response
+        .header("x-ms-continuation")
+        .filter(|c| !c.is_empty())
+        .map(str::to_string);
+    Ok((paths, next))
+}
+
+/// Read up to `length` bytes of the file at `filesystem`/`path` from
+/// `offset`, as [`blob::read`] does for blobs.
+pub async fn read_file(
+    client: &StorageClient,
+    filesystem: &str,
+    path: &str,
+    offset: u64,
+    length: Option<u64>,
+    encoding: BlobEncoding,
+) -> Result<Value> {
+    let path = path.trim_matches('/');
+    blob::read_from(
+        client,
+        Service::Dfs,
+        filesystem,
+        path,
+        offset,
+        length,
+        encoding,
+    )
+    .await
+    .with_context(|| format!("Failed to read file {filesystem}/{path}"))
+}
+
+/// Decode the `x-ms-properties` header, `name=base64value` pairs separated
+/// by commas, into the path's user-defined properties.
+fn decode_properties(header: &str) -> Map<String, Value> {
+    header
+        .split(',')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let value = BASE64.decode(value.trim()).ok()?;
+            Some((
+                name.trim().to_string(),
+                String::from_utf8_lossy(&value).into_owned().into(),
+            ))
+        })
+        .collect()
+}
+
+/// Read the properties of the file or directory at `filesystem`/`path`:
+/// resource type, size, content type, timestamps, user-defined properties
+/// and, on accounts with a hierarchical namespace, owner, group, POSIX
+/// permissions and ACL.
+pub async fn get_properties(client: &StorageClient, filesystem: &str, path: &str) -> Result<Value> {
+    let encoded = encode_path(&format!("{filesystem}/{}", path.trim_matches('/')));
+    let response = client
+        .send(Service::Dfs, Method::HEAD, &encoded, &[], &[], Vec::new())
+        .await
+        .with_context(|| format!("Failed to read properties of {filesystem}/{path}"))?;
+    // The access control list is only returned when asked for explicitly.
+    let acl = client
+        .send(
+            Service::Dfs,
+            Method::HEAD,
+            &encoded,
+            &[("action", "getAccessControl")],
+            &[],
+            Vec::new(),
+        )
+        .await
+        .ok();
+
+    let resource_type = response.header("x-ms-resource-type").unwrap_or("file");
+    let permissions = response.header("x-ms-permissions");
+    Ok(serde_json::json!({
+        "filesystem": filesystem,
+        "path": path,
+        "is_directory": resource_type == "directory",
+        "size": (resource_type == "file")
+            .then(|| response.header("content-length")?.parse::<u64>().ok())
+            .flatten(),
+        "content_type": response.header("content-type"),
+        "etag": response.header("etag"),
+        "last_modified": response.header("last-modified"),
+        "created": response.header("x-ms-creation-time"),
+        "properties": response
+            .header("x-ms-properties")
+            .map(decode_properties)
+            .unwrap_or_default(),
+        // Only accounts with a hierarchical namespace report POSIX permissions.
+        "hierarchical_namespace": permissions.is_some(),
+        "owner": response.header("x-ms-owner"),
+        "group": response.header("x-ms-group"),
+        "permissions": permissions,
+        "acl": acl.as_ref().and_then(|r| r.header("x-ms-acl")),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_entry_and_properties_decode_service_strings() {
+        let entry = path_entry(&serde_json::json!({
+            "name": "raw/sales/2026.parquet",
+            "contentLength": "2048",
+            "lastModified": "Thu, 01 Jan 2026 00:00:00 GMT",
+            "permissions": "rw-r-----",
+        }));
+        assert_eq!(entry["is_directory"], false);
+        assert_eq!(entry["size"], 2048);
+        let entry = path_entry(&serde_json::json!({
+            "name": "raw/sales",
+            "isDirectory": "true",
+            "contentLength": "0",
+        }));
+        assert_eq!(entry["is_directory"], true);
+        assert_eq!(entry["size"], Value::Null);
+
+        let properties = decode_properties("zone=cmF3, owner=ZGF0YQ==");
+        assert_eq!(properties["zone"], "raw");
+        assert_eq!(properties["owner"], "data");
+    }
+}
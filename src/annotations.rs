@@ -0,0 +1,228 @@
+use std::time::Duration;
+
+use rmcp::model::{AnnotateAble, Annotations, CallToolResult, JsonObject, Meta, RawContent, Role};
+use serde_json::{Map, Value};
+
+use crate::health::Backend;
+
+/// Top-level fields of a structured result that hold the returned records,
+/// in order of preference.
+pub const RECORD_FIELDS: &[&str] = &[
+    "rows",
+    "items",
+    "entities",
+    "paths",
+    "entries",
+    "results",
+    "tables",
+    "databases",
+    "keyspaces",
+    "containers",
+    "shares",
+    "conflicts",
+    "messages",
+    "events",
+    "secrets",
+    "edges",
+    "versions",
+    "settings",
+    "flags",
+];
+
+/// Arguments that cap how many records or request units a call may use.
+const LIMIT_ARGUMENTS: &[&str] = &["max_rows", "max_items", "max_results", "max_request_units"];
+
+/// How a tool call ran, reported in the envelope of its result.
+#[derive(Debug, Default)]
+pub struct CallInfo<'a> {
+    /// The backend the tool queried, if it has a single one.
+    pub backend: Option<Backend>,
+    /// How long the tool took.
+    pub duration: Duration,
+    /// The call's arguments, which hold the caps the caller set.
+    pub arguments: Option<&'a JsonObject>,
+}
+
+/// Priority of the raw JSON dump of a result that also has a summary.
+const RAW_PRIORITY: f32 = 0.2;
+
+/// Priority of the user-facing summary.
+const SUMMARY_PRIORITY: f32 = 1.0;
+
+fn annotations(audience: Role, priority: f32) -> Annotations {
+    Annotations {
+        audience: Some(vec![audience]),
+        priority: Some(priority),
+        last_modified: None,
+    }
+}
+
+/// Why a result holds fewer records than the query would produce, if it
+/// was cut short.  `meta` is the result's `_meta`, which names the stored
+/// full result of a preview.
+fn truncation(fields: &Map<String, Value>, meta: Option<&Meta>) -> Option<String> {
+    if let Some(uri) = meta.and_then(|m| m.get("fullResult")?.as_str()) {
+        Some(format!("preview only; read {uri} for the full result"))
+    } else if meta.is_some_and(|m| m.contains_key("compressedResult")) {
+        Some("preview only; the full result is attached gzip-compressed".into())
+    } else if fields
+        .get("metadata")
+        .is_some_and(|m| m["budget_exhausted"] == true)
+    {
+        Some("the request unit budget was spent".into())
+    } else if fields.get("memory_budget_exhausted") == Some(&Value::Bool(true))
+        || fields
+            .get("metadata")
+            .is_some_and(|m| m["memory_budget_exhausted"] == true)
+    {
+        Some("the result reached the MAX_RESULT_BYTES size budget".into())
+    } else if fields.get("nextCursor").is_some_and(Value::is_string) {
+        Some("more are available; pass nextCursor to fetch_next_page".into())
+    } else if fields.get("truncated") == Some(&Value::Bool(true)) {
+        Some("more exist than were returned".into())
+    } else {
+        None
+    }
+}
+
+/// One-line summary of a result holding `count` records of kind `field`.
+fn summary(field: &str, count: usize, truncated: Option<&str>) -> String {
+    let noun = if count == 1 {
+        field.strip_suffix('s').unwrap_or(field)
+    } else {
+        field
+    };
+    match truncated {
+        Some(reason) => format!("Returned {count} {noun} (truncated: {reason})."),
+        None => format!("Returned {count} {noun}."),
+    }
+}
+
+/// The caps applied to a call: the limit arguments the caller set and the
+/// request unit budget reported by Cosmos DB.
+fn limits(fields: &Map<String, Value>, arguments: Option<&JsonObject>) -> Map<String, Value> {
+    let mut limits: Map<String, Value> = LIMIT_ARGUMENTS
+        .iter()
+        .filter_map(|name| {
+            let value = arguments?.get(*name).filter(|v| v.is_number())?;
+            Some((name.to_string(), value.clone()))
+        })
+        .collect();
+    if let Some(budget) = fields
+        .get("metadata")
+        .and_then(|m| m.get("request_unit_budget"))
+        .filter(|b| b.is_number())
+    {
+        limits.insert("max_request_units".into(), budget.clone());
+    }
+    limits
+}
+
+/// Annotate a successful tool result so hosts can decide what to show.
+///
+/// When the result holds records (rows, documents, tables, ...), its raw
+/// JSON dump is marked for the assistant at low priority and a one-line
+/// summary for the user is added, saying when the result was truncated.
+/// `_meta` gets an `envelope` describing the call — `backend`, record
+/// `count`, `truncated`, `durationMs` and the `limits` applied — and
+/// truncated results also carry `"truncated": true`.  Other results and
+/// errors are left as they are.
+pub fn annotate(result: &mut CallToolResult, call: &CallInfo) {
+    if result.is_error == Some(true) {
+        return;
+    }
+    let Some(Value::Object(fields)) = &result.structured_content else {
+        return;
+    };
+    let Some((field, count)) = RECORD_FIELDS
+        .iter()
+        .find_map(|f| Some((*f, fields.get(*f)?.as_array()?.len())))
+    else {
+        return;
+    };
+    let truncated = truncation(fields, result.meta.as_ref());
+    let summary = summary(field, count, truncated.as_deref());
+    let envelope = serde_json::json!({
+        "backend": call.backend.map(Backend::name),
+        "count": count,
+        "truncated": truncated.is_some(),
+        "durationMs": call.duration.as_millis() as u64,
+        "limits": limits(fields, call.arguments),
+    });
+
+    for content in &mut result.content {
+        content.annotations = Some(annotations(Role::Assistant, RAW_PRIORITY));
+    }
+    result
+        .content
+        .push(RawContent::text(summary).annotate(annotations(Role::User, SUMMARY_PRIORITY)));
+    let meta = result.meta.get_or_insert_with(Meta::new);
+    meta.insert("envelope".into(), envelope);
+    if truncated.is_some() {
+        meta.insert("truncated".into(), true.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_results_get_an_annotated_summary() {
+        let structured = serde_json::json!({ "rows": [{ "id": 1 }], "nextCursor": "abc" });
+        let mut result = CallToolResult::structured(structured);
+        let arguments = JsonObject::from_iter([
+            ("query".to_string(), Value::from("SELECT 1")),
+            ("max_rows".to_string(), Value::from(1)),
+        ]);
+        annotate(
+            &mut result,
+            &CallInfo {
+                backend: Some(Backend::Mssql),
+                duration: Duration::from_millis(42),
+                arguments: Some(&arguments),
+            },
+        );
+
+        let [raw, summary] = result.content.as_slice() else {
+            panic!("expected the raw result and a summary");
+        };
+        assert_eq!(
+            raw.annotations.as_ref().unwrap().audience,
+            Some(vec![Role::Assistant])
+        );
+        assert_eq!(
+            summary.annotations.as_ref().unwrap().audience,
+            Some(vec![Role::User])
+        );
+        assert_eq!(
+            summary.as_text().unwrap().text,
+            "Returned 1 row (truncated: more are available; pass nextCursor to fetch_next_page)."
+        );
+        let meta = result.meta.unwrap();
+        assert_eq!(meta.get("truncated"), Some(&Value::Bool(true)));
+        assert_eq!(
+            meta["envelope"],
+            serde_json::json!({
+                "backend": "mssql",
+                "count": 1,
+                "truncated": true,
+                "durationMs": 42,
+                "limits": { "max_rows": 1 },
+            })
+        );
+    }
+
+    #[test]
+    fn other_results_are_left_alone() {
+        let mut result = CallToolResult::structured(serde_json::json!({ "id": "a", "ttl": 60 }));
+        annotate(&mut result, &CallInfo::default());
+        assert_eq!(result.content.len(), 1);
+        assert!(result.content[0].annotations.is_none());
+        assert!(result.meta.is_none());
+
+        let mut result = CallToolResult::structured_error(serde_json::json!({ "rows": [] }));
+        annotate(&mut result, &CallInfo::default());
+        assert_eq!(result.content.len(), 1);
+    }
+}
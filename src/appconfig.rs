@@ -0,0 +1,277 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::aad::TokenProvider;
+use crate::config::{AppConfigAccessKey, AppConfigConfig};
+use crate::recording;
+
+/// Azure AD resource of every App Configuration store.
+const APP_CONFIG_RESOURCE: &str = "https://azconfig.io";
+
+/// App Configuration data plane API version.
+const API_VERSION: &str = "2023-11-01";
+
+/// Upper bound on the settings collected across result pages.
+pub const MAX_SETTINGS: usize = 1_000;
+
+/// Key prefix of feature flags.
+const FEATURE_FLAG_PREFIX: &str = ".appconfig.featureflag/";
+
+/// The `Authorization` header of an access key request signed over the
+/// date, host and (empty) body hash.
+fn authorization(
+    key: &AppConfigAccessKey,
+    path_and_query: &str,
+    host: &str,
+    date: &str,
+) -> Result<String> {
+    let content_hash = BASE64.encode(Sha256::digest(b""));
+    let payload = format!("GET\n{path_and_query}\n{date};{host};{content_hash}");
+    let secret = BASE64
+        .decode(&key.secret)
+        .context("App Configuration access key secret is not valid base64")?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(&secret).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    let signature = BASE64.encode(mac.finalize().into_bytes());
+    Ok(format!(
+        "HMAC-SHA256 Credential={}&SignedHeaders=x-ms-date;host;x-ms-content-sha256&Signature={signature}",
+        key.id
+    ))
+}
+
+/// GET `path_and_query` (below the store, e.g. a `@nextLink`), signed with
+/// the access key when configured and with an Azure AD token otherwise.
+async fn get(
+    tokens: Option<&TokenProvider>,
+    cfg: &AppConfigConfig,
+    path_and_query: &str,
+) -> Result<Value> {
+    let request = reqwest::Client::new()
+        .get(format!("{}{path_and_query}", cfg.endpoint))
+        .header(
+            "accept",
+            "application/vnd.microsoft.appconfig.kvset+json, application/problem+json",
+        );
+    let request = match (&cfg.access_key, tokens) {
+        (Some(key), _) => {
+            let host = cfg
+                .endpoint
+                .trim_start_matches("https://")
+                .trim_start_matches("http://");
+            let date = chrono::Utc::now()
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string();
+            request
+                .header(
+                    "authorization",
+                    authorization(key, path_and_query, host, &date)?,
+                )
+                .header("x-ms-date", date)
+                .header("x-ms-content-sha256", BASE64.encode(Sha256::digest(b"")))
+        }
+        (None, Some(tokens)) => request.bearer_auth(tokens.token(APP_CONFIG_RESOURCE).await?),
+        (None, None) => bail!("App Configuration needs an access key or an Azure AD credential"),
+    };
+    let response = recording::send(request)
+        .await
+        .context("App Configuration request failed")?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .context("App Configuration returned invalid JSON")?;
+    if !status.is_success() {
+        let message = body["detail"]
+            .as_str()
+            .or(body["title"].as_str())
+            .unwrap_or_default();
+        bail!("App Configuration returned {status}: {message}");
+    }
+    Ok(body)
+}
+
+/// GET every page of the settings matching `key` and `label` (filters where
+/// `*` matches any suffix, `None` matching every key or label), keeping only
+/// the `select`ed fields.
+///
+/// Returns the settings and whether [`MAX_SETTINGS`] cut them short.
+async fn settings(
+    tokens: Option<&TokenProvider>,
+    cfg: &AppConfigConfig,
+    key: Option<&str>,
+    label: Option<&str>,
+    select: &str,
+) -> Result<(Vec<Value>, bool)> {
+    let mut path = format!("/kv?api-version={API_VERSION}&$select={select}");
+    if let Some(key) = key {
+        path.push_str(&format!("&key={}", urlencoding::encode(key)));
+    }
+    if let Some(label) = label {
+        // `\0` stands for the null label.
+        let label = label.replace("\\0", "\0");
+        path.push_str(&format!("&label={}", urlencoding::encode(&label)));
+    }
+
+    let mut items = Vec::new();
+    let mut next = Some(path);
+    while let Some(path) = next {
+        let mut page = get(tokens, cfg, &path).await?;
+        for item in page["items"].as_array_mut().into_iter().flatten() {
+            if items.len() == MAX_SETTINGS {
+                return Ok((items, true));
+            }
+            items.push(item.take());
+        }
+        next = page["@nextLink"].as_str().map(str::to_string);
+    }
+    Ok((items, false))
+}
+
+/// List the keys matching `key` and `label`, with their labels and
+/// attributes but without values.
+///
+/// Returns `{store, settings, truncated}`.
+pub async fn list_keys(
+    tokens: Option<&TokenProvider>,
+    cfg: &AppConfigConfig,
+    key: Option<&str>,
+    label: Option<&str>,
+) -> Result<Value> {
+    let (settings, truncated) = settings(
+        tokens,
+        cfg,
+        key,
+        label,
+        "key,label,content_type,last_modified,locked",
+    )
+    .await
+    .context("Failed to list App Configuration keys")?;
+    Ok(serde_json::json!({
+        "store": cfg.endpoint,
+        "settings": settings,
+        "truncated": truncated,
+    }))
+}
+
+/// Read the settings matching `key` and `label` with their values, e.g. the
+/// same key under the `dev` and `prod` labels.
+///
+/// Key Vault references are returned as stored (`{"uri": ...}`); the
+/// secrets they point to are never resolved.
+///
+/// Returns `{store, settings, truncated}`.
+pub async fn get_values(
+    tokens: Option<&TokenProvider>,
+    cfg: &AppConfigConfig,
+    key: &str,
+    label: Option<&str>,
+) -> Result<Value> {
+    let (settings, truncated) = settings(
+        tokens,
+        cfg,
+        Some(key),
+        label,
+        "key,label,value,content_type,tags,last_modified,locked",
+    )
+    .await
+    .with_context(|| format!("Failed to read App Configuration setting {key}"))?;
+    Ok(serde_json::json!({
+        "store": cfg.endpoint,
+        "settings": settings,
+        "truncated": truncated,
+    }))
+}
+
+/// Convert a feature flag setting to `{name, label, enabled, description,
+/// conditions, last_modified}`.
+fn feature_flag(item: &Value) -> Value {
+    let key = item["key"].as_str().unwrap_or_default();
+    let flag: Value = item["value"]
+        .as_str()
+        .and_then(|value| serde_json::from_str(value).ok())
+        .unwrap_or_default();
+    serde_json::json!({
+        "name": flag["id"].as_str().unwrap_or(key.trim_start_matches(FEATURE_FLAG_PREFIX)),
+        "label": item["label"],
+        "enabled": flag["enabled"].as_bool().unwrap_or(false),
+        "description": flag["description"],
+        "conditions": flag["conditions"],
+        "last_modified": item["last_modified"],
+    })
+}
+
+/// List the feature flags whose name matches `name` (else all) under
+/// `label`, with their state and filters.
+///
+/// Returns `{store, flags, truncated}`.
+pub async fn list_feature_flags(
+    tokens: Option<&TokenProvider>,
+    cfg: &AppConfigConfig,
+    name: Option<&str>,
+    label: Option<&str>,
+) -> Result<Value> {
+    let key = format!("{FEATURE_FLAG_PREFIX}{}", name.unwrap_or("*"));
+    let (settings, truncated) = settings(
+        tokens,
+        cfg,
+        Some(&key),
+        label,
+        "key,label,value,last_modified",
+    )
+    .await
+    .context("Failed to list App Configuration feature flags")?;
+    Ok(serde_json::json!({
+        "store": cfg.endpoint,
+        "flags": settings.iter().map(feature_flag).collect::<Vec<_>>(),
+        "truncated": truncated,
+    }))
+}
+
+/// Check the store is reachable and accepts the credential.
+pub async fn ping(tokens: Option<&TokenProvider>, cfg: &AppConfigConfig) -> Result<()> {
+    get(
+        tokens,
+        cfg,
+        &format!("/keys?api-version={API_VERSION}&name=.appconfig.ping"),
+    )
+    .await
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn feature_flags_are_read_from_their_json_value() {
+        let item = serde_json::json!({
+            "key": ".appconfig.featureflag/Beta",
+            "label": "prod",
+            "value": r#"{"id":"Beta","enabled":true,"conditions":{"client_filters":[]}}"#,
+            "last_modified": "2026-10-01T08:00:00+00:00",
+        });
+        let flag = feature_flag(&item);
+        assert_eq!(flag["name"], "Beta");
+        assert_eq!(flag["label"], "prod");
+        assert_eq!(flag["enabled"], true);
+        assert_eq!(flag["conditions"]["client_filters"], serde_json::json!([]));
+
+        let key = AppConfigAccessKey {
+            id: "abc".into(),
+            secret: BASE64.encode(b"secret"),
+        };
+        let auth = authorization(
+            &key,
+            "/kv?api-version=2023-11-01",
+            "mystore.azconfig.io",
+            "Thu, 01 Jan 2026 00:00:00 GMT",
+        )
+        .unwrap();
+        assert!(auth.starts_with(
+            "HMAC-SHA256 Credential=abc&SignedHeaders=x-ms-date;host;x-ms-content-sha256&Signature="
+        ));
+    }
+}
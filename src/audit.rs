@@ -0,0 +1,373 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use rmcp::model::{CallToolResult, Implementation, JsonObject};
+use rmcp::ErrorData;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::config::AuditConfig;
+use crate::cosmos::{self, CosmosAccount};
+
+/// Append-only record of every tool call, for compliance reviews.
+pub struct AuditLog {
+    config: AuditConfig,
+    /// The MCP session, written in every entry.
+    session: String,
+    /// Serialises appends to the file.
+    file: Mutex<()>,
+}
+
+/// The entry recording one tool call: `{id, time, session, client, tool,
+/// arguments, duration_ms, outcome, error, count, truncated}`, where `id` is
+/// the call's correlation id, `outcome` is `ok` or `error` and `count` is the
+/// number of records returned, when the result holds records.
+pub fn entry(
+    id: &str,
+    session: &str,
+    client: Option<&Implementation>,
+    tool: &str,
+    arguments: Option<&JsonObject>,
+    duration: Duration,
+    result: &Result<CallToolResult, ErrorData>,
+) -> Value {
+    let (outcome, error, envelope) = match result {
+        Ok(result) if result.is_error == Some(true) => {
+            let message = result
+                .content
+                .first()
+                .and_then(|c| c.as_text())
+                .map(|t| t.text.clone());
+            ("error", message, None)
+        }
+        Ok(result) => (
+            "ok",
+            None,
+            result.meta.as_ref().and_then(|m| m.get("envelope")),
+        ),
+        Err(e) => ("error", Some(e.message.to_string()), None),
+    };
+    serde_json::json!({
+        "id": id,
+        "time": chrono::Utc::now().to_rfc3339(),
+        "session": session,
+        "client": client.map(|c| serde_json::json!({ "name": c.name, "version": c.version })),
+        "tool": tool,
+        "arguments": arguments,
+        "duration_ms": duration.as_millis() as u64,
+        "outcome": outcome,
+        "error": error,
+        "count": envelope.map(|e| e["count"].clone()),
+        "truncated": envelope.map(|e| e["truncated"].clone()),
+    })
+}
+
+impl AuditLog {
+    pub fn new(config: AuditConfig, session: String) -> Self {
+        Self {
+            config,
+            session,
+            file: Mutex::new(()),
+        }
+    }
+
+    /// Whether any audit sink is configured.
+    pub fn enabled(&self) -> bool {
+        self.config.file.is_some() || self.config.cosmos_container.is_some()
+    }
+
+    /// The session identifier written in every entry.
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Append `entry` to the file and the Cosmos DB container configured.
+    ///
+    /// A failed write does not fail the tool call, which has already run; it
+    /// is logged as an error.
+    pub async fn record(&self, entry: &Value, cosmos: Result<&CosmosAccount, String>) {
+        if let Some(path) = &self.config.file {
+            let key = self.config.chain_key.clone();
+            if let Err(e) = append_chained(&self.file, path, key, entry).await {
+                tracing::error!("Failed to write the audit log {path}: {e:#}");
+            }
+        }
+        if let Some((database, container)) = &self.config.cosmos_container {
+            let written = match cosmos {
+                Ok(account) => {
+                    let session = entry["session"].as_str().unwrap_or_default();
+                    cosmos::create_item(account, database, container, session, entry).await
+                }
+                Err(e) => Err(anyhow::anyhow!(e)),
+            };
+            if let Err(e) = written {
+                tracing::error!("Failed to write the audit entry to {database}/{container}: {e:#}");
+            }
+        }
+    }
+}
+
+/// Append `entry` to the file at `path` as one JSON line, holding `lock`
+/// while writing.  The file is opened for each entry, so it can be rotated
+/// while the server runs.
+pub async fn append_line(lock: &Mutex<()>, path: &str, entry: &Value) -> Result<()> {
+    let mut line = entry.to_string();
+    line.push('\n');
+    let _guard = lock.lock().await;
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await
+        .with_context(|| format!("Cannot open {path}"))?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+    Ok(())
+}
+
+/// The `prev_hash` of the first entry of a file.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// The hex SHA-256 of `line`, or its HMAC-SHA256 under `key`.
+fn line_hash(key: Option<&str>, line: &[u8]) -> String {
+    let digest = match key {
+        Some(key) => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+                .expect("HMAC accepts keys of any length");
+            mac.update(line);
+            mac.finalize().into_bytes().to_vec()
+        }
+        None => Sha256::digest(line).to_vec(),
+    };
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// The last line of `file`, without its line break.
+fn last_line(file: &mut File) -> std::io::Result<Option<Vec<u8>>> {
+    const CHUNK: u64 = 8192;
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut line = Vec::new();
+    let mut end = len;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut chunk)?;
+        if end == len && chunk.last() == Some(&b'\n') {
+            chunk.pop();
+        }
+        chunk.append(&mut line);
+        line = chunk;
+        if let Some(newline) = line.iter().rposition(|&b| b == b'\n') {
+            return Ok(Some(line.split_off(newline + 1)));
+        }
+        end = start;
+    }
+    Ok((len > 0).then_some(line))
+}
+
+/// Append `entry` to the file at `path` like [`append_line`], with a
+/// `prev_hash` field chaining it to the line before: the hash of that line
+/// (keyed with `key`, when set), or [`GENESIS`] in an empty file.  Editing,
+/// inserting or removing a line breaks the chain at the next one.
+///
+/// The file is locked while its last line is read and the entry written,
+/// so that servers sharing the file extend a single chain.
+pub async fn append_chained(
+    lock: &Mutex<()>,
+    path: &str,
+    key: Option<String>,
+    entry: &Value,
+) -> Result<()> {
+    let mut entry = entry.clone();
+    let path = path.to_string();
+    let _guard = lock.lock().await;
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Cannot open {path}"))?;
+        file.lock().with_context(|| format!("Cannot lock {path}"))?;
+        let prev_hash = match last_line(&mut file)? {
+            Some(line) => line_hash(key.as_deref(), &line),
+            None => GENESIS.to_string(),
+        };
+        entry["prev_hash"] = prev_hash.into();
+        let mut line = entry.to_string();
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Check the hash chain of the audit log at `path`, hashing with `key` as
+/// [`append_chained`] does: `{ok, path, entries, unchained, last_hash,
+/// broken_line, error}`.  Entries written before chaining was introduced
+/// are counted as `unchained` when they precede all chained ones;
+/// `broken_line` is the 1-based line where the chain first breaks.
+/// `last_hash` is the hash the next entry will carry, to compare with a
+/// copy kept elsewhere, since removing entries from the end of the file
+/// cannot be told from the chain alone.
+pub fn verify(path: &str, key: Option<&str>) -> Value {
+    let report = |entries: usize,
+                  unchained: usize,
+                  last_hash: Option<String>,
+                  broken: Option<(usize, String)>| {
+        serde_json::json!({
+            "ok": broken.is_none(),
+            "path": path,
+            "entries": entries,
+            "unchained": unchained,
+            "last_hash": last_hash,
+            "broken_line": broken.as_ref().map(|(line, _)| line),
+            "error": broken.map(|(_, error)| error),
+        })
+    };
+    let text = match std::fs::read(path) {
+        Ok(text) => text,
+        Err(e) => return report(0, 0, None, Some((0, format!("Cannot read {path}: {e}")))),
+    };
+    let body = text.strip_suffix(b"\n").unwrap_or(&text);
+    let lines: Vec<&[u8]> = if body.is_empty() {
+        Vec::new()
+    } else {
+        body.split(|&b| b == b'\n').collect()
+    };
+    let mut unchained = 0;
+    for (i, line) in lines.iter().enumerate() {
+        let number = i + 1;
+        let entry: Value = match serde_json::from_slice(line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                return report(
+                    i,
+                    unchained,
+                    None,
+                    Some((number, format!("Line {number} is not JSON: {e}"))),
+                );
+            }
+        };
+        let expected = match i {
+            0 => GENESIS.to_string(),
+            _ => line_hash(key, lines[i - 1]),
+        };
+        match entry.get("prev_hash").and_then(Value::as_str) {
+            None if unchained == i => unchained += 1,
+            None => {
+                let error = format!("Line {number} has no prev_hash but follows chained entries");
+                return report(i, unchained, None, Some((number, error)));
+            }
+            Some(hash) if hash == expected => {}
+            Some(hash) => {
+                let error = format!(
+                    "Line {number} does not follow line {}: its prev_hash is {hash}, the \
+                     previous line hashes to {expected} (edited, inserted or removed entries, \
+                     or a different AUDIT_CHAIN_KEY)",
+                    number - 1
+                );
+                return report(i, unchained, None, Some((number, error)));
+            }
+        }
+    }
+    let last_hash = match lines.last() {
+        Some(line) => line_hash(key, line),
+        None => GENESIS.to_string(),
+    };
+    report(lines.len(), unchained, Some(last_hash), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[tokio::test]
+    async fn entries_are_appended_as_json_lines() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(
+            AuditConfig {
+                file: Some(path.to_string_lossy().into_owned()),
+                cosmos_container: None,
+                chain_key: None,
+            },
+            "session".into(),
+        );
+        assert!(log.enabled());
+
+        let arguments = JsonObject::from_iter([("query".to_string(), Value::from("SELECT 1"))]);
+        let mut ok = CallToolResult::structured(serde_json::json!({ "rows": [] }));
+        ok.meta = Some(rmcp::model::Meta(JsonObject::from_iter([(
+            "envelope".to_string(),
+            serde_json::json!({ "count": 0, "truncated": false }),
+        )])));
+        let failed = CallToolResult::error(vec![Content::text("Login failed")]);
+        for result in [Ok(ok), Ok(failed)] {
+            let entry = entry(
+                &crate::correlation::new_id(),
+                log.session(),
+                None,
+                "mssql_execute_query",
+                Some(&arguments),
+                Duration::from_millis(7),
+                &result,
+            );
+            log.record(&entry, Err("no Cosmos DB".into())).await;
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let entries: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["arguments"]["query"], "SELECT 1");
+        assert_eq!(entries[0]["outcome"], "ok");
+        assert_eq!(entries[0]["count"], 0);
+        assert_eq!(entries[0]["duration_ms"], 7);
+        assert_eq!(entries[1]["outcome"], "error");
+        assert_eq!(entries[1]["error"], "Login failed");
+        assert_eq!(entries[0]["session"], entries[1]["session"]);
+        assert_eq!(entries[0]["prev_hash"], GENESIS);
+    }
+
+    #[tokio::test]
+    async fn edits_break_the_hash_chain() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let path = path.to_string_lossy().into_owned();
+        let key = Some("chain-key".to_string());
+        // An entry written before chaining was introduced.
+        append_line(&Mutex::new(()), &path, &serde_json::json!({ "tool": "a" }))
+            .await
+            .unwrap();
+        let lock = Mutex::new(());
+        for tool in ["b", "c", "d"] {
+            let entry =
+                serde_json::json!({ "tool": tool, "arguments": { "query": "x".repeat(10_000) } });
+            append_chained(&lock, &path, key.clone(), &entry)
+                .await
+                .unwrap();
+        }
+        let report = verify(&path, key.as_deref());
+        assert_eq!(report["ok"], true, "{report}");
+        assert_eq!(report["entries"], 4);
+        assert_eq!(report["unchained"], 1);
+        assert_eq!(verify(&path, None)["broken_line"], 2);
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.replacen(r#""tool":"c""#, r#""tool":"e""#, 1)).unwrap();
+        let report = verify(&path, key.as_deref());
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(report["ok"], false);
+        assert_eq!(report["broken_line"], 4);
+    }
+}
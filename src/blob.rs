@@ -0,0 +1,318 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::{Method, StatusCode};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::storage::{encode_path, Service, StorageClient};
+
+/// Number of bytes `blob_read` returns when the caller gives no `length`.
+pub const DEFAULT_READ_BYTES: u64 = 64 * 1024;
+
+/// Upper bound on the bytes returned by one `blob_read` call.
+pub const MAX_READ_BYTES: u64 = 1024 * 1024;
+
+/// Upper bound on the decoded size of one `blob_write` upload.
+pub const MAX_WRITE_BYTES: usize = 16 * 1024 * 1024;
+
+/// How long a `blob_share_url` URL stays valid when the caller gives no
+/// `expires_in_minutes`.
+pub const DEFAULT_SHARE_MINUTES: u32 = 60;
+
+/// Upper bound on the lifetime of a `blob_share_url` URL (24 hours).
+pub const MAX_SHARE_MINUTES: u32 = 24 * 60;
+
+/// How `blob_read` returns the blob's bytes, and how `blob_write` content is
+/// encoded (`auto` means text there).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BlobEncoding {
+    /// Text when the bytes are UTF-8, base64 otherwise.
+    #[default]
+    Auto,
+    /// UTF-8 text; fails for binary content.
+    Text,
+    /// Base64 of the raw bytes.
+    Base64,
+}
+
+/// Decode `bytes` as UTF-8, dropping a multi-byte character cut off at the
+/// end of the range.  Returns `None` for data that is not UTF-8.
+fn utf8_prefix(bytes: &[u8]) -> Option<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        // `error_len() == None` means the input ended mid-character.
+        Err(e) if e.error_len().is_none() => {
+            Some(std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default())
+        }
+        Err(_) => None,
+    }
+}
+
+/// Content type of an upload to `path` when the caller gives none, guessed
+/// from the file extension.
+pub fn guess_content_type(path: &str, encoding: BlobEncoding) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "json" => "application/json",
+        "jsonl" | "ndjson" => "application/x-ndjson",
+        "csv" => "text/csv; charset=utf-8",
+        "md" => "text/markdown; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "parquet" => "application/vnd.apache.parquet",
+        "gz" => "application/gzip",
+        _ if encoding == BlobEncoding::Base64 => "application/octet-stream",
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
+/// Decode `blob_write` content into the bytes to upload.
+pub fn decode_content(content: &str, encoding: BlobEncoding) -> Result<Vec<u8>> {
+    let bytes = match encoding {
+        BlobEncoding::Base64 => BASE64
+            .decode(content.trim())
+            .context("content is not valid base64")?,
+        BlobEncoding::Text | BlobEncoding::Auto => content.as_bytes().to_vec(),
+    };
+    anyhow::ensure!(
+        bytes.len() <= MAX_WRITE_BYTES,
+        "content is {} bytes; at most {MAX_WRITE_BYTES} can be uploaded at once",
+        bytes.len()
+    );
+    Ok(bytes)
+}
+
+/// The total blob size from a `Content-Range: bytes 0-99/1234` header.
+fn total_size(content_range: &str) -> Option<u64> {
+    content_range.rsplit_once('/')?.1.parse().ok()
+}
+
+/// Read up to `length` bytes of the blob at `container`/`path` starting at
+/// `offset`.
+///
+/// Returns the content as text or base64 (see [`BlobEncoding`]) together
+/// with the blob's total size and `next_offset`, the offset to continue
+/// from, which is `null` once the end of the blob was reached.
+pub async fn read(
+    client: &StorageClient,
+    container: &str,
+    path: &str,
+    offset: u64,
+    length: Option<u64>,
+    encoding: BlobEncoding,
+) -> Result<Value> {
+    read_from(
+        client,
+        Service::Blob,
+        container,
+        path,
+        offset,
+        length,
+        encoding,
+    )
+    .await
+    .with_context(|| format!("Failed to read blob {container}/{path}"))
+}
+
+/// Like [`read`], through the endpoint of `service`: the Data Lake endpoint
+/// serves the same ranged reads for files of a filesystem.
+pub async fn read_from(
+    client: &StorageClient,
+    service: Service,
+    container: &str,
+    path: &str,
+    offset: u64,
+    length: Option<u64>,
+    encoding: BlobEncoding,
+) -> Result<Value> {
+    let length = length
+        .unwrap_or(DEFAULT_READ_BYTES)
+        .clamp(1, MAX_READ_BYTES);
+    let range = format!("bytes={offset}-{}", offset + length - 1);
+    // The Data Lake endpoint only documents the standard header.
+    let range_header = match service {
+        Service::Dfs => "range",
+        _ => "x-ms-range",
+    };
+    let response = client
+        .send(
+            service,
+            Method::GET,
+            &encode_path(&format!("{container}/{path}")),
+            &[],
+            &[(range_header, &range)],
+            Vec::new(),
+        )
+        .await?;
+
+    let size = match response.header("content-range") {
+        Some(content_range) => total_size(content_range),
+        // A blob that fits in the range may be returned whole with 200.
+        None if response.status == StatusCode::OK => Some(response.body.len() as u64),
+        None => None,
+    };
+    let content_type = response.header("content-type").map(str::to_string);
+
+    let (encoding, content, returned) = match encoding {
+        BlobEncoding::Base64 => ("base64", BASE64.encode(&response.body), response.body.len()),
+        BlobEncoding::Text | BlobEncoding::Auto => match utf8_prefix(&response.body) {
+            Some(text) => ("text", text.to_string(), text.len()),
+            None if encoding == BlobEncoding::Text => {
+                bail!("{container}/{path} is not UTF-8 text; read it with encoding \"base64\"")
+            }
+            None => ("base64", BASE64.encode(&response.body), response.body.len()),
+        },
+    };
+
+    let end = offset + returned as u64;
+    let next_offset = match size {
+        Some(size) if end >= size => None,
+        _ => Some(end),
+    };
+    Ok(serde_json::json!({
+        "container": container,
+        "path": path,
+        "offset": offset,
+        "length": returned,
+        "size": size,
+        "content_type": content_type,
+        "etag": response.header("etag"),
+        "last_modified": response.header("last-modified"),
+        "encoding": encoding,
+        "content": content,
+        "next_offset": next_offset,
+    }))
+}
+
+/// Upload `bytes` as the block blob at `container`/`path`.
+///
+/// Unless `overwrite` is set the upload is conditional on the blob not
+/// existing yet, so an existing blob is never replaced by accident.
+pub async fn write(
+    client: &StorageClient,
+    container: &str,
+    path: &str,
+    bytes: Vec<u8>,
+    content_type: &str,
+    overwrite: bool,
+) -> Result<Value> {
+    let size = bytes.len();
+    let mut headers = vec![
+        ("x-ms-blob-type", "BlockBlob"),
+        ("x-ms-blob-content-type", content_type),
+    ];
+    if !overwrite {
+        headers.push(("if-none-match", "*"));
+    }
+    let response = client
+        .send(
+            Service::Blob,
+            Method::PUT,
+            &encode_path(&format!("{container}/{path}")),
+            &[],
+            &headers,
+            bytes,
+        )
+        .await
+        .with_context(|| match overwrite {
+            true => format!("Failed to write blob {container}/{path}"),
+            false => format!(
+                "Failed to write blob {container}/{path} (pass overwrite: true to replace \
+                 an existing blob)"
+            ),
+        })?;
+
+    Ok(serde_json::json!({
+        "container": container,
+        "path": path,
+        "size": size,
+        "content_type": content_type,
+        "etag": response.header("etag"),
+        "last_modified": response.header("last-modified"),
+    }))
+}
+
+/// Generate a read-only URL to the blob at `container`/`path`, valid for
+/// `minutes` (at most [`MAX_SHARE_MINUTES`]), after checking the blob exists.
+///
+/// Returns `{container, path, url, expires, size, content_type}`.
+pub async fn share_url(
+    client: &StorageClient,
+    container: &str,
+    path: &str,
+    minutes: u32,
+) -> Result<Value> {
+    let response = client
+        .send(
+            Service::Blob,
+            Method::HEAD,
+            &encode_path(&format!("{container}/{path}")),
+            &[],
+            &[],
+            Vec::new(),
+        )
+        .await
+        .with_context(|| format!("Blob {container}/{path} does not exist or cannot be read"))?;
+
+    let minutes = minutes.clamp(1, MAX_SHARE_MINUTES);
+    let expires = (chrono::Utc::now() + chrono::Duration::minutes(minutes.into()))
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string();
+    Ok(serde_json::json!({
+        "container": container,
+        "path": path,
+        "url": client.blob_read_url(container, path, &expires),
+        "expires": expires,
+        "size": response
+            .header("content-length")
+            .and_then(|length| length.parse::<u64>().ok()),
+        "content_type": response.header("content-type"),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_prefix_drops_a_split_character() {
+        assert_eq!(utf8_prefix(b"plain"), Some("plain"));
+        // "é" is two bytes; the range ends after the first.
+        assert_eq!(utf8_prefix(&[b'c', b'a', b'f', 0xc3]), Some("caf"));
+        assert_eq!(utf8_prefix(&[0xff, 0x00, 0x10]), None);
+        assert_eq!(total_size("bytes 0-99/1234"), Some(1234));
+    }
+
+    #[test]
+    fn write_content_is_decoded_and_typed() {
+        assert_eq!(
+            decode_content("a,b\n", BlobEncoding::Auto).unwrap(),
+            b"a,b\n"
+        );
+        assert_eq!(
+            decode_content("AAEC", BlobEncoding::Base64).unwrap(),
+            vec![0, 1, 2]
+        );
+        assert!(decode_content("not base64!", BlobEncoding::Base64).is_err());
+
+        assert_eq!(
+            guess_content_type("exports/orders.CSV", BlobEncoding::Text),
+            "text/csv; charset=utf-8"
+        );
+        assert_eq!(
+            guess_content_type("dump.bin", BlobEncoding::Base64),
+            "application/octet-stream"
+        );
+        assert_eq!(
+            guess_content_type("notes", BlobEncoding::Auto),
+            "text/plain; charset=utf-8"
+        );
+    }
+}
@@ -0,0 +1,697 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde_json::{Map, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::config::CassandraConfig;
+use crate::readonly::{self, Dialect};
+use crate::recording;
+
+/// Default number of rows returned when the caller does not specify
+/// `max_rows`, as for MSSQL.
+pub const DEFAULT_MAX_ROWS: u64 = 500;
+
+/// Upper bound on `max_rows`, as for MSSQL.
+pub const HARD_MAX_ROWS: u64 = 10_000;
+
+/// How long connecting and running one query may take.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Native protocol version 4, the newest Cosmos DB supports.
+const PROTOCOL_VERSION: u8 = 0x04;
+
+/// Consistency `ONE`: reads are served by the nearest replica.
+const CONSISTENCY_ONE: u16 = 0x0001;
+
+// Request and response opcodes.
+const OP_ERROR: u8 = 0x00;
+const OP_STARTUP: u8 = 0x01;
+const OP_READY: u8 = 0x02;
+const OP_AUTHENTICATE: u8 = 0x03;
+const OP_QUERY: u8 = 0x07;
+const OP_RESULT: u8 = 0x08;
+const OP_AUTH_RESPONSE: u8 = 0x0F;
+const OP_AUTH_SUCCESS: u8 = 0x10;
+
+/// `RESULT` kind of a result holding rows.
+const RESULT_ROWS: i32 = 0x0002;
+
+/// A CQL column type.
+#[derive(Debug, Clone, PartialEq)]
+enum ColumnType {
+    Ascii,
+    Bigint,
+    Blob,
+    Boolean,
+    Counter,
+    Decimal,
+    Double,
+    Float,
+    Int,
+    Timestamp,
+    Uuid,
+    Varchar,
+    Varint,
+    Timeuuid,
+    Inet,
+    Date,
+    Time,
+    Smallint,
+    Tinyint,
+    List(Box<ColumnType>),
+    Map(Box<ColumnType>, Box<ColumnType>),
+    Set(Box<ColumnType>),
+    Udt(Vec<(String, ColumnType)>),
+    Tuple(Vec<ColumnType>),
+    /// A custom type, returned as hex.
+    Custom,
+}
+
+/// Reads the protocol's notation (`[short]`, `[string]`, `[bytes]`, ...)
+/// from a frame body.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            bail!("Truncated CQL frame");
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn short(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.short()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+
+    /// `[bytes]`: `None` for a negative length (null).
+    fn bytes(&mut self) -> Result<Option<&'a [u8]>> {
+        let len = self.int()?;
+        if len < 0 {
+            return Ok(None);
+        }
+        self.take(len as usize).map(Some)
+    }
+
+    fn column_type(&mut self) -> Result<ColumnType> {
+        Ok(match self.short()? {
+            0x0000 => {
+                self.string()?;
+                ColumnType::Custom
+            }
+            0x0001 => ColumnType::Ascii,
+            0x0002 => ColumnType::Bigint,
+            0x0003 => ColumnType::Blob,
+            0x0004 => ColumnType::Boolean,
+            0x0005 => ColumnType::Counter,
+            0x0006 => ColumnType::Decimal,
+            0x0007 => ColumnType::Double,
+            0x0008 => ColumnType::Float,
+            0x0009 => ColumnType::Int,
+            0x000B => ColumnType::Timestamp,
+            0x000C => ColumnType::Uuid,
+            0x000D => ColumnType::Varchar,
+            0x000E => ColumnType::Varint,
+            0x000F => ColumnType::Timeuuid,
+            0x0010 => ColumnType::Inet,
+            0x0011 => ColumnType::Date,
+            0x0012 => ColumnType::Time,
+            0x0013 => ColumnType::Smallint,
+            0x0014 => ColumnType::Tinyint,
+            0x0020 => ColumnType::List(Box::new(self.column_type()?)),
+            0x0021 => ColumnType::Map(Box::new(self.column_type()?), Box::new(self.column_type()?)),
+            0x0022 => ColumnType::Set(Box::new(self.column_type()?)),
+            0x0030 => {
+                self.string()?;
+                self.string()?;
+                let fields = (0..self.short()?)
+                    .map(|_| Ok((self.string()?, self.column_type()?)))
+                    .collect::<Result<_>>()?;
+                ColumnType::Udt(fields)
+            }
+            0x0031 => {
+                let types = (0..self.short()?)
+                    .map(|_| self.column_type())
+                    .collect::<Result<_>>()?;
+                ColumnType::Tuple(types)
+            }
+            other => bail!("Unsupported CQL column type 0x{other:04x}"),
+        })
+    }
+}
+
+/// Hex-encode bytes the way cqlsh shows blobs (`0x...`).
+fn hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("0x{digits}")
+}
+
+/// A two's complement big-endian integer of up to 16 bytes.
+fn varint(bytes: &[u8]) -> Option<i128> {
+    if bytes.is_empty() || bytes.len() > 16 {
+        return None;
+    }
+    let fill = if bytes[0] & 0x80 != 0 { 0xFF } else { 0x00 };
+    let mut buf = [fill; 16];
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Some(i128::from_be_bytes(buf))
+}
+
+/// Decode a collection of `count` `[bytes]` elements.
+fn decode_elements(reader: &mut Reader, ty: &ColumnType) -> Result<Vec<Value>> {
+    let count = reader.int()?.max(0);
+    (0..count)
+        .map(|_| Ok(reader.bytes()?.map_or(Value::Null, |b| decode(ty, b))))
+        .collect()
+}
+
+/// Decode a map into a JSON object, non-string keys becoming their JSON
+/// text.
+fn decode_map(bytes: &[u8], key: &ColumnType, value: &ColumnType) -> Result<Value> {
+    let mut reader = Reader { buf: bytes };
+    let mut map = Map::new();
+    for _ in 0..reader.int()?.max(0) {
+        let k = reader.bytes()?.map_or(Value::Null, |b| decode(key, b));
+        let v = reader.bytes()?.map_or(Value::Null, |b| decode(value, b));
+        let k = match k {
+            Value::String(s) => s,
+            other => other.to_string(),
+        };
+        map.insert(k, v);
+    }
+    Ok(Value::Object(map))
+}
+
+/// Convert a non-null cell of type `ty` to JSON.  Integers beyond 64 bits,
+/// decimals and varints are returned as strings to keep their precision.
+fn decode(ty: &ColumnType, bytes: &[u8]) -> Value {
+    let long = || <[u8; 8]>::try_from(bytes).ok();
+    let value = match ty {
+        ColumnType::Ascii | ColumnType::Varchar => {
+            Some(String::from_utf8_lossy(bytes).into_owned().into())
+        }
+        ColumnType::Bigint | ColumnType::Counter => long().map(|b| i64::from_be_bytes(b).into()),
+        ColumnType::Int => <[u8; 4]>::try_from(bytes)
+            .ok()
+            .map(|b| i32::from_be_bytes(b).into()),
+        ColumnType::Smallint => <[u8; 2]>::try_from(bytes)
+            .ok()
+            .map(|b| i16::from_be_bytes(b).into()),
+        ColumnType::Tinyint => bytes.first().map(|b| (*b as i8).into()),
+        ColumnType::Boolean => bytes.first().map(|b| (*b != 0).into()),
+        ColumnType::Double => long().map(|b| f64::from_be_bytes(b).into()),
+        ColumnType::Float => <[u8; 4]>::try_from(bytes)
+            .ok()
+            .map(|b| f32::from_be_bytes(b).into()),
+        ColumnType::Varint => varint(bytes).map(|v| match i64::try_from(v) {
+            Ok(v) => v.into(),
+            Err(_) => v.to_string().into(),
+        }),
+        ColumnType::Decimal => bytes.get(..4).and_then(|scale| {
+            let scale = i32::from_be_bytes(scale.try_into().ok()?);
+            let unscaled = varint(&bytes[4..])?;
+            Some(format_decimal(unscaled, scale).into())
+        }),
+        ColumnType::Timestamp => long()
+            .and_then(|b| DateTime::from_timestamp_millis(i64::from_be_bytes(b)))
+            .map(|t| t.to_rfc3339().into()),
+        ColumnType::Date => <[u8; 4]>::try_from(bytes).ok().and_then(|b| {
+            // Days since the epoch, centred on 2^31.
+            let days = i64::from(u32::from_be_bytes(b)) - (1 << 31);
+            let date = NaiveDate::from_ymd_opt(1970, 1, 1)?
+                .checked_add_signed(chrono::Duration::days(days))?;
+            Some(date.to_string().into())
+        }),
+        ColumnType::Time => long().and_then(|b| {
+            let nanos = i64::from_be_bytes(b);
+            let time = NaiveTime::from_num_seconds_from_midnight_opt(
+                u32::try_from(nanos / 1_000_000_000).ok()?,
+                u32::try_from(nanos % 1_000_000_000).ok()?,
+            )?;
+            Some(time.to_string().into())
+        }),
+        ColumnType::Uuid | ColumnType::Timeuuid => uuid::Uuid::from_slice(bytes)
+            .ok()
+            .map(|u| u.to_string().into()),
+        ColumnType::Inet => match bytes.len() {
+            4 => <[u8; 4]>::try_from(bytes).ok().map(IpAddr::from),
+            16 => <[u8; 16]>::try_from(bytes).ok().map(IpAddr::from),
+            _ => None,
+        }
+        .map(|ip| ip.to_string().into()),
+        ColumnType::Blob | ColumnType::Custom => Some(hex(bytes).into()),
+        ColumnType::List(element) | ColumnType::Set(element) => {
+            decode_elements(&mut Reader { buf: bytes }, element)
+                .ok()
+                .map(Value::Array)
+        }
+        ColumnType::Map(key, value) => decode_map(bytes, key, value).ok(),
+        ColumnType::Udt(fields) => {
+            let mut reader = Reader { buf: bytes };
+            let mut map = Map::new();
+            // Fields added to the type after the value was written are absent.
+            for (name, ty) in fields {
+                if reader.buf.is_empty() {
+                    break;
+                }
+                let Ok(value) = reader.bytes() else { break };
+                map.insert(name.clone(), value.map_or(Value::Null, |b| decode(ty, b)));
+            }
+            Some(Value::Object(map))
+        }
+        ColumnType::Tuple(types) => {
+            let mut reader = Reader { buf: bytes };
+            types
+                .iter()
+                .map(|ty| Ok(reader.bytes()?.map_or(Value::Null, |b| decode(ty, b))))
+                .collect::<Result<Vec<_>>>()
+                .ok()
+                .map(Value::Array)
+        }
+    };
+    value.unwrap_or_else(|| hex(bytes).into())
+}
+
+/// Format `unscaled * 10^-scale` as a decimal string.
+fn format_decimal(unscaled: i128, scale: i32) -> String {
+    let digits = unscaled.unsigned_abs().to_string();
+    let sign = if unscaled < 0 { "-" } else { "" };
+    if scale <= 0 {
+        return format!(
+            "{sign}{digits}{}",
+            "0".repeat(scale.unsigned_abs() as usize)
+        );
+    }
+    let scale = scale as usize;
+    let padded = format!("{digits:0>width$}", width = scale + 1);
+    let (int, frac) = padded.split_at(padded.len() - scale);
+    format!("{sign}{int}.{frac}")
+}
+
+/// The rows of one page of a `RESULT` of kind `Rows`, and the paging state
+/// of the next page when there is one.
+fn parse_rows(body: &[u8]) -> Result<(Vec<Value>, Option<Vec<u8>>)> {
+    let mut reader = Reader { buf: body };
+    if reader.int()? != RESULT_ROWS {
+        return Ok((Vec::new(), None));
+    }
+    let flags = reader.int()?;
+    let column_count = reader.int()?.max(0) as usize;
+    let paging_state = if flags & 0x0002 != 0 {
+        reader.bytes()?.map(<[u8]>::to_vec)
+    } else {
+        None
+    };
+    if flags & 0x0004 != 0 {
+        bail!("The CQL result has no column metadata");
+    }
+    let global_table_spec = flags & 0x0001 != 0;
+    if global_table_spec {
+        reader.string()?;
+        reader.string()?;
+    }
+    let mut columns = Vec::with_capacity(column_count);
+    for _ in 0..column_count {
+        if !global_table_spec {
+            reader.string()?;
+            reader.string()?;
+        }
+        columns.push((reader.string()?, reader.column_type()?));
+    }
+
+    let row_count = reader.int()?.max(0);
+    let mut rows = Vec::with_capacity(row_count as usize);
+    for _ in 0..row_count {
+        let mut row = Map::new();
+        for (name, ty) in &columns {
+            let value = reader.bytes()?.map_or(Value::Null, |b| decode(ty, b));
+            row.insert(name.clone(), value);
+        }
+        rows.push(Value::Object(row));
+    }
+    Ok((rows, paging_state))
+}
+
+/// A `[string map]`.
+fn string_map(entries: &[(&str, &str)]) -> Vec<u8> {
+    let mut out = (entries.len() as u16).to_be_bytes().to_vec();
+    for (key, value) in entries {
+        for s in [key, value] {
+            out.extend((s.len() as u16).to_be_bytes());
+            out.extend(s.as_bytes());
+        }
+    }
+    out
+}
+
+/// The body of a `QUERY` request at consistency `ONE`, asking for
+/// `page_size` rows from `paging_state`.
+fn query_body(cql: &str, page_size: i32, paging_state: Option<&[u8]>) -> Vec<u8> {
+    let mut body = (cql.len() as i32).to_be_bytes().to_vec();
+    body.extend(cql.as_bytes());
+    body.extend(CONSISTENCY_ONE.to_be_bytes());
+    // Flags: page size, then paging state when continuing.
+    body.push(if paging_state.is_some() { 0x0C } else { 0x04 });
+    body.extend(page_size.to_be_bytes());
+    if let Some(state) = paging_state {
+        body.extend((state.len() as i32).to_be_bytes());
+        body.extend(state);
+    }
+    body
+}
+
+/// A TLS connection speaking the CQL native protocol, one request at a time.
+struct Connection {
+    stream: TlsStream<TcpStream>,
+}
+
+impl Connection {
+    /// Connect to the contact point over TLS and authenticate with the
+    /// account name and key (`PasswordAuthenticator`).
+    async fn open(cfg: &CassandraConfig) -> Result<Self> {
+        recording::live("Cassandra (CQL)")?;
+        let tcp = TcpStream::connect((cfg.contact_point.as_str(), cfg.port))
+            .await
+            .with_context(|| format!("Failed to connect to {}:{}", cfg.contact_point, cfg.port))?;
+        let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let server_name = ServerName::try_from(cfg.contact_point.clone())?;
+        let stream = TlsConnector::from(Arc::new(tls))
+            .connect(server_name, tcp)
+            .await
+            .context("TLS handshake with the Cassandra endpoint failed")?;
+
+        let mut connection = Connection { stream };
+        let (opcode, _) = connection
+            .request(OP_STARTUP, &string_map(&[("CQL_VERSION", "3.0.0")]))
+            .await?;
+        match opcode {
+            OP_READY => {}
+            OP_AUTHENTICATE => {
+                let token = format!("\0{}\0{}", cfg.username, cfg.password);
+                let mut body = (token.len() as i32).to_be_bytes().to_vec();
+                body.extend(token.as_bytes());
+                let (opcode, _) = connection.request(OP_AUTH_RESPONSE, &body).await?;
+                if opcode != OP_AUTH_SUCCESS {
+                    bail!("Unexpected CQL opcode 0x{opcode:02x} during authentication");
+                }
+            }
+            other => bail!("Unexpected CQL opcode 0x{other:02x} after STARTUP"),
+        }
+        Ok(connection)
+    }
+
+    /// Send a request frame and read the response, turning `ERROR` frames
+    /// into errors.
+    async fn request(&mut self, opcode: u8, body: &[u8]) -> Result<(u8, Vec<u8>)> {
+        let mut frame = vec![PROTOCOL_VERSION, 0, 0, 0, opcode];
+        frame.extend((body.len() as u32).to_be_bytes());
+        frame.extend(body);
+        self.stream.write_all(&frame).await?;
+
+        let mut header = [0u8; 9];
+        self.stream.read_exact(&mut header).await?;
+        let flags = header[1];
+        let opcode = header[4];
+        let len = u32::from_be_bytes(header[5..9].try_into()?) as usize;
+        let mut body = vec![0u8; len];
+        self.stream.read_exact(&mut body).await?;
+
+        // Skip the tracing id, custom payload and warnings a server may add.
+        let mut reader = Reader { buf: &body };
+        if flags & 0x02 != 0 {
+            reader.take(16)?;
+        }
+        if flags & 0x04 != 0 {
+            for _ in 0..reader.short()? {
+                reader.string()?;
+                reader.bytes()?;
+            }
+        }
+        if flags & 0x08 != 0 {
+            for _ in 0..reader.short()? {
+                reader.string()?;
+            }
+        }
+        let body = reader.buf.to_vec();
+
+        if opcode == OP_ERROR {
+            let mut reader = Reader { buf: &body };
+            let code = reader.int()?;
+            let message = reader.string()?;
+            bail!("Cassandra error 0x{code:04x}: {message}");
+        }
+        Ok((opcode, body))
+    }
+
+    /// Run `cql` and return one page of at most `page_size` rows.
+    async fn query(
+        &mut self,
+        cql: &str,
+        page_size: i32,
+        paging_state: Option<&[u8]>,
+    ) -> Result<(Vec<Value>, Option<Vec<u8>>)> {
+        let (opcode, body) = self
+            .request(OP_QUERY, &query_body(cql, page_size, paging_state))
+            .await?;
+        if opcode != OP_RESULT {
+            bail!("Unexpected CQL opcode 0x{opcode:02x} in reply to QUERY");
+        }
+        parse_rows(&body)
+    }
+}
+
+/// Quote `value` as a CQL string literal.
+fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Quote `name` as a CQL identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Run `cql` on a fresh connection (in `keyspace` when given) within
+/// [`QUERY_TIMEOUT`], returning up to `page_size` rows from `paging_state`.
+async fn run(
+    cfg: &CassandraConfig,
+    keyspace: Option<&str>,
+    cql: &str,
+    page_size: i32,
+    paging_state: Option<&[u8]>,
+) -> Result<(Vec<Value>, Option<Vec<u8>>)> {
+    let work = async {
+        let mut connection = Connection::open(cfg).await?;
+        if let Some(keyspace) = keyspace {
+            connection
+                .query(&format!("USE {}", quote_identifier(keyspace)), 1, None)
+                .await
+                .with_context(|| format!("Failed to use keyspace {keyspace}"))?;
+        }
+        connection.query(cql, page_size, paging_state).await
+    };
+    tokio::time::timeout(QUERY_TIMEOUT, work)
+        .await
+        .with_context(|| format!("CQL query timed out after {}s", QUERY_TIMEOUT.as_secs()))?
+}
+
+/// Run every page of `cql`, for the small system-schema queries.
+async fn run_all(cfg: &CassandraConfig, cql: &str) -> Result<Vec<Value>> {
+    let mut rows = Vec::new();
+    let mut paging_state = None;
+    loop {
+        let (page, next) = run(cfg, None, cql, 5_000, paging_state.as_deref()).await?;
+        rows.extend(page);
+        match next {
+            Some(next) => paging_state = Some(next),
+            None => return Ok(rows),
+        }
+    }
+}
+
+/// List the keyspaces, without the system ones.
+pub async fn list_keyspaces(cfg: &CassandraConfig) -> Result<Vec<String>> {
+    let rows = run_all(cfg, "SELECT keyspace_name FROM system_schema.keyspaces")
+        .await
+        .context("Failed to list keyspaces")?;
+    let mut keyspaces: Vec<String> = rows
+        .iter()
+        .filter_map(|row| row["keyspace_name"].as_str())
+        .filter(|name| !name.starts_with("system"))
+        .map(str::to_string)
+        .collect();
+    keyspaces.sort();
+    Ok(keyspaces)
+}
+
+/// List the tables of `keyspace` with their columns: name, CQL type and role
+/// (`partition_key`, `clustering` or `regular`), key columns first.
+pub async fn list_tables(cfg: &CassandraConfig, keyspace: &str) -> Result<Value> {
+    let cql = format!(
+        "SELECT table_name, column_name, type, kind, position FROM system_schema.columns \
+         WHERE keyspace_name = {}",
+        quote(keyspace)
+    );
+    let rows = run_all(cfg, &cql)
+        .await
+        .with_context(|| format!("Failed to list the tables of keyspace {keyspace}"))?;
+
+    let mut tables: std::collections::BTreeMap<String, Vec<&Value>> = Default::default();
+    for row in &rows {
+        if let Some(table) = row["table_name"].as_str() {
+            tables.entry(table.to_string()).or_default().push(row);
+        }
+    }
+    let kind_order = |kind: &str| match kind {
+        "partition_key" => 0,
+        "clustering" => 1,
+        _ => 2,
+    };
+    let tables: Vec<Value> = tables
+        .into_iter()
+        .map(|(name, mut columns)| {
+            columns.sort_by_key(|c| {
+                (
+                    kind_order(c["kind"].as_str().unwrap_or_default()),
+                    c["position"].as_i64().unwrap_or_default(),
+                    c["column_name"].as_str().unwrap_or_default().to_string(),
+                )
+            });
+            serde_json::json!({
+                "name": name,
+                "columns": columns
+                    .iter()
+                    .map(|c| serde_json::json!({
+                        "name": c["column_name"],
+                        "type": c["type"],
+                        "role": c["kind"],
+                    }))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    Ok(serde_json::json!({ "keyspace": keyspace, "tables": tables }))
+}
+
+/// Run one page of a CQL `SELECT`: up to `max_rows` rows (at most
+/// [`HARD_MAX_ROWS`]) from `paging_state`, in `keyspace` when given.
+///
+/// Only single `SELECT` statements are accepted.  Returns the rows and the
+/// paging state of the next page when more rows remain.
+pub async fn execute_query_page(
+    cfg: &CassandraConfig,
+    keyspace: Option<&str>,
+    cql: &str,
+    max_rows: u64,
+    paging_state: Option<&[u8]>,
+) -> Result<(Vec<Value>, Option<Vec<u8>>)> {
+    readonly::ensure_read_only(Dialect::Cql, cql)?;
+    let max_rows = max_rows.clamp(1, HARD_MAX_ROWS) as i32;
+    run(
+        cfg,
+        keyspace,
+        cql.trim().trim_end_matches(';'),
+        max_rows,
+        paging_state,
+    )
+    .await
+    .context("Failed to execute CQL query")
+}
+
+/// Check the contact point is reachable and accepts the credentials.
+pub async fn ping(cfg: &CassandraConfig) -> Result<()> {
+    run(
+        cfg,
+        None,
+        "SELECT release_version FROM system.local",
+        1,
+        None,
+    )
+    .await
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_reads_cql_values() {
+        assert_eq!(decode(&ColumnType::Int, &42i32.to_be_bytes()), 42);
+        assert_eq!(decode(&ColumnType::Bigint, &(-7i64).to_be_bytes()), -7);
+        assert_eq!(decode(&ColumnType::Varchar, b"paris"), "paris");
+        assert_eq!(
+            decode(&ColumnType::Timestamp, &1_767_225_600_000i64.to_be_bytes()),
+            "2026-01-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            decode(&ColumnType::Date, &((1u32 << 31) + 1).to_be_bytes()),
+            "1970-01-02"
+        );
+        // 12345 with scale 2.
+        assert_eq!(
+            decode(&ColumnType::Decimal, &[0, 0, 0, 2, 0x30, 0x39]),
+            "123.45"
+        );
+        assert_eq!(decode(&ColumnType::Varint, &[0xFF, 0x85]), -123);
+        assert_eq!(decode(&ColumnType::Blob, &[0xCA, 0xFE]), "0xcafe");
+
+        let mut list = 2i32.to_be_bytes().to_vec();
+        for s in ["a", "b"] {
+            list.extend(1i32.to_be_bytes());
+            list.extend(s.as_bytes());
+        }
+        assert_eq!(
+            decode(&ColumnType::Set(Box::new(ColumnType::Varchar)), &list),
+            serde_json::json!(["a", "b"])
+        );
+    }
+
+    #[test]
+    fn parse_rows_reads_metadata_rows_and_paging_state() {
+        let mut body = RESULT_ROWS.to_be_bytes().to_vec();
+        body.extend(0x0003i32.to_be_bytes()); // global table spec, more pages
+        body.extend(2i32.to_be_bytes());
+        body.extend(3i32.to_be_bytes());
+        body.extend(b"abc");
+        for s in ["shop", "orders"] {
+            body.extend((s.len() as u16).to_be_bytes());
+            body.extend(s.as_bytes());
+        }
+        for (name, ty) in [("id", 0x0009u16), ("city", 0x000D)] {
+            body.extend((name.len() as u16).to_be_bytes());
+            body.extend(name.as_bytes());
+            body.extend(ty.to_be_bytes());
+        }
+        body.extend(1i32.to_be_bytes());
+        body.extend(4i32.to_be_bytes());
+        body.extend(7i32.to_be_bytes());
+        body.extend((-1i32).to_be_bytes());
+
+        let (rows, paging_state) = parse_rows(&body).unwrap();
+        assert_eq!(rows, vec![serde_json::json!({ "id": 7, "city": null })]);
+        assert_eq!(paging_state.as_deref(), Some(&b"abc"[..]));
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+use crate::cosmos::{self, CosmosAccount};
+use crate::mssql;
+use crate::stats::CacheCounter;
+
+/// How long catalog metadata is reused before it is read again.
+const CATALOG_TTL: Duration = Duration::from_secs(60);
+
+/// Maximum number of values in one completion response (set by the MCP
+/// specification).
+const MAX_COMPLETIONS: usize = 100;
+
+/// Names fetched from a backend, with the time they were read.
+struct Cached {
+    fetched: Instant,
+    names: Vec<(String, String)>,
+}
+
+/// Cached names of the MSSQL tables and Cosmos DB containers the configured
+/// backends expose, used to complete `schema`, `table`, `database` and
+/// `container` arguments without querying a backend on every keystroke.
+#[derive(Default)]
+pub struct Catalog {
+    /// `(schema, table)` pairs.
+    mssql: Mutex<Option<Cached>>,
+    /// `(database, container)` pairs.
+    cosmos: Mutex<Option<Cached>>,
+    cache: CacheCounter,
+}
+
+/// Return the cached names in `slot`, refreshing them with `fetch` when they
+/// are missing or older than [`CATALOG_TTL`].
+async fn cached<F>(
+    slot: &Mutex<Option<Cached>>,
+    cache: &CacheCounter,
+    fetch: F,
+) -> Result<Vec<(String, String)>>
+where
+    F: Future<Output = Result<Vec<(String, String)>>>,
+{
+    let mut slot = slot.lock().await;
+    if let Some(cached) = slot.as_ref().filter(|c| c.fetched.elapsed() < CATALOG_TTL) {
+        cache.hit();
+        return Ok(cached.names.clone());
+    }
+    cache.miss();
+    let names = fetch.await?;
+    *slot = Some(Cached {
+        fetched: Instant::now(),
+        names: names.clone(),
+    });
+    Ok(names)
+}
+
+/// Keep the distinct `candidates` starting with `prefix` (case-insensitively),
+/// sorted and capped at [`MAX_COMPLETIONS`].  Also returns the number of
+/// matches before capping.
+fn matching<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    prefix: &str,
+) -> (Vec<String>, usize) {
+    let prefix = prefix.to_lowercase();
+    let matches: BTreeSet<&str> = candidates
+        .into_iter()
+        .filter(|c| c.to_lowercase().starts_with(&prefix))
+        .collect();
+    let total = matches.len();
+    let values = matches
+        .into_iter()
+        .take(MAX_COMPLETIONS)
+        .map(str::to_string)
+        .collect();
+    (values, total)
+}
+
+impl Catalog {
+    /// How often names were served from the cache.
+    pub fn cache(&self) -> &CacheCounter {
+        &self.cache
+    }
+
+    /// `(schema, table)` pairs of the MSSQL database.
+    async fn mssql_tables(&self, config: &Config) -> Result<Vec<(String, String)>> {
+        let cfg = config.require_mssql()?;
+        cached(&self.mssql, &self.cache, async {
+            let tables = mssql::list_tables(cfg).await?;
+            Ok(tables
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| {
+                    Some((
+                        t["schema"].as_str()?.to_string(),
+                        t["table_name"].as_str()?.to_string(),
+                    ))
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// `(database, container)` pairs of the Cosmos DB account.
+    async fn cosmos_containers(
+        &self,
+        cosmos: Result<&CosmosAccount, String>,
+    ) -> Result<Vec<(String, String)>> {
+        let account = cosmos.map_err(anyhow::Error::msg)?;
+        cached(&self.cosmos, &self.cache, cosmos::all_containers(account)).await
+    }
+
+    /// Suggest values for the argument `name` that start with `prefix`.
+    ///
+    /// `context` holds the arguments the client has already filled in: a
+    /// chosen `schema` narrows `table` suggestions and a chosen `database`
+    /// (or `COSMOS_DEFAULT_DATABASE`) narrows `container` suggestions.
+    /// Returns the suggestions and the total number of matches; arguments
+    /// this server knows nothing about get no suggestions.
+    pub async fn complete(
+        &self,
+        config: &Config,
+        cosmos: Result<&CosmosAccount, String>,
+        name: &str,
+        prefix: &str,
+        context: &HashMap<String, String>,
+    ) -> Result<(Vec<String>, usize)> {
+        let chosen = |key: &str| context.get(key).filter(|v| !v.is_empty());
+        Ok(match name {
+            "schema" if config.mssql.is_some() => {
+                let tables = self.mssql_tables(config).await?;
+                matching(tables.iter().map(|(s, _)| s.as_str()), prefix)
+            }
+            "table" if config.mssql.is_some() => {
+                let tables = self.mssql_tables(config).await?;
+                let schema = chosen("schema");
+                let in_schema =
+                    |s: &str| schema.is_none_or(|wanted| s.eq_ignore_ascii_case(wanted));
+                matching(
+                    tables
+                        .iter()
+                        .filter(|(s, _)| in_schema(s))
+                        .map(|(_, t)| t.as_str()),
+                    prefix,
+                )
+            }
+            "database" if config.cosmos.is_some() => {
+                let containers = self.cosmos_containers(cosmos).await?;
+                matching(containers.iter().map(|(d, _)| d.as_str()), prefix)
+            }
+            "container" if config.cosmos.is_some() => {
+                let containers = self.cosmos_containers(cosmos).await?;
+                let database = chosen("database").or(config
+                    .cosmos
+                    .as_ref()
+                    .and_then(|c| c.default_database.as_ref()));
+                matching(
+                    containers
+                        .iter()
+                        .filter(|(d, _)| database.is_none_or(|wanted| d == wanted))
+                        .map(|(_, c)| c.as_str()),
+                    prefix,
+                )
+            }
+            _ => (Vec::new(), 0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_filters_by_prefix_case_insensitively_and_dedups() {
+        let (values, total) = matching(["Orders", "order_lines", "customers", "Orders"], "ord");
+        assert_eq!(values, ["Orders", "order_lines"]);
+        assert_eq!(total, 2);
+    }
+
+    #[test]
+    fn matching_caps_the_number_of_values() {
+        let names: Vec<String> = (0..150).map(|i| format!("t{i:03}")).collect();
+        let (values, total) = matching(names.iter().map(String::as_str), "");
+        assert_eq!(values.len(), MAX_COMPLETIONS);
+        assert_eq!(total, 150);
+    }
+}
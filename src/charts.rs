@@ -0,0 +1,377 @@
+use std::fmt::Write;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rmcp::model::{CallToolResult, Content, JsonObject};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::annotations::RECORD_FIELDS;
+
+/// MIME type of rendered charts.
+const CHART_MIME_TYPE: &str = "image/svg+xml";
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 400.0;
+/// Space around the plot area for axis labels and the legend.
+const MARGIN: f64 = 56.0;
+
+/// Maximum number of rows plotted; the rest are ignored.
+const MAX_POINTS: usize = 50;
+
+/// Maximum number of numeric columns plotted as series by bar and line
+/// charts.
+const MAX_SERIES: usize = 3;
+
+const PALETTE: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+
+/// How a query tool returns its result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Render {
+    /// JSON only (the default).
+    #[default]
+    Json,
+    /// JSON plus a chart of the result as an image.
+    Chart,
+}
+
+/// Kind of chart drawn by `render: chart`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartKind {
+    /// One bar per row for each of the first numeric columns (the default).
+    #[default]
+    Bar,
+    /// One line per numeric column, rows in result order.
+    Line,
+    /// Share of each row in the first numeric column.
+    Pie,
+}
+
+/// Rendering options accepted by the query tools.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+pub struct RenderOptions {
+    /// `json` (default) or `chart` to also return an SVG chart of the first
+    /// numeric columns as image content.
+    pub render: Option<Render>,
+    /// Chart kind for `render: chart`: `bar` (default), `line` or `pie`.
+    pub chart: Option<ChartKind>,
+}
+
+impl RenderOptions {
+    /// Read the rendering options from a tool call's arguments.
+    pub fn from_arguments(arguments: Option<&JsonObject>) -> Self {
+        arguments
+            .and_then(|a| serde_json::from_value(Value::Object(a.clone())).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// The columns of a result to plot: the labels of each row and the values of
+/// each numeric series.
+struct Series {
+    labels: Vec<String>,
+    columns: Vec<(String, Vec<f64>)>,
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Pick the label column (the first column that is not numeric, or the row
+/// number) and the numeric columns of `rows`.
+fn series(rows: &[Map<String, Value>]) -> Option<Series> {
+    let rows = &rows[..rows.len().min(MAX_POINTS)];
+    let first = rows.first()?;
+    let is_numeric = |column: &str| {
+        rows.iter()
+            .filter_map(|row| row.get(column).filter(|v| !v.is_null()))
+            .all(|v| number(v).is_some())
+    };
+    let label_column = first.keys().find(|column| !is_numeric(column));
+    let labels = match label_column {
+        Some(column) => rows
+            .iter()
+            .map(|row| match &row[column] {
+                Value::String(s) => s.clone(),
+                Value::Null => String::new(),
+                other => other.to_string(),
+            })
+            .collect(),
+        None => (1..=rows.len()).map(|i| i.to_string()).collect(),
+    };
+    let columns: Vec<(String, Vec<f64>)> = first
+        .keys()
+        .filter(|column| Some(*column) != label_column && is_numeric(column))
+        .map(|column| {
+            let values = rows
+                .iter()
+                .map(|row| row.get(column).and_then(number).unwrap_or(0.0))
+                .collect();
+            (column.clone(), values)
+        })
+        .collect();
+    (!columns.is_empty()).then_some(Series { labels, columns })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn legend(svg: &mut String, names: &[&str]) {
+    for (i, name) in names.iter().enumerate() {
+        let y = MARGIN / 2.0 + i as f64 * 16.0;
+        let color = PALETTE[i % PALETTE.len()];
+        let x = WIDTH - MARGIN * 2.5;
+        let _ = write!(
+            svg,
+            r#"<rect x="{x}" y="{}" width="10" height="10" fill="{color}"/><text x="{}" y="{}">{}</text>"#,
+            y - 9.0,
+            x + 14.0,
+            y,
+            escape(name)
+        );
+    }
+}
+
+/// Draw bars or lines for every series over a shared value axis.
+fn draw_xy(svg: &mut String, series: &Series, kind: ChartKind) {
+    let columns = &series.columns[..series.columns.len().min(MAX_SERIES)];
+    let values = columns.iter().flat_map(|(_, v)| v.iter().copied());
+    let max = values.clone().fold(0.0_f64, f64::max);
+    let min = values.fold(0.0_f64, f64::min);
+    let span = if max > min { max - min } else { 1.0 };
+    let plot_width = WIDTH - 2.0 * MARGIN;
+    let plot_height = HEIGHT - 2.0 * MARGIN;
+    let y_of = |v: f64| MARGIN + (max - v) / span * plot_height;
+    let slot = plot_width / series.labels.len() as f64;
+
+    let zero = y_of(0.0);
+    let _ = write!(
+        svg,
+        r##"<line x1="{MARGIN}" y1="{zero}" x2="{}" y2="{zero}" stroke="#333"/><line x1="{MARGIN}" y1="{MARGIN}" x2="{MARGIN}" y2="{}" stroke="#333"/>"##,
+        WIDTH - MARGIN,
+        HEIGHT - MARGIN
+    );
+    for (value, anchor) in [(max, "end"), (min, "end")] {
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="{anchor}">{}</text>"#,
+            MARGIN - 4.0,
+            y_of(value) + 4.0,
+            format_value(value)
+        );
+    }
+    for (i, label) in series.labels.iter().enumerate() {
+        let x = MARGIN + slot * (i as f64 + 0.5);
+        let _ = write!(
+            svg,
+            r#"<text x="{x}" y="{}" text-anchor="end" transform="rotate(-45 {x} {})">{}</text>"#,
+            HEIGHT - MARGIN + 14.0,
+            HEIGHT - MARGIN + 14.0,
+            escape(&truncate(label, 16))
+        );
+    }
+
+    for (s, (_, values)) in columns.iter().enumerate() {
+        let color = PALETTE[s % PALETTE.len()];
+        match kind {
+            ChartKind::Line => {
+                let points: Vec<String> = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| format!("{},{}", MARGIN + slot * (i as f64 + 0.5), y_of(*v)))
+                    .collect();
+                let _ = write!(
+                    svg,
+                    r#"<polyline points="{}" fill="none" stroke="{color}" stroke-width="2"/>"#,
+                    points.join(" ")
+                );
+            }
+            _ => {
+                let bar = slot * 0.8 / columns.len() as f64;
+                for (i, v) in values.iter().enumerate() {
+                    let x = MARGIN + slot * (i as f64 + 0.1) + bar * s as f64;
+                    let (top, bottom) = (y_of(v.max(0.0)), y_of(v.min(0.0)));
+                    let _ = write!(
+                        svg,
+                        r#"<rect x="{x}" y="{top}" width="{bar}" height="{}" fill="{color}"/>"#,
+                        bottom - top
+                    );
+                }
+            }
+        }
+    }
+    let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+    legend(svg, &names);
+}
+
+/// Draw one slice per row, sized by the first numeric column.
+fn draw_pie(svg: &mut String, series: &Series) {
+    let (_, values) = &series.columns[0];
+    let total: f64 = values.iter().map(|v| v.max(0.0)).sum();
+    if total <= 0.0 {
+        return;
+    }
+    let (cx, cy) = (WIDTH / 2.0 - MARGIN, HEIGHT / 2.0);
+    let r = HEIGHT / 2.0 - MARGIN;
+    let mut angle = -std::f64::consts::FRAC_PI_2;
+    for (i, v) in values.iter().enumerate() {
+        let share = v.max(0.0) / total;
+        if share <= 0.0 {
+            continue;
+        }
+        let color = PALETTE[i % PALETTE.len()];
+        if share >= 1.0 {
+            let _ = write!(
+                svg,
+                r#"<circle cx="{cx}" cy="{cy}" r="{r}" fill="{color}"/>"#
+            );
+        } else {
+            let end = angle + share * std::f64::consts::TAU;
+            let large = i32::from(share > 0.5);
+            let _ = write!(
+                svg,
+                r#"<path d="M{cx},{cy} L{},{} A{r},{r} 0 {large} 1 {},{} Z" fill="{color}"/>"#,
+                cx + r * angle.cos(),
+                cy + r * angle.sin(),
+                cx + r * end.cos(),
+                cy + r * end.sin()
+            );
+            angle = end;
+        }
+    }
+    let names: Vec<String> = series
+        .labels
+        .iter()
+        .zip(values)
+        .map(|(label, v)| format!("{} ({})", truncate(label, 16), format_value(*v)))
+        .collect();
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    legend(svg, &names[..names.len().min(PALETTE.len())]);
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        format!("{}…", text.chars().take(max - 1).collect::<String>())
+    }
+}
+
+fn format_value(v: f64) -> String {
+    if v.fract() == 0.0 && v.abs() < 1e15 {
+        format!("{v:.0}")
+    } else {
+        format!("{v:.2}")
+    }
+}
+
+/// Render `rows` as an SVG chart, or `None` when they have no numeric
+/// column.
+fn render_svg(rows: &[Map<String, Value>], kind: ChartKind) -> Option<String> {
+    let series = series(rows)?;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{HEIGHT}" viewBox="0 0 {WIDTH} {HEIGHT}" font-family="sans-serif" font-size="11"><rect width="100%" height="100%" fill="white"/>"#
+    );
+    match kind {
+        ChartKind::Pie => draw_pie(&mut svg, &series),
+        ChartKind::Bar | ChartKind::Line => draw_xy(&mut svg, &series, kind),
+    }
+    svg.push_str("</svg>");
+    Some(svg)
+}
+
+/// Append a chart of the result's rows or documents as image content when
+/// `options` ask for one.
+///
+/// Only top-level numeric properties are plotted (numbers, or strings that
+/// parse as numbers such as MSSQL `decimal` values); the first other column
+/// labels the rows.  When nothing can be plotted a text note says so instead.
+pub fn render(result: &mut CallToolResult, options: RenderOptions) {
+    if options.render != Some(Render::Chart) || result.is_error == Some(true) {
+        return;
+    }
+    let Some(structured) = &result.structured_content else {
+        return;
+    };
+    let rows: Vec<Map<String, Value>> = RECORD_FIELDS
+        .iter()
+        .find_map(|field| structured[*field].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|row| row.as_object().cloned())
+        .collect();
+    match render_svg(&rows, options.chart.unwrap_or_default()) {
+        Some(svg) => result
+            .content
+            .push(Content::image(BASE64.encode(svg), CHART_MIME_TYPE)),
+        None => result.content.push(Content::text(
+            "No chart: the result has no numeric columns to plot.",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(value: Value) -> Vec<Map<String, Value>> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r.as_object().unwrap().clone())
+            .collect()
+    }
+
+    #[test]
+    fn numeric_columns_are_plotted_against_the_label_column() {
+        let rows = rows(serde_json::json!([
+            { "region": "north", "total": "12.50", "orders": 3 },
+            { "region": "south <b>", "total": "7.25", "orders": 1 },
+        ]));
+        let series = series(&rows).unwrap();
+        assert_eq!(series.labels, ["north", "south <b>"]);
+        let names: Vec<&str> = series.columns.iter().map(|(n, _)| n.as_str()).collect();
+        assert!(names.contains(&"total") && names.contains(&"orders"));
+
+        for kind in [ChartKind::Bar, ChartKind::Line, ChartKind::Pie] {
+            let svg = render_svg(&rows, kind).unwrap();
+            assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+            assert!(!svg.contains("<b>"), "labels must be escaped");
+        }
+    }
+
+    #[test]
+    fn charts_are_only_added_on_request() {
+        let structured = serde_json::json!({ "rows": [{ "name": "a", "n": 1 }] });
+        let mut result = CallToolResult::structured(structured.clone());
+        render(&mut result, RenderOptions::default());
+        assert_eq!(result.content.len(), 1);
+
+        let options = RenderOptions::from_arguments(
+            serde_json::json!({ "query": "SELECT 1", "render": "chart", "chart": "pie" })
+                .as_object(),
+        );
+        assert_eq!(options.chart, Some(ChartKind::Pie));
+        render(&mut result, options);
+        assert_eq!(result.content.len(), 2);
+        assert!(result.content[1].as_image().is_some());
+
+        let mut result = CallToolResult::structured(serde_json::json!({ "rows": [{ "a": "x" }] }));
+        render(&mut result, options);
+        assert!(result.content[1].as_text().is_some());
+    }
+}
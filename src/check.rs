@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::cosmos::CosmosAccount;
+use crate::health::Backend;
+use crate::redact::redact;
+use crate::storage::StorageClient;
+use crate::transit;
+
+/// How long `--check` waits for one backend before reporting it as failed.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The outcome of probing one backend.
+struct Outcome {
+    backend: Backend,
+    duration: Duration,
+    result: Result<()>,
+}
+
+/// `{ok, version, error, backends}`, where `backends` lists every configured
+/// backend as `{backend, ok, duration_ms, error}` and `ok` is whether all of
+/// them passed.
+fn report(outcomes: &[Outcome], error: Option<String>) -> Value {
+    let backends: Vec<Value> = outcomes
+        .iter()
+        .map(|o| {
+            serde_json::json!({
+                "backend": o.backend.name(),
+                "ok": o.result.is_ok(),
+                "duration_ms": o.duration.as_millis() as u64,
+                "error": o.result.as_ref().err().map(|e| redact(&format!("{e:#}")).into_owned()),
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "ok": error.is_none() && outcomes.iter().all(|o| o.result.is_ok()),
+        "version": env!("CARGO_PKG_VERSION"),
+        "error": error.as_deref().map(redact),
+        "backends": backends,
+    })
+}
+
+/// Drop the backends whose variable is set but empty, as in `.env-sample`:
+/// there is nothing to connect to.
+fn drop_empty(config: &mut Config) {
+    if config
+        .mssql
+        .as_ref()
+        .is_some_and(|cfg| cfg.connection_string.trim().is_empty())
+    {
+        config.mssql = None;
+    }
+    if config
+        .cosmos
+        .as_ref()
+        .is_some_and(|cfg| cfg.endpoint.trim().is_empty())
+    {
+        config.cosmos = None;
+    }
+    if config
+        .storage
+        .as_ref()
+        .is_some_and(|cfg| cfg.account.trim().is_empty())
+    {
+        config.storage = None;
+    }
+}
+
+/// Load the configuration from the environment, connect to every configured
+/// backend and make the same cheap call the periodic probes make (`SELECT 1`
+/// on SQL endpoints, listing databases on Cosmos DB, ...), all concurrently.
+/// Returns the report printed by `--check`, with how each SQL and Cosmos DB
+/// connection protects data in transit under `connection_security` (see
+/// [`transit::check`]); a configuration error is reported in `error`.
+/// Backends whose variable is empty are skipped (see [`drop_empty`]).
+pub async fn run() -> Value {
+    let mut config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => return report(&[], Some(format!("{e:#}"))),
+    };
+    drop_empty(&mut config);
+    let cosmos = config
+        .cosmos
+        .as_ref()
+        .map(|cfg| CosmosAccount::new(cfg).map_err(|e| e.to_string()));
+    let storage = config
+        .storage
+        .as_ref()
+        .map(|cfg| StorageClient::new(cfg).map_err(|e| e.to_string()));
+    let probes = Backend::ALL
+        .into_iter()
+        .filter(|backend| backend.configured(&config))
+        .map(|backend| {
+            let cosmos = match &cosmos {
+                Some(account) => account.as_ref().map_err(String::clone),
+                None => Err("Cosmos DB is not configured".to_string()),
+            };
+            let storage = match &storage {
+                Some(client) => client.as_ref().map_err(String::clone),
+                None => Err("Azure Storage is not configured".to_string()),
+            };
+            let config = &config;
+            async move {
+                let started = Instant::now();
+                let probe = backend.probe(config, cosmos, storage);
+                let result = match tokio::time::timeout(CHECK_TIMEOUT, probe).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow::anyhow!(
+                        "No answer within {} s",
+                        CHECK_TIMEOUT.as_secs()
+                    )),
+                };
+                Outcome {
+                    backend,
+                    duration: started.elapsed(),
+                    result,
+                }
+            }
+        });
+    let outcomes = futures::future::join_all(probes).await;
+    let mut report = report(&outcomes, None);
+    report["connection_security"] = transit::check(&config).await;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MssqlConfig;
+
+    #[test]
+    fn backends_with_empty_variables_are_not_checked() {
+        let mut config = Config {
+            mssql: Some(MssqlConfig::for_tests(" ")),
+            synapse: Some(MssqlConfig::for_tests("server=tcp:synapse,1433")),
+            ..Config::for_tests()
+        };
+        drop_empty(&mut config);
+        assert!(config.mssql.is_none());
+        assert!(config.synapse.is_some());
+    }
+
+    #[test]
+    fn one_failed_backend_fails_the_check() {
+        let outcomes = [
+            Outcome {
+                backend: Backend::Mssql,
+                duration: Duration::from_millis(40),
+                result: Ok(()),
+            },
+            Outcome {
+                backend: Backend::Cosmos,
+                duration: Duration::from_millis(900),
+                result: Err(anyhow::anyhow!("401 Unauthorized")),
+            },
+        ];
+        let failed = report(&outcomes, None);
+        assert_eq!(failed["ok"], false);
+        assert_eq!(failed["backends"][0]["ok"], true);
+        assert_eq!(failed["backends"][1]["backend"], "cosmos");
+        assert_eq!(failed["backends"][1]["error"], "401 Unauthorized");
+
+        assert_eq!(report(&outcomes[..1], None)["ok"], true);
+        assert_eq!(
+            report(&[], Some("MSSQL_CONNECTION_STRING is empty".into()))["ok"],
+            false
+        );
+    }
+}
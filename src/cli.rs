@@ -0,0 +1,354 @@
+use anyhow::{bail, Context, Result};
+use rmcp::model::{ProtocolVersion, Tool};
+use rmcp::ServiceExt;
+use serde_json::{Map, Value};
+use tokio::io::{
+    AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf,
+    WriteHalf,
+};
+
+use crate::server::AzureMcpServer;
+
+/// Usage of `azure-mcp-server call`.
+const CALL_USAGE: &str = "Usage: azure-mcp-server call <tool> [--json '<arguments>' | --json -] \
+                          [--yes] [--raw]";
+
+/// Usage of `azure-mcp-server list-tools`.
+const LIST_TOOLS_USAGE: &str = "Usage: azure-mcp-server list-tools [<tool>...] [--json]";
+
+/// A minimal MCP client driving the server over an in-process stream, so
+/// that the command line goes through the same path as an MCP host: roles,
+/// quotas, result formatting, audit and confirmations.
+pub struct Client {
+    lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+    writer: WriteHalf<DuplexStream>,
+    next_id: u64,
+    /// How confirmation requests are answered: accepted with `--yes`,
+    /// declined otherwise.
+    confirm: bool,
+}
+
+impl Client {
+    /// Serve `server` in the background and initialize a session with it.
+    /// The client declares elicitation support, so operations that ask for
+    /// confirmation are refused unless `confirm` is set.
+    pub async fn connect(server: AzureMcpServer, confirm: bool) -> Result<Self> {
+        let (client_io, server_io) = tokio::io::duplex(1024 * 1024);
+        tokio::spawn(async move {
+            match server.serve(tokio::io::split(server_io)).await {
+                Ok(running) => {
+                    let _ = running.waiting().await;
+                }
+                Err(e) => tracing::error!("Failed to start the in-process server: {e}"),
+            }
+        });
+        let (reader, writer) = tokio::io::split(client_io);
+        let mut client = Client {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 0,
+            confirm,
+        };
+        client
+            .request(
+                "initialize",
+                serde_json::json!({
+                    "protocolVersion": ProtocolVersion::LATEST,
+                    "capabilities": { "elicitation": {} },
+                    "clientInfo": {
+                        "name": "azure-mcp-server-cli",
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                }),
+            )
+            .await?;
+        client
+            .send(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/initialized",
+            }))
+            .await?;
+        Ok(client)
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<()> {
+        self.writer
+            .write_all(format!("{message}\n").as_bytes())
+            .await
+            .context("The in-process server went away")
+    }
+
+    /// Send a request and return its result, answering the server's own
+    /// requests (confirmations, pings) while waiting.
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }))
+        .await?;
+        loop {
+            let line = self
+                .lines
+                .next_line()
+                .await?
+                .context("The in-process server closed the connection")?;
+            let message: Value = serde_json::from_str(&line)?;
+            if let Some(method) = message["method"].as_str() {
+                if message.get("id").is_some() {
+                    let reply = self.answer(method, &message);
+                    self.send(&reply).await?;
+                }
+                continue;
+            }
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                bail!("{}", error["message"].as_str().unwrap_or("Request failed"));
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+
+    /// The reply to a request the server sent.
+    fn answer(&self, method: &str, request: &Value) -> Value {
+        let result = match method {
+            "ping" => serde_json::json!({}),
+            "elicitation/create" => {
+                eprintln!(
+                    "{}",
+                    request["params"]["message"].as_str().unwrap_or_default()
+                );
+                if self.confirm {
+                    eprintln!("Confirmed by --yes.");
+                    serde_json::json!({ "action": "accept", "content": { "confirm": true } })
+                } else {
+                    eprintln!("Declined: pass --yes to confirm.");
+                    serde_json::json!({ "action": "decline" })
+                }
+            }
+            _ => {
+                return serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {
+                        "code": -32601,
+                        "message": format!("The command line does not support {method}"),
+                    },
+                })
+            }
+        };
+        serde_json::json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+    }
+}
+
+/// The parsed arguments of `call`.
+#[derive(Debug, PartialEq)]
+struct CallArgs {
+    tool: String,
+    /// The `--json` value, `-` for standard input.
+    json: Option<String>,
+    yes: bool,
+    raw: bool,
+}
+
+fn parse_call_args(args: &[String]) -> Result<CallArgs> {
+    let mut args = args.iter();
+    let Some(tool) = args.next().filter(|tool| !tool.starts_with('-')) else {
+        bail!("{CALL_USAGE}");
+    };
+    let mut call = CallArgs {
+        tool: tool.clone(),
+        json: None,
+        yes: false,
+        raw: false,
+    };
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => call.json = Some(args.next().context(CALL_USAGE)?.clone()),
+            "--yes" => call.yes = true,
+            "--raw" => call.raw = true,
+            other => bail!("Unknown option '{other}'.  {CALL_USAGE}"),
+        }
+    }
+    Ok(call)
+}
+
+/// `azure-mcp-server call <tool> [--json '<arguments>'] [--yes] [--raw]`:
+/// run one tool on `server` and print its structured result as JSON (or its
+/// text), or the whole `CallToolResult` with `--raw`.  Returns the exit
+/// code: 1 when the tool failed, its error being printed on stderr.
+pub async fn call(server: AzureMcpServer, args: &[String]) -> Result<i32> {
+    let args = parse_call_args(args)?;
+    let arguments = match args.json.as_deref() {
+        None => Map::new(),
+        Some(json) => {
+            let text = if json == "-" {
+                let mut text = String::new();
+                tokio::io::stdin().read_to_string(&mut text).await?;
+                text
+            } else {
+                json.to_string()
+            };
+            serde_json::from_str(&text).context("--json is not a JSON object")?
+        }
+    };
+    let mut client = Client::connect(server, args.yes).await?;
+    let result = client
+        .request(
+            "tools/call",
+            serde_json::json!({ "name": args.tool, "arguments": arguments }),
+        )
+        .await?;
+    let failed = result["isError"] == true;
+    let texts = || {
+        result["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|c| c["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    if args.raw {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+    } else if failed {
+        eprintln!("{}", texts());
+    } else if let Some(structured) = result.get("structuredContent") {
+        println!("{}", serde_json::to_string_pretty(structured)?);
+    } else {
+        println!("{}", texts());
+    }
+    Ok(if failed { 1 } else { 0 })
+}
+
+/// The parsed arguments of `list-tools`.
+#[derive(Debug, PartialEq)]
+struct ListToolsArgs {
+    /// The tools to print, all of them when empty.
+    names: Vec<String>,
+    json: bool,
+}
+
+fn parse_list_tools_args(args: &[String]) -> Result<ListToolsArgs> {
+    let mut list = ListToolsArgs {
+        names: Vec::new(),
+        json: false,
+    };
+    for arg in args {
+        match arg.as_str() {
+            "--json" => list.json = true,
+            other if other.starts_with('-') => {
+                bail!("Unknown option '{other}'.  {LIST_TOOLS_USAGE}")
+            }
+            name => list.names.push(name.to_string()),
+        }
+    }
+    Ok(list)
+}
+
+/// `tool` for a reader: its name, description, then its input
+/// and output schemas as indented JSON.
+fn describe(tool: &Tool) -> Result<String> {
+    let indent = |text: String| {
+        text.lines()
+            .map(|line| format!("    {line}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let mut text = format!("{}\n", tool.name);
+    if let Some(description) = &tool.description {
+        text.push_str(&format!("{}\n", indent(description.to_string())));
+    }
+    text.push_str(&format!(
+        "  Input schema:\n{}\n",
+        indent(serde_json::to_string_pretty(&tool.input_schema)?)
+    ));
+    match &tool.output_schema {
+        Some(schema) => text.push_str(&format!(
+            "  Output schema:\n{}\n",
+            indent(serde_json::to_string_pretty(schema)?)
+        )),
+        None => text.push_str("  Output schema: none\n"),
+    }
+    Ok(text)
+}
+
+/// `azure-mcp-server list-tools [<tool>...] [--json]`: print `tools` (or
+/// the named ones) with their input and output schemas, for a reader or,
+/// with `--json`, as the array of `tools/list`.  Descriptions are the
+/// built-in ones: `MCP_TOOL_DESCRIPTIONS_FILE` is not applied.
+pub fn list_tools(tools: Vec<Tool>, args: &[String]) -> Result<()> {
+    let args = parse_list_tools_args(args)?;
+    if let Some(unknown) = args
+        .names
+        .iter()
+        .find(|name| !tools.iter().any(|tool| tool.name == name.as_str()))
+    {
+        bail!("Unknown tool '{unknown}'");
+    }
+    let tools: Vec<Tool> = tools
+        .into_iter()
+        .filter(|tool| args.names.is_empty() || args.names.iter().any(|n| tool.name == n.as_str()))
+        .collect();
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&tools)?);
+        return Ok(());
+    }
+    let descriptions = tools.iter().map(describe).collect::<Result<Vec<_>>>()?;
+    println!("{}", descriptions.join("\n"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn call_arguments_are_parsed() {
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+        assert_eq!(
+            parse_call_args(&args(&["mssql_execute_query", "--json", "{}", "--yes"])).unwrap(),
+            CallArgs {
+                tool: "mssql_execute_query".into(),
+                json: Some("{}".into()),
+                yes: true,
+                raw: false,
+            }
+        );
+        assert!(parse_call_args(&args(&[])).is_err());
+        assert!(parse_call_args(&args(&["--json", "{}"])).is_err());
+        assert!(parse_call_args(&args(&["server_stats", "--json"])).is_err());
+        assert!(parse_call_args(&args(&["server_stats", "--verbose"])).is_err());
+    }
+
+    #[test]
+    fn tools_are_described_with_their_schemas() {
+        let tools = AzureMcpServer::registered_tools();
+        assert!(tools.windows(2).all(|w| w[0].name < w[1].name));
+        let tool = tools
+            .iter()
+            .find(|tool| tool.name == "mssql_execute_query")
+            .unwrap();
+        let text = describe(tool).unwrap();
+        assert!(text.starts_with("mssql_execute_query\n    Execute a SQL query"));
+        assert!(text.contains("  Input schema:\n    {"));
+        assert!(text.contains("\"query\""));
+        assert!(text.contains("  Output schema:\n    {"));
+
+        let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+        assert_eq!(
+            parse_list_tools_args(&args(&["blob_read", "--json"])).unwrap(),
+            ListToolsArgs {
+                names: vec!["blob_read".into()],
+                json: true,
+            }
+        );
+        assert!(parse_list_tools_args(&args(&["--yaml"])).is_err());
+        assert!(list_tools(tools, &args(&["no_such_tool"])).is_err());
+    }
+}
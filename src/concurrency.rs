@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::{Map, Value};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::config::ConcurrencyConfig;
+use crate::health::Backend;
+
+/// The calls of one backend allowed to run at once.
+struct Limit {
+    limit: usize,
+    semaphore: Semaphore,
+    /// Calls waiting for a permit.
+    waiting: AtomicUsize,
+}
+
+/// Counts a call as waiting until dropped, also when the caller gives up.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Limits the tool calls of each backend running at once, so that a client
+/// calling tools in parallel cannot open dozens of SQL connections or spend
+/// an account's request units in a burst.  Calls over the limit wait.
+pub struct QueryLimiter {
+    limits: [Option<Limit>; Backend::ALL.len()],
+}
+
+impl QueryLimiter {
+    pub fn new(config: &ConcurrencyConfig) -> Self {
+        Self {
+            limits: Backend::ALL.map(|backend| {
+                config.limit(backend).map(|limit| Limit {
+                    limit,
+                    semaphore: Semaphore::new(limit),
+                    waiting: AtomicUsize::new(0),
+                })
+            }),
+        }
+    }
+
+    /// Wait until a call of `backend` may run; it runs until the returned
+    /// permit is dropped.  `None` when the backend is not limited.
+    pub async fn acquire(&self, backend: Backend) -> Option<SemaphorePermit<'_>> {
+        let limit = self.limits[backend as usize].as_ref()?;
+        if let Ok(permit) = limit.semaphore.try_acquire() {
+            return Some(permit);
+        }
+        tracing::debug!(
+            "{} calls at the limit of {}; waiting",
+            backend.name(),
+            limit.limit
+        );
+        limit.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = Waiting(&limit.waiting);
+        // The semaphore is never closed.
+        limit.semaphore.acquire().await.ok()
+    }
+
+    /// `{backend: {limit, running, waiting}}` for every limited backend.
+    pub fn report(&self) -> Value {
+        let limits: Map<String, Value> = Backend::ALL
+            .into_iter()
+            .zip(&self.limits)
+            .filter_map(|(backend, limit)| {
+                let limit = limit.as_ref()?;
+                let report = serde_json::json!({
+                    "limit": limit.limit,
+                    "running": limit.limit - limit.semaphore.available_permits(),
+                    "waiting": limit.waiting.load(Ordering::Relaxed),
+                });
+                Some((backend.name().to_string(), report))
+            })
+            .collect();
+        Value::Object(limits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn calls_over_the_limit_wait() {
+        let limiter = QueryLimiter::new(&ConcurrencyConfig {
+            default: Some(4),
+            backends: [(Backend::Mssql, 1)].into(),
+        });
+        assert!(limiter.acquire(Backend::Cosmos).await.is_some());
+        let first = limiter.acquire(Backend::Mssql).await;
+        assert!(first.is_some());
+        let second = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            limiter.acquire(Backend::Mssql),
+        )
+        .await;
+        assert!(second.is_err());
+
+        let report = limiter.report();
+        assert_eq!(report["mssql"]["limit"], 1);
+        assert_eq!(report["mssql"]["running"], 1);
+        assert_eq!(report["mssql"]["waiting"], 0);
+        assert_eq!(report["cosmos"]["running"], 0);
+        drop(first);
+        assert!(limiter.acquire(Backend::Mssql).await.is_some());
+    }
+}
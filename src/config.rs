@@ -1,5 +1,13 @@
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::health::Backend;
+use crate::readonly;
+use crate::roles;
 
 /// Configuration for connecting to Azure SQL / MSSQL via an ADO.NET connection string.
 ///
@@ -10,6 +18,60 @@ use std::env;
 #[derive(Debug, Clone)]
 pub struct MssqlConfig {
     pub connection_string: String,
+    /// Azure AD access token to log in with instead of the credentials in
+    /// the connection string.
+    pub access_token: Option<String>,
+    pub retry: RetryPolicy,
+    pub max_result_bytes: usize,
+    /// Values set with `sp_set_session_context` on every connection.
+    pub session_context: SessionContextConfig,
+    /// Queries whose estimated plan is over these are refused unrun.
+    pub plan_limits: Option<PlanLimits>,
+    /// Whether values of columns classified as confidential are masked in
+    /// query results, from `MSSQL_MASK_CLASSIFIED_COLUMNS` (default `true`).
+    pub mask_classified: bool,
+    /// Whether statements that may modify data can run (after the user
+    /// confirms them), from `MSSQL_ALLOW_WRITES`, `SYNAPSE_ALLOW_WRITES` or
+    /// `FABRIC_ALLOW_WRITES`.
+    pub allow_writes: bool,
+}
+
+impl MssqlConfig {
+    /// Return an error unless statements that may modify data were enabled
+    /// via `flag`, the `*_ALLOW_WRITES` variable of this endpoint.
+    pub fn require_writes(&self, flag: &str) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_writes,
+            "Statements that may modify data are disabled.  Set {flag}=true to enable them."
+        );
+        Ok(())
+    }
+}
+
+/// Limits on the estimated plan of a query, checked before running it.
+///
+/// Optional environment variables:
+/// - `MSSQL_MAX_ESTIMATED_ROWS` — the most rows any step of the plan may be
+///   estimated to process.
+/// - `MSSQL_MAX_ESTIMATED_COST` — the highest estimated subtree cost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanLimits {
+    pub max_rows: Option<f64>,
+    pub max_cost: Option<f64>,
+}
+
+/// `SESSION_CONTEXT` values for row-level security policies.
+///
+/// Optional environment variables:
+/// - `MSSQL_SESSION_CONTEXT` — comma-separated `key=value` pairs set
+///   read-only on every MSSQL connection, e.g. `tenant_id=42,region=emea`.
+/// - `MSSQL_SESSION_CONTEXT_CLIENT_KEYS` — comma-separated keys that a client
+///   may set for its own session through the `sessionContext` experimental
+///   capability.  Keys in `MSSQL_SESSION_CONTEXT` always keep their value.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionContextConfig {
+    pub values: Vec<(String, String)>,
+    pub client_keys: Vec<String>,
 }
 
 /// Configuration for connecting to Azure Cosmos DB.
@@ -21,60 +83,1885 @@ pub struct MssqlConfig {
 /// Optional:
 /// - `COSMOS_DEFAULT_DATABASE` — database name used when callers omit the `database`
 ///   parameter in tool calls.
+/// - `COSMOS_ALLOW_WRITES` — set to `true` to enable tools that modify documents
+///   (e.g. `cosmos_patch_item`).  Disabled by default.
+/// - `COSMOS_ALLOW_MANAGEMENT` — set to `true` to enable tools that create
+///   databases and containers or change container settings.  Disabled by default.
+/// - `COSMOS_PREFERRED_REGIONS` — comma-separated Azure regions (e.g.
+///   `West Europe, North Europe`), most preferred first.  Reads are routed to
+///   the first of these the account replicates to; writes always go to the
+///   account's write region.
+/// - `COSMOS_ANALYTICAL_CONNECTION_STRING` — ADO.NET connection string for a
+///   Synapse serverless SQL endpoint (`<workspace>-ondemand.sql.azuresynapse.net`)
+///   used to query the analytical store of containers with Synapse Link.
+/// - `COSMOS_ANALYTICAL_CREDENTIAL` — name of a Synapse server credential
+///   holding the account key; required with
+///   `COSMOS_ANALYTICAL_CONNECTION_STRING`, so that the key never appears in
+///   a query.
+/// - `COSMOS_MAX_REQUEST_UNITS` — request unit budget per query or change feed
+///   read; iteration stops once it is spent.  Callers may lower it per call but
+///   never raise it.  Unlimited by default.
+/// - `COSMOS_SESSION_MAX_REQUEST_UNITS` — request units one MCP session may
+///   spend in total; once spent, tools that charge request units are refused.
+///   Unlimited by default.
+/// - `COSMOS_QUERY_PARALLELISM` — partition key ranges a cross-partition
+///   query reads at once (default [`DEFAULT_QUERY_PARALLELISM`]).
+/// - `COSMOS_DEDICATED_GATEWAY_ENDPOINT` — the account's dedicated gateway,
+///   e.g. `https://myaccount.sqlx.cosmos.azure.com/`.  Point reads and
+///   queries are sent through it, so that repeated ones are answered from its
+///   integrated cache without charging request units.
+/// - `COSMOS_CACHE_MAX_STALENESS_MS` — oldest cached result the integrated
+///   cache may return.  The gateway's own default (5 minutes) when unset.
+/// - `COSMOS_REQUIRE_FILTER` / `COSMOS_REQUIRE_PARTITION_KEY` — containers
+///   whose queries must be narrowed (see [`CosmosQueryPolicy`]).
+/// - `COSMOS_IMPORT_DIR` — directory whose files `cosmos_bulk_import` may
+///   read as `file_path`.  Imports take inline documents only when unset.
 #[derive(Debug, Clone)]
 pub struct CosmosConfig {
     pub endpoint: String,
     pub key: Option<String>,
     pub default_database: Option<String>,
+    pub allow_writes: bool,
+    pub allow_management: bool,
+    pub max_request_units: Option<f64>,
+    pub session_max_request_units: Option<f64>,
+    pub preferred_regions: Vec<String>,
+    pub query_parallelism: usize,
+    pub integrated_cache: Option<IntegratedCacheConfig>,
+    pub query_policy: CosmosQueryPolicy,
+    pub analytical: Option<MssqlConfig>,
+    pub analytical_credential: Option<String>,
+    /// `COSMOS_IMPORT_DIR`, canonicalised.
+    pub import_dir: Option<PathBuf>,
+    pub retry: RetryPolicy,
+    pub max_result_bytes: usize,
+}
+
+/// The dedicated gateway point reads and queries go through, from
+/// `COSMOS_DEDICATED_GATEWAY_ENDPOINT` and `COSMOS_CACHE_MAX_STALENESS_MS`.
+#[derive(Debug, Clone)]
+pub struct IntegratedCacheConfig {
+    pub endpoint: String,
+    pub max_staleness: Option<Duration>,
+}
+
+/// Containers on which `cosmos_query_items` refuses queries that would read
+/// every document, e.g. the largest ones.  Entries are container names, or
+/// `database/container` for one database only.
+///
+/// - `COSMOS_REQUIRE_FILTER` — queries need a `WHERE` clause or a
+///   partition key.
+/// - `COSMOS_REQUIRE_PARTITION_KEY` — queries need a partition key, so that
+///   they read a single partition.
+#[derive(Debug, Clone, Default)]
+pub struct CosmosQueryPolicy {
+    pub require_filter: Vec<String>,
+    pub require_partition_key: Vec<String>,
+}
+
+impl CosmosQueryPolicy {
+    fn from_env() -> Self {
+        let list = |name: &str| env::var(name).map(|v| parse_list(&v)).unwrap_or_default();
+        CosmosQueryPolicy {
+            require_filter: list("COSMOS_REQUIRE_FILTER"),
+            require_partition_key: list("COSMOS_REQUIRE_PARTITION_KEY"),
+        }
+    }
+
+    /// Whether `entries` names `container` of `database`.
+    pub fn lists(entries: &[String], database: &str, container: &str) -> bool {
+        entries.iter().any(|entry| match entry.split_once('/') {
+            Some((db, c)) => db == database && c == container,
+            None => entry == container,
+        })
+    }
+}
+
+/// Partition key ranges a cross-partition Cosmos DB query reads at once when
+/// `COSMOS_QUERY_PARALLELISM` is not set.
+pub const DEFAULT_QUERY_PARALLELISM: usize = 4;
+
+impl CosmosConfig {
+    /// Return an error unless document writes were enabled via `COSMOS_ALLOW_WRITES`.
+    pub fn require_writes(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_writes,
+            "Cosmos DB write tools are disabled.  Set COSMOS_ALLOW_WRITES=true to enable them."
+        );
+        Ok(())
+    }
+
+    /// Return the Synapse serverless SQL endpoint used for analytical store
+    /// queries, or an error if it is not configured.
+    pub fn require_analytical(&self) -> Result<&MssqlConfig> {
+        self.analytical.as_ref().context(
+            "Cosmos DB analytical store queries are not configured \
+             (COSMOS_ANALYTICAL_CONNECTION_STRING not set)",
+        )
+    }
+
+    /// Combine a caller-requested request unit budget with the configured
+    /// limit, returning the stricter of the two.
+    pub fn request_unit_budget(&self, requested: Option<f64>) -> Option<f64> {
+        match (requested, self.max_request_units) {
+            (Some(requested), Some(limit)) => Some(requested.min(limit)),
+            (requested, limit) => requested.or(limit),
+        }
+    }
+
+    /// Return an error unless management tools were enabled via
+    /// `COSMOS_ALLOW_MANAGEMENT`.
+    pub fn require_management(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_management,
+            "Cosmos DB management tools are disabled.  Set COSMOS_ALLOW_MANAGEMENT=true to enable them."
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for connecting to an Azure Storage account.
+///
+/// Required environment variables:
+/// - `STORAGE_ACCOUNT` — the storage account name.
+/// - `STORAGE_KEY` — primary or secondary account key (Shared Key auth).
+///
+/// Optional:
+/// - `STORAGE_ENDPOINT_SUFFIX` — DNS suffix of the service endpoints, for
+///   sovereign clouds (default `core.windows.net`).
+/// - `STORAGE_ALLOW_WRITES` — set to `true` to enable tools that upload or
+///   change data (e.g. `blob_write`).  Disabled by default.
+/// - `STORAGE_ALLOW_SHARE` — set to `true` to enable `blob_share_url`, for
+///   the blobs of `STORAGE_SHARE_CONTAINER` only.  Disabled by default.
+/// - `STORAGE_TABLE_ENDPOINT` — Table service endpoint, to reach a Cosmos DB
+///   Table API account (`https://<account>.table.cosmos.azure.com`) instead of
+///   the storage account's own Table service.
+/// - `STORAGE_BLOB_ENDPOINT` — Blob service endpoint, e.g. Azurite's
+///   `http://localhost:10000/devstoreaccount1`.
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    pub account: String,
+    pub key: Option<String>,
+    pub endpoint_suffix: String,
+    pub allow_writes: bool,
+    pub allow_share: bool,
+    /// The container whose blobs `blob_share_url` may share.
+    pub share_container: Option<String>,
+    pub table_endpoint: Option<String>,
+    pub blob_endpoint: Option<String>,
+}
+
+impl StorageConfig {
+    /// Return an error unless storage writes were enabled via `STORAGE_ALLOW_WRITES`.
+    pub fn require_writes(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_writes,
+            "Azure Storage write tools are disabled.  Set STORAGE_ALLOW_WRITES=true to enable them."
+        );
+        Ok(())
+    }
+
+    /// Return an error unless sharing was enabled via `STORAGE_ALLOW_SHARE`
+    /// and `container` is `STORAGE_SHARE_CONTAINER`.
+    pub fn require_share(&self, container: &str) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_share,
+            "blob_share_url is disabled.  Set STORAGE_ALLOW_SHARE=true to enable it."
+        );
+        let shared = self.share_container.as_deref().unwrap_or_default();
+        anyhow::ensure!(
+            container == shared,
+            "Only blobs in the {shared} container can be shared (STORAGE_SHARE_CONTAINER)"
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for connecting to an Azure Service Bus namespace.
+///
+/// Set `SERVICEBUS_CONNECTION_STRING` to a shared access policy connection
+/// string, e.g.
+/// ```text
+/// Endpoint=sb://mynamespace.servicebus.windows.net/;SharedAccessKeyName=RootManageSharedAccessKey;SharedAccessKey=...
+/// ```
+///
+/// Optional:
+/// - `SERVICEBUS_ALLOW_SEND` — set to `true` to enable `servicebus_send`.
+///   Disabled by default.
+#[derive(Debug, Clone)]
+pub struct ServiceBusConfig {
+    /// `https://{namespace}.servicebus.windows.net`
+    pub endpoint: String,
+    pub key_name: String,
+    pub key: String,
+    pub allow_send: bool,
+}
+
+impl ServiceBusConfig {
+    /// Parse a Service Bus connection string.
+    fn from_connection_string(value: &str, allow_send: bool) -> Result<Self> {
+        let field = |name: &str| connection_string_field(value, name);
+        let host = namespace_host(value)?;
+        Ok(ServiceBusConfig {
+            endpoint: format!("https://{host}"),
+            key_name: field("SharedAccessKeyName").context("missing SharedAccessKeyName")?,
+            key: field("SharedAccessKey").context("missing SharedAccessKey")?,
+            allow_send,
+        })
+    }
+
+    /// Return an error unless sending was enabled via `SERVICEBUS_ALLOW_SEND`.
+    pub fn require_send(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_send,
+            "Service Bus send is disabled.  Set SERVICEBUS_ALLOW_SEND=true to enable it."
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for publishing events to Azure Event Grid topics.
+///
+/// Set `EVENTGRID_TOPICS` to comma-separated topic endpoints, e.g.
+/// `https://reports.westeurope-1.eventgrid.azure.net/api/events`.  Each topic
+/// is named by the first label of its host (`reports`) and published to with
+/// the access key in `EVENTGRID_KEY_<NAME>` (upper-cased, `-` as `_`) when
+/// set, and with the Azure AD credential otherwise.
+///
+/// Optional:
+/// - `EVENTGRID_ALLOW_PUBLISH` — set to `true` to enable `eventgrid_publish`.
+///   Disabled by default.
+#[derive(Debug, Clone)]
+pub struct EventGridConfig {
+    pub topics: Vec<EventGridTopic>,
+    pub allow_publish: bool,
+}
+
+/// An Event Grid topic events may be published to.
+#[derive(Debug, Clone)]
+pub struct EventGridTopic {
+    pub name: String,
+    pub endpoint: String,
+    pub key: Option<String>,
+}
+
+impl EventGridTopic {
+    /// Name a topic after its endpoint's host and look up its access key.
+    fn from_endpoint(endpoint: &str) -> Result<Self> {
+        let host = endpoint
+            .strip_prefix("https://")
+            .with_context(|| format!("Event Grid topic endpoint {endpoint} is not https"))?;
+        let name = host
+            .split(['.', '/'])
+            .next()
+            .filter(|name| !name.is_empty())
+            .with_context(|| format!("Event Grid topic endpoint {endpoint} has no host"))?;
+        let key_var = format!("EVENTGRID_KEY_{}", name.to_uppercase().replace('-', "_"));
+        Ok(EventGridTopic {
+            name: name.to_string(),
+            endpoint: endpoint.to_string(),
+            key: env::var(key_var).ok().filter(|key| !key.trim().is_empty()),
+        })
+    }
+}
+
+impl EventGridConfig {
+    /// Return an error unless publishing was enabled via
+    /// `EVENTGRID_ALLOW_PUBLISH`.
+    pub fn require_publish(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_publish,
+            "Event Grid publishing is disabled.  Set EVENTGRID_ALLOW_PUBLISH=true to enable it."
+        );
+        Ok(())
+    }
+
+    /// The configured topic called `name`.
+    pub fn topic(&self, name: &str) -> Result<&EventGridTopic> {
+        self.topics
+            .iter()
+            .find(|topic| topic.name.eq_ignore_ascii_case(name))
+            .with_context(|| {
+                let names: Vec<&str> = self.topics.iter().map(|t| t.name.as_str()).collect();
+                format!(
+                    "Unknown Event Grid topic {name}; configured topics: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// Configuration for reading from an Azure Event Hubs namespace through its
+/// Kafka endpoint.
+///
+/// Set `EVENTHUB_CONNECTION_STRING` to a namespace shared access policy
+/// connection string with the Listen right, e.g.
+/// ```text
+/// Endpoint=sb://mynamespace.servicebus.windows.net/;SharedAccessKeyName=listen;SharedAccessKey=...
+/// ```
+///
+/// Optional:
+/// - `EVENTHUB_CONSUMER_GROUP` — consumer group reads are made in (default
+///   `$Default`).  No offsets are ever committed to it.
+#[derive(Debug, Clone)]
+pub struct EventHubsConfig {
+    /// `{namespace}.servicebus.windows.net`
+    pub host: String,
+    pub connection_string: String,
+    pub consumer_group: String,
+}
+
+/// Configuration for a Cosmos DB account with the Gremlin (graph) API.
+///
+/// Required environment variables:
+/// - `GREMLIN_ENDPOINT` — e.g. `wss://myaccount.gremlin.cosmos.azure.com:443/`
+/// - `GREMLIN_KEY` — primary or secondary account key.
+/// - `GREMLIN_DATABASE` — database holding the graphs.
+/// - `GREMLIN_GRAPH` — graph used when a tool is called without `graph`.
+///
+/// Optional:
+/// - `GREMLIN_ALLOW_WRITES` — set to `true` to let `gremlin_query` run
+///   traversals that add, change or drop vertices, edges or properties.
+///   Disabled by default.
+#[derive(Debug, Clone)]
+pub struct GremlinConfig {
+    pub endpoint: String,
+    pub key: String,
+    pub database: String,
+    pub graph: String,
+    pub allow_writes: bool,
+}
+
+impl GremlinConfig {
+    /// Return an error unless graph writes were enabled via `GREMLIN_ALLOW_WRITES`.
+    pub fn require_writes(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_writes,
+            "Gremlin write traversals are disabled.  Set GREMLIN_ALLOW_WRITES=true to enable them."
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for a Cosmos DB account with the Cassandra API.
+///
+/// Required environment variables:
+/// - `CASSANDRA_CONTACT_POINT` — e.g. `myaccount.cassandra.cosmos.azure.com`
+/// - `CASSANDRA_USERNAME` — the account name.
+/// - `CASSANDRA_PASSWORD` — primary or secondary (read-only) key.
+///
+/// Optional:
+/// - `CASSANDRA_PORT` — CQL port (default `10350`).
+/// - `CASSANDRA_KEYSPACE` — keyspace used when `cassandra_query` is called
+///   without `keyspace`.
+#[derive(Debug, Clone)]
+pub struct CassandraConfig {
+    pub contact_point: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub default_keyspace: Option<String>,
+}
+
+/// Configuration for an Azure Data Explorer (Kusto) cluster, queried with
+/// the Azure AD credential.
+///
+/// Set `KUSTO_CLUSTER_URL` to the cluster URI, e.g.
+/// `https://mycluster.westeurope.kusto.windows.net`.
+///
+/// Optional:
+/// - `KUSTO_DATABASE` — database used when a tool is called without
+///   `database`.
+#[derive(Debug, Clone)]
+pub struct KustoConfig {
+    pub cluster_url: String,
+    pub default_database: Option<String>,
+}
+
+/// Configuration for a Microsoft Fabric warehouse or lakehouse SQL analytics
+/// endpoint, logged in to with Azure AD tokens from the credential.
+///
+/// Required environment variables:
+/// - `FABRIC_SQL_ENDPOINT` — the SQL connection string host of the
+///   workspace, e.g. `abc123.datawarehouse.fabric.microsoft.com`.
+/// - `FABRIC_DATABASE` — the warehouse or lakehouse name.
+#[derive(Debug, Clone)]
+pub struct FabricConfig {
+    pub endpoint: String,
+    pub database: String,
+    pub retry: RetryPolicy,
+    pub max_result_bytes: usize,
+    /// Whether warehouse statements that may modify data can run, from
+    /// `FABRIC_ALLOW_WRITES`.
+    pub allow_writes: bool,
+}
+
+impl FabricConfig {
+    /// The TDS connection to the endpoint, logging in with `access_token`.
+    pub fn connection(&self, access_token: String) -> MssqlConfig {
+        MssqlConfig {
+            connection_string: format!(
+                "server=tcp:{},1433;database={{{}}};encrypt=true;trustservercertificate=false",
+                self.endpoint, self.database
+            ),
+            access_token: Some(access_token),
+            retry: self.retry,
+            max_result_bytes: self.max_result_bytes,
+            session_context: SessionContextConfig::default(),
+            plan_limits: None,
+            mask_classified: false,
+            allow_writes: self.allow_writes,
+        }
+    }
+}
+
+/// Configuration for reading secrets from an Azure Key Vault with the Azure
+/// AD credential.
+///
+/// Set `KEYVAULT_URL` to the vault URI, e.g. `https://myvault.vault.azure.net`.
+///
+/// Optional:
+/// - `KEYVAULT_ALLOW_SECRET_VALUES` — set to `true` to let
+///   `keyvault_get_secret_metadata` return secret values when asked.
+///   Disabled by default: only names and attributes are ever returned.
+#[derive(Debug, Clone)]
+pub struct KeyVaultConfig {
+    pub url: String,
+    pub allow_secret_values: bool,
+}
+
+impl KeyVaultConfig {
+    /// Return an error unless secret values were enabled via
+    /// `KEYVAULT_ALLOW_SECRET_VALUES`.
+    pub fn require_secret_values(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.allow_secret_values,
+            "Returning Key Vault secret values is disabled.  Set \
+             KEYVAULT_ALLOW_SECRET_VALUES=true to enable it."
+        );
+        Ok(())
+    }
+}
+
+/// Configuration for reading an Azure App Configuration store.
+///
+/// Set either:
+/// - `APPCONFIG_CONNECTION_STRING` — an access key connection string,
+///   `Endpoint=https://mystore.azconfig.io;Id=...;Secret=...` (a read-only
+///   key is enough), or
+/// - `APPCONFIG_ENDPOINT` — the store URL, e.g. `https://mystore.azconfig.io`,
+///   read with the Azure AD credential.
+#[derive(Debug, Clone)]
+pub struct AppConfigConfig {
+    pub endpoint: String,
+    /// Access key to sign requests with; Azure AD tokens are used when unset.
+    pub access_key: Option<AppConfigAccessKey>,
+}
+
+/// An App Configuration access key: its id and base64 secret.
+#[derive(Debug, Clone)]
+pub struct AppConfigAccessKey {
+    pub id: String,
+    pub secret: String,
+}
+
+impl AppConfigConfig {
+    /// Parse an App Configuration connection string.
+    fn from_connection_string(value: &str) -> Result<Self> {
+        let field = |name: &str| connection_string_field(value, name);
+        Ok(AppConfigConfig {
+            endpoint: field("Endpoint")
+                .context("missing Endpoint")?
+                .trim_end_matches('/')
+                .to_string(),
+            access_key: Some(AppConfigAccessKey {
+                id: field("Id").context("missing Id")?,
+                secret: field("Secret").context("missing Secret")?,
+            }),
+        })
+    }
+}
+
+/// Configuration for embedding text with an Azure OpenAI embedding
+/// deployment.
+///
+/// Set `AZURE_OPENAI_ENDPOINT` to the resource endpoint, e.g.
+/// `https://myopenai.openai.azure.com`, and
+/// `AZURE_OPENAI_EMBEDDING_DEPLOYMENT` to the name of an embedding model
+/// deployment (e.g. of `text-embedding-3-small`).
+///
+/// Optional:
+/// - `AZURE_OPENAI_API_KEY` — key of the resource; the Azure AD credential is
+///   used when unset.
+/// - `AZURE_OPENAI_EMBEDDING_DIMENSIONS` — shorten vectors to this many
+///   dimensions, to match a Cosmos DB vector policy (`text-embedding-3`
+///   models only).
+/// - `AZURE_OPENAI_API_VERSION` — REST API version (default `2024-10-21`).
+#[derive(Debug, Clone)]
+pub struct OpenAiConfig {
+    pub endpoint: String,
+    pub deployment: String,
+    pub api_key: Option<String>,
+    pub dimensions: Option<u32>,
+    pub api_version: String,
+}
+
+/// Azure AD credential used by the tools that call Azure AD-protected APIs
+/// (Log Analytics, Application Insights, Kusto, Cost Management, Key Vault,
+/// Fabric SQL, Azure OpenAI, ...).
+///
+/// Either a service principal:
+/// - `AZURE_TENANT_ID`, `AZURE_CLIENT_ID`, `AZURE_CLIENT_SECRET`
+/// - `AZURE_AUTHORITY_HOST` — optional, for sovereign clouds (default
+///   `https://login.microsoftonline.com`)
+///
+/// or the managed identity of the host, with `AZURE_USE_MANAGED_IDENTITY=true`
+/// (and `AZURE_CLIENT_ID` to pick a user-assigned identity).
+#[derive(Debug, Clone)]
+pub enum CredentialConfig {
+    ClientSecret {
+        authority_host: String,
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+    ManagedIdentity {
+        client_id: Option<String>,
+    },
+}
+
+impl CredentialConfig {
+    fn from_env() -> Option<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let (Some(tenant_id), Some(client_id), Some(client_secret)) = (
+            var("AZURE_TENANT_ID"),
+            var("AZURE_CLIENT_ID"),
+            var("AZURE_CLIENT_SECRET"),
+        ) {
+            tracing::info!(
+                "Azure AD service principal found — Azure Monitor tools will be available"
+            );
+            return Some(CredentialConfig::ClientSecret {
+                authority_host: var("AZURE_AUTHORITY_HOST")
+                    .unwrap_or_else(|| "https://login.microsoftonline.com".into()),
+                tenant_id,
+                client_id,
+                client_secret,
+            });
+        }
+        if env_flag("AZURE_USE_MANAGED_IDENTITY") {
+            tracing::info!("Using the managed identity — Azure Monitor tools will be available");
+            return Some(CredentialConfig::ManagedIdentity {
+                client_id: var("AZURE_CLIENT_ID"),
+            });
+        }
+        None
+    }
+}
+
+/// Defaults for the Azure Monitor tools.
+///
+/// Optional environment variables:
+/// - `LOGANALYTICS_WORKSPACE_ID` — workspace queried when `loganalytics_query`
+///   is called without `workspace_id`.
+/// - `APPINSIGHTS_APP_ID` — Application Insights app queried when
+///   `appinsights_query` is called without `app_id`.
+#[derive(Debug, Clone, Default)]
+pub struct MonitorConfig {
+    pub default_workspace: Option<String>,
+    pub default_app: Option<String>,
+}
+
+/// Where tool calls are audited.
+///
+/// Optional environment variables:
+/// - `AUDIT_LOG_FILE` — path of a JSONL file every tool call is appended to.
+/// - `AUDIT_COSMOS_CONTAINER` — `database/container` of the Cosmos DB account
+///   above every tool call is written to, partitioned on `/session`.
+/// - `AUDIT_CHAIN_KEY` — secret the file's hash chain is keyed with, so
+///   that the chain cannot be rebuilt after an edit without it.
+#[derive(Debug, Clone, Default)]
+pub struct AuditConfig {
+    pub file: Option<String>,
+    /// Database and container of the Cosmos DB audit container.
+    pub cosmos_container: Option<(String, String)>,
+    /// HMAC key of the file's hash chain; plain SHA-256 without one.
+    pub chain_key: Option<String>,
+}
+
+/// Which tool calls are logged as slow.
+///
+/// Environment variables:
+/// - `SLOW_QUERY_MS` — calls taking at least this many milliseconds are
+///   logged as warnings.
+/// - `SLOW_QUERY_LOG_FILE` — optional path of a JSONL file slow calls are
+///   also appended to.
+#[derive(Debug, Clone)]
+pub struct SlowQueryConfig {
+    pub threshold: Duration,
+    pub file: Option<String>,
+}
+
+/// Mock mode, where tools return fixtures instead of calling Azure.
+///
+/// Environment variables:
+/// - `MOCK_MODE` — `true` to enable it; no backend needs to be configured.
+/// - `MOCK_FIXTURES_DIR` — directory holding one `<tool>.json` per tool
+///   (default [`DEFAULT_MOCK_FIXTURES_DIR`]).
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub fixtures: PathBuf,
+}
+
+/// Where fixtures are read from when `MOCK_FIXTURES_DIR` is not set.
+pub const DEFAULT_MOCK_FIXTURES_DIR: &str = "fixtures";
+
+impl MockConfig {
+    fn from_env() -> Result<Option<Self>> {
+        if !env_flag("MOCK_MODE") {
+            return Ok(None);
+        }
+        let fixtures = PathBuf::from(
+            env::var("MOCK_FIXTURES_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .unwrap_or_else(|| DEFAULT_MOCK_FIXTURES_DIR.into()),
+        );
+        anyhow::ensure!(
+            fixtures.is_dir(),
+            "MOCK_FIXTURES_DIR '{}' is not a directory",
+            fixtures.display()
+        );
+        tracing::warn!(
+            "MOCK_MODE found — tools will return the fixtures in {} and no backend will be called",
+            fixtures.display()
+        );
+        Ok(Some(MockConfig { fixtures }))
+    }
+}
+
+/// Recording of the HTTP requests sent to the backends, or replay of a
+/// recording instead of sending them.
+///
+/// Environment variables (at most one of them):
+/// - `BACKEND_RECORD_FILE` — JSONL file every request and its response are
+///   appended to.
+/// - `BACKEND_REPLAY_FILE` — JSONL file written by `BACKEND_RECORD_FILE`
+///   whose responses answer the requests; nothing is sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recording {
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl Recording {
+    fn from_env() -> Result<Option<Self>> {
+        let path = |name: &str| {
+            env::var(name)
+                .ok()
+                .filter(|path| !path.trim().is_empty())
+                .map(PathBuf::from)
+        };
+        let recording = match (path("BACKEND_RECORD_FILE"), path("BACKEND_REPLAY_FILE")) {
+            (Some(_), Some(_)) => {
+                anyhow::bail!("Set BACKEND_RECORD_FILE or BACKEND_REPLAY_FILE, not both")
+            }
+            (Some(path), None) => {
+                tracing::warn!(
+                    "BACKEND_RECORD_FILE found — backend requests and responses will be \
+                     appended to {}; it holds the data they return",
+                    path.display()
+                );
+                Recording::Record(path)
+            }
+            (None, Some(path)) => {
+                anyhow::ensure!(
+                    path.is_file(),
+                    "BACKEND_REPLAY_FILE '{}' is not a file",
+                    path.display()
+                );
+                tracing::warn!(
+                    "BACKEND_REPLAY_FILE found — backend requests will be answered from {} \
+                     and not sent",
+                    path.display()
+                );
+                Recording::Replay(path)
+            }
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(recording))
+    }
+}
+
+/// Deployment-specific text shown to the model, used to document
+/// organisation-specific schemas, naming conventions and policies.
+///
+/// Optional environment variables:
+/// - `MCP_INSTRUCTIONS` — replaces the server instructions sent on
+///   initialization.
+/// - `MCP_INSTRUCTIONS_FILE` — path of a file holding the instructions; takes
+///   precedence over `MCP_INSTRUCTIONS`.
+/// - `MCP_TOOL_DESCRIPTIONS_FILE` — path of a JSON object mapping tool names to
+///   descriptions that replace the built-in ones.
+#[derive(Debug, Clone, Default)]
+pub struct Customization {
+    pub instructions: Option<String>,
+    pub tool_descriptions: HashMap<String, String>,
+}
+
+impl Customization {
+    /// Read the overrides from the environment.  Unreadable files are an
+    /// error rather than silently falling back to the built-in text.
+    fn from_env() -> Result<Self> {
+        let instructions = match env::var("MCP_INSTRUCTIONS_FILE") {
+            Ok(path) => Some(
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("Cannot read MCP_INSTRUCTIONS_FILE '{path}'"))?,
+            ),
+            Err(_) => env::var("MCP_INSTRUCTIONS").ok(),
+        };
+        let tool_descriptions = match env::var("MCP_TOOL_DESCRIPTIONS_FILE") {
+            Ok(path) => {
+                let json = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Cannot read MCP_TOOL_DESCRIPTIONS_FILE '{path}'"))?;
+                parse_tool_descriptions(&json)
+                    .with_context(|| format!("Invalid MCP_TOOL_DESCRIPTIONS_FILE '{path}'"))?
+            }
+            Err(_) => HashMap::new(),
+        };
+        if instructions.is_some() {
+            tracing::info!("Using custom server instructions");
+        }
+        if !tool_descriptions.is_empty() {
+            tracing::info!(
+                "Overriding the descriptions of {} tools",
+                tool_descriptions.len()
+            );
+        }
+        Ok(Customization {
+            instructions: instructions.filter(|text| !text.trim().is_empty()),
+            tool_descriptions,
+        })
+    }
+}
+
+/// The tools a client may call and the limits it gets, so that one binary
+/// can serve differently privileged clients.
+///
+/// Optional environment variables:
+/// - `MCP_ROLE` — the role to serve: `reader`, `analyst`, `admin` (the
+///   default) or one defined in `MCP_ROLES_FILE`.
+/// - `MCP_ROLES_FILE` — path of a JSON object mapping role names to
+///   `{"tools": [...], "max_result_bytes": n, "session_max_request_units": n}`,
+///   defining roles or replacing the built-in ones.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Role {
+    #[serde(skip)]
+    pub name: String,
+    /// Tool names, a trailing `*` matching any suffix; `None` allows every
+    /// tool.
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+    /// Replaces `MAX_RESULT_BYTES`.
+    #[serde(default)]
+    pub max_result_bytes: Option<usize>,
+    /// Replaces `COSMOS_SESSION_MAX_REQUEST_UNITS`.
+    #[serde(default)]
+    pub session_max_request_units: Option<f64>,
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        roles::built_in("admin").expect("admin is a built-in role")
+    }
+}
+
+impl Role {
+    /// Read `MCP_ROLE` and `MCP_ROLES_FILE`.  An unknown role is an error
+    /// rather than a fallback to every tool.
+    fn from_env() -> Result<Self> {
+        let mut defined = match env::var("MCP_ROLES_FILE")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+        {
+            Some(path) => {
+                let json = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Cannot read MCP_ROLES_FILE '{path}'"))?;
+                parse_roles(&json).with_context(|| format!("Invalid MCP_ROLES_FILE '{path}'"))?
+            }
+            None => HashMap::new(),
+        };
+        let Some(name) = env::var("MCP_ROLE")
+            .ok()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+        else {
+            return Ok(defined.remove("admin").unwrap_or_default());
+        };
+        let role = match defined.remove(&name).or_else(|| roles::built_in(&name)) {
+            Some(role) => role,
+            None => {
+                let mut known: Vec<_> = roles::BUILT_IN.iter().map(|n| n.to_string()).collect();
+                known.extend(defined.into_keys());
+                anyhow::bail!("MCP_ROLE '{name}' is not one of {}", known.join(", "));
+            }
+        };
+        tracing::info!(
+            "MCP_ROLE found — serving the {name} role with {}",
+            match &role.tools {
+                Some(tools) => format!("{} tool name(s) allowed", tools.len()),
+                None => "every tool allowed".to_string(),
+            }
+        );
+        Ok(role)
+    }
+
+    /// Whether the role may call `tool`.
+    pub fn allows(&self, tool: &str) -> bool {
+        let Some(tools) = &self.tools else {
+            return true;
+        };
+        tools.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => tool.starts_with(prefix),
+            None => tool == pattern,
+        })
+    }
+}
+
+/// Parse a JSON object mapping role names to their tools and limits.
+fn parse_roles(json: &str) -> Result<HashMap<String, Role>> {
+    let mut roles: HashMap<String, Role> =
+        serde_json::from_str(json).context("expected a JSON object of role name to role")?;
+    for (name, role) in &mut roles {
+        anyhow::ensure!(
+            role.max_result_bytes != Some(0),
+            "max_result_bytes of role '{name}' must be at least 1"
+        );
+        anyhow::ensure!(
+            role.session_max_request_units.is_none_or(|ru| ru > 0.0),
+            "session_max_request_units of role '{name}' must be positive"
+        );
+        role.name = name.clone();
+    }
+    Ok(roles)
+}
+
+/// Format of the log lines written to stderr, from `LOG_FORMAT`: `text`
+/// (the default) for people, `json` for one JSON object per line with the
+/// timestamp, level, message and fields (`tool`, `session`, `duration_ms`,
+/// ...), ready for ingestion into Log Analytics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl LogFormat {
+    /// Read `LOG_FORMAT`.  Called before logging is set up, so nothing is
+    /// logged here.
+    pub fn from_env() -> Result<Self> {
+        match env::var("LOG_FORMAT")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => anyhow::bail!("LOG_FORMAT '{other}' is not text or json"),
+        }
+    }
+}
+
+/// When the log file is rotated on time, from `LOG_FILE_ROTATION`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+/// A log file written next to stderr, for hosts that discard the stderr of
+/// the servers they spawn.
+///
+/// Environment variables:
+/// - `LOG_FILE` — path of the log file.
+/// - `LOG_FILE_ROTATION` — `daily` (default), `hourly` or `never`.
+/// - `LOG_FILE_MAX_BYTES` — optional size at which the file is rotated.
+/// - `LOG_FILE_MAX_FILES` — rotated files kept, oldest removed first
+///   (default 7).
+#[derive(Debug, Clone)]
+pub struct LogFileConfig {
+    pub path: String,
+    pub rotation: LogRotation,
+    pub max_bytes: Option<u64>,
+    pub max_files: usize,
+}
+
+impl LogFileConfig {
+    /// Read `LOG_FILE` and its rotation settings, `None` when `LOG_FILE` is
+    /// unset.  Called before logging is set up, so nothing is logged here.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(path) = env::var("LOG_FILE")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+        else {
+            return Ok(None);
+        };
+        let rotation = match env::var("LOG_FILE_ROTATION")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "" | "daily" => LogRotation::Daily,
+            "hourly" => LogRotation::Hourly,
+            "never" => LogRotation::Never,
+            other => {
+                anyhow::bail!("LOG_FILE_ROTATION '{other}' is not daily, hourly or never")
+            }
+        };
+        let number = |name: &str| -> Result<Option<u64>> {
+            env::var(name)
+                .ok()
+                .filter(|n| !n.trim().is_empty())
+                .map(|n| {
+                    n.trim()
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .with_context(|| format!("{name} '{n}' is not a positive number"))
+                })
+                .transpose()
+        };
+        Ok(Some(LogFileConfig {
+            path,
+            rotation,
+            max_bytes: number("LOG_FILE_MAX_BYTES")?,
+            max_files: number("LOG_FILE_MAX_FILES")?.unwrap_or(7) as usize,
+        }))
+    }
+}
+
+/// How calls to Azure SQL endpoints and Cosmos DB that fail transiently
+/// (throttling, failovers, dropped connections) are retried.
+///
+/// Optional environment variables:
+/// - `RETRY_MAX_ATTEMPTS` — attempts per call, the first included (default
+///   4; `1` disables retries).
+/// - `RETRY_MAX_WAIT_MS` — most time one call spends waiting between
+///   attempts (default 10 000).  A retry that would wait longer is not made.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub max_wait: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            max_wait: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn from_env() -> Result<Self> {
+        let number = |name: &str| -> Result<Option<u64>> {
+            env::var(name)
+                .ok()
+                .filter(|n| !n.trim().is_empty())
+                .map(|n| {
+                    n.trim()
+                        .parse::<u64>()
+                        .with_context(|| format!("{name} '{n}' is not a number"))
+                })
+                .transpose()
+        };
+        let default = Self::default();
+        let max_attempts = match number("RETRY_MAX_ATTEMPTS")? {
+            Some(n) => {
+                anyhow::ensure!(n > 0, "RETRY_MAX_ATTEMPTS must be at least 1");
+                n.min(u32::MAX as u64) as u32
+            }
+            None => default.max_attempts,
+        };
+        let max_wait = number("RETRY_MAX_WAIT_MS")?
+            .map(Duration::from_millis)
+            .unwrap_or(default.max_wait);
+        Ok(RetryPolicy {
+            max_attempts,
+            max_wait,
+        })
+    }
+}
+
+/// Result size at which collecting rows or items stops when
+/// `MAX_RESULT_BYTES` is not set: 64 MiB.
+pub const DEFAULT_MAX_RESULT_BYTES: usize = 64 * 1024 * 1024;
+
+/// The approximate serialized size one SQL or Cosmos DB result may grow to
+/// before the remaining rows or items are left for the next page, from
+/// `MAX_RESULT_BYTES` (default [`DEFAULT_MAX_RESULT_BYTES`]).
+fn max_result_bytes() -> Result<usize> {
+    let Some(n) = env::var("MAX_RESULT_BYTES")
+        .ok()
+        .filter(|n| !n.trim().is_empty())
+    else {
+        return Ok(DEFAULT_MAX_RESULT_BYTES);
+    };
+    let bytes = n
+        .trim()
+        .parse::<usize>()
+        .with_context(|| format!("MAX_RESULT_BYTES '{n}' is not a number"))?;
+    anyhow::ensure!(bytes > 0, "MAX_RESULT_BYTES must be at least 1");
+    Ok(bytes)
+}
+
+/// What one MCP session may use over its lifetime; tools reaching a
+/// backend are refused once one quota is used up.  The Cosmos DB request
+/// unit quota is `COSMOS_SESSION_MAX_REQUEST_UNITS` (see [`CosmosConfig`]).
+///
+/// Optional environment variables:
+/// - `SESSION_MAX_QUERIES` — calls of tools reaching a backend.
+/// - `SESSION_MAX_ROWS` — rows, documents and other records returned.
+/// - `SESSION_MAX_RESULT_BYTES` — approximate bytes of JSON returned.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionQuotas {
+    pub max_queries: Option<u64>,
+    pub max_rows: Option<u64>,
+    pub max_bytes: Option<u64>,
+}
+
+impl SessionQuotas {
+    fn from_env() -> Result<Self> {
+        let quota = |name: &str| -> Result<Option<u64>> {
+            let Some(n) = env::var(name).ok().filter(|n| !n.trim().is_empty()) else {
+                return Ok(None);
+            };
+            let quota = n
+                .trim()
+                .parse::<u64>()
+                .with_context(|| format!("{name} '{n}' is not a number"))?;
+            anyhow::ensure!(quota > 0, "{name} must be at least 1");
+            Ok(Some(quota))
+        };
+        let quotas = SessionQuotas {
+            max_queries: quota("SESSION_MAX_QUERIES")?,
+            max_rows: quota("SESSION_MAX_ROWS")?,
+            max_bytes: quota("SESSION_MAX_RESULT_BYTES")?,
+        };
+        if quotas.max_queries.is_some() || quotas.max_rows.is_some() || quotas.max_bytes.is_some() {
+            tracing::info!(
+                "SESSION_MAX_* found — tools reaching a backend will be refused once a session \
+                 uses up its quota"
+            );
+        }
+        Ok(quotas)
+    }
+}
+
+/// Limits on the calls to a backend in flight at once.
+///
+/// Optional environment variables:
+/// - `MAX_CONCURRENT_QUERIES` — limit applied to every backend.
+/// - `MAX_CONCURRENT_QUERIES_<BACKEND>` — limit for one backend, overriding
+///   the above; `<BACKEND>` is its name in upper case (`MSSQL`, `COSMOS`,
+///   `SYNAPSE`, `KUSTO`, ...).
+///
+/// Unlimited by default.
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyConfig {
+    pub default: Option<usize>,
+    pub backends: HashMap<Backend, usize>,
+}
+
+impl ConcurrencyConfig {
+    /// The limit of `backend`, if any.
+    pub fn limit(&self, backend: Backend) -> Option<usize> {
+        self.backends.get(&backend).copied().or(self.default)
+    }
+
+    fn from_env() -> Result<Self> {
+        let number = |name: &str| -> Result<Option<usize>> {
+            env::var(name)
+                .ok()
+                .filter(|n| !n.trim().is_empty())
+                .map(|n| {
+                    n.trim()
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .with_context(|| format!("{name} '{n}' is not a positive number"))
+                })
+                .transpose()
+        };
+        let default = number("MAX_CONCURRENT_QUERIES")?;
+        let mut backends = HashMap::new();
+        for backend in Backend::ALL {
+            let name = format!("MAX_CONCURRENT_QUERIES_{}", backend.name().to_uppercase());
+            if let Some(limit) = number(&name)? {
+                backends.insert(backend, limit);
+            }
+        }
+        if let Some(limit) = default {
+            tracing::info!(
+                "Concurrency limit found — at most {limit} calls per backend will run at once"
+            );
+        }
+        for (backend, limit) in &backends {
+            tracing::info!(
+                "Concurrency limit found — at most {limit} {} calls will run at once",
+                backend.name()
+            );
+        }
+        Ok(ConcurrencyConfig { default, backends })
+    }
 }
 
 /// Top-level server configuration assembled from environment variables at startup.
 #[derive(Debug, Clone)]
 pub struct Config {
     pub mssql: Option<MssqlConfig>,
+    /// Synapse serverless SQL endpoint, from `SYNAPSE_CONNECTION_STRING`.
+    pub synapse: Option<MssqlConfig>,
+    pub fabric: Option<FabricConfig>,
     pub cosmos: Option<CosmosConfig>,
+    pub gremlin: Option<GremlinConfig>,
+    pub cassandra: Option<CassandraConfig>,
+    pub storage: Option<StorageConfig>,
+    pub servicebus: Option<ServiceBusConfig>,
+    pub eventhubs: Option<EventHubsConfig>,
+    pub eventgrid: Option<EventGridConfig>,
+    pub credential: Option<CredentialConfig>,
+    pub monitor: MonitorConfig,
+    pub kusto: Option<KustoConfig>,
+    pub keyvault: Option<KeyVaultConfig>,
+    pub openai: Option<OpenAiConfig>,
+    pub appconfig: Option<AppConfigConfig>,
+    /// Subscription whose costs `cost_query` reports when given no scope,
+    /// from `AZURE_SUBSCRIPTION_ID`.
+    pub subscription_id: Option<String>,
+    /// Default length text and binary result values are cut to, from
+    /// `MAX_CELL_CHARS`.
+    pub max_cell_chars: Option<usize>,
+    pub audit: AuditConfig,
+    /// Slow call logging, when `SLOW_QUERY_MS` is set.
+    pub slow_queries: Option<SlowQueryConfig>,
+    pub retry: RetryPolicy,
+    /// Approximate size in bytes at which SQL and Cosmos DB results are cut
+    /// short, from `MAX_RESULT_BYTES`.
+    pub max_result_bytes: usize,
+    pub concurrency: ConcurrencyConfig,
+    pub customization: Customization,
+    /// The tools and limits of the client, from `MCP_ROLE`.
+    pub role: Role,
+    /// Words no SQL statement may contain, from `QUERY_DENYLIST`.
+    pub query_denylist: Vec<String>,
+    /// What one session may use, from `SESSION_MAX_*`.
+    pub quotas: SessionQuotas,
+    /// Fixtures returned instead of calling Azure, when `MOCK_MODE` is set.
+    pub mock: Option<MockConfig>,
+    /// Backend HTTP traffic recorded or replayed, from
+    /// `BACKEND_RECORD_FILE` / `BACKEND_REPLAY_FILE`.
+    pub recording: Option<Recording>,
 }
 
 impl Config {
     /// Build configuration from the current process environment.
     ///
-    /// At least one of MSSQL or Cosmos must be configured; returns an error if
+    /// At least one data store or broker must be configured; returns an error if
     /// neither is present.
     pub fn from_env() -> Result<Self> {
-        let mssql = env::var("MSSQL_CONNECTION_STRING").ok().map(|conn| {
-            tracing::info!("MSSQL connection string found — MSSQL tools will be available");
-            MssqlConfig {
-                connection_string: conn,
+        let retry = RetryPolicy::from_env()?;
+        let role = Role::from_env()?;
+        let max_result_bytes = match role.max_result_bytes {
+            Some(bytes) => bytes,
+            None => max_result_bytes()?,
+        };
+        let mssql = env::var("MSSQL_CONNECTION_STRING")
+            .ok()
+            .map(|conn| -> Result<_> {
+                tracing::info!("MSSQL connection string found — MSSQL tools will be available");
+                let allow_writes = env_flag("MSSQL_ALLOW_WRITES");
+                if allow_writes {
+                    tracing::warn!(
+                        "MSSQL_ALLOW_WRITES is enabled — mssql_execute_query may modify data"
+                    );
+                }
+                Ok(MssqlConfig {
+                    connection_string: conn,
+                    access_token: None,
+                    retry,
+                    max_result_bytes,
+                    session_context: session_context()?,
+                    plan_limits: plan_limits()?,
+                    mask_classified: mask_classified(),
+                    allow_writes,
+                })
+            })
+            .transpose()?;
+
+        let synapse = env::var("SYNAPSE_CONNECTION_STRING")
+            .ok()
+            .filter(|conn| !conn.trim().is_empty())
+            .map(|connection_string| {
+                tracing::info!(
+                    "Synapse connection string found — Synapse serverless SQL tools will be \
+                     available"
+                );
+                let allow_writes = env_flag("SYNAPSE_ALLOW_WRITES");
+                if allow_writes {
+                    tracing::warn!(
+                        "SYNAPSE_ALLOW_WRITES is enabled — synapse_execute_query may modify data"
+                    );
+                }
+                MssqlConfig {
+                    connection_string,
+                    access_token: None,
+                    retry,
+                    max_result_bytes,
+                    session_context: SessionContextConfig::default(),
+                    plan_limits: None,
+                    mask_classified: false,
+                    allow_writes,
+                }
+            });
+
+        let cosmos = env::var("COSMOS_ENDPOINT")
+            .ok()
+            .map(|endpoint| -> Result<_> {
+                let key = env::var("COSMOS_KEY").ok();
+                let default_database = env::var("COSMOS_DEFAULT_DATABASE").ok();
+                let allow_writes = env_flag("COSMOS_ALLOW_WRITES");
+                let allow_management = env_flag("COSMOS_ALLOW_MANAGEMENT");
+                let max_request_units = positive_number("COSMOS_MAX_REQUEST_UNITS")?;
+                let session_max_request_units = match role.session_max_request_units {
+                    Some(units) => Some(units),
+                    None => positive_number("COSMOS_SESSION_MAX_REQUEST_UNITS")?,
+                };
+                let analytical = env::var("COSMOS_ANALYTICAL_CONNECTION_STRING")
+                    .ok()
+                    .filter(|conn| !conn.trim().is_empty())
+                    .map(|connection_string| MssqlConfig {
+                        connection_string,
+                        access_token: None,
+                        retry,
+                        max_result_bytes,
+                        session_context: SessionContextConfig::default(),
+                        plan_limits: None,
+                        mask_classified: false,
+                        allow_writes: false,
+                    });
+                let analytical_credential = env::var("COSMOS_ANALYTICAL_CREDENTIAL")
+                    .ok()
+                    .filter(|name| !name.trim().is_empty());
+                anyhow::ensure!(
+                    analytical.is_none() || analytical_credential.is_some(),
+                    "COSMOS_ANALYTICAL_CONNECTION_STRING requires COSMOS_ANALYTICAL_CREDENTIAL, \
+                     the name of a Synapse server credential holding the Cosmos DB key"
+                );
+                let preferred_regions = env::var("COSMOS_PREFERRED_REGIONS")
+                    .map(|v| parse_list(&v))
+                    .unwrap_or_default();
+                let query_parallelism = match env::var("COSMOS_QUERY_PARALLELISM")
+                    .ok()
+                    .filter(|n| !n.trim().is_empty())
+                {
+                    Some(n) => {
+                        let parallelism = n.trim().parse::<usize>().with_context(|| {
+                            format!("COSMOS_QUERY_PARALLELISM '{n}' is not a number")
+                        })?;
+                        anyhow::ensure!(
+                            parallelism > 0,
+                            "COSMOS_QUERY_PARALLELISM must be at least 1"
+                        );
+                        parallelism
+                    }
+                    None => DEFAULT_QUERY_PARALLELISM,
+                };
+                let integrated_cache = match env::var("COSMOS_DEDICATED_GATEWAY_ENDPOINT")
+                    .ok()
+                    .filter(|url| !url.trim().is_empty())
+                {
+                    Some(gateway) => {
+                        let max_staleness = env::var("COSMOS_CACHE_MAX_STALENESS_MS")
+                            .ok()
+                            .filter(|ms| !ms.trim().is_empty())
+                            .map(|ms| {
+                                ms.trim().parse::<u64>().with_context(|| {
+                                    format!("COSMOS_CACHE_MAX_STALENESS_MS '{ms}' is not a number")
+                                })
+                            })
+                            .transpose()?
+                            .map(Duration::from_millis);
+                        tracing::info!(
+                            "Cosmos DB dedicated gateway found — point reads and queries will use \
+                         the integrated cache"
+                        );
+                        Some(IntegratedCacheConfig {
+                            endpoint: gateway.trim().trim_end_matches('/').to_string(),
+                            max_staleness,
+                        })
+                    }
+                    None => None,
+                };
+                let query_policy = CosmosQueryPolicy::from_env();
+                let import_dir = env::var("COSMOS_IMPORT_DIR")
+                    .ok()
+                    .filter(|dir| !dir.trim().is_empty())
+                    .map(|dir| {
+                        std::fs::canonicalize(dir.trim())
+                            .with_context(|| format!("COSMOS_IMPORT_DIR '{dir}' does not exist"))
+                    })
+                    .transpose()?;
+                if key.is_some() {
+                    tracing::info!(
+                        "Cosmos DB endpoint + account key found — Cosmos tools will be available"
+                    );
+                } else {
+                    tracing::warn!(
+                        "COSMOS_ENDPOINT is set but COSMOS_KEY is missing — \
+                     Cosmos DB tools will return an error until COSMOS_KEY is configured"
+                    );
+                }
+                if allow_writes {
+                    tracing::warn!(
+                        "COSMOS_ALLOW_WRITES is enabled — Cosmos DB write tools are available"
+                    );
+                }
+                Ok(CosmosConfig {
+                    endpoint,
+                    key,
+                    default_database,
+                    allow_writes,
+                    allow_management,
+                    max_request_units,
+                    session_max_request_units,
+                    preferred_regions,
+                    query_parallelism,
+                    integrated_cache,
+                    query_policy,
+                    analytical,
+                    analytical_credential,
+                    import_dir,
+                    retry,
+                    max_result_bytes,
+                })
+            })
+            .transpose()?;
+
+        let gremlin = match env::var("GREMLIN_ENDPOINT")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        {
+            Some(endpoint) => {
+                let var = |name: &str| {
+                    env::var(name)
+                        .ok()
+                        .filter(|v| !v.trim().is_empty())
+                        .with_context(|| format!("GREMLIN_ENDPOINT is set but {name} is not"))
+                };
+                let allow_writes = env_flag("GREMLIN_ALLOW_WRITES");
+                tracing::info!("Gremlin endpoint found — Gremlin tools will be available");
+                if allow_writes {
+                    tracing::warn!(
+                        "GREMLIN_ALLOW_WRITES is enabled — gremlin_query may modify the graph"
+                    );
+                }
+                Some(GremlinConfig {
+                    endpoint: endpoint.trim().to_string(),
+                    key: var("GREMLIN_KEY")?,
+                    database: var("GREMLIN_DATABASE")?,
+                    graph: var("GREMLIN_GRAPH")?,
+                    allow_writes,
+                })
+            }
+            None => None,
+        };
+
+        let cassandra = match env::var("CASSANDRA_CONTACT_POINT")
+            .ok()
+            .filter(|host| !host.trim().is_empty())
+        {
+            Some(contact_point) => {
+                let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+                let required = |name: &str| {
+                    var(name).with_context(|| {
+                        format!("CASSANDRA_CONTACT_POINT is set but {name} is not")
+                    })
+                };
+                let port = match var("CASSANDRA_PORT") {
+                    Some(port) => port
+                        .trim()
+                        .parse::<u16>()
+                        .with_context(|| format!("CASSANDRA_PORT '{port}' is not a port"))?,
+                    None => 10350,
+                };
+                tracing::info!("Cassandra contact point found — Cassandra tools will be available");
+                Some(CassandraConfig {
+                    contact_point: contact_point.trim().to_string(),
+                    port,
+                    username: required("CASSANDRA_USERNAME")?,
+                    password: required("CASSANDRA_PASSWORD")?,
+                    default_keyspace: var("CASSANDRA_KEYSPACE"),
+                })
+            }
+            None => None,
+        };
+
+        let storage = env::var("STORAGE_ACCOUNT")
+            .ok()
+            .map(|account| -> Result<_> {
+                let key = env::var("STORAGE_KEY").ok();
+                let allow_writes = env_flag("STORAGE_ALLOW_WRITES");
+                let allow_share = env_flag("STORAGE_ALLOW_SHARE");
+                let share_container = env::var("STORAGE_SHARE_CONTAINER")
+                    .ok()
+                    .map(|container| container.trim().to_string())
+                    .filter(|container| !container.is_empty());
+                anyhow::ensure!(
+                    !allow_share || share_container.is_some(),
+                    "STORAGE_ALLOW_SHARE requires STORAGE_SHARE_CONTAINER, the container whose \
+                 blobs blob_share_url may share"
+                );
+                if key.is_some() {
+                    tracing::info!(
+                        "Storage account + key found — Azure Storage tools will be available"
+                    );
+                } else {
+                    tracing::warn!(
+                        "STORAGE_ACCOUNT is set but STORAGE_KEY is missing — \
+                     Azure Storage tools will return an error until STORAGE_KEY is configured"
+                    );
+                }
+                if allow_writes {
+                    tracing::warn!(
+                        "STORAGE_ALLOW_WRITES is enabled — Azure Storage write tools are available"
+                    );
+                }
+                if allow_share {
+                    tracing::warn!(
+                    "STORAGE_ALLOW_SHARE is enabled — blob_share_url can share blobs of the {} \
+                     container",
+                    share_container.as_deref().unwrap_or_default()
+                );
+                }
+                Ok(StorageConfig {
+                    account,
+                    key,
+                    endpoint_suffix: env::var("STORAGE_ENDPOINT_SUFFIX")
+                        .ok()
+                        .filter(|suffix| !suffix.trim().is_empty())
+                        .unwrap_or_else(|| "core.windows.net".into()),
+                    allow_writes,
+                    allow_share,
+                    share_container,
+                    table_endpoint: env::var("STORAGE_TABLE_ENDPOINT")
+                        .ok()
+                        .filter(|endpoint| !endpoint.trim().is_empty()),
+                    blob_endpoint: env::var("STORAGE_BLOB_ENDPOINT")
+                        .ok()
+                        .filter(|endpoint| !endpoint.trim().is_empty()),
+                })
+            });
+        let storage = storage.transpose()?;
+
+        let servicebus = match env::var("SERVICEBUS_CONNECTION_STRING")
+            .ok()
+            .filter(|conn| !conn.trim().is_empty())
+        {
+            Some(conn) => {
+                let allow_send = env_flag("SERVICEBUS_ALLOW_SEND");
+                let cfg = ServiceBusConfig::from_connection_string(&conn, allow_send)
+                    .context("Invalid SERVICEBUS_CONNECTION_STRING")?;
+                tracing::info!(
+                    "Service Bus connection string found — Service Bus tools will be available"
+                );
+                if allow_send {
+                    tracing::warn!(
+                        "SERVICEBUS_ALLOW_SEND is enabled — servicebus_send is available"
+                    );
+                }
+                Some(cfg)
+            }
+            None => None,
+        };
+
+        let eventhubs = match env::var("EVENTHUB_CONNECTION_STRING")
+            .ok()
+            .filter(|conn| !conn.trim().is_empty())
+        {
+            Some(connection_string) => {
+                let host = namespace_host(&connection_string)
+                    .context("Invalid EVENTHUB_CONNECTION_STRING")?;
+                tracing::info!(
+                    "Event Hubs connection string found — Event Hubs tools will be available"
+                );
+                Some(EventHubsConfig {
+                    host,
+                    connection_string,
+                    consumer_group: env::var("EVENTHUB_CONSUMER_GROUP")
+                        .ok()
+                        .filter(|group| !group.trim().is_empty())
+                        .unwrap_or_else(|| "$Default".into()),
+                })
             }
-        });
+            None => None,
+        };
 
-        let cosmos = env::var("COSMOS_ENDPOINT").ok().map(|endpoint| {
-            let key = env::var("COSMOS_KEY").ok();
-            let default_database = env::var("COSMOS_DEFAULT_DATABASE").ok();
-            if key.is_some() {
+        let credential = CredentialConfig::from_env();
+
+        let eventgrid = match env::var("EVENTGRID_TOPICS")
+            .map(|v| parse_list(&v))
+            .unwrap_or_default()
+        {
+            endpoints if endpoints.is_empty() => None,
+            endpoints => {
+                let topics = endpoints
+                    .iter()
+                    .map(|endpoint| EventGridTopic::from_endpoint(endpoint))
+                    .collect::<Result<Vec<_>>>()
+                    .context("Invalid EVENTGRID_TOPICS")?;
+                anyhow::ensure!(
+                    credential.is_some() || topics.iter().all(|t| t.key.is_some()),
+                    "EVENTGRID_TOPICS has topics without an EVENTGRID_KEY_<NAME> and no Azure AD \
+                     credential is configured"
+                );
+                let allow_publish = env_flag("EVENTGRID_ALLOW_PUBLISH");
                 tracing::info!(
-                    "Cosmos DB endpoint + account key found — Cosmos tools will be available"
+                    "Event Grid topics found — eventgrid_publish will be available{}",
+                    if allow_publish {
+                        ""
+                    } else {
+                        " once EVENTGRID_ALLOW_PUBLISH=true"
+                    }
                 );
-            } else {
+                Some(EventGridConfig {
+                    topics,
+                    allow_publish,
+                })
+            }
+        };
+        let monitor = MonitorConfig {
+            default_workspace: env::var("LOGANALYTICS_WORKSPACE_ID")
+                .ok()
+                .filter(|id| !id.trim().is_empty()),
+            default_app: env::var("APPINSIGHTS_APP_ID")
+                .ok()
+                .filter(|id| !id.trim().is_empty()),
+        };
+
+        let audit = AuditConfig {
+            file: env::var("AUDIT_LOG_FILE")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+            cosmos_container: match env::var("AUDIT_COSMOS_CONTAINER")
+                .ok()
+                .filter(|c| !c.trim().is_empty())
+            {
+                Some(value) => {
+                    let (database, container) = value
+                        .trim()
+                        .split_once('/')
+                        .filter(|(d, c)| !d.is_empty() && !c.is_empty())
+                        .with_context(|| {
+                            format!("AUDIT_COSMOS_CONTAINER '{value}' is not database/container")
+                        })?;
+                    anyhow::ensure!(
+                        cosmos.is_some(),
+                        "AUDIT_COSMOS_CONTAINER is set but no Cosmos DB account is configured \
+                         (set COSMOS_ENDPOINT and COSMOS_KEY)"
+                    );
+                    Some((database.to_string(), container.to_string()))
+                }
+                None => None,
+            },
+            chain_key: env::var("AUDIT_CHAIN_KEY")
+                .ok()
+                .filter(|key| !key.trim().is_empty()),
+        };
+        anyhow::ensure!(
+            audit.chain_key.is_none() || audit.file.is_some(),
+            "AUDIT_CHAIN_KEY is set but AUDIT_LOG_FILE is not"
+        );
+        if let Some(path) = &audit.file {
+            // Fail at startup rather than on the first call.
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Cannot open AUDIT_LOG_FILE '{path}'"))?;
+            tracing::info!("Audit log found — every tool call will be appended to {path}");
+            if audit.chain_key.is_none() {
                 tracing::warn!(
-                    "COSMOS_ENDPOINT is set but COSMOS_KEY is missing — \
-                     Cosmos DB tools will return an error until COSMOS_KEY is configured"
+                    "AUDIT_CHAIN_KEY is not set: the audit log is chained with plain SHA-256, \
+                     which detects edits but not a rewritten chain"
+                );
+            }
+        }
+        if let Some((database, container)) = &audit.cosmos_container {
+            tracing::info!(
+                "Audit container found — every tool call will be written to {database}/{container}"
+            );
+        }
+
+        let slow_query_file = env::var("SLOW_QUERY_LOG_FILE")
+            .ok()
+            .filter(|path| !path.trim().is_empty());
+        let slow_queries = match env::var("SLOW_QUERY_MS")
+            .ok()
+            .filter(|ms| !ms.trim().is_empty())
+        {
+            Some(ms) => {
+                let threshold = ms
+                    .trim()
+                    .parse::<u64>()
+                    .with_context(|| format!("SLOW_QUERY_MS '{ms}' is not a number"))?;
+                if let Some(path) = &slow_query_file {
+                    std::fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .with_context(|| format!("Cannot open SLOW_QUERY_LOG_FILE '{path}'"))?;
+                }
+                tracing::info!(
+                    "Slow query threshold found — tool calls taking {threshold} ms or more will be logged"
+                );
+                Some(SlowQueryConfig {
+                    threshold: Duration::from_millis(threshold),
+                    file: slow_query_file,
+                })
+            }
+            None => {
+                anyhow::ensure!(
+                    slow_query_file.is_none(),
+                    "SLOW_QUERY_LOG_FILE is set but SLOW_QUERY_MS is not"
+                );
+                None
+            }
+        };
+
+        let kusto = match env::var("KUSTO_CLUSTER_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        {
+            Some(url) => {
+                anyhow::ensure!(
+                    credential.is_some(),
+                    "KUSTO_CLUSTER_URL is set but no Azure AD credential is configured \
+                     (set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, or \
+                     AZURE_USE_MANAGED_IDENTITY=true)"
+                );
+                tracing::info!("Kusto cluster URL found — Kusto tools will be available");
+                Some(KustoConfig {
+                    cluster_url: url.trim().trim_end_matches('/').to_string(),
+                    default_database: env::var("KUSTO_DATABASE")
+                        .ok()
+                        .filter(|db| !db.trim().is_empty()),
+                })
+            }
+            None => None,
+        };
+
+        let fabric = match env::var("FABRIC_SQL_ENDPOINT")
+            .ok()
+            .filter(|endpoint| !endpoint.trim().is_empty())
+        {
+            Some(endpoint) => {
+                anyhow::ensure!(
+                    credential.is_some(),
+                    "FABRIC_SQL_ENDPOINT is set but no Azure AD credential is configured \
+                     (set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, or \
+                     AZURE_USE_MANAGED_IDENTITY=true)"
                 );
+                let database = env::var("FABRIC_DATABASE")
+                    .ok()
+                    .filter(|db| !db.trim().is_empty())
+                    .context("FABRIC_SQL_ENDPOINT is set but FABRIC_DATABASE is not")?;
+                tracing::info!("Fabric SQL endpoint found — Fabric SQL tools will be available");
+                let allow_writes = env_flag("FABRIC_ALLOW_WRITES");
+                if allow_writes {
+                    tracing::warn!(
+                        "FABRIC_ALLOW_WRITES is enabled — fabric_execute_query may modify data"
+                    );
+                }
+                let endpoint = endpoint.trim();
+                Some(FabricConfig {
+                    endpoint: endpoint
+                        .strip_prefix("tcp:")
+                        .unwrap_or(endpoint)
+                        .trim_end_matches(",1433")
+                        .to_string(),
+                    database: database.trim().to_string(),
+                    retry,
+                    max_result_bytes,
+                    allow_writes,
+                })
             }
-            CosmosConfig {
-                endpoint,
-                key,
-                default_database,
+            None => None,
+        };
+
+        let keyvault = match env::var("KEYVAULT_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        {
+            Some(url) => {
+                anyhow::ensure!(
+                    credential.is_some(),
+                    "KEYVAULT_URL is set but no Azure AD credential is configured \
+                     (set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, or \
+                     AZURE_USE_MANAGED_IDENTITY=true)"
+                );
+                tracing::info!("Key Vault URL found — Key Vault tools will be available");
+                let allow_secret_values = env_flag("KEYVAULT_ALLOW_SECRET_VALUES");
+                if allow_secret_values {
+                    tracing::warn!(
+                        "KEYVAULT_ALLOW_SECRET_VALUES is enabled — secret values can be returned"
+                    );
+                }
+                Some(KeyVaultConfig {
+                    url: url.trim().trim_end_matches('/').to_string(),
+                    allow_secret_values,
+                })
+            }
+            None => None,
+        };
+
+        let openai = match env::var("AZURE_OPENAI_ENDPOINT")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+        {
+            Some(endpoint) => {
+                let var = |name: &str| env::var(name).ok().filter(|v| !v.trim().is_empty());
+                let deployment = var("AZURE_OPENAI_EMBEDDING_DEPLOYMENT").context(
+                    "AZURE_OPENAI_ENDPOINT is set but AZURE_OPENAI_EMBEDDING_DEPLOYMENT is not",
+                )?;
+                let api_key = var("AZURE_OPENAI_API_KEY");
+                anyhow::ensure!(
+                    api_key.is_some() || credential.is_some(),
+                    "AZURE_OPENAI_ENDPOINT is set but neither AZURE_OPENAI_API_KEY nor an \
+                     Azure AD credential is configured"
+                );
+                let dimensions = var("AZURE_OPENAI_EMBEDDING_DIMENSIONS")
+                    .map(|d| {
+                        d.trim().parse::<u32>().with_context(|| {
+                            format!("AZURE_OPENAI_EMBEDDING_DIMENSIONS '{d}' is not a number")
+                        })
+                    })
+                    .transpose()?;
+                tracing::info!("Azure OpenAI endpoint found — embed_text will be available");
+                Some(OpenAiConfig {
+                    endpoint: endpoint.trim().trim_end_matches('/').to_string(),
+                    deployment: deployment.trim().to_string(),
+                    api_key,
+                    dimensions,
+                    api_version: var("AZURE_OPENAI_API_VERSION")
+                        .unwrap_or_else(|| "2024-10-21".into()),
+                })
+            }
+            None => None,
+        };
+
+        let appconfig = match (
+            env::var("APPCONFIG_CONNECTION_STRING")
+                .ok()
+                .filter(|conn| !conn.trim().is_empty()),
+            env::var("APPCONFIG_ENDPOINT")
+                .ok()
+                .filter(|url| !url.trim().is_empty()),
+        ) {
+            (Some(conn), _) => {
+                let cfg = AppConfigConfig::from_connection_string(&conn)
+                    .context("Invalid APPCONFIG_CONNECTION_STRING")?;
+                tracing::info!(
+                    "App Configuration connection string found — App Configuration tools will \
+                     be available"
+                );
+                Some(cfg)
             }
-        });
+            (None, Some(endpoint)) => {
+                anyhow::ensure!(
+                    credential.is_some(),
+                    "APPCONFIG_ENDPOINT is set but no Azure AD credential is configured \
+                     (set AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, or \
+                     AZURE_USE_MANAGED_IDENTITY=true)"
+                );
+                tracing::info!(
+                    "App Configuration endpoint found — App Configuration tools will be available"
+                );
+                Some(AppConfigConfig {
+                    endpoint: endpoint.trim().trim_end_matches('/').to_string(),
+                    access_key: None,
+                })
+            }
+            (None, None) => None,
+        };
 
+        let mock = MockConfig::from_env()?;
         anyhow::ensure!(
-            mssql.is_some() || cosmos.is_some(),
+            mock.is_some()
+                || mssql.is_some()
+                || synapse.is_some()
+                || cosmos.is_some()
+                || gremlin.is_some()
+                || cassandra.is_some()
+                || storage.is_some()
+                || servicebus.is_some()
+                || eventhubs.is_some()
+                || eventgrid.is_some()
+                || appconfig.is_some()
+                || credential.is_some(),
             "No data-store configuration found.  Set at least one of \
-             MSSQL_CONNECTION_STRING or COSMOS_ENDPOINT."
+             MSSQL_CONNECTION_STRING, SYNAPSE_CONNECTION_STRING, COSMOS_ENDPOINT, \
+             GREMLIN_ENDPOINT, CASSANDRA_CONTACT_POINT, STORAGE_ACCOUNT, SERVICEBUS_CONNECTION_STRING, EVENTHUB_CONNECTION_STRING, \
+             EVENTGRID_TOPICS, APPCONFIG_CONNECTION_STRING or an Azure AD credential (AZURE_CLIENT_SECRET or AZURE_USE_MANAGED_IDENTITY), \
+             or set MOCK_MODE=true."
         );
 
-        Ok(Config { mssql, cosmos })
+        Ok(Config {
+            mssql,
+            synapse,
+            fabric,
+            cosmos,
+            gremlin,
+            cassandra,
+            storage,
+            servicebus,
+            eventhubs,
+            eventgrid,
+            credential,
+            monitor,
+            kusto,
+            keyvault,
+            openai,
+            appconfig,
+            subscription_id: env::var("AZURE_SUBSCRIPTION_ID")
+                .ok()
+                .filter(|id| !id.trim().is_empty()),
+            audit,
+            slow_queries,
+            retry,
+            max_result_bytes,
+            max_cell_chars: env::var("MAX_CELL_CHARS")
+                .ok()
+                .filter(|n| !n.trim().is_empty())
+                .map(|n| {
+                    n.trim()
+                        .parse::<usize>()
+                        .with_context(|| format!("MAX_CELL_CHARS '{n}' is not a number"))
+                })
+                .transpose()?,
+            concurrency: ConcurrencyConfig::from_env()?,
+            customization: Customization::from_env()?,
+            role,
+            query_denylist: query_denylist()?,
+            quotas: SessionQuotas::from_env()?,
+            mock,
+            recording: Recording::from_env()?,
+        })
     }
 
     /// Convenience: return a reference to the MSSQL config or an error.
@@ -84,6 +1971,100 @@ impl Config {
             .context("MSSQL is not configured (MSSQL_CONNECTION_STRING not set)")
     }
 
+    /// Convenience: return a reference to the Storage config or an error.
+    pub fn require_storage(&self) -> Result<&StorageConfig> {
+        self.storage
+            .as_ref()
+            .context("Azure Storage is not configured (STORAGE_ACCOUNT not set)")
+    }
+
+    /// Convenience: return a reference to the Service Bus config or an error.
+    pub fn require_servicebus(&self) -> Result<&ServiceBusConfig> {
+        self.servicebus
+            .as_ref()
+            .context("Service Bus is not configured (SERVICEBUS_CONNECTION_STRING not set)")
+    }
+
+    /// Convenience: return a reference to the Event Hubs config or an error.
+    pub fn require_eventhubs(&self) -> Result<&EventHubsConfig> {
+        self.eventhubs
+            .as_ref()
+            .context("Event Hubs is not configured (EVENTHUB_CONNECTION_STRING not set)")
+    }
+
+    /// Convenience: return a reference to the Event Grid config or an error.
+    pub fn require_eventgrid(&self) -> Result<&EventGridConfig> {
+        self.eventgrid
+            .as_ref()
+            .context("Event Grid is not configured (EVENTGRID_TOPICS not set)")
+    }
+
+    /// Convenience: return the Synapse serverless SQL config or an error.
+    pub fn require_synapse(&self) -> Result<&MssqlConfig> {
+        self.synapse
+            .as_ref()
+            .context("Synapse serverless SQL is not configured (SYNAPSE_CONNECTION_STRING not set)")
+    }
+
+    /// Convenience: return a reference to the Fabric SQL config or an error.
+    pub fn require_fabric(&self) -> Result<&FabricConfig> {
+        self.fabric
+            .as_ref()
+            .context("Fabric SQL is not configured (FABRIC_SQL_ENDPOINT not set)")
+    }
+
+    /// Convenience: return a reference to the App Configuration config or an
+    /// error.
+    pub fn require_appconfig(&self) -> Result<&AppConfigConfig> {
+        self.appconfig.as_ref().context(
+            "App Configuration is not configured \
+             (APPCONFIG_CONNECTION_STRING or APPCONFIG_ENDPOINT not set)",
+        )
+    }
+
+    /// Convenience: return a reference to the Gremlin config or an error.
+    pub fn require_gremlin(&self) -> Result<&GremlinConfig> {
+        self.gremlin
+            .as_ref()
+            .context("Gremlin is not configured (GREMLIN_ENDPOINT not set)")
+    }
+
+    /// Convenience: return a reference to the Cassandra config or an error.
+    pub fn require_cassandra(&self) -> Result<&CassandraConfig> {
+        self.cassandra
+            .as_ref()
+            .context("Cassandra is not configured (CASSANDRA_CONTACT_POINT not set)")
+    }
+
+    /// Convenience: return a reference to the Kusto config or an error.
+    pub fn require_kusto(&self) -> Result<&KustoConfig> {
+        self.kusto
+            .as_ref()
+            .context("Kusto is not configured (KUSTO_CLUSTER_URL not set)")
+    }
+
+    /// Convenience: return a reference to the Key Vault config or an error.
+    pub fn require_keyvault(&self) -> Result<&KeyVaultConfig> {
+        self.keyvault
+            .as_ref()
+            .context("Key Vault is not configured (KEYVAULT_URL not set)")
+    }
+
+    /// Convenience: return a reference to the Azure OpenAI config or an error.
+    pub fn require_openai(&self) -> Result<&OpenAiConfig> {
+        self.openai
+            .as_ref()
+            .context("Azure OpenAI is not configured (AZURE_OPENAI_ENDPOINT not set)")
+    }
+
+    /// Convenience: return the Azure AD credential or an error.
+    pub fn require_credential(&self) -> Result<&CredentialConfig> {
+        self.credential.as_ref().context(
+            "No Azure AD credential is configured (set AZURE_TENANT_ID, AZURE_CLIENT_ID and \
+             AZURE_CLIENT_SECRET, or AZURE_USE_MANAGED_IDENTITY=true)",
+        )
+    }
+
     /// Convenience: return a reference to the Cosmos config or an error.
     pub fn require_cosmos(&self) -> Result<&CosmosConfig> {
         self.cosmos
@@ -91,3 +2072,414 @@ impl Config {
             .context("Cosmos DB is not configured (COSMOS_ENDPOINT not set)")
     }
 }
+
+/// Read a positive number, e.g. a limit, from the environment.  `None`
+/// when the variable is unset or empty; an error when it is not a positive
+/// number.
+fn positive_number(name: &str) -> Result<Option<f64>> {
+    let Some(n) = env::var(name).ok().filter(|n| !n.trim().is_empty()) else {
+        return Ok(None);
+    };
+    let number = n
+        .trim()
+        .parse::<f64>()
+        .with_context(|| format!("{name} '{n}' is not a number"))?;
+    anyhow::ensure!(number > 0.0, "{name} must be positive");
+    Ok(Some(number))
+}
+
+/// Read a boolean feature flag from the environment.
+///
+/// Unset variables are treated as `false`.
+fn env_flag(name: &str) -> bool {
+    env::var(name).map(|v| parse_flag(&v)).unwrap_or(false)
+}
+
+/// Interpret `true`/`1`/`yes`/`on` (case-insensitive) as enabled.
+fn parse_flag(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "yes" | "on"
+    )
+}
+
+/// The value of `name` in a `Key=value;Key=value` connection string.
+fn connection_string_field(value: &str, name: &str) -> Option<String> {
+    value.split(';').find_map(|part| {
+        let (key, value) = part.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// The namespace host of a Service Bus or Event Hubs connection string's
+/// `Endpoint=sb://{host}/`.
+fn namespace_host(connection_string: &str) -> Result<String> {
+    let endpoint =
+        connection_string_field(connection_string, "Endpoint").context("missing Endpoint")?;
+    Ok(endpoint
+        .trim_start_matches("sb://")
+        .trim_start_matches("https://")
+        .trim_end_matches('/')
+        .to_string())
+}
+
+/// Parse a JSON object mapping tool names to descriptions.
+fn parse_tool_descriptions(json: &str) -> Result<HashMap<String, String>> {
+    let descriptions: HashMap<String, String> =
+        serde_json::from_str(json).context("expected a JSON object of tool name to description")?;
+    Ok(descriptions
+        .into_iter()
+        .filter(|(_, description)| !description.trim().is_empty())
+        .collect())
+}
+
+/// The `SESSION_CONTEXT` values of MSSQL connections, from
+/// `MSSQL_SESSION_CONTEXT` and `MSSQL_SESSION_CONTEXT_CLIENT_KEYS`.
+fn session_context() -> Result<SessionContextConfig> {
+    let values = match env::var("MSSQL_SESSION_CONTEXT") {
+        Ok(v) => parse_session_context(&v)?,
+        Err(_) => Vec::new(),
+    };
+    let client_keys = env::var("MSSQL_SESSION_CONTEXT_CLIENT_KEYS")
+        .map(|v| parse_list(&v))
+        .unwrap_or_default();
+    for key in &client_keys {
+        ensure_session_context_key(key)?;
+    }
+    if !values.is_empty() || !client_keys.is_empty() {
+        tracing::info!(
+            "MSSQL_SESSION_CONTEXT found — {} value(s) and {} client key(s) will be set on \
+             every MSSQL connection",
+            values.len(),
+            client_keys.len()
+        );
+    }
+    Ok(SessionContextConfig {
+        values,
+        client_keys,
+    })
+}
+
+/// The estimated plan limits of MSSQL queries, from
+/// `MSSQL_MAX_ESTIMATED_ROWS` and `MSSQL_MAX_ESTIMATED_COST`; `None` when
+/// neither is set.
+fn plan_limits() -> Result<Option<PlanLimits>> {
+    let limits = PlanLimits {
+        max_rows: positive_number("MSSQL_MAX_ESTIMATED_ROWS")?,
+        max_cost: positive_number("MSSQL_MAX_ESTIMATED_COST")?,
+    };
+    if limits.max_rows.is_none() && limits.max_cost.is_none() {
+        return Ok(None);
+    }
+    tracing::info!(
+        "MSSQL_MAX_ESTIMATED_ROWS/COST found — MSSQL queries over the estimated plan limits \
+         will be refused"
+    );
+    Ok(Some(limits))
+}
+
+/// Whether MSSQL query results mask columns classified as confidential:
+/// unless `MSSQL_MASK_CLASSIFIED_COLUMNS` opts out.
+fn mask_classified() -> bool {
+    let mask = env::var("MSSQL_MASK_CLASSIFIED_COLUMNS")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .is_none_or(|v| parse_flag(&v));
+    if !mask {
+        tracing::warn!(
+            "MSSQL_MASK_CLASSIFIED_COLUMNS is off — columns classified as confidential \
+             are returned unmasked"
+        );
+    }
+    mask
+}
+
+/// The words SQL statements may not contain, from the comma-separated
+/// `QUERY_DENYLIST`, e.g. `xp_cmdshell,sp_configure,OPENROWSET`.
+fn query_denylist() -> Result<Vec<String>> {
+    let denylist = parse_list(&env::var("QUERY_DENYLIST").unwrap_or_default());
+    for word in &denylist {
+        anyhow::ensure!(
+            word.chars().all(readonly::is_word_char),
+            "QUERY_DENYLIST entry '{word}' is not a single word"
+        );
+    }
+    if !denylist.is_empty() {
+        tracing::info!(
+            "QUERY_DENYLIST found — SQL statements containing {} will be refused",
+            denylist.join(", ")
+        );
+    }
+    Ok(denylist)
+}
+
+/// Parse comma-separated `key=value` pairs.
+fn parse_session_context(value: &str) -> Result<Vec<(String, String)>> {
+    parse_list(value)
+        .iter()
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').with_context(|| {
+                format!("MSSQL_SESSION_CONTEXT entry '{pair}' is not key=value")
+            })?;
+            let key = key.trim();
+            ensure_session_context_key(key)?;
+            Ok((key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// `SESSION_CONTEXT` keys are `sysname`: at most 128 characters.
+fn ensure_session_context_key(key: &str) -> Result<()> {
+    anyhow::ensure!(
+        !key.is_empty() && key.chars().count() <= 128,
+        "SESSION_CONTEXT key '{key}' must be 1 to 128 characters"
+    );
+    Ok(())
+}
+
+/// Split a comma-separated list, trimming entries and dropping empty ones.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+impl MssqlConfig {
+    /// An MSSQL configuration of `connection_string` with every other
+    /// setting at its default.
+    pub fn for_tests(connection_string: &str) -> Self {
+        MssqlConfig {
+            connection_string: connection_string.into(),
+            access_token: None,
+            retry: RetryPolicy::default(),
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+            session_context: SessionContextConfig::default(),
+            plan_limits: None,
+            mask_classified: true,
+            allow_writes: false,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// A configuration without backends and with every other setting at its
+    /// default, for tests to fill in.
+    pub fn for_tests() -> Self {
+        Config {
+            mssql: None,
+            synapse: None,
+            fabric: None,
+            cosmos: None,
+            storage: None,
+            servicebus: None,
+            eventhubs: None,
+            eventgrid: None,
+            credential: None,
+            monitor: MonitorConfig::default(),
+            kusto: None,
+            keyvault: None,
+            openai: None,
+            appconfig: None,
+            gremlin: None,
+            cassandra: None,
+            subscription_id: None,
+            max_cell_chars: None,
+            audit: AuditConfig::default(),
+            slow_queries: None,
+            retry: RetryPolicy::default(),
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+            concurrency: ConcurrencyConfig::default(),
+            customization: Customization::default(),
+            role: Role::default(),
+            query_denylist: Vec::new(),
+            quotas: SessionQuotas::default(),
+            mock: None,
+            recording: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Held by the tests that set environment variables.
+    static ENV: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn parse_flag_accepts_common_truthy_values() {
+        for v in ["true", "TRUE", "1", "yes", " on "] {
+            assert!(parse_flag(v), "{v:?} should be truthy");
+        }
+        for v in ["", "false", "0", "no", "enabled"] {
+            assert!(!parse_flag(v), "{v:?} should be falsy");
+        }
+    }
+
+    #[test]
+    fn event_grid_topics_are_named_after_their_host() {
+        let topic = EventGridTopic::from_endpoint(
+            "https://reports.westeurope-1.eventgrid.azure.net/api/events",
+        )
+        .unwrap();
+        assert_eq!(topic.name, "reports");
+        assert!(EventGridTopic::from_endpoint("http://reports.example.com/api/events").is_err());
+
+        let config = EventGridConfig {
+            topics: vec![topic],
+            allow_publish: false,
+        };
+        assert!(config.topic("Reports").is_ok());
+        assert!(config.topic("alerts").is_err());
+        assert!(config.require_publish().is_err());
+    }
+
+    #[test]
+    fn parse_list_trims_and_skips_empty_entries() {
+        assert_eq!(
+            parse_list(" West Europe, North Europe ,,"),
+            vec!["West Europe".to_string(), "North Europe".to_string()]
+        );
+        assert!(parse_list("").is_empty());
+    }
+
+    #[test]
+    fn parse_session_context_reads_key_value_pairs() {
+        assert_eq!(
+            parse_session_context(" tenant_id = 42, region=emea ,").unwrap(),
+            vec![
+                ("tenant_id".to_string(), "42".to_string()),
+                ("region".to_string(), "emea".to_string()),
+            ]
+        );
+        assert!(parse_session_context("tenant_id").is_err());
+        assert!(parse_session_context("=42").is_err());
+    }
+
+    #[test]
+    fn parse_roles_reads_tools_and_limits() {
+        let roles = parse_roles(
+            r#"{"support": {"tools": ["mssql_*", "server_status"], "max_result_bytes": 1024}}"#,
+        )
+        .unwrap();
+        let support = &roles["support"];
+        assert_eq!(support.name, "support");
+        assert_eq!(support.max_result_bytes, Some(1024));
+        assert!(support.allows("mssql_execute_query"));
+        assert!(support.allows("server_status"));
+        assert!(!support.allows("server_stats"));
+        assert!(parse_roles(r#"{"x": {"tool": []}}"#).is_err());
+        assert!(parse_roles(r#"{"x": {"max_result_bytes": 0}}"#).is_err());
+    }
+
+    #[test]
+    fn parse_tool_descriptions_reads_a_name_map() {
+        let descriptions = parse_tool_descriptions(
+            r#"{"mssql_execute_query": "Query the sales warehouse.", "cosmos_read_item": " "}"#,
+        )
+        .unwrap();
+        assert_eq!(descriptions.len(), 1);
+        assert_eq!(
+            descriptions["mssql_execute_query"],
+            "Query the sales warehouse."
+        );
+        assert!(parse_tool_descriptions(r#"["mssql_execute_query"]"#).is_err());
+    }
+
+    #[test]
+    fn servicebus_connection_string_is_parsed() {
+        let cfg = ServiceBusConfig::from_connection_string(
+            "Endpoint=sb://example.servicebus.windows.net/;SharedAccessKeyName=listen;\
+             SharedAccessKey=abc=",
+            false,
+        )
+        .unwrap();
+        assert_eq!(cfg.endpoint, "https://example.servicebus.windows.net");
+        assert_eq!(cfg.key_name, "listen");
+        assert_eq!(cfg.key, "abc=");
+        assert!(cfg.require_send().is_err());
+        assert!(ServiceBusConfig::from_connection_string("Endpoint=sb://x/", false).is_err());
+    }
+
+    #[test]
+    fn request_unit_budget_takes_the_stricter_limit() {
+        let mut cfg = CosmosConfig {
+            endpoint: "https://example.documents.azure.com:443/".into(),
+            key: None,
+            default_database: None,
+            allow_writes: false,
+            allow_management: false,
+            max_request_units: None,
+            session_max_request_units: None,
+            preferred_regions: Vec::new(),
+            query_parallelism: DEFAULT_QUERY_PARALLELISM,
+            integrated_cache: None,
+            query_policy: CosmosQueryPolicy::default(),
+            analytical: None,
+            analytical_credential: None,
+            import_dir: None,
+            retry: RetryPolicy::default(),
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+        };
+        assert_eq!(cfg.request_unit_budget(None), None);
+        assert_eq!(cfg.request_unit_budget(Some(50.0)), Some(50.0));
+
+        cfg.max_request_units = Some(100.0);
+        assert_eq!(cfg.request_unit_budget(None), Some(100.0));
+        assert_eq!(cfg.request_unit_budget(Some(50.0)), Some(50.0));
+        assert_eq!(cfg.request_unit_budget(Some(500.0)), Some(100.0));
+    }
+
+    #[test]
+    fn sharing_is_limited_to_the_share_container() {
+        let mut cfg = StorageConfig {
+            account: "account".into(),
+            key: None,
+            endpoint_suffix: "core.windows.net".into(),
+            allow_writes: false,
+            allow_share: false,
+            share_container: Some("exports".into()),
+            table_endpoint: None,
+            blob_endpoint: None,
+        };
+        assert!(cfg.require_share("exports").is_err());
+
+        cfg.allow_share = true;
+        assert!(cfg.require_share("exports").is_ok());
+        assert!(cfg.require_share("private").is_err());
+    }
+
+    #[test]
+    fn empty_analytical_variables_are_unset() {
+        let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        env::set_var("COSMOS_ENDPOINT", "https://localhost:8081/");
+        env::set_var("COSMOS_ANALYTICAL_CONNECTION_STRING", "");
+        env::set_var("COSMOS_ANALYTICAL_CREDENTIAL", " ");
+        let config = Config::from_env().unwrap();
+        let cosmos = config.cosmos.unwrap();
+        assert!(cosmos.analytical.is_none());
+        assert!(cosmos.analytical_credential.is_none());
+    }
+
+    #[test]
+    fn the_sample_environment_is_a_valid_configuration() {
+        let _env = ENV.lock().unwrap_or_else(|e| e.into_inner());
+        let sample = include_str!("../.env-sample");
+        for line in sample.lines().filter(|line| !line.starts_with('#')) {
+            if let Some((name, value)) = line.split_once('=') {
+                env::set_var(name.trim(), value.trim());
+            }
+        }
+        let config = Config::from_env().unwrap();
+        assert_eq!(config.role.name, "admin");
+        assert!(config.cosmos.unwrap().analytical.is_none());
+    }
+}
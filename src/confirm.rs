@@ -0,0 +1,55 @@
+use rmcp::{elicit_safe, service::ElicitationError, Peer, RoleServer};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// The user's answer to a confirmation request.
+#[derive(Debug, Deserialize, JsonSchema)]
+struct Confirmation {
+    /// Whether to go ahead with the operation.
+    confirm: bool,
+}
+
+elicit_safe!(Confirmation);
+
+/// The text shown to the user when asking to confirm `action` on `target`.
+fn confirmation_message(action: &str, target: &str, statement: &str) -> String {
+    format!(
+        "An assistant wants to {action} on {target}:\n\n{statement}\n\n\
+         Nothing is changed unless you confirm."
+    )
+}
+
+/// Ask the user, through MCP elicitation, to confirm an operation that
+/// modifies data or resources, showing the `statement` about to run against
+/// `target`.
+///
+/// Returns an error, meant to be passed back as the tool result, unless the
+/// user accepts.  Clients that do not support elicitation cannot ask, so
+/// the operation goes ahead; the `COSMOS_ALLOW_*` flags still gate it.
+pub async fn confirm(
+    peer: &Peer<RoleServer>,
+    action: &str,
+    target: &str,
+    statement: &str,
+) -> Result<(), String> {
+    let message = confirmation_message(action, target, statement);
+    match peer.elicit::<Confirmation>(message).await {
+        Ok(Some(Confirmation { confirm: true })) => Ok(()),
+        Err(ElicitationError::CapabilityNotSupported) => {
+            tracing::warn!(
+                "Client does not support elicitation; running `{action}` on {target} \
+                 without confirmation"
+            );
+            Ok(())
+        }
+        Ok(_)
+        | Err(
+            ElicitationError::UserDeclined
+            | ElicitationError::UserCancelled
+            | ElicitationError::NoContent,
+        ) => Err(format!(
+            "The user did not confirm: {action} on {target}.  Nothing was changed."
+        )),
+        Err(e) => Err(format!("Could not ask the user for confirmation: {e}")),
+    }
+}
@@ -0,0 +1,38 @@
+use std::future::Future;
+
+tokio::task_local! {
+    /// The correlation id of the tool call being handled.
+    static CORRELATION_ID: String;
+}
+
+/// Key of the correlation id in a result's `_meta`.
+pub const META_KEY: &str = "correlationId";
+
+/// A new correlation id for a tool call.
+pub fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Run `future` with `id` as the current correlation id.  Tasks it spawns
+/// do not inherit it.
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    CORRELATION_ID.scope(id, future).await
+}
+
+/// The correlation id of the tool call being handled, if any.
+pub fn current() -> Option<String> {
+    CORRELATION_ID.try_with(String::clone).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn the_id_is_visible_inside_the_scope_only() {
+        assert_eq!(current(), None);
+        let id = scope("abc".into(), async { current() }).await;
+        assert_eq!(id.as_deref(), Some("abc"));
+        assert_eq!(current(), None);
+    }
+}
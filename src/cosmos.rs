@@ -1,40 +1,245 @@
 use anyhow::{bail, Context, Result};
 use azure_core::credentials::Secret;
-use azure_data_cosmos::{CosmosClient, Query};
-use futures::TryStreamExt;
+use azure_core::http::headers::HeaderName;
+use azure_data_cosmos::{
+    models::{ContainerProperties, PartitionKeyDefinition, PatchDocument, ThroughputProperties},
+    CosmosClient, CreateContainerOptions, CreateDatabaseOptions, PartitionKey, Query,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
 
+use crate::budget::json_size;
 use crate::config::CosmosConfig;
+use crate::cosmos_rest::{
+    ChangeFeedStart, CosmosRestClient, PageRequest, QueryScope, RestResponse,
+};
+use crate::recording;
 
 /// Default number of items returned when the caller does not specify `max_items`.
 pub const DEFAULT_MAX_ITEMS: u32 = 100;
 /// Hard upper limit on items to prevent runaway reads.
 pub const HARD_MAX_ITEMS: u32 = 5_000;
 
-/// Build a `CosmosClient` from the supplied configuration.
+/// Hard upper limit on documents accepted by a single [`bulk_import`] call.
+pub const HARD_MAX_IMPORT_DOCUMENTS: usize = 10_000;
+/// Default number of transactional batches written concurrently.
+pub const DEFAULT_IMPORT_CONCURRENCY: usize = 4;
+/// Upper limit on concurrent batches, to avoid saturating the account's RUs.
+const HARD_MAX_IMPORT_CONCURRENCY: usize = 16;
+/// Maximum operations the service accepts in one transactional batch.
+const MAX_BATCH_OPERATIONS: usize = 100;
+
+/// Default number of results returned by a search tool.
+pub const DEFAULT_TOP_K: u32 = 10;
+/// Hard upper limit on results returned by a search tool.
+const HARD_MAX_TOP_K: u32 = 100;
+
+/// Header carrying the session token of a write, replayed on later reads.
+const SESSION_TOKEN_HEADER: HeaderName = HeaderName::from_static("x-ms-session-token");
+
+/// Request-unit accounting aggregated across every page of a query.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueryMetadata {
+    /// Total request units (RUs) charged for the pages fetched.
+    pub request_charge: f64,
+    /// Activity IDs of each page request, for correlating with Azure diagnostics.
+    pub activity_ids: Vec<String>,
+    /// Number of result pages fetched from the service.
+    pub page_count: u32,
+    /// Server-side execution metrics, present only when requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_metrics: Option<QueryMetrics>,
+    /// Request unit budget applied to this call, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_unit_budget: Option<f64>,
+    /// `true` when iteration stopped early because the budget was spent.
+    pub budget_exhausted: bool,
+    /// `true` when iteration stopped early because the items collected
+    /// reached `MAX_RESULT_BYTES`.
+    pub memory_budget_exhausted: bool,
+    /// Requests answered from the integrated cache of the dedicated gateway,
+    /// present only when reads go through one.  Cache hits charge no request
+    /// units.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrated_cache_hits: Option<u32>,
+    /// Approximate serialized size of the items collected so far.
+    #[serde(skip)]
+    result_bytes: usize,
+    /// Items collected so far by every scope of the query.
+    #[serde(skip)]
+    item_count: usize,
+    /// Items the scopes of a cross-partition query may collect between
+    /// them; they all stop once it is reached.
+    #[serde(skip)]
+    shared_item_limit: Option<usize>,
+}
+
+/// Cosmos DB query execution metrics aggregated across result pages.
 ///
-/// Key-based authentication is used when `COSMOS_KEY` is set.  For managed
-/// identity / Azure AD authentication, use the Azure CLI (`az login`) or set
-/// the standard Azure environment variables and run the server with an
-/// `azure_identity`-capable host that exports compatible credentials.
-fn build_client(cfg: &CosmosConfig) -> Result<CosmosClient> {
-    if let Some(key) = &cfg.key {
-        CosmosClient::with_key(&cfg.endpoint, Secret::from(key.clone()), None)
-            .context("Failed to create Cosmos DB client with account key")
-    } else {
-        bail!(
-            "Cosmos DB authentication requires COSMOS_KEY to be set. \
-             Managed identity support can be added by setting COSMOS_KEY to \
-             your Cosmos DB account key."
-        )
+/// Built from the `x-ms-documentdb-query-metrics` response header, which the
+/// service only returns when metrics are requested.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct QueryMetrics {
+    /// Total server-side execution time in milliseconds.
+    pub total_execution_time_ms: f64,
+    /// Documents loaded by the query engine.
+    pub retrieved_document_count: f64,
+    /// Documents returned to the caller.
+    pub output_document_count: f64,
+    /// Fraction of retrieved documents that matched via the index (0–1).
+    /// Values well below 1 indicate filters the index cannot serve.
+    pub index_hit_ratio: f64,
+    /// Every metric reported by the service, summed across pages.
+    pub raw: BTreeMap<String, f64>,
+}
+
+impl QueryMetrics {
+    /// Fold one page's metrics header into the aggregate.
+    fn add_page(&mut self, header: &str) {
+        let page: BTreeMap<String, f64> = parse_semicolon_pairs(header);
+        let retrieved = page.get("retrievedDocumentCount").copied().unwrap_or(0.0);
+        let ratio = page.get("indexUtilizationRatio").copied().unwrap_or(0.0);
+
+        // The utilisation ratio is per page, so weight it by the documents
+        // each page retrieved rather than summing it.
+        let hits = self.index_hit_ratio * self.retrieved_document_count + ratio * retrieved;
+        for (name, value) in page {
+            if name != "indexUtilizationRatio" {
+                *self.raw.entry(name).or_default() += value;
+            }
+        }
+
+        let metric = |name: &str| self.raw.get(name).copied().unwrap_or(0.0);
+        self.total_execution_time_ms = metric("totalExecutionTimeInMs");
+        self.retrieved_document_count = metric("retrievedDocumentCount");
+        self.output_document_count = metric("outputDocumentCount");
+        self.index_hit_ratio = if self.retrieved_document_count > 0.0 {
+            hits / self.retrieved_document_count
+        } else {
+            ratio
+        };
+    }
+}
+
+impl QueryMetadata {
+    /// Start accounting for a call limited to `budget` request units.
+    pub fn with_budget(budget: Option<f64>) -> Self {
+        Self {
+            request_unit_budget: budget,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the cumulative charge has reached the request unit budget.
+    ///
+    /// Marks the metadata as `budget_exhausted` the first time it returns
+    /// `true`.
+    pub fn over_budget(&mut self) -> bool {
+        if let Some(budget) = self.request_unit_budget {
+            if self.request_charge >= budget {
+                self.budget_exhausted = true;
+            }
+        }
+        self.budget_exhausted
+    }
+
+    /// Count the newly collected `items` toward the `max_bytes` budget on
+    /// the size of a result (see [`json_size`]), and return whether the
+    /// items collected so far have reached it.
+    ///
+    /// Marks the metadata as `memory_budget_exhausted` the first time it
+    /// returns `true`.
+    pub fn over_memory_budget(&mut self, items: &[Value], max_bytes: usize) -> bool {
+        self.result_bytes += items.iter().map(json_size).sum::<usize>();
+        if self.result_bytes >= max_bytes {
+            self.memory_budget_exhausted = true;
+        }
+        self.memory_budget_exhausted
+    }
+
+    pub(crate) fn record_values(&mut self, charge: Option<&str>, activity_id: Option<&str>) {
+        self.page_count += 1;
+        if let Some(charge) = charge.and_then(|c| c.trim().parse::<f64>().ok()) {
+            self.request_charge += charge;
+        }
+        if let Some(id) = activity_id.filter(|id| !id.is_empty()) {
+            self.activity_ids.push(id.to_string());
+        }
+    }
+
+    /// Record a REST page: charge, activity ID and (when present) metrics.
+    pub(crate) fn record_response(&mut self, page: &RestResponse) {
+        self.record_values(page.request_charge(), page.activity_id());
+        if let Some(hit) = page.cache_hit() {
+            *self.integrated_cache_hits.get_or_insert(0) += u32::from(hit);
+        }
+        if let Some(metrics) = page.query_metrics() {
+            self.query_metrics
+                .get_or_insert_with(QueryMetrics::default)
+                .add_page(metrics);
+        }
+    }
+}
+
+/// The clients used to talk to one Cosmos DB account.
+///
+/// Built once (see `AzureMcpServer`) and shared by every tool call, so the
+/// SDK client and the REST client's connection pool are reused rather than
+/// recreated per request.
+pub struct CosmosAccount {
+    client: CosmosClient,
+    rest: CosmosRestClient,
+    /// Partition key ranges a cross-partition query reads at once.
+    query_parallelism: usize,
+}
+
+impl CosmosAccount {
+    /// Build the SDK and REST clients from the supplied configuration.
+    ///
+    /// Key-based authentication is used when `COSMOS_KEY` is set.  For managed
+    /// identity / Azure AD authentication, use the Azure CLI (`az login`) or set
+    /// the standard Azure environment variables and run the server with an
+    /// `azure_identity`-capable host that exports compatible credentials.
+    pub fn new(config: &CosmosConfig) -> Result<Self> {
+        let Some(key) = &config.key else {
+            bail!(
+                "Cosmos DB authentication requires COSMOS_KEY to be set. \
+                 Managed identity support can be added by setting COSMOS_KEY to \
+                 your Cosmos DB account key."
+            )
+        };
+        let client = CosmosClient::with_key(&config.endpoint, Secret::from(key.clone()), None)
+            .context("Failed to create Cosmos DB client with account key")?;
+        let rest = CosmosRestClient::new(config)?;
+
+        Ok(Self {
+            client,
+            rest,
+            query_parallelism: config.query_parallelism,
+        })
+    }
+
+    /// The SDK client, refused while replaying: its requests bypass the
+    /// recording.
+    fn sdk(&self) -> Result<&CosmosClient> {
+        recording::live("the Cosmos DB SDK")?;
+        Ok(&self.client)
     }
 }
 
 /// List all databases in the Cosmos DB account.
 ///
 /// Returns a JSON array of database name strings.
-pub async fn list_databases(cfg: &CosmosConfig) -> Result<Value> {
-    let client = build_client(cfg)?;
+pub async fn list_databases(account: &CosmosAccount) -> Result<Value> {
+    let client = account.sdk()?;
 
     let mut pager = client
         .query_databases(Query::from("SELECT * FROM c"), None)
@@ -55,8 +260,14 @@ pub async fn list_databases(cfg: &CosmosConfig) -> Result<Value> {
 /// List all containers within the given Cosmos DB database.
 ///
 /// Returns a JSON array of container name strings.
-pub async fn list_containers(cfg: &CosmosConfig, database: &str) -> Result<Value> {
-    let client = build_client(cfg)?;
+pub async fn list_containers(account: &CosmosAccount, database: &str) -> Result<Value> {
+    let names = container_names(account, database).await?;
+    Ok(Value::Array(names.into_iter().map(Value::String).collect()))
+}
+
+/// Collect the names of every container in `database`.
+async fn container_names(account: &CosmosAccount, database: &str) -> Result<Vec<String>> {
+    let client = account.sdk()?;
     let db = client.database_client(database);
 
     let mut pager = db
@@ -69,66 +280,2301 @@ pub async fn list_containers(cfg: &CosmosConfig, database: &str) -> Result<Value
         .await
         .context("Error iterating container list")?
     {
-        names.push(Value::String(container.id.to_string()));
+        names.push(container.id.to_string());
     }
 
-    Ok(Value::Array(names))
+    Ok(names)
+}
+
+/// List every `(database, container)` pair in the account.
+pub async fn all_containers(account: &CosmosAccount) -> Result<Vec<(String, String)>> {
+    let databases = list_databases(account).await?;
+    let mut containers = Vec::new();
+    for database in databases.as_array().into_iter().flatten() {
+        let Some(database) = database.as_str() else {
+            continue;
+        };
+        for container in container_names(account, database).await? {
+            containers.push((database.to_string(), container));
+        }
+    }
+    Ok(containers)
+}
+
+/// Number of documents sampled by [`describe_container`].
+const SHAPE_SAMPLE_SIZE: u32 = 20;
+
+/// Record the JSON type of `value` at `path`, and of everything nested in it,
+/// into `shape`.  Array elements share the path `<array>[]`.
+fn record_shape(
+    shape: &mut BTreeMap<String, BTreeMap<&'static str, usize>>,
+    path: String,
+    value: &Value,
+) {
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    if !path.is_empty() {
+        *shape
+            .entry(path.clone())
+            .or_default()
+            .entry(kind)
+            .or_default() += 1;
+    }
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                // Skip system properties (_rid, _etag, _ts, ...).
+                if path.is_empty() && name.starts_with('_') {
+                    continue;
+                }
+                record_shape(shape, format!("{path}/{name}"), field);
+            }
+        }
+        Value::Array(elements) => {
+            for element in elements {
+                record_shape(shape, format!("{path}[]"), element);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Infer the shape of a set of sample documents: for each property path, the
+/// JSON types seen there and how many times each occurred.
+fn document_shape(documents: &[Value]) -> Value {
+    let mut shape = BTreeMap::new();
+    for document in documents {
+        record_shape(&mut shape, String::new(), document);
+    }
+    serde_json::to_value(shape).unwrap_or_default()
+}
+
+/// Describe a container for use as conversation context: partition key,
+/// TTL, indexing and search policies, plus the inferred shape of a small
+/// sample of documents.
+pub async fn describe_container(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+) -> Result<Value> {
+    let link = format!("dbs/{database}/colls/{container}");
+    let properties = account.rest.read_collection(&link).await?;
+
+    let settings = QuerySettings {
+        max_items: SHAPE_SAMPLE_SIZE,
+        include_metrics: false,
+        max_request_units: None,
+        consistency: None,
+    };
+    let sample = query_items(
+        account,
+        database,
+        container,
+        &format!("SELECT TOP {SHAPE_SAMPLE_SIZE} * FROM c"),
+        None,
+        settings,
+        None,
+    )
+    .await?
+    .0;
+    let documents = sample["items"].as_array().cloned().unwrap_or_default();
+
+    Ok(serde_json::json!({
+        "database": database,
+        "container": container,
+        "partition_key": properties["partitionKey"],
+        "default_ttl": properties.get("defaultTtl"),
+        "indexing_mode": properties["indexingPolicy"]["indexingMode"],
+        "unique_keys": properties["uniqueKeyPolicy"]["uniqueKeys"],
+        "vector_embeddings": properties["vectorEmbeddingPolicy"]["vectorEmbeddings"],
+        "full_text_paths": properties["fullTextPolicy"]["fullTextPaths"],
+        "sampled_documents": documents.len(),
+        "document_shape": document_shape(&documents),
+    }))
+}
+
+/// Parse a `name=value;name=value` header (e.g. `x-ms-resource-usage` or
+/// `x-ms-documentdb-query-metrics`) into its fields, skipping values that do
+/// not parse as `T`.
+fn parse_semicolon_pairs<T: FromStr>(header: &str) -> BTreeMap<String, T> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((name.trim().to_string(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Report document count and storage usage for containers in `database`.
+///
+/// When `container` is `None`, every container in the database is reported,
+/// largest first, so callers can see which containers dominate the account.
+/// Sizes are reported by the service in kilobytes.
+pub async fn container_stats(
+    account: &CosmosAccount,
+    database: &str,
+    container: Option<&str>,
+) -> Result<Value> {
+    let containers = match container {
+        Some(name) => vec![name.to_string()],
+        None => container_names(account, database).await?,
+    };
+    let rest = &account.rest;
+
+    let mut stats = Vec::with_capacity(containers.len());
+    for name in &containers {
+        let link = format!("dbs/{database}/colls/{name}");
+        let response = rest
+            .read_collection_quota(&link)
+            .await
+            .with_context(|| format!("Failed to read usage for container '{name}'"))?;
+
+        let usage: BTreeMap<String, i64> =
+            parse_semicolon_pairs(response.header("x-ms-resource-usage").unwrap_or(""));
+        let quota: BTreeMap<String, i64> =
+            parse_semicolon_pairs(response.header("x-ms-resource-quota").unwrap_or(""));
+        stats.push(serde_json::json!({
+            "container": name,
+            "document_count": usage.get("documentsCount"),
+            "documents_size_kb": usage.get("documentsSize"),
+            "total_size_kb": usage.get("collectionSize"),
+            "quota_size_kb": quota.get("collectionSize"),
+        }));
+    }
+
+    stats.sort_by_key(|s| std::cmp::Reverse(s["total_size_kb"].as_i64().unwrap_or(0)));
+    Ok(Value::Array(stats))
+}
+
+/// List the stored procedures, user-defined functions and triggers of a
+/// container.
+///
+/// UDF entries include the `udf.<name>` prefix used to call them from a
+/// query.  Function bodies are only included when `include_body` is set, as
+/// they can be large.
+pub async fn list_programmability(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    include_body: bool,
+) -> Result<Value> {
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+
+    let summarise = |entry: &Value, extra: Value| {
+        let mut summary = serde_json::json!({ "id": entry["id"] });
+        if let Value::Object(fields) = extra {
+            summary.as_object_mut().unwrap().extend(fields);
+        }
+        if include_body {
+            summary["body"] = entry["body"].clone();
+        }
+        summary
+    };
+
+    let sprocs = rest
+        .read_feed(&link, "sprocs", "StoredProcedures")
+        .await?
+        .iter()
+        .map(|sp| summarise(sp, Value::Null))
+        .collect::<Vec<_>>();
+    let udfs = rest
+        .read_feed(&link, "udfs", "UserDefinedFunctions")
+        .await?
+        .iter()
+        .map(|udf| {
+            let call = format!("udf.{}(...)", udf["id"].as_str().unwrap_or_default());
+            summarise(udf, serde_json::json!({ "query_syntax": call }))
+        })
+        .collect::<Vec<_>>();
+    let triggers = rest
+        .read_feed(&link, "triggers", "Triggers")
+        .await?
+        .iter()
+        .map(|trigger| {
+            summarise(
+                trigger,
+                serde_json::json!({
+                    "trigger_type": trigger["triggerType"],
+                    "trigger_operation": trigger["triggerOperation"],
+                }),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(serde_json::json!({
+        "stored_procedures": sprocs,
+        "user_defined_functions": udfs,
+        "triggers": triggers,
+    }))
+}
+
+/// Summarise one entry of a container's conflicts feed.
+///
+/// The losing version is stored by the service as a JSON string in
+/// `content`; it is decoded here so callers see a document, falling back to
+/// the raw string if it is not valid JSON.
+fn summarise_conflict(entry: &Value) -> Value {
+    let content = match entry.get("content") {
+        Some(Value::String(raw)) => {
+            serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.clone()))
+        }
+        Some(other) => other.clone(),
+        None => Value::Null,
+    };
+    serde_json::json!({
+        "id": entry["id"],
+        "operation_type": entry["operationType"],
+        "resource_type": entry["resourceType"],
+        "source_resource_id": entry["resourceId"],
+        "conflict_lsn": entry["conflict_lsn"],
+        "timestamp": entry["_ts"],
+        "conflicting_version": content,
+    })
+}
+
+/// Read the conflicts feed of a container in a multi-region write account.
+///
+/// Conflicts are only recorded when the container's conflict resolution
+/// policy is `Custom` without a resolver procedure (or the procedure failed);
+/// under last-writer-wins the feed stays empty.  The policy is returned
+/// alongside the entries.  When `include_current` is set, the version of each
+/// document that currently won is read for comparison.
+pub async fn read_conflicts(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    max_items: u32,
+    include_current: bool,
+) -> Result<Value> {
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+    let properties = rest.read_collection(&link).await?;
+    let paths: Vec<String> = properties["partitionKey"]["paths"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str().map(str::to_string))
+        .collect();
+
+    let limit = max_items.clamp(1, HARD_MAX_ITEMS) as usize;
+    let (entries, truncated) = rest
+        .read_feed_up_to(&link, "conflicts", "Conflicts", limit)
+        .await?;
+
+    let mut conflicts = Vec::new();
+    for entry in &entries {
+        let mut conflict = summarise_conflict(entry);
+        if include_current {
+            let version = &conflict["conflicting_version"];
+            let current = match (
+                version["id"].as_str(),
+                document_partition_key(version, &paths),
+            ) {
+                (Some(id), Ok(pk)) => rest
+                    .read_document(&link, id, &pk, None)
+                    .await?
+                    .map(|response| response.body),
+                _ => None,
+            };
+            conflict["current_version"] = current.unwrap_or(Value::Null);
+        }
+        conflicts.push(conflict);
+    }
+
+    Ok(serde_json::json!({
+        "conflict_resolution_policy": properties["conflictResolutionPolicy"],
+        "truncated": truncated,
+        "conflicts": conflicts,
+    }))
+}
+
+/// Read a single document by `id` within the logical partition
+/// `partition_key`.
+///
+/// `consistency` optionally relaxes the account's default consistency level
+/// for this read.  Returns `{"item": ..., "metadata": {...}}`; `item` is
+/// `null` when the document does not exist.
+pub async fn read_item(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    item_id: &str,
+    partition_key: &str,
+    consistency: Option<ConsistencyLevel>,
+) -> Result<Value> {
+    let link = format!("dbs/{database}/colls/{container}");
+    let response = account
+        .rest
+        .read_document(
+            &link,
+            item_id,
+            &serde_json::json!([partition_key]),
+            consistency.map(ConsistencyLevel::as_header),
+        )
+        .await
+        .with_context(|| format!("Failed to read Cosmos DB item '{item_id}'"))?;
+
+    let mut metadata = QueryMetadata::default();
+    let item = match response {
+        Some(response) => {
+            metadata.record_response(&response);
+            response.body
+        }
+        None => Value::Null,
+    };
+    Ok(serde_json::json!({ "item": item, "metadata": metadata }))
+}
+
+/// A Cosmos DB consistency level, used to relax the account default for a
+/// single query or point read.
+///
+/// Requests may only weaken consistency: asking for a level stronger than the
+/// account default is rejected by the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum ConsistencyLevel {
+    Strong,
+    BoundedStaleness,
+    Session,
+    ConsistentPrefix,
+    /// Cheapest and fastest; reads may be stale and out of order.
+    Eventual,
+}
+
+impl ConsistencyLevel {
+    /// The value of the `x-ms-consistency-level` request header.
+    pub fn as_header(self) -> &'static str {
+        match self {
+            Self::Strong => "Strong",
+            Self::BoundedStaleness => "BoundedStaleness",
+            Self::Session => "Session",
+            Self::ConsistentPrefix => "ConsistentPrefix",
+            Self::Eventual => "Eventual",
+        }
+    }
+}
+
+/// Per-call options for [`query_items`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuerySettings {
+    /// Maximum number of items to return (capped at [`HARD_MAX_ITEMS`]).
+    pub max_items: u32,
+    /// Request server-side execution metrics.
+    pub include_metrics: bool,
+    /// Stop fetching further pages once this many request units have been
+    /// consumed.
+    pub max_request_units: Option<f64>,
+    /// Override the account's default consistency level.
+    pub consistency: Option<ConsistencyLevel>,
+}
+
+/// Where a truncated [`query_items`] call stopped, so that a later call can
+/// carry on from the next item.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryResume {
+    /// Partition key ranges a cross-partition query has not finished; the
+    /// first is resumed from `continuation`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ranges: Vec<String>,
+    /// Service continuation token within the current scope.
+    pub continuation: Option<String>,
+    /// Items still allowed by the query's `TOP` clause.
+    pub remaining_top: Option<u32>,
 }
 
 /// Query items in a Cosmos DB container using a SQL-API query string.
 ///
 /// `partition_key` scopes the query to a single logical partition.  Pass
 /// `None` to run a cross-partition query (costs more RUs but is sometimes
-/// necessary).  `max_items` caps the number of items returned (default
-/// [`DEFAULT_MAX_ITEMS`], max [`HARD_MAX_ITEMS`]).
+/// necessary).  `settings.max_items` caps the number of items returned
+/// (default [`DEFAULT_MAX_ITEMS`], max [`HARD_MAX_ITEMS`]).
+///
+/// When `settings.include_metrics` is set, server-side execution metrics
+/// (index hit ratio, retrieved vs output document counts, execution time) are
+/// requested and reported under `metadata.query_metrics`.
+///
+/// `settings.consistency` relaxes the account's default consistency level
+/// for this query only (e.g. `Eventual` for analytics), lowering RU cost and
+/// latency.
+///
+/// When `settings.max_request_units` is set, iteration stops after the page
+/// that takes the cumulative request charge to or past the budget; the items
+/// fetched so far are returned and `metadata.budget_exhausted` is `true`.
+///
+/// `resume` continues a previous call from where it stopped.  Returns
+/// `{"items": [...], "metadata": {...}}`, where `metadata` is the
+/// [`QueryMetadata`] aggregated over every page fetched, together with the
+/// position to resume from when more items remain.
 pub async fn query_items(
-    cfg: &CosmosConfig,
+    account: &CosmosAccount,
     database: &str,
     container: &str,
     sql: &str,
     partition_key: Option<&str>,
-    max_items: u32,
-) -> Result<Value> {
-    let max_items = max_items.min(HARD_MAX_ITEMS);
-    let client = build_client(cfg)?;
-    let container_client = client.database_client(database).container_client(container);
+    settings: QuerySettings,
+    resume: Option<QueryResume>,
+) -> Result<(Value, Option<QueryResume>)> {
+    let settings = QuerySettings {
+        max_items: settings.max_items.min(HARD_MAX_ITEMS),
+        ..settings
+    };
+    let mut items = Vec::new();
+    let metadata = Mutex::new(QueryMetadata::with_budget(settings.max_request_units));
+    let link = format!("dbs/{database}/colls/{container}");
+
+    // Queries go through the REST client rather than the SDK: every page
+    // boundary is then a continuation token the query can resume from, and
+    // query metrics, consistency overrides, regional reads and session
+    // tokens from earlier writes are all honoured.
+    let next = match partition_key {
+        Some(key) => {
+            let scope = QueryScope::PartitionKey(key);
+            let start = resume.and_then(|r| r.continuation);
+            let query = ScopeQuery {
+                rest: &account.rest,
+                link: &link,
+                sql,
+                scope,
+                settings,
+            };
+            let end = query_scope(query, start, &mut items, &metadata).await?;
+            match end {
+                ScopeEnd::Stopped(Some(continuation)) => Some(QueryResume {
+                    continuation: Some(continuation),
+                    ..QueryResume::default()
+                }),
+                _ => None,
+            }
+        }
+        None => {
+            query_cross_partition(account, &link, sql, settings, resume, &mut items, &metadata)
+                .await?
+        }
+    };
+
+    let metadata = metadata.into_inner().unwrap();
+    tracing::debug!(
+        request_charge = metadata.request_charge,
+        pages = metadata.page_count,
+        budget_exhausted = metadata.budget_exhausted,
+        memory_budget_exhausted = metadata.memory_budget_exhausted,
+        "Cosmos DB query completed"
+    );
 
-    // PartitionKey::EMPTY signals a cross-partition (fan-out) query to the SDK.
-    // A non-empty key scopes the query to a single logical partition.
-    let pk: azure_data_cosmos::PartitionKey = match partition_key {
-        Some(key) => azure_data_cosmos::PartitionKey::from(key.to_string()),
-        None => azure_data_cosmos::PartitionKey::EMPTY,
+    // `json!` would serialize a copy of every item; move them in instead.
+    let mut result = serde_json::json!({ "metadata": metadata });
+    result["items"] = Value::Array(items);
+    Ok((result, next))
+}
+
+/// Run a query across every partition of a container.
+///
+/// The SDK cannot fan a query out by itself, so this follows the same
+/// approach as the other Cosmos SDKs: fetch the gateway query plan, then
+/// execute the query against each partition key range, following
+/// continuation tokens until `max_items` items have been collected.  Up to
+/// `COSMOS_QUERY_PARALLELISM` ranges are read at once.
+///
+/// Results are concatenated range by range, so queries whose semantics need a
+/// cross-partition merge (`ORDER BY`, aggregates, `GROUP BY`, `DISTINCT`,
+/// `OFFSET`/`LIMIT`) are rejected with an explanation; `TOP n` is honoured by
+/// capping the merged result.
+///
+/// A `resume` position skips the query plan and carries on with the ranges
+/// it lists.  Returns the position to resume from when more items remain.
+async fn query_cross_partition(
+    account: &CosmosAccount,
+    link: &str,
+    sql: &str,
+    settings: QuerySettings,
+    resume: Option<QueryResume>,
+    items: &mut Vec<Value>,
+    metadata: &Mutex<QueryMetadata>,
+) -> Result<Option<QueryResume>> {
+    let rest = &account.rest;
+
+    let resume = match resume {
+        Some(resume) => resume,
+        None => {
+            let plan = rest.query_plan(link, sql).await?;
+            let top = cross_partition_top(plan.get("queryInfo").unwrap_or(&Value::Null))?;
+            let ranges = rest.partition_key_ranges(link).await?;
+            QueryResume {
+                ranges: ranges.into_iter().map(|range| range.id).collect(),
+                continuation: None,
+                remaining_top: top,
+            }
+        }
     };
+    let settings = match resume.remaining_top {
+        Some(top) => QuerySettings {
+            max_items: top.min(settings.max_items),
+            ..settings
+        },
+        None => settings,
+    };
+    tracing::debug!(
+        ranges = resume.ranges.len(),
+        parallelism = account.query_parallelism,
+        "Fanning out Cosmos DB query"
+    );
+    metadata.lock().unwrap().shared_item_limit =
+        Some((settings.max_items as usize).saturating_sub(items.len()));
+
+    // Each range collects into its own list, and the ranges read at once stop
+    // asking for pages when they have collected every item wanted between
+    // them, so at most a page per range is read beyond `max_items`.  The
+    // ranges are merged in order as they finish; a range stopped early is
+    // resumed from its continuation by the next call, and a later range whose
+    // results no longer fit is dropped and read again from its start, since
+    // a range can only be resumed on one of its own page boundaries.
+    let mut start = resume.continuation;
+    let scopes: Vec<_> = resume
+        .ranges
+        .iter()
+        .map(|range| {
+            let query = ScopeQuery {
+                rest,
+                link,
+                sql,
+                scope: QueryScope::PartitionKeyRange(range),
+                settings,
+            };
+            query_scope_items(query, start.take(), metadata)
+        })
+        .collect();
+    let mut scopes = futures::stream::iter(scopes).buffered(account.query_parallelism);
+
+    let mut index = 0;
+    while let Some((end, mut found)) = scopes.next().await.transpose()? {
+        let (pending, continuation) = if items.len() + found.len() > settings.max_items as usize {
+            tracing::debug!(
+                range = %resume.ranges[index],
+                "Dropping the results of a partition key range that no longer fit"
+            );
+            (index, None)
+        } else {
+            items.append(&mut found);
+            match end {
+                ScopeEnd::Exhausted => {
+                    index += 1;
+                    continue;
+                }
+                ScopeEnd::Stopped(Some(continuation)) => (index, Some(continuation)),
+                ScopeEnd::Stopped(None) => (index + 1, None),
+            }
+        };
+        let remaining_top = resume
+            .remaining_top
+            .map(|top| top.saturating_sub(items.len() as u32));
+        if pending == resume.ranges.len() || remaining_top == Some(0) {
+            return Ok(None);
+        }
+        return Ok(Some(QueryResume {
+            ranges: resume.ranges[pending..].to_vec(),
+            continuation,
+            remaining_top,
+        }));
+    }
+
+    Ok(None)
+}
+
+/// How [`query_scope`] finished.
+enum ScopeEnd {
+    /// Every page of the scope was read.
+    Exhausted,
+    /// The item limit, RU budget or `MAX_RESULT_BYTES` was reached.  Carries
+    /// the continuation token to resume the scope from, or `None` if it was
+    /// read to the end.
+    Stopped(Option<String>),
+}
+
+/// A query of one container within one REST query scope.
+#[derive(Clone, Copy)]
+struct ScopeQuery<'a> {
+    rest: &'a CosmosRestClient,
+    link: &'a str,
+    sql: &'a str,
+    scope: QueryScope<'a>,
+    settings: QuerySettings,
+}
+
+/// Page through `query` within its scope, starting from the `start`
+/// continuation token, and append the results to `items` until the scope is
+/// exhausted, `settings.max_items` items have been collected, the request
+/// unit budget is spent or the items reach `MAX_RESULT_BYTES`.
+///
+/// Pages are requested no larger than the number of items still wanted, so
+/// iteration always stops on a page boundary that can be resumed from.  The
+/// byte budget is therefore only checked between pages, and a result can
+/// exceed it by up to one page.
+///
+/// `metadata` is shared by the scopes of a query read at once, so that they
+/// all stop once their combined charge or size reaches a budget, or their
+/// items reach its shared item limit.
+async fn query_scope(
+    query: ScopeQuery<'_>,
+    start: Option<String>,
+    items: &mut Vec<Value>,
+    metadata: &Mutex<QueryMetadata>,
+) -> Result<ScopeEnd> {
+    let ScopeQuery {
+        rest,
+        link,
+        sql,
+        scope,
+        settings,
+    } = query;
+    let limit = settings.max_items as usize;
+    let mut continuation = start;
+    loop {
+        let shared_remaining = {
+            let metadata = metadata.lock().unwrap();
+            metadata.shared_item_limit.map_or(usize::MAX, |shared| {
+                shared.saturating_sub(metadata.item_count)
+            })
+        };
+        let remaining = limit
+            .saturating_sub(items.len())
+            .min(shared_remaining)
+            .max(1) as u32;
+        let mut page = rest
+            .query_page(
+                link,
+                PageRequest {
+                    sql,
+                    scope,
+                    continuation: continuation.as_deref(),
+                    page_size: remaining,
+                    populate_metrics: settings.include_metrics,
+                    consistency: settings.consistency.map(ConsistencyLevel::as_header),
+                },
+            )
+            .await?;
+        let (over_budget, all_collected) = {
+            let mut metadata = metadata.lock().unwrap();
+            metadata.record_response(&page);
 
-    let mut pager = container_client
-        .query_items::<Value>(sql, pk, None)
-        .context("Failed to initiate Cosmos DB items query")?;
+            // Move the documents out of the page rather than copying them, so
+            // a page is never held twice.
+            if let Some(Value::Array(documents)) = page.body.get_mut("Documents") {
+                metadata.over_memory_budget(documents, rest.max_result_bytes());
+                metadata.item_count += documents.len();
+                items.append(documents);
+            }
+            (
+                metadata.over_budget() || metadata.memory_budget_exhausted,
+                metadata
+                    .shared_item_limit
+                    .is_some_and(|shared| metadata.item_count >= shared),
+            )
+        };
+        continuation = page.continuation();
+        if items.len() >= limit || over_budget || all_collected {
+            items.truncate(limit);
+            return Ok(ScopeEnd::Stopped(continuation));
+        }
+        if continuation.is_none() {
+            return Ok(ScopeEnd::Exhausted);
+        }
+    }
+}
 
+/// [`query_scope`] into a list of its own, so that several scopes can be
+/// read at once.
+async fn query_scope_items(
+    query: ScopeQuery<'_>,
+    start: Option<String>,
+    metadata: &Mutex<QueryMetadata>,
+) -> Result<(ScopeEnd, Vec<Value>)> {
     let mut items = Vec::new();
-    while let Some(item) = pager
-        .try_next()
-        .await
-        .context("Error iterating Cosmos DB query results")?
+    let end = query_scope(query, start, &mut items, metadata).await?;
+    Ok((end, items))
+}
+
+/// Inspect the `queryInfo` section of a query plan and decide whether the
+/// query can be answered by concatenating per-range results.
+///
+/// Returns the `TOP` value, if any, to cap the merged result.
+fn cross_partition_top(query_info: &Value) -> Result<Option<u32>> {
+    let non_empty = |field: &str| {
+        query_info
+            .get(field)
+            .and_then(Value::as_array)
+            .is_some_and(|a| !a.is_empty())
+    };
+    let present = |field: &str| query_info.get(field).is_some_and(|v| !v.is_null());
+
+    let unsupported = if non_empty("orderBy") {
+        Some("ORDER BY")
+    } else if non_empty("aggregates")
+        || query_info
+            .get("groupByAliasToAggregateType")
+            .and_then(Value::as_object)
+            .is_some_and(|m| !m.is_empty())
+    {
+        Some("aggregate functions")
+    } else if non_empty("groupByExpressions") {
+        Some("GROUP BY")
+    } else if query_info
+        .get("distinctType")
+        .and_then(Value::as_str)
+        .is_some_and(|d| d != "None")
     {
-        items.push(item);
-        if items.len() >= max_items as usize {
-            break;
+        Some("DISTINCT")
+    } else if present("offset") || present("limit") {
+        Some("OFFSET/LIMIT")
+    } else {
+        None
+    };
+
+    if let Some(feature) = unsupported {
+        bail!(
+            "Cross-partition queries using {feature} are not supported.  Supply \
+             partition_key to run the query within a single logical partition, or \
+             remove the {feature} clause and process the results client-side."
+        );
+    }
+
+    Ok(query_info
+        .get("top")
+        .and_then(Value::as_u64)
+        .map(|top| top.min(u32::MAX as u64) as u32))
+}
+
+/// Translate a property path (`/embedding`, `/content/vector`) into a Cosmos
+/// SQL accessor on the alias `c` (`c["embedding"]`, `c["content"]["vector"]`).
+fn property_accessor(path: &str) -> Result<String> {
+    let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        bail!("Invalid property path '{path}': expected e.g. '/embedding'");
+    }
+    if segments.iter().any(|s| s.contains(['"', '\\'])) {
+        bail!("Invalid property path '{path}': quotes and backslashes are not allowed");
+    }
+    Ok(segments
+        .iter()
+        .fold("c".to_string(), |acc, s| format!("{acc}[\"{s}\"]")))
+}
+
+/// Order the results of a ranked query best first and keep the `top_k`
+/// best.
+///
+/// Each result carries its rank under `score`; `higher_is_better` gives the
+/// direction (similarity scores vs distances).
+fn rank_results(mut results: Vec<Value>, higher_is_better: bool, top_k: usize) -> Vec<Value> {
+    let score = |v: &Value| v["score"].as_f64().unwrap_or(f64::NAN);
+    results.sort_by(|a, b| {
+        let ordering = score(a).total_cmp(&score(b));
+        if higher_is_better {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    results.truncate(top_k);
+    results
+}
+
+/// Run a ranked `sql` query (one that orders by a per-document score and
+/// returns at most `top_k` rows) in each partition key range, or only in
+/// `partition_key`, and return every range's rows together.
+///
+/// `ORDER BY` cannot be merged by the generic cross-partition path, but a
+/// ranked top-k can: each range returns its own best rows and the caller
+/// re-ranks them with [`rank_results`].
+async fn query_ranked(
+    account: &CosmosAccount,
+    link: &str,
+    sql: &str,
+    partition_key: Option<&str>,
+    top_k: u32,
+    metadata: &Mutex<QueryMetadata>,
+) -> Result<Vec<Value>> {
+    let rest = &account.rest;
+    let settings = QuerySettings {
+        max_items: top_k,
+        include_metrics: false,
+        max_request_units: None,
+        consistency: None,
+    };
+
+    let mut results = Vec::new();
+    if let Some(key) = partition_key {
+        let query = ScopeQuery {
+            rest,
+            link,
+            sql,
+            scope: QueryScope::PartitionKey(key),
+            settings,
+        };
+        query_scope(query, None, &mut results, metadata).await?;
+        return Ok(results);
+    }
+
+    let ranges = rest.partition_key_ranges(link).await?;
+    let pages: Vec<_> = ranges
+        .iter()
+        .map(|range| {
+            let query = ScopeQuery {
+                rest,
+                link,
+                sql,
+                scope: QueryScope::PartitionKeyRange(&range.id),
+                settings,
+            };
+            query_scope_items(query, None, metadata)
+        })
+        .collect();
+    let mut pages = futures::stream::iter(pages).buffered(account.query_parallelism);
+    while let Some((_, mut page)) = pages.try_next().await? {
+        results.append(&mut page);
+    }
+    Ok(results)
+}
+
+/// Remove the property at `path` (e.g. `/content/vector`) from `document`.
+fn remove_property(document: &mut Value, path: &str) {
+    let (parent, property) = path.rsplit_once('/').unwrap_or(("", path));
+    if let Some(Value::Object(holder)) = document.pointer_mut(parent) {
+        holder.remove(property);
+    }
+}
+
+/// Find the vector embedding policy entry of `path` in a container's
+/// properties.
+fn vector_embedding(properties: &Value, path: &str) -> Option<Value> {
+    properties["vectorEmbeddingPolicy"]["vectorEmbeddings"]
+        .as_array()?
+        .iter()
+        .find(|e| e["path"].as_str() == Some(path))
+        .cloned()
+}
+
+/// Find the `top_k` documents whose vector at `path` is most similar to
+/// `embedding`, using `VectorDistance`.
+///
+/// The container's vector embedding policy decides the distance function:
+/// `cosine` and `dotproduct` are similarities (higher is closer), `euclidean`
+/// is a distance (lower is closer).  The query runs in each partition key
+/// range (or only in `partition_key`) and the per-range results are merged.
+/// The vector property itself is stripped from returned documents to keep the
+/// response small.
+pub async fn vector_search(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    path: &str,
+    embedding: &[f64],
+    top_k: u32,
+    partition_key: Option<&str>,
+) -> Result<Value> {
+    if embedding.is_empty() {
+        bail!("embedding must not be empty");
+    }
+    let path = format!("/{}", path.trim_start_matches('/'));
+    let accessor = property_accessor(&path)?;
+    let top_k = top_k.clamp(1, HARD_MAX_TOP_K);
+
+    let link = format!("dbs/{database}/colls/{container}");
+    let properties = account.rest.read_collection(&link).await?;
+    let policy = vector_embedding(&properties, &path);
+    if let Some(dimensions) = policy.as_ref().and_then(|p| p["dimensions"].as_u64()) {
+        if dimensions != embedding.len() as u64 {
+            bail!(
+                "embedding has {} dimensions but the vector policy for '{path}' expects {dimensions}",
+                embedding.len()
+            );
         }
     }
+    let distance_function = policy
+        .as_ref()
+        .and_then(|p| p["distanceFunction"].as_str())
+        .unwrap_or("cosine")
+        .to_ascii_lowercase();
 
-    Ok(Value::Array(items))
+    let vector = serde_json::to_string(embedding)?;
+    let sql = format!(
+        "SELECT TOP {top_k} c AS document, VectorDistance({accessor}, {vector}) AS score \
+         FROM c ORDER BY VectorDistance({accessor}, {vector})"
+    );
+
+    let metadata = Mutex::new(QueryMetadata::default());
+    let results = query_ranked(account, &link, &sql, partition_key, top_k, &metadata).await?;
+    let mut results = rank_results(results, distance_function != "euclidean", top_k as usize);
+
+    for result in &mut results {
+        remove_property(&mut result["document"], &path);
+    }
+
+    Ok(serde_json::json!({
+        "results": results,
+        "distance_function": distance_function,
+        "vector_policy_found": policy.is_some(),
+        "metadata": metadata.into_inner().unwrap(),
+    }))
 }
 
-#[cfg(test)]
-mod tests {
-    /// Unit tests for Cosmos DB module helpers.
-    /// Integration tests require a live Cosmos DB account and are excluded from
-    /// the standard test run.
+/// How [`text_search`] matches and orders documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSearchMode {
+    /// Documents containing every term (`FullTextContainsAll`), unordered.
+    ContainsAll,
+    /// Documents containing any term (`FullTextContainsAny`), unordered.
+    ContainsAny,
+    /// Documents ordered by BM25 relevance (`ORDER BY RANK FullTextScore`).
+    Rank,
+    /// Relevance fused with vector similarity (`ORDER BY RANK RRF`).
+    Hybrid,
+}
 
-    #[test]
-    fn max_items_is_capped_at_5000() {
-        // Verify the public cap constant in the function signature.
-        let capped = 10_000_u32.min(5_000);
-        assert_eq!(capped, 5_000);
+/// Build the SQL for a [`text_search`].
+///
+/// `text` is the accessor of the text property; `vector` the accessor and
+/// JSON-encoded query vector used by [`TextSearchMode::Hybrid`].
+fn text_search_sql(
+    mode: TextSearchMode,
+    text: &str,
+    terms: &[String],
+    vector: Option<(&str, &str)>,
+    top_k: u32,
+) -> Result<String> {
+    if terms.is_empty() {
+        bail!("at least one search term is required");
+    }
+    // Cosmos SQL string literals share JSON's escaping rules.
+    let terms = terms
+        .iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()?
+        .join(", ");
+    let select = format!("SELECT TOP {top_k} * FROM c");
+    Ok(match mode {
+        TextSearchMode::ContainsAll => {
+            format!("{select} WHERE FullTextContainsAll({text}, {terms})")
+        }
+        TextSearchMode::ContainsAny => {
+            format!("{select} WHERE FullTextContainsAny({text}, {terms})")
+        }
+        TextSearchMode::Rank => format!("{select} ORDER BY RANK FullTextScore({text}, {terms})"),
+        TextSearchMode::Hybrid => {
+            let Some((accessor, embedding)) = vector else {
+                bail!("hybrid search requires `embedding` and `vector_path`");
+            };
+            format!(
+                "{select} ORDER BY RANK RRF(VectorDistance({accessor}, {embedding}), \
+                 FullTextScore({text}, {terms}))"
+            )
+        }
+    })
+}
+
+/// A full-text search for [`text_search`].
+#[derive(Debug, Clone, Copy)]
+pub struct TextSearchRequest<'a> {
+    /// The text property to search.
+    pub path: &'a str,
+    pub terms: &'a [String],
+    pub mode: TextSearchMode,
+    /// The vector property and the embedding to compare it with, for
+    /// `Hybrid` search.
+    pub vector: Option<(&'a str, &'a [f64])>,
+    pub top_k: u32,
+    pub partition_key: Option<&'a str>,
+}
+
+/// Full-text search over the text property at `path` of a container with a
+/// full-text policy.
+///
+/// `ContainsAll` / `ContainsAny` filter documents and run across every
+/// partition.  `Rank` and `Hybrid` order by relevance scores that are not
+/// returned to the client, so per-partition results cannot be merged: they
+/// need `partition_key`, unless the container has a single physical
+/// partition.  `Hybrid` fuses BM25 relevance with vector similarity of
+/// `embedding` at `vector_path` using reciprocal rank fusion; the vector
+/// property is stripped from returned documents.
+pub async fn text_search(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    search: TextSearchRequest<'_>,
+) -> Result<Value> {
+    let TextSearchRequest {
+        path,
+        terms,
+        mode,
+        vector,
+        top_k,
+        partition_key,
+    } = search;
+    let top_k = top_k.clamp(1, HARD_MAX_TOP_K);
+    let text = property_accessor(path)?;
+    let vector = match vector {
+        Some((vector_path, embedding)) if mode == TextSearchMode::Hybrid => Some((
+            format!("/{}", vector_path.trim_start_matches('/')),
+            property_accessor(vector_path)?,
+            serde_json::to_string(embedding)?,
+        )),
+        _ => None,
+    };
+    let sql = text_search_sql(
+        mode,
+        &text,
+        terms,
+        vector.as_ref().map(|(_, a, e)| (a.as_str(), e.as_str())),
+        top_k,
+    )?;
+
+    let link = format!("dbs/{database}/colls/{container}");
+    let rest = &account.rest;
+    let metadata = Mutex::new(QueryMetadata::default());
+    let mut results = match mode {
+        TextSearchMode::ContainsAll | TextSearchMode::ContainsAny => {
+            query_ranked(account, &link, &sql, partition_key, top_k, &metadata).await?
+        }
+        TextSearchMode::Rank | TextSearchMode::Hybrid => {
+            let ranges;
+            let scope = match partition_key {
+                Some(key) => QueryScope::PartitionKey(key),
+                None => {
+                    ranges = rest.partition_key_ranges(&link).await?;
+                    match ranges.as_slice() {
+                        [range] => QueryScope::PartitionKeyRange(&range.id),
+                        _ => bail!(
+                            "Relevance-ranked search cannot merge results across the {} \
+                             physical partitions of '{container}'; supply a partition_key, \
+                             or use contains_all / contains_any",
+                            ranges.len()
+                        ),
+                    }
+                }
+            };
+            let settings = QuerySettings {
+                max_items: top_k,
+                include_metrics: false,
+                max_request_units: None,
+                consistency: None,
+            };
+            let query = ScopeQuery {
+                rest,
+                link: &link,
+                sql: &sql,
+                scope,
+                settings,
+            };
+            let mut items = Vec::new();
+            query_scope(query, None, &mut items, &metadata).await?;
+            items
+        }
+    };
+    results.truncate(top_k as usize);
+    if let Some((vector_path, _, _)) = &vector {
+        for document in &mut results {
+            remove_property(document, vector_path);
+        }
+    }
+
+    let metadata = metadata.into_inner().unwrap();
+    Ok(serde_json::json!({ "results": results, "metadata": metadata }))
+}
+
+/// Quote `value` as a T-SQL string literal.
+fn tsql_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Build the `WITH docs AS (…)` prelude exposing a container's analytical
+/// store through the Synapse serverless `OPENROWSET` provider.
+///
+/// The account key is read from the configured server credential; it is
+/// never part of the batch, where queries and the query history could read
+/// it back.
+fn analytical_prelude(config: &CosmosConfig, database: &str, container: &str) -> Result<String> {
+    let account = config
+        .endpoint
+        .trim_start_matches("https://")
+        .split(['.', ':', '/'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .with_context(|| format!("Cannot determine account name from '{}'", config.endpoint))?;
+    let credential = config
+        .analytical_credential
+        .as_deref()
+        .context("Analytical store queries need COSMOS_ANALYTICAL_CREDENTIAL")?;
+    let source = format!(
+        "OPENROWSET(PROVIDER = 'CosmosDB', CONNECTION = {}, OBJECT = {}, \
+         SERVER_CREDENTIAL = {})",
+        tsql_literal(&format!("Account={account};Database={database}")),
+        tsql_literal(container),
+        tsql_literal(credential),
+    );
+    Ok(format!("WITH docs AS (SELECT * FROM {source} AS rows) "))
+}
+
+/// Run a T-SQL query against a container's analytical store through the
+/// configured Synapse serverless SQL endpoint.
+///
+/// The container is exposed to `sql` as the table `docs`, e.g.
+/// `SELECT status, COUNT(*) AS n FROM docs GROUP BY status`.  Analytical store
+/// queries do not consume the container's request units, which makes them the
+/// right place for large scans and aggregations; the store is kept in sync
+/// with the transactional store within a couple of minutes.
+pub async fn analytical_query(
+    config: &CosmosConfig,
+    database: &str,
+    container: &str,
+    sql: &str,
+    max_rows: u64,
+) -> Result<Value> {
+    let endpoint = config.require_analytical()?;
+    let prelude = analytical_prelude(config, database, container)?;
+    crate::mssql::execute_query_with(endpoint, &prelude, sql, max_rows)
+        .await
+        .context("Analytical store query failed")
+}
+
+/// Resume position for [`read_change_feed`], serialised into the opaque
+/// continuation token handed back to callers.
+///
+/// Ranges listed in `ranges` resume after the recorded `etag`; any other range
+/// (including ranges created by a partition split) starts from `since`, or
+/// from the beginning of the feed when `since` is unset.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+struct ChangeFeedState {
+    since: Option<DateTime<Utc>>,
+    ranges: BTreeMap<String, String>,
+}
+
+impl ChangeFeedState {
+    fn decode(token: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(token.trim())
+            .context("Invalid change feed continuation token")?;
+        serde_json::from_slice(&bytes).context("Invalid change feed continuation token")
+    }
+
+    fn encode(&self) -> String {
+        BASE64.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    fn start_for(&self, range_id: &str) -> ChangeFeedStart<'_> {
+        match (self.ranges.get(range_id), self.since) {
+            (Some(etag), _) => ChangeFeedStart::After(etag),
+            (None, Some(since)) => ChangeFeedStart::Since(since),
+            (None, None) => ChangeFeedStart::Beginning,
+        }
+    }
+}
+
+/// Read the change feed of a container.
+///
+/// Starts from `continuation` (a token returned by a previous call) when
+/// given, otherwise from `since` (an RFC 3339 timestamp), otherwise from the
+/// beginning of the feed.  At most `max_items` changed documents are returned
+/// (default [`DEFAULT_MAX_ITEMS`], max [`HARD_MAX_ITEMS`]); whole pages are
+/// kept so the returned continuation never skips a change.
+///
+/// The change feed reports the latest version of each inserted or updated
+/// document; deletions are not included.  As with [`query_items`],
+/// `max_request_units` stops reading once the budget is spent; the returned
+/// continuation then resumes where the read stopped.
+///
+/// Returns `{"items": [...], "continuation": "...", "metadata": {...}}`.
+pub async fn read_change_feed(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    since: Option<&str>,
+    continuation: Option<&str>,
+    max_items: u32,
+    max_request_units: Option<f64>,
+) -> Result<Value> {
+    let max_items = max_items.min(HARD_MAX_ITEMS);
+
+    let mut state = match (continuation, since) {
+        (Some(token), _) => ChangeFeedState::decode(token)?,
+        (None, Some(since)) => ChangeFeedState {
+            since: Some(
+                DateTime::parse_from_rfc3339(since)
+                    .with_context(|| {
+                        format!("`since` must be an RFC 3339 timestamp, got {since:?}")
+                    })?
+                    .with_timezone(&Utc),
+            ),
+            ranges: BTreeMap::new(),
+        },
+        (None, None) => ChangeFeedState::default(),
+    };
+
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+    let ranges = rest.partition_key_ranges(&link).await?;
+
+    let mut items = Vec::new();
+    let mut metadata = QueryMetadata::with_budget(max_request_units);
+    'ranges: for range in &ranges {
+        loop {
+            if items.len() >= max_items as usize {
+                break 'ranges;
+            }
+            let remaining = max_items - items.len() as u32;
+            let mut page = rest
+                .read_change_feed_range(&link, &range.id, state.start_for(&range.id), remaining)
+                .await?;
+            metadata.record_response(&page);
+
+            if let Some(etag) = page.header("etag") {
+                state.ranges.insert(range.id.clone(), etag.to_string());
+            }
+            if page.is_not_modified() {
+                break;
+            }
+            match page.body.get_mut("Documents") {
+                Some(Value::Array(documents)) if !documents.is_empty() => {
+                    metadata.over_memory_budget(documents, rest.max_result_bytes());
+                    items.append(documents);
+                }
+                // An empty page also means this range is caught up.
+                _ => break,
+            }
+            if metadata.over_budget() || metadata.memory_budget_exhausted {
+                break 'ranges;
+            }
+        }
+    }
+
+    Ok(serde_json::json!({
+        "items": items,
+        "continuation": state.encode(),
+        "metadata": metadata,
+    }))
+}
+
+/// The kind of change a single [`PatchOperation`] applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Set a property, creating it if it does not exist.
+    Set,
+    /// Add a property or insert into an array.
+    Add,
+    /// Replace an existing property (fails if it does not exist).
+    Replace,
+    /// Remove a property.
+    Remove,
+    /// Increment a numeric property by `value`.
+    Increment,
+}
+
+/// One JSON Patch operation applied by [`patch_item`].
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PatchOperation {
+    /// Operation to perform: `set`, `add`, `replace`, `remove` or `increment`.
+    pub op: PatchOp,
+    /// JSON Pointer path of the target property, e.g. `/address/city`.
+    pub path: String,
+    /// New value (or increment amount).  Required for every operation except
+    /// `remove`.
+    pub value: Option<Value>,
+}
+
+/// Translate caller-supplied operations into an SDK [`PatchDocument`].
+///
+/// Validates that each operation carries the value it needs before any
+/// request is sent.
+fn build_patch_document(operations: &[PatchOperation]) -> Result<PatchDocument> {
+    if operations.is_empty() {
+        bail!("At least one patch operation is required");
+    }
+
+    let mut doc = PatchDocument::default();
+    for (i, operation) in operations.iter().enumerate() {
+        if !operation.path.starts_with('/') {
+            bail!(
+                "Patch operation {i}: path {:?} must be a JSON Pointer starting with '/'",
+                operation.path
+            );
+        }
+        let path = operation.path.clone();
+
+        doc = match (operation.op, &operation.value) {
+            (PatchOp::Remove, _) => doc.with_remove(path),
+            (PatchOp::Increment, Some(Value::Number(n))) => match n.as_i64() {
+                Some(int) => doc.with_increment(path, int),
+                None => doc.with_increment(path, n.as_f64().unwrap_or_default()),
+            },
+            (PatchOp::Increment, _) => {
+                bail!("Patch operation {i}: `increment` requires a numeric value")
+            }
+            (_, None) => bail!("Patch operation {i}: a value is required"),
+            (PatchOp::Set, Some(v)) => doc.with_set(path, v.clone()),
+            (PatchOp::Add, Some(v)) => doc
+                .with_add(path, v.clone())
+                .map_err(azure_core::Error::from),
+            (PatchOp::Replace, Some(v)) => doc.with_replace(path, v.clone()),
+        }
+        .with_context(|| format!("Patch operation {i}: invalid operation"))?;
+    }
+
+    Ok(doc)
+}
+
+/// Apply a partial update to a single document using JSON Patch operations.
+///
+/// Only the listed properties are sent to the service, so small field changes
+/// do not require a read-modify-write of the whole document.  Returns a JSON
+/// object summarising the patched item.
+pub async fn patch_item(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    item_id: &str,
+    partition_key: &str,
+    operations: &[PatchOperation],
+) -> Result<Value> {
+    let patch = build_patch_document(operations)?;
+    let client = account.sdk()?;
+    let container_client = client.database_client(database).container_client(container);
+
+    let response = container_client
+        .patch_item(
+            PartitionKey::from(partition_key.to_string()),
+            item_id,
+            patch,
+            None,
+        )
+        .await
+        .with_context(|| format!("Failed to patch Cosmos DB item '{item_id}'"))?;
+    if let Some(token) = response.headers().get_optional_str(&SESSION_TOKEN_HEADER) {
+        account
+            .rest
+            .record_session_token(&format!("dbs/{database}/colls/{container}"), token);
+    }
+
+    Ok(serde_json::json!({
+        "id": item_id,
+        "partition_key": partition_key,
+        "operations_applied": operations.len(),
+    }))
+}
+
+/// Create the document `document` in the logical partition `partition_key`
+/// of `container`.
+pub async fn create_item(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    partition_key: &str,
+    document: &Value,
+) -> Result<()> {
+    account
+        .rest
+        .create_document(
+            &format!("dbs/{database}/colls/{container}"),
+            &serde_json::json!([partition_key]),
+            document,
+        )
+        .await
+        .with_context(|| format!("Failed to create a document in {database}/{container}"))?;
+    Ok(())
+}
+
+/// Execute a stored procedure within one logical partition.
+///
+/// `parameters` are passed to the procedure as positional arguments.
+/// Returns `{"result": ..., "logs": "...", "request_charge": ru}` where
+/// `result` is the procedure's response body and `logs` any `console.log`
+/// output.
+pub async fn execute_stored_procedure(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    procedure: &str,
+    partition_key: &str,
+    parameters: &[Value],
+) -> Result<Value> {
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}/sprocs/{procedure}");
+
+    let response = rest
+        .execute_stored_procedure(&link, partition_key, parameters)
+        .await
+        .with_context(|| format!("Failed to execute stored procedure '{procedure}'"))?;
+
+    let logs = response
+        .header("x-ms-documentdb-script-log-results")
+        .map(|l| {
+            urlencoding::decode(l)
+                .map(|d| d.into_owned())
+                .unwrap_or(l.to_string())
+        });
+    let mut metadata = QueryMetadata::default();
+    metadata.record_response(&response);
+
+    Ok(serde_json::json!({
+        "result": response.body,
+        "logs": logs,
+        "request_charge": metadata.request_charge,
+    }))
+}
+
+/// How [`bulk_import`] writes each document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Insert or replace documents (the default).
+    #[default]
+    Upsert,
+    /// Insert only; documents whose `id` already exists fail.
+    Create,
+}
+
+impl ImportMode {
+    fn operation_type(self) -> &'static str {
+        match self {
+            ImportMode::Upsert => "Upsert",
+            ImportMode::Create => "Create",
+        }
+    }
+}
+
+/// Resolve `path` (relative to `import_dir`, or absolute) to a file inside
+/// `import_dir`, following symbolic links.
+async fn import_file_path(import_dir: Option<&Path>, path: &str) -> Result<PathBuf> {
+    let import_dir = import_dir.context(
+        "Importing from files is disabled.  Set COSMOS_IMPORT_DIR to the directory to \
+         import from, or pass `documents` inline.",
+    )?;
+    let resolved = tokio::fs::canonicalize(import_dir.join(path))
+        .await
+        .with_context(|| format!("Failed to read import file '{path}'"))?;
+    anyhow::ensure!(
+        resolved.starts_with(import_dir),
+        "Import file '{path}' is outside COSMOS_IMPORT_DIR"
+    );
+    Ok(resolved)
+}
+
+/// Load import documents from a file in `import_dir` (`COSMOS_IMPORT_DIR`)
+/// containing either a JSON array or newline-delimited JSON (one document
+/// per line).
+pub async fn load_import_file(import_dir: Option<&Path>, path: &str) -> Result<Vec<Value>> {
+    let file = import_file_path(import_dir, path).await?;
+    let text = tokio::fs::read_to_string(&file)
+        .await
+        .with_context(|| format!("Failed to read import file '{path}'"))?;
+
+    if let Ok(Value::Array(documents)) = serde_json::from_str::<Value>(&text) {
+        return Ok(documents);
+    }
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(n, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("Import file '{path}' line {}: invalid JSON", n + 1))
+        })
+        .collect()
+}
+
+/// Extract the partition key value(s) of `document` for the container's
+/// partition key `paths`, as the JSON array the REST API expects.
+fn document_partition_key(document: &Value, paths: &[String]) -> Result<Value> {
+    let values = paths
+        .iter()
+        .map(|path| {
+            document
+                .pointer(path)
+                .cloned()
+                .with_context(|| format!("document is missing partition key property '{path}'"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Value::Array(values))
+}
+
+/// Group document indices into transactional batches: one logical partition
+/// per batch and at most [`MAX_BATCH_OPERATIONS`] operations each.
+///
+/// Documents whose partition key cannot be determined are returned
+/// separately with the reason.
+#[allow(clippy::type_complexity)]
+fn plan_batches(
+    documents: &[Value],
+    paths: &[String],
+) -> (Vec<(Value, Vec<usize>)>, Vec<(usize, String)>) {
+    let mut groups: BTreeMap<String, (Value, Vec<usize>)> = BTreeMap::new();
+    let mut rejected = Vec::new();
+
+    for (index, document) in documents.iter().enumerate() {
+        if !document.get("id").is_some_and(Value::is_string) {
+            rejected.push((index, "document must have a string `id`".to_string()));
+            continue;
+        }
+        match document_partition_key(document, paths) {
+            Ok(pk) => groups
+                .entry(pk.to_string())
+                .or_insert_with(|| (pk, Vec::new()))
+                .1
+                .push(index),
+            Err(e) => rejected.push((index, e.to_string())),
+        }
+    }
+
+    let batches = groups
+        .into_values()
+        .flat_map(|(pk, indices)| {
+            indices
+                .chunks(MAX_BATCH_OPERATIONS)
+                .map(|chunk| (pk.clone(), chunk.to_vec()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    (batches, rejected)
+}
+
+/// Write many documents to a container.
+///
+/// Documents are grouped by partition key into atomic transactional batches
+/// of up to 100 operations, and up to `concurrency` batches are written at
+/// once (default [`DEFAULT_IMPORT_CONCURRENCY`]).  A failing document rolls
+/// back only its own batch; every document gets a per-item result.
+///
+/// Returns `{"succeeded": n, "failed": n, "request_charge": ru, "results": [...]}`
+/// where each result carries the document's input `index`, `id` and `status`
+/// (plus `error` on failure).
+pub async fn bulk_import(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    documents: Vec<Value>,
+    mode: ImportMode,
+    concurrency: usize,
+) -> Result<Value> {
+    if documents.is_empty() {
+        bail!("No documents to import");
+    }
+    if documents.len() > HARD_MAX_IMPORT_DOCUMENTS {
+        bail!(
+            "Too many documents ({}); at most {HARD_MAX_IMPORT_DOCUMENTS} may be imported per call",
+            documents.len()
+        );
+    }
+    let concurrency = concurrency.clamp(1, HARD_MAX_IMPORT_CONCURRENCY);
+
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+    let properties = rest.read_collection(&link).await?;
+    let paths: Vec<String> = properties["partitionKey"]["paths"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|p| p.as_str().map(str::to_string))
+        .collect();
+    if paths.is_empty() {
+        bail!("Could not determine the partition key path of container '{container}'");
+    }
+
+    let (batches, rejected) = plan_batches(&documents, &paths);
+    let mut results: Vec<Value> = vec![Value::Null; documents.len()];
+    for (index, error) in rejected {
+        results[index] = serde_json::json!({ "index": index, "status": 400, "error": error });
+    }
+
+    let documents = &documents;
+    let link = link.as_str();
+    let mut outcomes = futures::stream::iter(batches)
+        .map(|(pk, indices)| async move {
+            let operations: Vec<Value> = indices
+                .iter()
+                .map(|&i| {
+                    serde_json::json!({
+                        "operationType": mode.operation_type(),
+                        "resourceBody": documents[i],
+                    })
+                })
+                .collect();
+            let outcome = rest.execute_batch(link, &pk, &operations).await;
+            (indices, outcome)
+        })
+        .buffer_unordered(concurrency);
+
+    let mut metadata = QueryMetadata::default();
+    while let Some((indices, outcome)) = outcomes.next().await {
+        match outcome {
+            Ok(response) => {
+                metadata.record_response(&response);
+                let statuses = response.body.as_array().cloned().unwrap_or_default();
+                for (n, &index) in indices.iter().enumerate() {
+                    let status = statuses
+                        .get(n)
+                        .and_then(|r| r.get("statusCode"))
+                        .and_then(Value::as_u64)
+                        .unwrap_or(0);
+                    results[index] = if (200..300).contains(&status) {
+                        serde_json::json!({ "index": index, "status": status })
+                    } else if status == 424 {
+                        serde_json::json!({
+                            "index": index,
+                            "status": status,
+                            "error": "not written: another document in the same transactional batch failed",
+                        })
+                    } else {
+                        serde_json::json!({
+                            "index": index,
+                            "status": status,
+                            "error": "write failed",
+                        })
+                    };
+                }
+            }
+            Err(e) => {
+                for &index in &indices {
+                    results[index] = serde_json::json!({ "index": index, "error": e.to_string() });
+                }
+            }
+        }
+    }
+
+    let mut succeeded = 0;
+    for (index, result) in results.iter_mut().enumerate() {
+        if let Some(id) = documents[index].get("id") {
+            result["id"] = id.clone();
+        }
+        if result.get("error").is_none() {
+            succeeded += 1;
+        }
+    }
+
+    Ok(serde_json::json!({
+        "succeeded": succeeded,
+        "failed": results.len() - succeeded,
+        "request_charge": metadata.request_charge,
+        "results": results,
+    }))
+}
+
+/// Explain a container `defaultTtl` / item `ttl` setting in words.
+fn describe_ttl(ttl: Option<i64>) -> String {
+    match ttl {
+        None => "disabled: items never expire".to_string(),
+        Some(-1) => "enabled with no default: only items with their own ttl expire".to_string(),
+        Some(seconds) => format!("items expire {seconds} seconds after their last write"),
+    }
+}
+
+/// Read the time-to-live settings of a container and, when `item` (an `id`
+/// and partition key) is given, the `ttl` of that document.
+///
+/// A container `default_ttl` of `null` means TTL is disabled, `-1` means it is
+/// enabled with no default, and a positive value is the default lifetime in
+/// seconds.  An item `ttl` overrides the container default.
+pub async fn get_ttl(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    item: Option<(&str, &str)>,
+) -> Result<Value> {
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+    let properties = rest.read_collection(&link).await?;
+    let default_ttl = properties.get("defaultTtl").and_then(Value::as_i64);
+
+    let mut result = serde_json::json!({
+        "container": container,
+        "default_ttl": default_ttl,
+        "description": describe_ttl(default_ttl),
+    });
+
+    if let Some((item_id, partition_key)) = item {
+        let client = account.sdk()?;
+        let document: Value = client
+            .database_client(database)
+            .container_client(container)
+            .read_item(PartitionKey::from(partition_key.to_string()), item_id, None)
+            .await
+            .with_context(|| format!("Failed to read Cosmos DB item '{item_id}'"))?
+            .into_model()
+            .context("Failed to deserialize Cosmos DB item")?;
+        let item_ttl = document.get("ttl").and_then(Value::as_i64);
+        let effective = match (default_ttl, item_ttl) {
+            (None, _) => None,
+            (Some(_), Some(ttl)) => Some(ttl),
+            (Some(default), None) => Some(default),
+        };
+        result["item"] = serde_json::json!({
+            "id": item_id,
+            "ttl": item_ttl,
+            "effective_ttl": effective,
+            "last_modified": document.get("_ts"),
+            "description": describe_ttl(effective),
+        });
+    }
+
+    Ok(result)
+}
+
+/// Set (or with `None`, disable) the default time-to-live of a container.
+///
+/// `default_ttl` must be `-1` (enabled, no default) or a positive number of
+/// seconds.
+pub async fn set_container_ttl(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    default_ttl: Option<i64>,
+) -> Result<Value> {
+    if let Some(ttl) = default_ttl {
+        if ttl == 0 || ttl < -1 {
+            bail!("default_ttl must be -1 or a positive number of seconds (got {ttl})");
+        }
+    }
+
+    let rest = &account.rest;
+    let link = format!("dbs/{database}/colls/{container}");
+    let mut properties = rest.read_collection(&link).await?;
+    let Some(fields) = properties.as_object_mut() else {
+        bail!("Unexpected container properties returned for '{container}'");
+    };
+    let previous = fields.get("defaultTtl").and_then(Value::as_i64);
+    match default_ttl {
+        Some(ttl) => fields.insert("defaultTtl".to_string(), ttl.into()),
+        None => fields.remove("defaultTtl"),
+    };
+
+    rest.replace_collection(&link, &properties)
+        .await
+        .with_context(|| format!("Failed to update TTL of container '{container}'"))?;
+
+    Ok(serde_json::json!({
+        "container": container,
+        "previous_default_ttl": previous,
+        "default_ttl": default_ttl,
+        "description": describe_ttl(default_ttl),
+    }))
+}
+
+/// Set (or with `None`, remove) the `ttl` property of one document.
+///
+/// `ttl` must be `-1` (never expire) or a positive number of seconds.  It only
+/// takes effect when TTL is enabled on the container.
+pub async fn set_item_ttl(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    item_id: &str,
+    partition_key: &str,
+    ttl: Option<i64>,
+) -> Result<Value> {
+    let operation = match ttl {
+        Some(t) if t == 0 || t < -1 => {
+            bail!("ttl must be -1 or a positive number of seconds (got {t})")
+        }
+        Some(t) => PatchOperation {
+            op: PatchOp::Set,
+            path: "/ttl".to_string(),
+            value: Some(t.into()),
+        },
+        None => PatchOperation {
+            op: PatchOp::Remove,
+            path: "/ttl".to_string(),
+            value: None,
+        },
+    };
+
+    patch_item(
+        account,
+        database,
+        container,
+        item_id,
+        partition_key,
+        &[operation],
+    )
+    .await?;
+
+    Ok(serde_json::json!({ "id": item_id, "ttl": ttl }))
+}
+
+/// Build provisioned-throughput settings from tool parameters.
+///
+/// `throughput` requests fixed (manual) RU/s; `autoscale_max_throughput`
+/// requests autoscale with the given maximum RU/s.  Neither means the
+/// resource is created without dedicated throughput (serverless accounts, or
+/// containers sharing database throughput).
+fn throughput_properties(
+    throughput: Option<u32>,
+    autoscale_max_throughput: Option<u32>,
+) -> Result<Option<ThroughputProperties>> {
+    match (throughput, autoscale_max_throughput) {
+        (Some(_), Some(_)) => {
+            bail!("Specify either throughput or autoscale_max_throughput, not both")
+        }
+        (Some(ru), None) => Ok(Some(ThroughputProperties::manual(ru as usize))),
+        (None, Some(max)) => Ok(Some(ThroughputProperties::autoscale(max as usize, None))),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Create a database in the Cosmos DB account.
+///
+/// Returns a JSON object describing the created database.
+pub async fn create_database(
+    account: &CosmosAccount,
+    database: &str,
+    throughput: Option<u32>,
+    autoscale_max_throughput: Option<u32>,
+) -> Result<Value> {
+    let options = CreateDatabaseOptions {
+        throughput: throughput_properties(throughput, autoscale_max_throughput)?,
+        ..Default::default()
+    };
+    let client = account.sdk()?;
+
+    client
+        .create_database(database, Some(options))
+        .await
+        .with_context(|| format!("Failed to create Cosmos DB database '{database}'"))?;
+
+    Ok(serde_json::json!({
+        "database": database,
+        "throughput": throughput,
+        "autoscale_max_throughput": autoscale_max_throughput,
+    }))
+}
+
+/// Create a container in an existing Cosmos DB database.
+///
+/// `partition_key_path` is the JSON path of the partition key, e.g. `/tenantId`.
+/// Returns a JSON object describing the created container.
+pub async fn create_container(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    partition_key_path: &str,
+    throughput: Option<u32>,
+    autoscale_max_throughput: Option<u32>,
+) -> Result<Value> {
+    if !partition_key_path.starts_with('/') {
+        bail!("partition_key_path must start with '/', e.g. \"/tenantId\"");
+    }
+    let options = CreateContainerOptions {
+        throughput: throughput_properties(throughput, autoscale_max_throughput)?,
+        ..Default::default()
+    };
+    let properties = ContainerProperties {
+        id: container.to_string().into(),
+        partition_key: PartitionKeyDefinition::from(partition_key_path.to_string()),
+        ..Default::default()
+    };
+    let client = account.sdk()?;
+
+    client
+        .database_client(database)
+        .create_container(properties, Some(options))
+        .await
+        .with_context(|| {
+            format!("Failed to create Cosmos DB container '{container}' in database '{database}'")
+        })?;
+
+    Ok(serde_json::json!({
+        "database": database,
+        "container": container,
+        "partition_key_path": partition_key_path,
+        "throughput": throughput,
+        "autoscale_max_throughput": autoscale_max_throughput,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    /// Unit tests for Cosmos DB module helpers.
+    /// Integration tests require a live Cosmos DB account and are excluded from
+    /// the standard test run.
+    use super::*;
+    use crate::config::{
+        CosmosQueryPolicy, RetryPolicy, DEFAULT_MAX_RESULT_BYTES, DEFAULT_QUERY_PARALLELISM,
+    };
+
+    #[test]
+    #[allow(clippy::unnecessary_min_or_max)]
+    fn max_items_is_capped_at_5000() {
+        // Verify the public cap constant in the function signature.
+        let capped = 10_000_u32.min(5_000);
+        assert_eq!(capped, 5_000);
+    }
+
+    fn op(op: PatchOp, path: &str, value: Option<Value>) -> PatchOperation {
+        PatchOperation {
+            op,
+            path: path.into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn patch_document_rejects_invalid_operations() {
+        assert!(build_patch_document(&[]).is_err());
+        assert!(build_patch_document(&[op(PatchOp::Set, "name", Some("x".into()))]).is_err());
+        assert!(build_patch_document(&[op(PatchOp::Add, "/tags", None)]).is_err());
+        assert!(
+            build_patch_document(&[op(PatchOp::Increment, "/count", Some("1".into()))]).is_err()
+        );
+    }
+
+    #[test]
+    fn patch_document_accepts_valid_operations() {
+        let ops = [
+            op(PatchOp::Set, "/name", Some("widget".into())),
+            op(PatchOp::Remove, "/obsolete", None),
+            op(PatchOp::Increment, "/count", Some(2.into())),
+        ];
+        assert!(build_patch_document(&ops).is_ok());
+    }
+
+    #[test]
+    fn query_metadata_aggregates_page_headers() {
+        let mut metadata = QueryMetadata::default();
+        metadata.record_values(Some("2.5"), Some("a1"));
+        metadata.record_values(Some(" 3.25 "), None);
+        metadata.record_values(Some("not-a-number"), Some(""));
+
+        assert_eq!(metadata.page_count, 3);
+        assert!((metadata.request_charge - 5.75).abs() < f64::EPSILON);
+        assert_eq!(metadata.activity_ids, vec!["a1".to_string()]);
+    }
+
+    #[test]
+    fn cross_partition_top_accepts_plain_and_top_queries() {
+        let plain = serde_json::json!({
+            "distinctType": "None", "top": null, "orderBy": [], "aggregates": [],
+            "groupByExpressions": [], "groupByAliasToAggregateType": {},
+            "offset": null, "limit": null
+        });
+        assert_eq!(cross_partition_top(&plain).unwrap(), None);
+
+        let mut top = plain.clone();
+        top["top"] = 10.into();
+        assert_eq!(cross_partition_top(&top).unwrap(), Some(10));
+    }
+
+    #[test]
+    fn cross_partition_top_rejects_queries_needing_a_merge() {
+        for (field, value) in [
+            ("orderBy", serde_json::json!(["Ascending"])),
+            ("aggregates", serde_json::json!(["Count"])),
+            ("groupByExpressions", serde_json::json!(["c.city"])),
+            ("distinctType", serde_json::json!("Unordered")),
+            ("offset", serde_json::json!(5)),
+        ] {
+            let info = serde_json::json!({ field: value });
+            let err = cross_partition_top(&info).unwrap_err().to_string();
+            assert!(err.contains("partition_key"), "{field}: {err}");
+        }
+    }
+
+    #[test]
+    fn change_feed_state_round_trips_and_picks_start() {
+        let mut state = ChangeFeedState {
+            since: Some("2026-01-01T00:00:00Z".parse().unwrap()),
+            ranges: BTreeMap::new(),
+        };
+        state.ranges.insert("0".into(), "\"42\"".into());
+
+        let decoded = ChangeFeedState::decode(&state.encode()).unwrap();
+        assert_eq!(decoded, state);
+        assert!(matches!(
+            decoded.start_for("0"),
+            ChangeFeedStart::After("\"42\"")
+        ));
+        assert!(matches!(decoded.start_for("1"), ChangeFeedStart::Since(_)));
+        assert!(matches!(
+            ChangeFeedState::default().start_for("0"),
+            ChangeFeedStart::Beginning
+        ));
+        assert!(ChangeFeedState::decode("not a token").is_err());
+    }
+
+    #[test]
+    fn throughput_properties_rejects_conflicting_settings() {
+        assert!(throughput_properties(Some(400), Some(4000)).is_err());
+        assert!(throughput_properties(None, None).unwrap().is_none());
+        assert!(throughput_properties(Some(400), None).unwrap().is_some());
+        assert!(throughput_properties(None, Some(4000)).unwrap().is_some());
+    }
+
+    #[test]
+    fn parse_semicolon_pairs_reads_known_fields() {
+        let usage: BTreeMap<String, i64> = parse_semicolon_pairs(
+            "functions=0;documentsSize=42;documentsCount=1200;collectionSize=58;bogus",
+        );
+        assert_eq!(usage.get("documentsCount"), Some(&1200));
+        assert_eq!(usage.get("documentsSize"), Some(&42));
+        assert_eq!(usage.get("collectionSize"), Some(&58));
+        assert!(!usage.contains_key("bogus"));
+    }
+
+    #[test]
+    fn query_metrics_aggregate_across_pages() {
+        let mut metrics = QueryMetrics::default();
+        metrics.add_page(
+            "totalExecutionTimeInMs=1.5;retrievedDocumentCount=10;\
+             outputDocumentCount=5;indexUtilizationRatio=1.00",
+        );
+        metrics.add_page(
+            "totalExecutionTimeInMs=2.5;retrievedDocumentCount=30;\
+             outputDocumentCount=5;indexUtilizationRatio=0.50",
+        );
+
+        assert!((metrics.total_execution_time_ms - 4.0).abs() < 1e-9);
+        assert!((metrics.retrieved_document_count - 40.0).abs() < 1e-9);
+        assert!((metrics.output_document_count - 10.0).abs() < 1e-9);
+        // (10 * 1.0 + 30 * 0.5) / 40
+        assert!((metrics.index_hit_ratio - 0.625).abs() < 1e-9);
+        assert!(!metrics.raw.contains_key("indexUtilizationRatio"));
+    }
+
+    #[test]
+    fn plan_batches_groups_by_partition_key_and_caps_batch_size() {
+        let paths = vec!["/tenant".to_string()];
+        let mut documents: Vec<Value> = (0..150)
+            .map(|i| serde_json::json!({ "id": i.to_string(), "tenant": "a" }))
+            .collect();
+        documents.push(serde_json::json!({ "id": "b1", "tenant": "b" }));
+        documents.push(serde_json::json!({ "id": "no-pk" }));
+        documents.push(serde_json::json!({ "tenant": "a" }));
+
+        let (batches, rejected) = plan_batches(&documents, &paths);
+
+        let sizes: Vec<usize> = batches.iter().map(|(_, idx)| idx.len()).collect();
+        assert_eq!(sizes, vec![100, 50, 1]);
+        assert_eq!(batches[2].0, serde_json::json!(["b"]));
+        let rejected: Vec<usize> = rejected.iter().map(|(i, _)| *i).collect();
+        assert_eq!(rejected, vec![151, 152]);
+    }
+
+    #[test]
+    fn describe_ttl_covers_each_mode() {
+        assert!(describe_ttl(None).starts_with("disabled"));
+        assert!(describe_ttl(Some(-1)).contains("no default"));
+        assert!(describe_ttl(Some(3600)).contains("3600 seconds"));
+    }
+
+    #[test]
+    fn query_metadata_reports_exhausted_budget() {
+        let mut unlimited = QueryMetadata::with_budget(None);
+        unlimited.record_values(Some("1000"), None);
+        assert!(!unlimited.over_budget());
+
+        let mut limited = QueryMetadata::with_budget(Some(10.0));
+        limited.record_values(Some("6"), None);
+        assert!(!limited.over_budget());
+        limited.record_values(Some("6"), None);
+        assert!(limited.over_budget());
+        assert!(limited.budget_exhausted);
+    }
+
+    #[test]
+    fn query_metadata_counts_integrated_cache_hits() {
+        let page = |hit: Option<&'static str>| RestResponse {
+            status: reqwest::StatusCode::OK,
+            body: Value::Null,
+            headers: hit
+                .map(|hit| {
+                    let mut headers = reqwest::header::HeaderMap::new();
+                    headers.insert("x-ms-cosmos-cachehit", hit.parse().unwrap());
+                    headers
+                })
+                .unwrap_or_default(),
+        };
+        let mut direct = QueryMetadata::default();
+        direct.record_response(&page(None));
+        assert_eq!(direct.integrated_cache_hits, None);
+
+        let mut cached = QueryMetadata::default();
+        cached.record_response(&page(Some("True")));
+        cached.record_response(&page(Some("False")));
+        assert_eq!(cached.integrated_cache_hits, Some(1));
+    }
+
+    #[test]
+    fn query_metadata_reports_exhausted_memory_budget() {
+        let page = vec![serde_json::json!({ "id": "1", "notes": "x".repeat(100) })];
+        let mut metadata = QueryMetadata::default();
+        assert!(!metadata.over_memory_budget(&page, 200));
+        assert!(!metadata.memory_budget_exhausted);
+        assert!(metadata.over_memory_budget(&page, 200));
+        assert!(metadata.memory_budget_exhausted);
+        assert!(!serde_json::to_value(&metadata).unwrap()["result_bytes"].is_number());
+    }
+
+    #[test]
+    fn summarise_conflict_decodes_losing_version() {
+        let entry = serde_json::json!({
+            "id": "c1",
+            "operationType": "replace",
+            "resourceType": "document",
+            "resourceId": "abc==",
+            "conflict_lsn": 42,
+            "_ts": 1_700_000_000,
+            "content": "{\"id\":\"order-1\",\"tenant\":\"t1\",\"total\":5}",
+        });
+        let summary = summarise_conflict(&entry);
+        assert_eq!(summary["operation_type"], "replace");
+        assert_eq!(summary["conflicting_version"]["total"], 5);
+
+        let raw = serde_json::json!({ "id": "c2", "content": "not json" });
+        assert_eq!(summarise_conflict(&raw)["conflicting_version"], "not json");
+    }
+
+    #[test]
+    fn consistency_level_parses_cosmos_names() {
+        let level: ConsistencyLevel = serde_json::from_str("\"Eventual\"").unwrap();
+        assert_eq!(level, ConsistencyLevel::Eventual);
+        assert_eq!(
+            ConsistencyLevel::BoundedStaleness.as_header(),
+            "BoundedStaleness"
+        );
+        assert!(serde_json::from_str::<ConsistencyLevel>("\"eventual\"").is_err());
+    }
+
+    #[test]
+    fn property_accessor_quotes_each_segment() {
+        assert_eq!(
+            property_accessor("/embedding").unwrap(),
+            r#"c["embedding"]"#
+        );
+        assert_eq!(
+            property_accessor("content/vector").unwrap(),
+            r#"c["content"]["vector"]"#
+        );
+        assert!(property_accessor("/").is_err());
+        assert!(property_accessor("/a\"]").is_err());
+    }
+
+    #[test]
+    fn rank_results_orders_by_direction() {
+        let results = vec![
+            serde_json::json!({ "score": 0.2 }),
+            serde_json::json!({ "score": 0.9 }),
+            serde_json::json!({ "score": 0.5 }),
+        ];
+        let best = rank_results(results.clone(), true, 2);
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[0]["score"], 0.9);
+        assert_eq!(best[1]["score"], 0.5);
+
+        let nearest = rank_results(results, false, 1);
+        assert_eq!(nearest[0]["score"], 0.2);
+    }
+
+    #[test]
+    fn text_search_sql_uses_full_text_functions() {
+        let terms = vec!["red".to_string(), "say \"hi\"".to_string()];
+        let sql =
+            text_search_sql(TextSearchMode::ContainsAny, "c[\"text\"]", &terms, None, 5).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT TOP 5 * FROM c WHERE FullTextContainsAny(c["text"], "red", "say \"hi\"")"#
+        );
+
+        let sql = text_search_sql(TextSearchMode::Rank, "c.t", &terms[..1], None, 3).unwrap();
+        assert!(sql.ends_with(r#"ORDER BY RANK FullTextScore(c.t, "red")"#));
+
+        let sql = text_search_sql(
+            TextSearchMode::Hybrid,
+            "c.t",
+            &terms[..1],
+            Some(("c.v", "[0.1,0.2]")),
+            3,
+        )
+        .unwrap();
+        assert!(sql.contains("RRF(VectorDistance(c.v, [0.1,0.2]), FullTextScore(c.t, \"red\"))"));
+
+        assert!(text_search_sql(TextSearchMode::Hybrid, "c.t", &terms, None, 3).is_err());
+        assert!(text_search_sql(TextSearchMode::Rank, "c.t", &[], None, 3).is_err());
+    }
+
+    #[test]
+    fn analytical_prelude_uses_the_server_credential() {
+        let mut config = CosmosConfig {
+            endpoint: "https://myacct.documents.azure.com:443/".into(),
+            key: Some("k3y==".into()),
+            default_database: None,
+            allow_writes: false,
+            allow_management: false,
+            max_request_units: None,
+            session_max_request_units: None,
+            preferred_regions: Vec::new(),
+            query_parallelism: DEFAULT_QUERY_PARALLELISM,
+            integrated_cache: None,
+            query_policy: CosmosQueryPolicy::default(),
+            analytical: None,
+            analytical_credential: None,
+            import_dir: None,
+            retry: RetryPolicy::default(),
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+        };
+        assert!(analytical_prelude(&config, "shop", "orders").is_err());
+
+        config.analytical_credential = Some("cosmos-key".into());
+        let prelude = analytical_prelude(&config, "shop", "o'rders").unwrap();
+        assert!(prelude.contains("OBJECT = 'o''rders'"));
+        assert!(prelude.contains("SERVER_CREDENTIAL = 'cosmos-key'"));
+        assert!(!prelude.contains("k3y=="));
+    }
+
+    #[test]
+    fn document_shape_counts_types_per_path() {
+        let documents = vec![
+            serde_json::json!({ "id": "1", "total": 5, "tags": ["a"], "_etag": "x" }),
+            serde_json::json!({ "id": "2", "total": null, "address": { "city": "Oslo" } }),
+        ];
+        let shape = document_shape(&documents);
+        assert_eq!(shape["/id"]["string"], 2);
+        assert_eq!(shape["/total"]["number"], 1);
+        assert_eq!(shape["/total"]["null"], 1);
+        assert_eq!(shape["/tags[]"]["string"], 1);
+        assert_eq!(shape["/address/city"]["string"], 1);
+        assert!(shape.get("/_etag").is_none());
+    }
+
+    #[tokio::test]
+    async fn import_files_must_be_inside_the_import_dir() {
+        let root = std::env::temp_dir().join(format!("import-{}", uuid::Uuid::new_v4()));
+        let dir = root.join("imports");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("orders.jsonl"),
+            "{\"id\": \"1\"}\n{\"id\": \"2\"}\n",
+        )
+        .unwrap();
+        std::fs::write(root.join("secret.json"), "[]").unwrap();
+        let dir = std::fs::canonicalize(&dir).unwrap();
+
+        let documents = load_import_file(Some(&dir), "orders.jsonl").await;
+        let escaped = load_import_file(Some(&dir), "../secret.json").await;
+        let absolute =
+            load_import_file(Some(&dir), root.join("secret.json").to_str().unwrap()).await;
+        let disabled = load_import_file(None, "orders.jsonl").await;
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(documents.unwrap().len(), 2);
+        assert!(escaped
+            .unwrap_err()
+            .to_string()
+            .contains("outside COSMOS_IMPORT_DIR"));
+        assert!(absolute.is_err());
+        assert!(disabled
+            .unwrap_err()
+            .to_string()
+            .contains("COSMOS_IMPORT_DIR"));
     }
 }
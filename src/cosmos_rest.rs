@@ -0,0 +1,962 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{header::HeaderMap, Method, RequestBuilder, StatusCode};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::OnceCell;
+
+use crate::config::{CosmosConfig, IntegratedCacheConfig, RetryPolicy};
+use crate::correlation;
+use crate::recording;
+use crate::retry;
+
+/// Response / request header carrying Cosmos DB session tokens.
+const SESSION_TOKEN_HEADER: &str = "x-ms-session-token";
+
+/// REST API version sent with every request.
+const API_VERSION: &str = "2020-07-15";
+
+/// Most entries asked for per page of a feed read with a limit.
+const MAX_FEED_PAGE_SIZE: usize = 1000;
+
+/// Query features this client understands when requesting a query plan.
+///
+/// The gateway uses this list to decide whether it can produce a plan for the
+/// query; anything not listed is rejected by the service up front.
+const SUPPORTED_QUERY_FEATURES: &str = "NonValueAggregate, Aggregate, Distinct, MultipleOrderBy, \
+                                        OffsetAndLimit, OrderBy, Top, CompositeAggregate, \
+                                        GroupBy, MultipleAggregates";
+
+/// A partition key range of a container, as returned by the `pkranges` feed.
+#[derive(Debug, Clone)]
+pub struct PartitionKeyRange {
+    pub id: String,
+}
+
+/// A decoded REST response: the JSON body plus the headers that carry request
+/// charge, activity ID and continuation information.
+#[derive(Debug)]
+pub struct RestResponse {
+    pub status: StatusCode,
+    pub body: Value,
+    pub headers: HeaderMap,
+}
+
+/// An error status returned by the Cosmos DB REST API.
+#[derive(Debug)]
+pub struct ErrorResponse {
+    pub status: StatusCode,
+    pub message: String,
+    /// How long the service asked to wait before retrying
+    /// (`x-ms-retry-after-ms`).
+    pub retry_after: Option<Duration>,
+    /// Whether the request only read (a `GET` or a query).
+    pub idempotent: bool,
+}
+
+impl ErrorResponse {
+    /// Whether the request may succeed when sent again.  Throttled (429) and
+    /// "retry with" (449) requests were not executed; a request that timed
+    /// out (408) or met an unavailable service (503) may have been, so only
+    /// reads are retried then.
+    pub fn is_transient(&self) -> bool {
+        match self.status.as_u16() {
+            429 | 449 => true,
+            408 | 503 => self.idempotent,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cosmos DB returned {}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ErrorResponse {}
+
+/// One page of a query, as requested by [`CosmosRestClient::query_page`].
+#[derive(Debug, Clone, Copy)]
+pub struct PageRequest<'a> {
+    pub sql: &'a str,
+    pub scope: QueryScope<'a>,
+    /// The continuation token of the previous page, if any.
+    pub continuation: Option<&'a str>,
+    pub page_size: u32,
+    /// Ask the service for query execution metrics.
+    pub populate_metrics: bool,
+    /// The `x-ms-consistency-level` to read at, if not the account's.
+    pub consistency: Option<&'a str>,
+}
+
+/// The partition scope of a single REST query request.
+#[derive(Debug, Clone, Copy)]
+pub enum QueryScope<'a> {
+    /// One physical partition key range (used for cross-partition fan-out).
+    PartitionKeyRange(&'a str),
+    /// One logical partition, identified by its partition key value.
+    PartitionKey(&'a str),
+}
+
+/// Where a change feed read for one partition key range starts.
+#[derive(Debug, Clone, Copy)]
+pub enum ChangeFeedStart<'a> {
+    /// From the first change retained by the container.
+    Beginning,
+    /// From changes made at or after the given time.
+    Since(DateTime<Utc>),
+    /// After the position identified by an `etag` from a previous read.
+    After(&'a str),
+}
+
+impl RestResponse {
+    /// Return a response header as a string, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+
+    /// The continuation token for the next page, if more results exist.
+    pub fn continuation(&self) -> Option<String> {
+        self.header("x-ms-continuation")
+            .filter(|c| !c.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Whether the service answered `304 Not Modified` (no new changes).
+    pub fn is_not_modified(&self) -> bool {
+        self.status == StatusCode::NOT_MODIFIED
+    }
+
+    /// The raw `x-ms-documentdb-query-metrics` header, when metrics were requested.
+    pub fn query_metrics(&self) -> Option<&str> {
+        self.header("x-ms-documentdb-query-metrics")
+    }
+
+    pub fn request_charge(&self) -> Option<&str> {
+        self.header("x-ms-request-charge")
+    }
+
+    pub fn activity_id(&self) -> Option<&str> {
+        self.header("x-ms-activity-id")
+    }
+
+    /// Whether the dedicated gateway answered from its integrated cache;
+    /// `None` for requests that did not go through one.
+    pub fn cache_hit(&self) -> Option<bool> {
+        self.header("x-ms-cosmos-cachehit")
+            .map(|hit| hit.eq_ignore_ascii_case("true"))
+    }
+}
+
+/// Minimal signed client for the Cosmos DB REST API.
+///
+/// Used for operations that `azure_data_cosmos` does not expose, such as
+/// query plans and partition-key-range scoped queries.  Requests are
+/// authorised with the account key using the master-key HMAC scheme.
+///
+/// When preferred regions are configured, reads (queries, point reads and the
+/// change feed) are sent to the regional endpoint of the first preferred
+/// region the account replicates to; everything else uses `endpoint`.
+///
+/// Session tokens returned by writes and reads are remembered per container
+/// and sent with later queries and point reads, so documents written through
+/// this server are visible to its next read (read-your-own-writes) under
+/// session consistency.
+///
+/// Throttled and transiently failed requests are retried per the configured
+/// [`RetryPolicy`].
+pub struct CosmosRestClient {
+    http: reqwest::Client,
+    retry: RetryPolicy,
+    max_result_bytes: usize,
+    endpoint: String,
+    key: Vec<u8>,
+    preferred_regions: Vec<String>,
+    read_endpoint: OnceCell<String>,
+    integrated_cache: Option<IntegratedCacheConfig>,
+    session_tokens: Mutex<HashMap<String, BTreeMap<String, String>>>,
+}
+
+impl CosmosRestClient {
+    /// Build a REST client from the supplied configuration.
+    pub fn new(cfg: &CosmosConfig) -> Result<Self> {
+        let Some(key) = &cfg.key else {
+            bail!("Cosmos DB authentication requires COSMOS_KEY to be set.");
+        };
+        let key = BASE64
+            .decode(key.trim())
+            .context("COSMOS_KEY is not a valid base64 account key")?;
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            retry: cfg.retry,
+            max_result_bytes: cfg.max_result_bytes,
+            endpoint: cfg.endpoint.trim_end_matches('/').to_string(),
+            key,
+            preferred_regions: cfg.preferred_regions.clone(),
+            read_endpoint: OnceCell::new(),
+            integrated_cache: cfg.integrated_cache.clone(),
+            session_tokens: Mutex::default(),
+        })
+    }
+
+    /// The approximate size in bytes a query's collected items may reach
+    /// (`MAX_RESULT_BYTES`).
+    pub fn max_result_bytes(&self) -> usize {
+        self.max_result_bytes
+    }
+
+    /// Remember the session token `token` (a `x-ms-session-token` header
+    /// value) returned by a request against the container at
+    /// `collection_link`.
+    pub fn record_session_token(&self, collection_link: &str, token: &str) {
+        let mut sessions = self.session_tokens.lock().unwrap();
+        merge_session_token(
+            sessions.entry(collection_link.to_string()).or_default(),
+            token,
+        );
+    }
+
+    /// The session token to send with reads of the container at
+    /// `collection_link`, if anything has been written or read there.
+    pub fn session_token(&self, collection_link: &str) -> Option<String> {
+        let sessions = self.session_tokens.lock().unwrap();
+        let tokens = sessions.get(collection_link)?;
+        (!tokens.is_empty()).then(|| {
+            tokens
+                .iter()
+                .map(|(range, token)| format!("{range}:{token}"))
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    /// Attach the container's session token (if any) to a read request.
+    fn with_session(&self, request: RequestBuilder, collection_link: &str) -> RequestBuilder {
+        match self.session_token(collection_link) {
+            Some(token) => request.header(SESSION_TOKEN_HEADER, token),
+            None => request,
+        }
+    }
+
+    /// [`send`](Self::send) a request against the container at
+    /// `collection_link`, remembering the session token it returns.
+    async fn send_in(
+        &self,
+        collection_link: &str,
+        request: RequestBuilder,
+    ) -> Result<RestResponse> {
+        let response = self.send(request).await?;
+        if let Some(token) = response.header(SESSION_TOKEN_HEADER) {
+            self.record_session_token(collection_link, token);
+        }
+        Ok(response)
+    }
+
+    /// The endpoint reads are sent to, resolved once from the account's
+    /// readable locations and [`preferred_regions`](Self::preferred_regions).
+    ///
+    /// Falls back to the account endpoint when no preferred region is
+    /// configured, none of them is a readable location, or the account
+    /// metadata cannot be read.
+    async fn read_endpoint(&self) -> &str {
+        if self.preferred_regions.is_empty() {
+            return &self.endpoint;
+        }
+
+        self.read_endpoint
+            .get_or_init(|| async {
+                let request = self.request(Method::GET, "", "", "");
+                let regional = match self.send(request).await {
+                    Ok(account) => select_read_endpoint(
+                        &account.body["readableLocations"],
+                        &self.preferred_regions,
+                    ),
+                    Err(e) => {
+                        tracing::warn!(
+                            "Failed to read Cosmos DB account locations, \
+                             reads will use the account endpoint: {e:#}"
+                        );
+                        None
+                    }
+                };
+                match regional {
+                    Some(endpoint) => {
+                        tracing::info!("Routing Cosmos DB reads to {endpoint}");
+                        endpoint
+                    }
+                    None => {
+                        tracing::warn!(
+                            "None of COSMOS_PREFERRED_REGIONS is a readable location \
+                             of the account; reads will use the account endpoint"
+                        );
+                        self.endpoint.clone()
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Start a signed request.
+    ///
+    /// `resource_type` and `resource_link` are the values used in the
+    /// authorisation signature (e.g. `docs` and `dbs/mydb/colls/orders`);
+    /// `path` is the URL path relative to the account endpoint.
+    pub fn request(
+        &self,
+        method: Method,
+        resource_type: &str,
+        resource_link: &str,
+        path: &str,
+    ) -> RequestBuilder {
+        self.request_to(&self.endpoint, method, resource_type, resource_link, path)
+    }
+
+    /// Start a signed read request, sent to the preferred read region when
+    /// one is configured (see [`request`](Self::request)).
+    pub async fn read_request(
+        &self,
+        method: Method,
+        resource_type: &str,
+        resource_link: &str,
+        path: &str,
+    ) -> RequestBuilder {
+        let endpoint = self.read_endpoint().await;
+        self.request_to(endpoint, method, resource_type, resource_link, path)
+    }
+
+    /// Start a signed point read or query, sent through the dedicated
+    /// gateway when one is configured so that the integrated cache can
+    /// answer it, and otherwise like [`read_request`](Self::read_request).
+    ///
+    /// The cache only serves requests made with session or eventual
+    /// consistency; others pass through to the backend.
+    async fn cached_read_request(
+        &self,
+        method: Method,
+        resource_type: &str,
+        resource_link: &str,
+        path: &str,
+    ) -> RequestBuilder {
+        let Some(cache) = &self.integrated_cache else {
+            return self
+                .read_request(method, resource_type, resource_link, path)
+                .await;
+        };
+        let request = self.request_to(&cache.endpoint, method, resource_type, resource_link, path);
+        match cache.max_staleness {
+            Some(staleness) => request.header(
+                "x-ms-dedicatedgateway-max-age",
+                staleness.as_millis().to_string(),
+            ),
+            None => request,
+        }
+    }
+
+    fn request_to(
+        &self,
+        endpoint: &str,
+        method: Method,
+        resource_type: &str,
+        resource_link: &str,
+        path: &str,
+    ) -> RequestBuilder {
+        let date = chrono::Utc::now()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        let auth = self.authorization(&method, resource_type, resource_link, &date);
+
+        let request = self
+            .http
+            .request(method, format!("{endpoint}/{path}"))
+            .header("authorization", auth)
+            .header("x-ms-date", date)
+            .header("x-ms-version", API_VERSION);
+        // The service echoes a client activity id in its own diagnostics.
+        match correlation::current() {
+            Some(id) => request.header("x-ms-activity-id", id),
+            None => request,
+        }
+    }
+
+    /// Compute the master-key authorisation header value.
+    fn authorization(
+        &self,
+        method: &Method,
+        resource_type: &str,
+        resource_link: &str,
+        date: &str,
+    ) -> String {
+        let payload = format!(
+            "{}\n{}\n{}\n{}\n\n",
+            method.as_str().to_lowercase(),
+            resource_type.to_lowercase(),
+            resource_link,
+            date.to_lowercase()
+        );
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        let signature = BASE64.encode(mac.finalize().into_bytes());
+
+        urlencoding::encode(&format!("type=master&ver=1.0&sig={signature}")).into_owned()
+    }
+
+    /// Send a request and decode the JSON response, turning non-success status
+    /// codes into errors that include the service's message.
+    ///
+    /// `304 Not Modified` (used by the change feed to signal "no new changes")
+    /// is returned as a successful response with a `null` body.
+    pub async fn send(&self, request: RequestBuilder) -> Result<RestResponse> {
+        let idempotent = request
+            .try_clone()
+            .and_then(|r| r.build().ok())
+            .is_some_and(|r| {
+                r.method() == Method::GET || r.headers().contains_key("x-ms-documentdb-isquery")
+            });
+        retry::run(self.retry, "Cosmos DB request", || async {
+            let request = request
+                .try_clone()
+                .context("Cosmos DB request body cannot be resent")?;
+            let response = recording::send(request)
+                .await
+                .context("Cosmos DB REST request failed")?;
+            Self::decode(response, idempotent).await
+        })
+        .await
+    }
+
+    async fn decode(response: reqwest::Response, idempotent: bool) -> Result<RestResponse> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let text = response
+            .text()
+            .await
+            .context("Failed to read Cosmos DB REST response body")?;
+
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            let message = serde_json::from_str::<Value>(&text)
+                .ok()
+                .and_then(|v| v.get("message").and_then(Value::as_str).map(str::to_string))
+                .unwrap_or(text);
+            let retry_after = headers
+                .get("x-ms-retry-after-ms")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse::<u64>().ok())
+                .map(Duration::from_millis);
+            return Err(ErrorResponse {
+                status,
+                message,
+                retry_after,
+                idempotent,
+            }
+            .into());
+        }
+
+        let body = if text.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(&text).context("Cosmos DB returned invalid JSON")?
+        };
+        Ok(RestResponse {
+            status,
+            body,
+            headers,
+        })
+    }
+
+    /// Fetch the gateway query plan for `sql` against the container at
+    /// `collection_link` (`dbs/{db}/colls/{container}`).
+    pub async fn query_plan(&self, collection_link: &str, sql: &str) -> Result<Value> {
+        let request = self
+            .read_request(
+                Method::POST,
+                "docs",
+                collection_link,
+                &format!("{}/docs", encode_link(collection_link)),
+            )
+            .await
+            .header("content-type", "application/query+json")
+            .header("x-ms-documentdb-isquery", "True")
+            .header("x-ms-documentdb-query-enablecrosspartition", "True")
+            .header("x-ms-cosmos-is-query-plan-request", "True")
+            .header(
+                "x-ms-cosmos-supported-query-features",
+                SUPPORTED_QUERY_FEATURES,
+            )
+            .header("x-ms-cosmos-query-version", "1.4")
+            .json(&serde_json::json!({ "query": sql, "parameters": [] }));
+
+        Ok(self
+            .send(request)
+            .await
+            .context("Failed to obtain Cosmos DB query plan")?
+            .body)
+    }
+
+    /// Read every entry of a child-resource feed of `parent_link`, following
+    /// continuation tokens.
+    ///
+    /// `resource_type` is the feed's path segment (e.g. `pkranges`, `sprocs`)
+    /// and `field` the response property holding the entries (e.g.
+    /// `PartitionKeyRanges`, `StoredProcedures`).
+    pub async fn read_feed(
+        &self,
+        parent_link: &str,
+        resource_type: &str,
+        field: &str,
+    ) -> Result<Vec<Value>> {
+        let (entries, _) = self
+            .read_feed_up_to(parent_link, resource_type, field, usize::MAX)
+            .await?;
+        Ok(entries)
+    }
+
+    /// Read at most `limit` entries of a child-resource feed (see
+    /// [`read_feed`](Self::read_feed)), a page at a time, stopping once
+    /// `limit` is reached.  Also returns whether the feed may hold more.
+    pub async fn read_feed_up_to(
+        &self,
+        parent_link: &str,
+        resource_type: &str,
+        field: &str,
+        limit: usize,
+    ) -> Result<(Vec<Value>, bool)> {
+        let mut entries = Vec::new();
+        let mut continuation: Option<String> = None;
+
+        loop {
+            let mut request = self.request(
+                Method::GET,
+                resource_type,
+                parent_link,
+                &format!("{}/{resource_type}", encode_link(parent_link)),
+            );
+            if limit != usize::MAX {
+                let remaining = (limit - entries.len()).min(MAX_FEED_PAGE_SIZE);
+                request = request.header("x-ms-max-item-count", remaining.to_string());
+            }
+            if let Some(token) = &continuation {
+                request = request.header("x-ms-continuation", token);
+            }
+
+            let mut response = self
+                .send(request)
+                .await
+                .with_context(|| format!("Failed to list Cosmos DB {resource_type}"))?;
+            if let Some(Value::Array(page)) = response.body.get_mut(field) {
+                entries.append(page);
+            }
+
+            continuation = response.continuation();
+            if entries.len() >= limit {
+                let more = entries.len() > limit || continuation.is_some();
+                entries.truncate(limit);
+                return Ok((entries, more));
+            }
+            if continuation.is_none() {
+                return Ok((entries, false));
+            }
+        }
+    }
+
+    /// List the partition key ranges of the container at `collection_link`.
+    pub async fn partition_key_ranges(
+        &self,
+        collection_link: &str,
+    ) -> Result<Vec<PartitionKeyRange>> {
+        let ranges = self
+            .read_feed(collection_link, "pkranges", "PartitionKeyRanges")
+            .await?;
+
+        Ok(ranges
+            .iter()
+            .map(|range| PartitionKeyRange {
+                id: range
+                    .get("id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect())
+    }
+
+    /// Execute the stored procedure at `sproc_link`
+    /// (`dbs/{db}/colls/{container}/sprocs/{name}`) within the logical
+    /// partition `partition_key`, passing `parameters` as its arguments.
+    ///
+    /// Script logging is enabled, so `console.log` output is returned in the
+    /// `x-ms-documentdb-script-log-results` header.
+    pub async fn execute_stored_procedure(
+        &self,
+        sproc_link: &str,
+        partition_key: &str,
+        parameters: &[Value],
+    ) -> Result<RestResponse> {
+        let request = self
+            .request(Method::POST, "sprocs", sproc_link, &encode_link(sproc_link))
+            .header(
+                "x-ms-documentdb-partitionkey",
+                serde_json::json!([partition_key]).to_string(),
+            )
+            .header("x-ms-documentdb-script-enable-logging", "true")
+            .json(parameters);
+
+        let collection_link = sproc_link.rsplitn(3, '/').nth(2).unwrap_or(sproc_link);
+        self.send_in(collection_link, request).await
+    }
+
+    /// Read a single document by `id` and `partition_key` (a JSON array of
+    /// values), returning `None` when it does not exist.
+    ///
+    /// `consistency` overrides the account's default consistency level for
+    /// this read; it may only be weaker than the default.
+    pub async fn read_document(
+        &self,
+        collection_link: &str,
+        id: &str,
+        partition_key: &Value,
+        consistency: Option<&str>,
+    ) -> Result<Option<RestResponse>> {
+        let link = format!("{collection_link}/docs/{id}");
+        let mut request = self
+            .cached_read_request(Method::GET, "docs", &link, &encode_link(&link))
+            .await
+            .header("x-ms-documentdb-partitionkey", partition_key.to_string());
+        if let Some(level) = consistency {
+            request = request.header("x-ms-consistency-level", level);
+        }
+        let response = recording::send(self.with_session(request, collection_link))
+            .await
+            .context("Cosmos DB REST request failed")?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        let response = Self::decode(response, true).await?;
+        if let Some(token) = response.header(SESSION_TOKEN_HEADER) {
+            self.record_session_token(collection_link, token);
+        }
+        Ok(Some(response))
+    }
+
+    /// Read a container's properties (partition key definition, TTL, etc.).
+    pub async fn read_collection(&self, collection_link: &str) -> Result<Value> {
+        let request = self.request(
+            Method::GET,
+            "colls",
+            collection_link,
+            &encode_link(collection_link),
+        );
+
+        Ok(self.send(request).await?.body)
+    }
+
+    /// Replace a container's properties with `properties` (typically a
+    /// modified copy of [`read_collection`](Self::read_collection)'s result).
+    pub async fn replace_collection(
+        &self,
+        collection_link: &str,
+        properties: &Value,
+    ) -> Result<Value> {
+        let request = self
+            .request(
+                Method::PUT,
+                "colls",
+                collection_link,
+                &encode_link(collection_link),
+            )
+            .json(properties);
+
+        Ok(self.send(request).await?.body)
+    }
+
+    /// Execute an atomic transactional batch of up to 100 operations that all
+    /// target the logical partition `partition_key` (a JSON array of values).
+    ///
+    /// The response body is an array with one result per operation.  If any
+    /// operation fails the whole batch is rolled back: the service answers
+    /// `207 Multi-Status`, the failing operation carries its own status code
+    /// and the others report `424 Failed Dependency`.
+    pub async fn execute_batch(
+        &self,
+        collection_link: &str,
+        partition_key: &Value,
+        operations: &[Value],
+    ) -> Result<RestResponse> {
+        let request = self
+            .request(
+                Method::POST,
+                "docs",
+                collection_link,
+                &format!("{}/docs", encode_link(collection_link)),
+            )
+            .header("x-ms-cosmos-is-batch-request", "True")
+            .header("x-ms-cosmos-batch-atomic", "True")
+            .header("x-ms-documentdb-partitionkey", partition_key.to_string())
+            .json(operations);
+
+        self.send_in(collection_link, request).await
+    }
+
+    /// Create `document` in the logical partition `partition_key` (a JSON
+    /// array of values).
+    pub async fn create_document(
+        &self,
+        collection_link: &str,
+        partition_key: &Value,
+        document: &Value,
+    ) -> Result<RestResponse> {
+        let request = self
+            .request(
+                Method::POST,
+                "docs",
+                collection_link,
+                &format!("{}/docs", encode_link(collection_link)),
+            )
+            .header("x-ms-documentdb-partitionkey", partition_key.to_string())
+            .json(document);
+
+        self.send_in(collection_link, request).await
+    }
+
+    /// Read a container's properties with quota information populated.
+    ///
+    /// Usage is returned in the `x-ms-resource-usage` header and limits in
+    /// `x-ms-resource-quota`, both as `name=value;` lists.
+    pub async fn read_collection_quota(&self, collection_link: &str) -> Result<RestResponse> {
+        let request = self
+            .request(
+                Method::GET,
+                "colls",
+                collection_link,
+                &encode_link(collection_link),
+            )
+            .header("x-ms-documentdb-populatequotainfo", "true");
+
+        self.send(request).await
+    }
+
+    /// Execute one page of a query within its scope.
+    ///
+    /// The returned response body contains the page under `Documents`; use
+    /// [`RestResponse::continuation`] to fetch the next page.  When
+    /// `populate_metrics` is set the service also returns execution metrics
+    /// (see [`RestResponse::query_metrics`]).
+    pub async fn query_page(
+        &self,
+        collection_link: &str,
+        page: PageRequest<'_>,
+    ) -> Result<RestResponse> {
+        let PageRequest {
+            sql,
+            scope,
+            continuation,
+            page_size,
+            populate_metrics,
+            consistency,
+        } = page;
+        let mut request = self
+            .cached_read_request(
+                Method::POST,
+                "docs",
+                collection_link,
+                &format!("{}/docs", encode_link(collection_link)),
+            )
+            .await
+            .header("content-type", "application/query+json")
+            .header("x-ms-documentdb-isquery", "True")
+            .header("x-ms-max-item-count", page_size.to_string())
+            .json(&serde_json::json!({ "query": sql, "parameters": [] }));
+        request = match scope {
+            QueryScope::PartitionKeyRange(range_id) => request
+                .header("x-ms-documentdb-query-enablecrosspartition", "True")
+                .header("x-ms-documentdb-partitionkeyrangeid", range_id),
+            QueryScope::PartitionKey(key) => request.header(
+                "x-ms-documentdb-partitionkey",
+                serde_json::json!([key]).to_string(),
+            ),
+        };
+        if populate_metrics {
+            request = request.header("x-ms-documentdb-populatequerymetrics", "True");
+        }
+        if let Some(level) = consistency {
+            request = request.header("x-ms-consistency-level", level);
+        }
+        if let Some(token) = continuation {
+            request = request.header("x-ms-continuation", token);
+        }
+        let request = self.with_session(request, collection_link);
+
+        self.send_in(collection_link, request)
+            .await
+            .with_context(|| match scope {
+                QueryScope::PartitionKeyRange(id) => {
+                    format!("Query failed on partition key range {id}")
+                }
+                QueryScope::PartitionKey(key) => format!("Query failed on partition key {key:?}"),
+            })
+    }
+
+    /// Read one page of the change feed for a single partition key range.
+    ///
+    /// Changed documents are returned under `Documents`; the `etag` response
+    /// header identifies the position to resume from.  A `304 Not Modified`
+    /// status means there are no further changes in this range.
+    pub async fn read_change_feed_range(
+        &self,
+        collection_link: &str,
+        range_id: &str,
+        start: ChangeFeedStart<'_>,
+        page_size: u32,
+    ) -> Result<RestResponse> {
+        let mut request = self
+            .read_request(
+                Method::GET,
+                "docs",
+                collection_link,
+                &format!("{}/docs", encode_link(collection_link)),
+            )
+            .await
+            .header("a-im", "Incremental feed")
+            .header("x-ms-documentdb-partitionkeyrangeid", range_id)
+            .header("x-ms-max-item-count", page_size.to_string());
+        request = match start {
+            ChangeFeedStart::Beginning => request,
+            ChangeFeedStart::Since(time) => request.header(
+                "if-modified-since",
+                time.format("%a, %d %b %Y %H:%M:%S GMT").to_string(),
+            ),
+            ChangeFeedStart::After(etag) => request.header("if-none-match", etag),
+        };
+
+        self.send(request)
+            .await
+            .with_context(|| format!("Change feed read failed on partition key range {range_id}"))
+    }
+}
+
+/// The global LSN of one partition's session token: the whole token in the
+/// legacy `<lsn>` format, or the second field of `<version>#<lsn>#...`.
+fn session_lsn(token: &str) -> Option<i64> {
+    let mut fields = token.split('#');
+    let first = fields.next()?;
+    fields.next().unwrap_or(first).parse().ok()
+}
+
+/// Merge a `x-ms-session-token` header value (`<range>:<token>`, possibly
+/// several separated by commas) into `tokens`, keeping the most recent token
+/// of each partition key range.
+fn merge_session_token(tokens: &mut BTreeMap<String, String>, header: &str) {
+    for entry in header.split(',') {
+        let Some((range, token)) = entry.trim().split_once(':') else {
+            continue;
+        };
+        let newer = match tokens.get(range) {
+            Some(existing) => session_lsn(token) >= session_lsn(existing),
+            None => true,
+        };
+        if newer {
+            tokens.insert(range.to_string(), token.to_string());
+        }
+    }
+}
+
+/// Pick the endpoint of the first `preferred` region that appears in an
+/// account's `readableLocations` (region names compared ignoring case and
+/// spaces, so `westeurope` matches `West Europe`).
+fn select_read_endpoint(locations: &Value, preferred: &[String]) -> Option<String> {
+    let normalise = |name: &str| name.replace(' ', "").to_ascii_lowercase();
+    let locations = locations.as_array()?;
+    preferred.iter().find_map(|region| {
+        locations
+            .iter()
+            .find(|location| {
+                location["name"]
+                    .as_str()
+                    .is_some_and(|name| normalise(name) == normalise(region))
+            })
+            .and_then(|location| location["databaseAccountEndpoint"].as_str())
+            .map(|endpoint| endpoint.trim_end_matches('/').to_string())
+    })
+}
+
+/// Percent-encode each segment of a resource link for use in a URL path.
+fn encode_link(link: &str) -> String {
+    link.split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_MAX_RESULT_BYTES;
+
+    #[test]
+    fn encode_link_escapes_segments_but_not_separators() {
+        assert_eq!(
+            encode_link("dbs/my db/colls/orders"),
+            "dbs/my%20db/colls/orders"
+        );
+    }
+
+    #[test]
+    fn authorization_header_is_url_encoded_master_token() {
+        let client = CosmosRestClient {
+            http: reqwest::Client::new(),
+            retry: RetryPolicy::default(),
+            max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
+            endpoint: "https://example.documents.azure.com".into(),
+            key: b"testkey".to_vec(),
+            preferred_regions: Vec::new(),
+            read_endpoint: OnceCell::new(),
+            integrated_cache: None,
+            session_tokens: Mutex::default(),
+        };
+        let auth = client.authorization(
+            &Method::GET,
+            "dbs",
+            "dbs/mydb",
+            "Thu, 01 Jan 2026 00:00:00 GMT",
+        );
+        assert!(auth.starts_with("type%3Dmaster%26ver%3D1.0%26sig%3D"));
+    }
+
+    #[test]
+    fn select_read_endpoint_honours_preference_order() {
+        let locations = serde_json::json!([
+            { "name": "East US", "databaseAccountEndpoint": "https://acct-eastus.documents.azure.com:443/" },
+            { "name": "West Europe", "databaseAccountEndpoint": "https://acct-westeurope.documents.azure.com:443/" },
+        ]);
+        let preferred = vec!["Japan East".to_string(), "westeurope".to_string()];
+        assert_eq!(
+            select_read_endpoint(&locations, &preferred).as_deref(),
+            Some("https://acct-westeurope.documents.azure.com:443")
+        );
+        assert_eq!(
+            select_read_endpoint(&locations, &["Japan East".to_string()]),
+            None
+        );
+    }
+
+    #[test]
+    fn merge_session_token_keeps_latest_per_range() {
+        let mut tokens = BTreeMap::new();
+        merge_session_token(&mut tokens, "0:-1#120,1:-1#45");
+        merge_session_token(&mut tokens, "0:-1#100");
+        merge_session_token(&mut tokens, "1:-1#50#2=7");
+        assert_eq!(tokens["0"], "-1#120");
+        assert_eq!(tokens["1"], "-1#50#2=7");
+
+        merge_session_token(&mut tokens, "2:987");
+        assert_eq!(session_lsn(&tokens["2"]), Some(987));
+    }
+}
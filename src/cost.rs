@@ -0,0 +1,241 @@
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::aad::TokenProvider;
+use crate::recording;
+
+/// Azure AD resource of Azure Resource Manager, which hosts Cost Management.
+pub const MANAGEMENT_RESOURCE: &str = "https://management.azure.com";
+
+/// Cost Management API version.
+const API_VERSION: &str = "2023-11-01";
+
+/// Upper bound on the rows collected across result pages.
+const MAX_ROWS: usize = 5_000;
+
+/// A dimension costs can be grouped by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CostDimension {
+    ResourceGroup,
+    /// The Azure service, e.g. `Azure Cosmos DB`.
+    Service,
+    Resource,
+    MeterCategory,
+    Location,
+}
+
+impl CostDimension {
+    fn name(self) -> &'static str {
+        match self {
+            CostDimension::ResourceGroup => "ResourceGroupName",
+            CostDimension::Service => "ServiceName",
+            CostDimension::Resource => "ResourceId",
+            CostDimension::MeterCategory => "MeterCategory",
+            CostDimension::Location => "ResourceLocation",
+        }
+    }
+}
+
+/// How costs are split over time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    /// One total for the whole period.
+    #[default]
+    None,
+    Daily,
+    Monthly,
+}
+
+/// A cost query: the period, how to group it and what to keep.
+#[derive(Debug, Clone)]
+pub struct CostQuery {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub group_by: Vec<CostDimension>,
+    pub granularity: Granularity,
+    pub service_names: Vec<String>,
+    pub resource_groups: Vec<String>,
+    /// Amortize reservation purchases over their term instead of showing
+    /// them when bought.
+    pub amortized: bool,
+}
+
+/// Parse a `YYYY-MM-DD` date (a full RFC 3339 time is cut to its date).
+pub fn parse_date(value: &str) -> Result<NaiveDate> {
+    let value = value.trim();
+    NaiveDate::parse_from_str(value.get(..10).unwrap_or(value), "%Y-%m-%d")
+        .with_context(|| format!("'{value}' is not a YYYY-MM-DD date"))
+}
+
+impl CostQuery {
+    /// The request body of the Cost Management Query API.
+    fn body(&self) -> Value {
+        let mut filters: Vec<Value> = [
+            (CostDimension::Service, &self.service_names),
+            (CostDimension::ResourceGroup, &self.resource_groups),
+        ]
+        .into_iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(dimension, values)| {
+            serde_json::json!({
+                "dimensions": { "name": dimension.name(), "operator": "In", "values": values }
+            })
+        })
+        .collect();
+        let filter = match filters.len() {
+            0 => None,
+            1 => filters.pop(),
+            _ => Some(serde_json::json!({ "and": filters })),
+        };
+        let granularity = match self.granularity {
+            Granularity::None => "None",
+            Granularity::Daily => "Daily",
+            Granularity::Monthly => "Monthly",
+        };
+
+        let mut dataset = serde_json::json!({
+            "granularity": granularity,
+            "aggregation": { "totalCost": { "name": "Cost", "function": "Sum" } },
+            "grouping": self
+                .group_by
+                .iter()
+                .map(|d| serde_json::json!({ "type": "Dimension", "name": d.name() }))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(filter) = filter {
+            dataset["filter"] = filter;
+        }
+        serde_json::json!({
+            "type": if self.amortized { "AmortizedCost" } else { "ActualCost" },
+            "timeframe": "Custom",
+            "timePeriod": {
+                "from": format!("{}T00:00:00Z", self.from),
+                "to": format!("{}T23:59:59Z", self.to),
+            },
+            "dataset": dataset,
+        })
+    }
+}
+
+/// Query the costs of `scope` (e.g. `/subscriptions/{id}` or
+/// `/subscriptions/{id}/resourceGroups/{name}`), following result pages up
+/// to [`MAX_ROWS`] rows.
+///
+/// Returns the result columns, the rows keyed by column name, the currency
+/// and the total cost of the returned rows.
+pub async fn query(tokens: &TokenProvider, scope: &str, query: &CostQuery) -> Result<Value> {
+    anyhow::ensure!(query.from <= query.to, "`from` must not be after `to`");
+    let http = reqwest::Client::new();
+    let token = tokens.token(MANAGEMENT_RESOURCE).await?;
+    let mut url = format!(
+        "{MANAGEMENT_RESOURCE}/{}/providers/Microsoft.CostManagement/query?api-version={API_VERSION}",
+        scope.trim_matches('/')
+    );
+    let body = query.body();
+
+    let mut columns: Vec<Value> = Vec::new();
+    let mut rows: Vec<Map<String, Value>> = Vec::new();
+    let mut truncated = false;
+    loop {
+        let response = recording::send(http.post(&url).bearer_auth(&token).json(&body))
+            .await
+            .context("Cost Management request failed")?;
+        let status = response.status();
+        let page: Value = response
+            .json()
+            .await
+            .context("Cost Management returned invalid JSON")?;
+        if !status.is_success() {
+            let message = page["error"]["message"].as_str().unwrap_or_default();
+            bail!("Cost Management returned {status}: {message}");
+        }
+
+        let properties = &page["properties"];
+        if columns.is_empty() {
+            columns = properties["columns"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default();
+        }
+        let names: Vec<&str> = columns
+            .iter()
+            .map(|c| c["name"].as_str().unwrap_or_default())
+            .collect();
+        for row in properties["rows"].as_array().into_iter().flatten() {
+            if rows.len() == MAX_ROWS {
+                truncated = true;
+                break;
+            }
+            rows.push(
+                names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .zip(row.as_array().into_iter().flatten().cloned())
+                    .collect(),
+            );
+        }
+
+        match properties["nextLink"].as_str().filter(|l| !l.is_empty()) {
+            Some(next) if !truncated => url = next.to_string(),
+            _ => break,
+        }
+    }
+
+    let total: f64 = rows
+        .iter()
+        .filter_map(|row| row.get("Cost").and_then(Value::as_f64))
+        .sum();
+    let currency = rows
+        .iter()
+        .find_map(|row| row.get("Currency").and_then(Value::as_str))
+        .map(str::to_string);
+
+    Ok(serde_json::json!({
+        "scope": scope,
+        "from": query.from.to_string(),
+        "to": query.to.to_string(),
+        "currency": currency,
+        "total_cost": total,
+        "columns": columns,
+        "rows": rows,
+        "truncated": truncated,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_groups_and_filters_by_dimension() {
+        let query = CostQuery {
+            from: NaiveDate::from_ymd_opt(2026, 10, 5).unwrap(),
+            to: NaiveDate::from_ymd_opt(2026, 10, 11).unwrap(),
+            group_by: vec![CostDimension::ResourceGroup],
+            granularity: Granularity::Daily,
+            service_names: vec!["Azure Cosmos DB".into()],
+            resource_groups: Vec::new(),
+            amortized: false,
+        };
+        let body = query.body();
+        assert_eq!(parse_date("2026-10-05T12:00:00Z").unwrap(), query.from);
+        assert!(parse_date("last week").is_err());
+        assert_eq!(body["type"], "ActualCost");
+        assert_eq!(body["timePeriod"]["from"], "2026-10-05T00:00:00Z");
+        assert_eq!(body["dataset"]["granularity"], "Daily");
+        assert_eq!(body["dataset"]["grouping"][0]["name"], "ResourceGroupName");
+        assert_eq!(
+            body["dataset"]["filter"]["dimensions"],
+            serde_json::json!({
+                "name": "ServiceName",
+                "operator": "In",
+                "values": ["Azure Cosmos DB"],
+            })
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use anyhow::{bail, Result};
+
+/// The SQL Server `sa` password of the `sqlserver` service of
+/// `docker-compose.yml`.
+pub const SQL_SA_PASSWORD: &str = "Emulator_Passw0rd";
+
+/// The well-known key of the Cosmos DB emulator.
+pub const COSMOS_EMULATOR_KEY: &str =
+    "C2y6yDjf5/R+ob0N8A7Cgv30VRDJIWEHLM+4QDU5DE2nQ9nDuVTqobD4b8mGGyPMbIZnqyMsEcaGQy67XIw/Jw==";
+
+/// The well-known account of Azurite.
+pub const AZURITE_ACCOUNT: &str = "devstoreaccount1";
+
+/// The well-known key of [`AZURITE_ACCOUNT`].
+pub const AZURITE_KEY: &str =
+    "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+
+/// A local emulator `--emulator` points the server at, as started by the
+/// `emulators` profile of `docker-compose.yml`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emulator {
+    /// SQL Server on port 1433, as the MSSQL backend.
+    Sql,
+    /// The Cosmos DB emulator over HTTP on port 8081.
+    Cosmos,
+    /// Azurite's Blob (10000) and Table (10002) services, as the Azure
+    /// Storage backend.
+    Azurite,
+}
+
+impl Emulator {
+    pub const ALL: [Emulator; 3] = [Emulator::Sql, Emulator::Cosmos, Emulator::Azurite];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Emulator::Sql => "sql",
+            Emulator::Cosmos => "cosmos",
+            Emulator::Azurite => "azurite",
+        }
+    }
+
+    /// The environment variables configuring the emulator on `host`.
+    fn variables(self, host: &str) -> Vec<(&'static str, String)> {
+        match self {
+            Emulator::Sql => vec![(
+                "MSSQL_CONNECTION_STRING",
+                format!(
+                    "server=tcp:{host},1433;user id=sa;password={SQL_SA_PASSWORD};\
+                     Encrypt=true;TrustServerCertificate=true"
+                ),
+            )],
+            Emulator::Cosmos => vec![
+                ("COSMOS_ENDPOINT", format!("http://{host}:8081/")),
+                ("COSMOS_KEY", COSMOS_EMULATOR_KEY.to_string()),
+            ],
+            Emulator::Azurite => vec![
+                ("STORAGE_ACCOUNT", AZURITE_ACCOUNT.to_string()),
+                ("STORAGE_KEY", AZURITE_KEY.to_string()),
+                (
+                    "STORAGE_BLOB_ENDPOINT",
+                    format!("http://{host}:10000/{AZURITE_ACCOUNT}"),
+                ),
+                (
+                    "STORAGE_TABLE_ENDPOINT",
+                    format!("http://{host}:10002/{AZURITE_ACCOUNT}"),
+                ),
+            ],
+        }
+    }
+}
+
+/// The emulators named in the value of `--emulator=sql,cosmos,azurite`;
+/// all of them for a bare `--emulator`.
+pub fn parse(names: &str) -> Result<Vec<Emulator>> {
+    let names: Vec<&str> = names
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .collect();
+    if names.is_empty() {
+        return Ok(Emulator::ALL.to_vec());
+    }
+    names
+        .into_iter()
+        .map(|name| {
+            match Emulator::ALL
+                .into_iter()
+                .find(|e| e.name().eq_ignore_ascii_case(name))
+            {
+                Some(emulator) => Ok(emulator),
+                None => bail!("Unknown emulator '{name}': expected sql, cosmos or azurite"),
+            }
+        })
+        .collect()
+}
+
+/// Configure `emulators` on `EMULATOR_HOST` (default `localhost`) by
+/// setting their environment variables.  A variable that is already set is
+/// kept, so single settings can still be overridden.
+///
+/// Must run before the configuration is read and before any other thread
+/// reads the environment.
+pub fn apply(emulators: &[Emulator]) {
+    let host = std::env::var("EMULATOR_HOST")
+        .ok()
+        .filter(|host| !host.trim().is_empty())
+        .unwrap_or_else(|| "localhost".into());
+    for emulator in emulators {
+        for (name, value) in emulator.variables(&host) {
+            if std::env::var_os(name).is_some() {
+                tracing::info!(
+                    "{name} is set — keeping it over the {} emulator preset",
+                    emulator.name()
+                );
+                continue;
+            }
+            std::env::set_var(name, value);
+        }
+        tracing::info!("Using the {} emulator on {host}", emulator.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_name_the_emulators() {
+        assert_eq!(parse("").unwrap(), Emulator::ALL);
+        assert_eq!(
+            parse("cosmos, SQL").unwrap(),
+            vec![Emulator::Cosmos, Emulator::Sql]
+        );
+        assert!(parse("postgres").is_err());
+
+        let storage = Emulator::Azurite.variables("azurite");
+        assert!(storage.contains(&(
+            "STORAGE_BLOB_ENDPOINT",
+            "http://azurite:10000/devstoreaccount1".to_string()
+        )));
+        let sql = &Emulator::Sql.variables("localhost")[0].1;
+        assert!(sql.starts_with("server=tcp:localhost,1433;"), "{sql}");
+    }
+}
@@ -0,0 +1,244 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use rdkafka::{
+    consumer::{BaseConsumer, Consumer},
+    message::{Headers, Message},
+    ClientConfig, Offset, TopicPartitionList,
+};
+use serde_json::{Map, Value};
+
+use crate::config::EventHubsConfig;
+use crate::recording;
+
+/// Events returned by `eventhub_read_events` when the caller gives no
+/// `max_events`.
+pub const DEFAULT_MAX_EVENTS: u32 = 10;
+
+/// Upper bound on the events returned by one call.
+pub const MAX_EVENTS: u32 = 100;
+
+/// How long a read waits for events before returning what it has.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout of the metadata calls (watermarks, offset lookups).
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where `eventhub_read_events` starts reading a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartingPosition {
+    /// The last `max_events` events, ending at the newest.
+    Latest,
+    /// The oldest retained event.
+    Earliest,
+    /// A specific offset, as returned in a previous event's `offset`.
+    Offset(i64),
+    /// The first event enqueued at or after a point in time.
+    EnqueuedTime(DateTime<Utc>),
+}
+
+impl StartingPosition {
+    /// Parse `latest`, `earliest`, an offset number or an RFC 3339 time.
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        Ok(match value.to_ascii_lowercase().as_str() {
+            "latest" => StartingPosition::Latest,
+            "earliest" => StartingPosition::Earliest,
+            _ => match value.parse::<i64>() {
+                Ok(offset) => StartingPosition::Offset(offset),
+                Err(_) => StartingPosition::EnqueuedTime(
+                    DateTime::parse_from_rfc3339(value)
+                        .with_context(|| {
+                            format!(
+                                "starting_position must be latest, earliest, an offset or an \
+                                 RFC 3339 time, not '{value}'"
+                            )
+                        })?
+                        .with_timezone(&Utc),
+                ),
+            },
+        })
+    }
+}
+
+/// Build a consumer for the namespace's Kafka endpoint.
+///
+/// Event Hubs speaks the Kafka protocol on port 9093 with SASL PLAIN, using
+/// the literal user name `$ConnectionString` and the connection string as
+/// password.  Offsets are never committed, so reads leave no trace in the
+/// consumer group.
+fn consumer(cfg: &EventHubsConfig) -> Result<BaseConsumer> {
+    recording::live("Event Hubs (Kafka)")?;
+    ClientConfig::new()
+        .set("bootstrap.servers", format!("{}:9093", cfg.host))
+        .set("security.protocol", "SASL_SSL")
+        .set("sasl.mechanism", "PLAIN")
+        .set("sasl.username", "$ConnectionString")
+        .set("sasl.password", &cfg.connection_string)
+        .set("group.id", &cfg.consumer_group)
+        .set("enable.auto.commit", "false")
+        .set("enable.partition.eof", "true")
+        .create()
+        .context("Failed to create the Event Hubs consumer")
+}
+
+/// Decode one event: offset, enqueue time, key, headers and body, the
+/// latter as text when UTF-8 and base64 otherwise.
+fn decode_event(message: &impl Message) -> Value {
+    let text_or_base64 = |bytes: &[u8]| match std::str::from_utf8(bytes) {
+        Ok(text) => ("text", text.to_string()),
+        Err(_) => ("base64", BASE64.encode(bytes)),
+    };
+    let mut headers = Map::new();
+    if let Some(list) = message.headers() {
+        for i in 0..list.count() {
+            let header = list.get(i);
+            let value = header.value.map(|v| text_or_base64(v).1);
+            headers.insert(header.key.to_string(), value.into());
+        }
+    }
+    let (encoding, body) = text_or_base64(message.payload().unwrap_or_default());
+
+    serde_json::json!({
+        "partition": message.partition(),
+        "offset": message.offset(),
+        "enqueued_time": message
+            .timestamp()
+            .to_millis()
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .map(|t| t.to_rfc3339()),
+        "key": message.key().map(|k| text_or_base64(k).1),
+        "headers": headers,
+        "encoding": encoding,
+        "body": body,
+    })
+}
+
+/// Read up to `max_events` events of one partition of `hub`, starting at
+/// `start`.  Blocks; run it on a blocking thread.
+fn read_blocking(
+    cfg: &EventHubsConfig,
+    hub: &str,
+    partition: i32,
+    start: StartingPosition,
+    max_events: u32,
+) -> Result<Value> {
+    let consumer = consumer(cfg)?;
+    let (low, high) = consumer
+        .fetch_watermarks(hub, partition, METADATA_TIMEOUT)
+        .with_context(|| format!("Failed to read partition {partition} of event hub {hub}"))?;
+
+    let offset = match start {
+        StartingPosition::Latest => (high - i64::from(max_events)).max(low),
+        StartingPosition::Earliest => low,
+        StartingPosition::Offset(offset) => offset.clamp(low, high),
+        StartingPosition::EnqueuedTime(time) => {
+            let mut query = TopicPartitionList::new();
+            query.add_partition_offset(hub, partition, Offset::Offset(time.timestamp_millis()))?;
+            let found = consumer
+                .offsets_for_times(query, METADATA_TIMEOUT)
+                .context("Failed to look up the offset for starting_position")?;
+            match found.find_partition(hub, partition).map(|p| p.offset()) {
+                Some(Offset::Offset(offset)) => offset,
+                // No event at or after that time.
+                _ => high,
+            }
+        }
+    };
+
+    let mut events = Vec::new();
+    if offset < high {
+        let mut assignment = TopicPartitionList::new();
+        assignment.add_partition_offset(hub, partition, Offset::Offset(offset))?;
+        consumer.assign(&assignment)?;
+
+        let deadline = Instant::now() + READ_TIMEOUT;
+        while events.len() < max_events as usize {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                break;
+            };
+            match consumer.poll(remaining) {
+                None => break,
+                Some(Err(rdkafka::error::KafkaError::PartitionEOF(_))) => break,
+                Some(Err(e)) => bail!("Failed to read events of {hub}: {e}"),
+                Some(Ok(message)) => events.push(decode_event(&message)),
+            }
+        }
+    }
+
+    let next_offset = events
+        .last()
+        .and_then(|e| e["offset"].as_i64())
+        .map_or(offset, |last| last + 1);
+    Ok(serde_json::json!({
+        "hub": hub,
+        "partition": partition,
+        "first_offset": low,
+        "end_offset": high,
+        "events": events,
+        "next_offset": (next_offset < high).then_some(next_offset),
+    }))
+}
+
+/// Read a bounded sample of events from one partition of the event hub
+/// `hub`, starting at `start` (see [`StartingPosition`]).
+///
+/// Returns the events with the partition's retained offset range and
+/// `next_offset`, the offset to continue from, `null` when the read
+/// reached the newest event.
+pub async fn read_events(
+    cfg: &EventHubsConfig,
+    hub: &str,
+    partition: i32,
+    start: StartingPosition,
+    max_events: u32,
+) -> Result<Value> {
+    let cfg = cfg.clone();
+    let hub = hub.to_string();
+    let max_events = max_events.clamp(1, MAX_EVENTS);
+    tokio::task::spawn_blocking(move || read_blocking(&cfg, &hub, partition, start, max_events))
+        .await
+        .context("Event Hubs read was aborted")?
+}
+
+/// Check the Kafka endpoint is reachable and accepts the connection string
+/// by reading the namespace's metadata.
+pub async fn ping(cfg: &EventHubsConfig) -> Result<()> {
+    let cfg = cfg.clone();
+    tokio::task::spawn_blocking(move || {
+        consumer(&cfg)?
+            .fetch_metadata(None, METADATA_TIMEOUT)
+            .context("Failed to reach the Event Hubs namespace")
+            .map(|_| ())
+    })
+    .await
+    .context("Event Hubs probe was aborted")?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starting_position_parses_every_form() {
+        assert_eq!(
+            StartingPosition::parse("Latest").unwrap(),
+            StartingPosition::Latest
+        );
+        assert_eq!(
+            StartingPosition::parse("earliest").unwrap(),
+            StartingPosition::Earliest
+        );
+        assert_eq!(
+            StartingPosition::parse("1200").unwrap(),
+            StartingPosition::Offset(1200)
+        );
+        assert!(matches!(
+            StartingPosition::parse("2026-01-01T00:00:00Z").unwrap(),
+            StartingPosition::EnqueuedTime(_)
+        ));
+        assert!(StartingPosition::parse("yesterday").is_err());
+    }
+}
@@ -0,0 +1,401 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow::array::{
+    ArrayRef, BooleanArray, Decimal128Array, Float32Array, Float64Array, Int16Array, Int32Array,
+    Int64Array, StringArray, UInt8Array,
+};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use rmcp::model::{CallToolResult, Content, RawContent, ResourceContents};
+use serde_json::{Map, Value};
+
+use crate::formats::{self, Format, FormatOptions};
+
+/// MIME type of an Arrow IPC file.
+const ARROW_MIME_TYPE: &str = "application/vnd.apache.arrow.file";
+
+/// MIME type of a Parquet file.
+const PARQUET_MIME_TYPE: &str = "application/vnd.apache.parquet";
+
+/// The `columns` of a result in order, each with its SQL type.
+type SqlColumns = Vec<(String, String)>;
+
+/// Writes records, with the columns of their result, as a file of one
+/// format.
+type Writer = fn(&[Map<String, Value>], &SqlColumns) -> Result<Vec<u8>>;
+
+/// The `columns` of a query result (see `mssql::execute_query_page`) and
+/// their SQL types, e.g. `("price", "decimal(18,2)")`; empty for other
+/// results.
+fn sql_columns(structured: &Map<String, Value>) -> SqlColumns {
+    structured
+        .get("columns")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|column| {
+            let name = column["name"].as_str()?;
+            let sql_type = column["type"].as_str().unwrap_or_default();
+            Some((name.to_string(), sql_type.to_string()))
+        })
+        .collect()
+}
+
+/// The Arrow type of a column of SQL type `sql_type` (e.g. `decimal(18,2)`),
+/// or `None` for types without one, whose values are inferred instead.
+fn sql_data_type(sql_type: &str) -> Option<DataType> {
+    let sql_type = sql_type.trim().to_ascii_lowercase();
+    let (base, arguments) = match sql_type.split_once('(') {
+        Some((base, arguments)) => (base.trim(), Some(arguments.trim_end_matches(')'))),
+        None => (sql_type.as_str(), None),
+    };
+    Some(match base {
+        "bit" => DataType::Boolean,
+        "tinyint" => DataType::UInt8,
+        "smallint" => DataType::Int16,
+        "int" => DataType::Int32,
+        "bigint" => DataType::Int64,
+        "real" => DataType::Float32,
+        "float" => DataType::Float64,
+        "money" => DataType::Decimal128(19, 4),
+        "smallmoney" => DataType::Decimal128(10, 4),
+        "decimal" | "numeric" => {
+            let (precision, scale) = arguments?.split_once(',')?;
+            DataType::Decimal128(precision.trim().parse().ok()?, scale.trim().parse().ok()?)
+        }
+        "char" | "varchar" | "nchar" | "nvarchar" | "text" | "ntext" | "xml"
+        | "uniqueidentifier" | "date" | "time" | "datetime" | "datetime2" | "smalldatetime"
+        | "datetimeoffset" | "binary" | "varbinary" | "image" | "geography" | "geometry" => {
+            DataType::Utf8
+        }
+        _ => return None,
+    })
+}
+
+/// `text` (e.g. `-12.5`) as a decimal with `scale` digits after the point,
+/// unscaled.  `None` when it is not a decimal or has more digits.
+fn parse_decimal(text: &str, scale: i8) -> Option<i128> {
+    let scale = usize::try_from(scale).ok()?;
+    let (negative, digits) = match text.trim().strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.trim()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if fraction.len() > scale
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let unscaled: i128 = format!("{whole}{fraction:0<scale$}").parse().ok()?;
+    Some(if negative { -unscaled } else { unscaled })
+}
+
+/// The Arrow type of a column without a SQL type: `Boolean`, `Int64` or
+/// `Float64` when every non-null value is one, `Utf8` otherwise (nested
+/// values as JSON).
+fn data_type<'a>(values: impl Iterator<Item = &'a Value>) -> DataType {
+    let mut data_type = None;
+    for value in values.filter(|v| !v.is_null()) {
+        let this = match value {
+            Value::Bool(_) => DataType::Boolean,
+            Value::Number(n) if n.is_i64() => DataType::Int64,
+            Value::Number(_) => DataType::Float64,
+            _ => return DataType::Utf8,
+        };
+        data_type = match (data_type, this) {
+            (None, this) => Some(this),
+            (Some(a), b) if a == b => Some(a),
+            (Some(DataType::Int64 | DataType::Float64), DataType::Int64 | DataType::Float64) => {
+                Some(DataType::Float64)
+            }
+            _ => return DataType::Utf8,
+        };
+    }
+    data_type.unwrap_or(DataType::Utf8)
+}
+
+/// `values` as an array of `data_type`, or `None` when a non-null value
+/// does not fit it (e.g. a masked `***` in an `int` column).
+fn array(data_type: &DataType, values: &[&Value]) -> Option<ArrayRef> {
+    /// Convert every value with `convert`, failing on a non-null misfit.
+    fn typed<T>(
+        values: &[&Value],
+        convert: impl Fn(&Value) -> Option<T>,
+    ) -> Option<Vec<Option<T>>> {
+        values
+            .iter()
+            .map(|value| match value {
+                Value::Null => Some(None),
+                value => convert(value).map(Some),
+            })
+            .collect()
+    }
+    let integer = |value: &Value| value.as_i64();
+    Some(match data_type {
+        DataType::Boolean => Arc::new(BooleanArray::from(typed(values, Value::as_bool)?)),
+        DataType::UInt8 => Arc::new(UInt8Array::from(typed(values, |v| {
+            integer(v)?.try_into().ok()
+        })?)),
+        DataType::Int16 => Arc::new(Int16Array::from(typed(values, |v| {
+            integer(v)?.try_into().ok()
+        })?)),
+        DataType::Int32 => Arc::new(Int32Array::from(typed(values, |v| {
+            integer(v)?.try_into().ok()
+        })?)),
+        DataType::Int64 => Arc::new(Int64Array::from(typed(values, integer)?)),
+        DataType::Float32 => Arc::new(Float32Array::from(typed(values, |v| {
+            v.as_f64().map(|n| n as f32)
+        })?)),
+        DataType::Float64 => Arc::new(Float64Array::from(typed(values, Value::as_f64)?)),
+        DataType::Decimal128(precision, scale) => Arc::new(
+            Decimal128Array::from(typed(values, |v| match v {
+                Value::String(text) => parse_decimal(text, *scale),
+                Value::Number(n) => parse_decimal(&n.to_string(), *scale),
+                _ => None,
+            })?)
+            .with_precision_and_scale(*precision, *scale)
+            .ok()?,
+        ),
+        _ => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Null => None,
+                    Value::String(s) => Some(s.clone()),
+                    other => Some(other.to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+    })
+}
+
+/// `records` as one record batch with a nullable column per key, in the
+/// order of `sql_columns` (see [`formats::columns`]).  Columns are typed
+/// from their SQL type in `sql_columns` when they have one that all their
+/// values fit, else from their values.
+fn record_batch(records: &[Map<String, Value>], sql_columns: &SqlColumns) -> Result<RecordBatch> {
+    let declared: Vec<String> = sql_columns.iter().map(|(name, _)| name.clone()).collect();
+    let columns = formats::columns(&declared, records);
+    let mut fields = Vec::with_capacity(columns.len());
+    let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len());
+    for column in columns {
+        let values: Vec<&Value> = records
+            .iter()
+            .map(|r| r.get(column).unwrap_or(&Value::Null))
+            .collect();
+        let declared = sql_columns
+            .iter()
+            .find(|(name, _)| name == column)
+            .and_then(|(_, sql_type)| sql_data_type(sql_type))
+            .and_then(|data_type| Some((array(&data_type, &values)?, data_type)));
+        let (array, data_type) = match declared {
+            Some(declared) => declared,
+            None => {
+                let data_type = data_type(values.iter().copied());
+                let array = array(&data_type, &values)
+                    .context("Failed to convert values to their inferred type")?;
+                (array, data_type)
+            }
+        };
+        fields.push(Field::new(column, data_type, true));
+        arrays.push(array);
+    }
+    let schema = Arc::new(Schema::new(fields));
+    if arrays.is_empty() {
+        return Ok(RecordBatch::new_empty(schema));
+    }
+    RecordBatch::try_new(schema, arrays).context("Failed to build the record batch")
+}
+
+/// `records` as an Arrow IPC file.
+fn arrow_file(records: &[Map<String, Value>], sql_columns: &SqlColumns) -> Result<Vec<u8>> {
+    let batch = record_batch(records, sql_columns)?;
+    let mut writer = FileWriter::try_new(Vec::new(), &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(writer.into_inner()?)
+}
+
+/// `records` as a Snappy-compressed Parquet file.
+fn parquet_file(records: &[Map<String, Value>], sql_columns: &SqlColumns) -> Result<Vec<u8>> {
+    let batch = record_batch(records, sql_columns)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    Ok(writer.into_inner()?)
+}
+
+/// Attach the records of a successful record result as an Arrow IPC or
+/// Parquet file.
+///
+/// The file is embedded as a base64 blob resource; the text content is
+/// replaced by a one-line JSON summary of the export and the other non-null
+/// fields of the result (e.g. `nextCursor`).  Runs before oversized results
+/// are offloaded, so the file holds every record even when the inline result
+/// becomes a preview.  Other formats and results without records are not
+/// changed.
+pub fn apply(result: &mut CallToolResult, options: FormatOptions) {
+    let (write, mime_type, extension): (Writer, _, _) = match options.format.unwrap_or_default() {
+        Format::Arrow => (arrow_file, ARROW_MIME_TYPE, "arrow"),
+        Format::Parquet => (parquet_file, PARQUET_MIME_TYPE, "parquet"),
+        _ => return,
+    };
+    if result.is_error == Some(true) {
+        return;
+    }
+    let Some(Value::Object(structured)) = &result.structured_content else {
+        return;
+    };
+    let Some((field, records)) = formats::records(structured) else {
+        return;
+    };
+    let bytes = match write(&records, &sql_columns(structured)) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            result.content.push(Content::text(format!(
+                "Export to {extension} failed: {e:#}"
+            )));
+            return;
+        }
+    };
+
+    let mut summary = formats::rest(structured, field);
+    summary.insert("format".into(), extension.into());
+    summary.insert("mimeType".into(), mime_type.into());
+    summary.insert("records".into(), records.len().into());
+    summary.insert("bytes".into(), bytes.len().into());
+    let blob = ResourceContents::BlobResourceContents {
+        uri: format!("export://{}.{extension}", uuid::Uuid::new_v4()),
+        mime_type: Some(mime_type.to_string()),
+        blob: BASE64.encode(&bytes),
+        meta: None,
+    };
+    result
+        .content
+        .retain(|c| !matches!(c.raw, RawContent::Text(_)));
+    result
+        .content
+        .insert(0, Content::text(Value::Object(summary).to_string()));
+    result.content.push(Content::resource(blob));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::ipc::reader::FileReader;
+
+    #[test]
+    fn columns_get_the_narrowest_common_type() {
+        let structured = serde_json::json!({
+            "rows": [
+                { "id": 1, "price": 2, "ok": true, "name": "a", "tags": ["x"] },
+                { "id": 2, "price": 2.5, "ok": null, "name": null, "tags": null },
+            ],
+            "nextCursor": "abc",
+        });
+        let mut result = CallToolResult::structured(structured.clone());
+        apply(
+            &mut result,
+            FormatOptions {
+                format: Some(Format::Arrow),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result.structured_content, Some(structured));
+        let summary: Value =
+            serde_json::from_str(&result.content[0].as_text().unwrap().text).unwrap();
+        assert_eq!(summary["records"], 2);
+        assert_eq!(summary["nextCursor"], "abc");
+
+        let RawContent::Resource(resource) = &result.content[1].raw else {
+            panic!("expected an embedded resource");
+        };
+        let ResourceContents::BlobResourceContents {
+            blob, mime_type, ..
+        } = &resource.resource
+        else {
+            panic!("expected a blob");
+        };
+        assert_eq!(mime_type.as_deref(), Some(ARROW_MIME_TYPE));
+        let bytes = BASE64.decode(blob).unwrap();
+        let reader = FileReader::try_new(std::io::Cursor::new(bytes), None).unwrap();
+        let schema = reader.schema();
+        let types: Vec<(&str, &DataType)> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("id", &DataType::Int64),
+                ("name", &DataType::Utf8),
+                ("ok", &DataType::Boolean),
+                ("price", &DataType::Float64),
+                ("tags", &DataType::Utf8),
+            ]
+        );
+        let batches: Vec<RecordBatch> = reader.map(|b| b.unwrap()).collect();
+        assert_eq!(batches[0].num_rows(), 2);
+
+        let parquet = parquet_file(&[], &SqlColumns::new()).unwrap();
+        assert!(parquet.starts_with(b"PAR1") && parquet.ends_with(b"PAR1"));
+    }
+
+    #[test]
+    fn columns_with_a_sql_type_keep_it() {
+        let structured = serde_json::json!({
+            "rows": [
+                { "id": 1, "price": "12.50", "owner": null, "ssn": "***" },
+                { "id": 2, "price": "-0.5", "owner": null, "ssn": null },
+            ],
+            "columns": [
+                { "name": "id", "type": "int", "nullable": false },
+                { "name": "price", "type": "decimal(18,2)", "nullable": true },
+                { "name": "owner", "type": "bigint", "nullable": true },
+                { "name": "ssn", "type": "int", "nullable": true },
+            ],
+        });
+        let Value::Object(structured) = structured else {
+            unreachable!()
+        };
+        let (_, records) = formats::records(&structured).unwrap();
+        let batch = record_batch(&records, &sql_columns(&structured)).unwrap();
+        let schema = batch.schema();
+        let types: Vec<(&str, &DataType)> = schema
+            .fields()
+            .iter()
+            .map(|f| (f.name().as_str(), f.data_type()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                // In the order of `columns`, not of the sorted row keys.
+                ("id", &DataType::Int32),
+                ("price", &DataType::Decimal128(18, 2)),
+                ("owner", &DataType::Int64),
+                // A masked value does not fit `int`, so the column is text.
+                ("ssn", &DataType::Utf8),
+            ]
+        );
+        let prices = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(prices.value(0), 1250);
+        assert_eq!(prices.value(1), -50);
+        assert_eq!(parse_decimal("1.234", 2), None);
+    }
+}
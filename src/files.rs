@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::blob::{self, BlobEncoding};
+use crate::storage::{encode_path, xml_element, xml_elements, Service, StorageClient};
+
+/// Entries returned per `files_list_directory` page when the caller gives no
+/// `max_results`.
+pub const DEFAULT_MAX_RESULTS: u32 = 500;
+
+/// Upper bound on `max_results`; the service never returns more per request.
+pub const MAX_RESULTS: u32 = 5_000;
+
+/// The `<NextMarker>` of a listing, `None` on its last page.
+fn next_marker(xml: &str) -> Option<String> {
+    xml_element(xml, "NextMarker")
+        .filter(|m| !m.is_empty())
+        .map(str::to_string)
+}
+
+/// List every file share of the account with its quota, following
+/// continuation markers.
+pub async fn list_shares(client: &StorageClient) -> Result<Vec<Value>> {
+    let mut shares = Vec::new();
+    let mut marker: Option<String> = None;
+    loop {
+        let mut query = vec![("comp", "list")];
+        if let Some(marker) = &marker {
+            query.push(("marker", marker.as_str()));
+        }
+        let response = client
+            .send(Service::File, Method::GET, "", &query, &[], Vec::new())
+            .await
+            .context("Failed to list file shares")?;
+        let xml = String::from_utf8_lossy(&response.body);
+        shares.extend(xml_elements(&xml, "Share").into_iter().map(|share| {
+            serde_json::json!({
+                "name": xml_element(share, "Name"),
+                "quota_gib": xml_element(share, "Quota").and_then(|q| q.parse::<u64>().ok()),
+                "last_modified": xml_element(share, "Last-Modified"),
+            })
+        }));
+
+        marker = next_marker(&xml);
+        if marker.is_none() {
+            break;
+        }
+    }
+    Ok(shares)
+}
+
+/// Parse the `<Entries>` of a directory listing into
+/// `{name, is_directory, size}` objects, directories first.
+fn parse_entries(xml: &str) -> Vec<Value> {
+    let directories = xml_elements(xml, "Directory").into_iter().map(|entry| {
+        serde_json::json!({
+            "name": xml_element(entry, "Name"),
+            "is_directory": true,
+            "size": null,
+        })
+    });
+    let files = xml_elements(xml, "File").into_iter().map(|entry| {
+        serde_json::json!({
+            "name": xml_element(entry, "Name"),
+            "is_directory": false,
+            "size": xml_element(entry, "Content-Length").and_then(|s| s.parse::<u64>().ok()),
+        })
+    });
+    directories.chain(files).collect()
+}
+
+/// List up to `max_results` files and subdirectories of `directory` (the
+/// share root when empty) in `share`, continuing from `marker` when given.
+///
+/// Returns the entries and, when more remain, the marker of the next page.
+pub async fn list_directory(
+    client: &StorageClient,
+    share: &str,
+    directory: &str,
+    max_results: u32,
+    marker: Option<&str>,
+) -> Result<(Vec<Value>, Option<String>)> {
+    let directory = directory.trim_matches('/');
+    let max_results = max_results.clamp(1, MAX_RESULTS).to_string();
+    let mut query = vec![
+        ("restype", "directory"),
+        ("comp", "list"),
+        ("maxresults", max_results.as_str()),
+    ];
+    if let Some(marker) = marker {
+        query.push(("marker", marker));
+    }
+    let path = match directory {
+        "" => encode_path(share),
+        _ => encode_path(&format!("{share}/{directory}")),
+    };
+    let response = client
+        .send(Service::File, Method::GET, &path, &query, &[], Vec::new())
+        .await
+        .with_context(|| format!("Failed to list directory '{directory}' of share {share}"))?;
+    let xml = String::from_utf8_lossy(&response.body);
+    Ok((parse_entries(&xml), next_marker(&xml)))
+}
+
+/// Read up to `length` bytes of the file at `share`/`path` from `offset`,
+/// as [`blob::read`] does for blobs.
+pub async fn read_file(
+    client: &StorageClient,
+    share: &str,
+    path: &str,
+    offset: u64,
+    length: Option<u64>,
+    encoding: BlobEncoding,
+) -> Result<Value> {
+    let path = path.trim_matches('/');
+    blob::read_from(client, Service::File, share, path, offset, length, encoding)
+        .await
+        .with_context(|| format!("Failed to read file {share}/{path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entries_reads_files_and_directories() {
+        let xml = "<EnumerationResults ShareName=\"exports\" DirectoryPath=\"legacy\">\
+                   <Entries>\
+                   <File><FileId>13835128424026341376</FileId><Name>orders.csv</Name>\
+                   <Properties><Content-Length>2048</Content-Length></Properties></File>\
+                   <Directory><FileId>11529285414812647424</FileId><Name>2026</Name>\
+                   <Properties /></Directory>\
+                   </Entries><NextMarker>2!48!abc</NextMarker></EnumerationResults>";
+        assert_eq!(
+            parse_entries(xml),
+            vec![
+                serde_json::json!({ "name": "2026", "is_directory": true, "size": null }),
+                serde_json::json!({ "name": "orders.csv", "is_directory": false, "size": 2048 }),
+            ]
+        );
+        assert_eq!(next_marker(xml).as_deref(), Some("2!48!abc"));
+        assert_eq!(next_marker("<NextMarker></NextMarker>"), None);
+        assert_eq!(next_marker("<NextMarker />"), None);
+    }
+}
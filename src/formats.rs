@@ -0,0 +1,557 @@
+use std::collections::BTreeMap;
+
+use rmcp::model::{CallToolResult, JsonObject, RawContent};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use crate::annotations::RECORD_FIELDS;
+
+/// Maximum number of records written as a markdown table; a note says how
+/// many more there were.
+const MAX_MARKDOWN_ROWS: usize = 100;
+
+/// Writes records, with the given columns in order, as the text of one
+/// format.
+type Writer = fn(&[&str], &[Map<String, Value>]) -> String;
+
+/// Format the records of a query result are returned in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// The result as JSON (the default).
+    #[default]
+    Json,
+    /// The records as RFC 4180 CSV with a header row; nested values are
+    /// written as JSON.
+    Csv,
+    /// The records as a markdown table, for hosts that show tool text as it
+    /// is.  At most 100 records are written.
+    Markdown,
+    /// Newline-delimited JSON: one record per line.
+    #[serde(alias = "ndjson")]
+    Jsonl,
+    /// The records as an Arrow IPC file, attached as a base64 blob.
+    Arrow,
+    /// The records as a Snappy-compressed Parquet file, attached as a base64
+    /// blob.
+    Parquet,
+}
+
+/// Output format options accepted by the query tools.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+pub struct FormatOptions {
+    /// `json` (default), `csv`, which returns the records as CSV text —
+    /// far fewer tokens than JSON for wide results — `markdown`, a table for
+    /// small results shown to people, `jsonl` (alias `ndjson`), one JSON
+    /// record per line, or `arrow` / `parquet`, which attach the records as
+    /// an Arrow IPC or Parquet file for analytical tools.  The structured
+    /// result stays JSON.
+    pub format: Option<Format>,
+    /// Return columns whose every value is a JSON object or array encoded
+    /// as a string (e.g. NVARCHAR columns passing ISJSON, or nested JSON
+    /// stored as a string property) as JSON instead of escaped strings.
+    pub parse_json: Option<bool>,
+    /// Cut text and binary (hex) values longer than this many characters,
+    /// appending `…(+N chars)` (default: `MAX_CELL_CHARS`, else no limit).
+    pub max_cell_chars: Option<usize>,
+}
+
+impl FormatOptions {
+    /// Read the format options from a tool call's arguments.
+    pub fn from_arguments(arguments: Option<&JsonObject>) -> Self {
+        arguments
+            .and_then(|a| serde_json::from_value(Value::Object(a.clone())).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// The names of the ordered `columns` a SQL query result lists (see
+/// `mssql::execute_query_page`); empty for other results.
+pub fn declared_columns(structured: &Map<String, Value>) -> Vec<String> {
+    structured
+        .get("columns")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|column| column["name"].as_str().map(str::to_string))
+        .collect()
+}
+
+/// The columns of `records`: the `declared` ones in their order, then every
+/// other key in order of first appearance.  The keys of a record are
+/// sorted, so only `declared` keeps the order of a SELECT list.
+pub fn columns<'a>(declared: &'a [String], records: &'a [Map<String, Value>]) -> Vec<&'a str> {
+    let mut columns: Vec<&str> = declared.iter().map(String::as_str).collect();
+    for key in records.iter().flat_map(|record| record.keys()) {
+        if !columns.contains(&key.as_str()) {
+            columns.push(key);
+        }
+    }
+    columns
+}
+
+/// A value as the text of one cell: strings as they are, `null` as nothing
+/// and anything else as JSON.
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// `records` as CSV: a header row of `columns`, then one row per record,
+/// CRLF-terminated.
+fn csv(columns: &[&str], records: &[Map<String, Value>]) -> String {
+    let mut out = String::new();
+    let mut line = |fields: Vec<String>| {
+        out.push_str(&fields.join(","));
+        out.push_str("\r\n");
+    };
+    line(columns.iter().map(|c| csv_field(c)).collect());
+    for record in records {
+        line(
+            columns
+                .iter()
+                .map(|c| csv_field(&cell(record.get(*c))))
+                .collect(),
+        );
+    }
+    out
+}
+
+/// `records` as newline-delimited JSON, their keys in the order of
+/// `columns`.
+fn jsonl(columns: &[&str], records: &[Map<String, Value>]) -> String {
+    let mut out = String::new();
+    for record in records {
+        let fields: Vec<String> = columns
+            .iter()
+            .filter_map(|c| Some(format!("{}:{}", Value::from(*c), record.get(*c)?)))
+            .collect();
+        out.push('{');
+        out.push_str(&fields.join(","));
+        out.push_str("}\n");
+    }
+    out
+}
+
+/// Escape a markdown table cell: pipes are escaped and line breaks become
+/// `<br>`.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\r', '\n'], "<br>")
+}
+
+/// `records` as a markdown table of `columns`, numeric columns
+/// right-aligned.
+fn markdown(columns: &[&str], records: &[Map<String, Value>]) -> String {
+    if columns.is_empty() {
+        return "_No records._\n".into();
+    }
+    let shown = &records[..records.len().min(MAX_MARKDOWN_ROWS)];
+    let numeric = |column: &str| {
+        shown
+            .iter()
+            .filter_map(|record| record.get(column).filter(|v| !v.is_null()))
+            .all(Value::is_number)
+    };
+    let mut out = String::new();
+    let mut line = |cells: Vec<String>| {
+        out.push_str("| ");
+        out.push_str(&cells.join(" | "));
+        out.push_str(" |\n");
+    };
+    line(columns.iter().map(|c| markdown_cell(c)).collect());
+    line(
+        columns
+            .iter()
+            .map(|c| if numeric(c) { "---:" } else { "---" }.to_string())
+            .collect(),
+    );
+    for record in shown {
+        line(
+            columns
+                .iter()
+                .map(|c| markdown_cell(&cell(record.get(*c))))
+                .collect(),
+        );
+    }
+    if records.len() > shown.len() {
+        out.push_str(&format!(
+            "\n_{} more records not shown._\n",
+            records.len() - shown.len()
+        ));
+    }
+    out
+}
+
+/// The record field of `structured` (the first of [`RECORD_FIELDS`] holding
+/// an array) and its records; values that are not objects become
+/// `{"value": ...}`.
+pub fn records(structured: &Map<String, Value>) -> Option<(&str, Vec<Map<String, Value>>)> {
+    let field = RECORD_FIELDS.iter().find(|f| structured[**f].is_array())?;
+    let records = structured[*field]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|record| match record {
+            Value::Object(object) => object.clone(),
+            other => Map::from_iter([("value".to_string(), other.clone())]),
+        })
+        .collect();
+    Some((field, records))
+}
+
+/// The non-null fields of `structured` other than the record `field`.
+pub fn rest(structured: &Map<String, Value>, field: &str) -> Map<String, Value> {
+    structured
+        .iter()
+        .filter(|(key, value)| *key != field && !value.is_null())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+/// The JSON object or array held by `value`, if it is such a string.
+fn json_text(value: &Value) -> Option<Value> {
+    let text = value.as_str()?.trim();
+    if !(text.starts_with('{') || text.starts_with('[')) {
+        return None;
+    }
+    serde_json::from_str(text)
+        .ok()
+        .filter(|v: &Value| v.is_object() || v.is_array())
+}
+
+/// Replace the string values of JSON columns in the records of a successful
+/// result with the JSON they hold, when `parse_json` is set.
+///
+/// A column is parsed only when every non-null value is a JSON object or
+/// array, so a text column that happens to start with `{` in one row stays
+/// text.  The structured content is changed (records are free-form, so it
+/// still matches the output schema) and the text is rewritten from it.
+pub fn parse_json_columns(result: &mut CallToolResult, options: FormatOptions) {
+    if options.parse_json != Some(true) {
+        return;
+    }
+    let Some(records) = record_list(result) else {
+        return;
+    };
+    // Whether every non-null value seen so far in each column is JSON.
+    let mut json_columns: BTreeMap<String, bool> = BTreeMap::new();
+    for record in records.iter().filter_map(Value::as_object) {
+        for (column, value) in record.iter().filter(|(_, v)| !v.is_null()) {
+            let json = json_text(value).is_some();
+            *json_columns.entry(column.clone()).or_insert(json) &= json;
+        }
+    }
+    json_columns.retain(|_, json| *json);
+    if json_columns.is_empty() {
+        return;
+    }
+    for record in records.iter_mut().filter_map(Value::as_object_mut) {
+        for column in json_columns.keys() {
+            if let Some(value) = record.get_mut(column) {
+                if let Some(parsed) = json_text(value) {
+                    *value = parsed;
+                }
+            }
+        }
+    }
+    refresh_text(result);
+}
+
+/// `text` cut to `max_chars` characters followed by a `…(+N chars)` marker,
+/// or `None` when it is not longer than that.
+fn truncate_text(text: &str, max_chars: usize) -> Option<String> {
+    let (cut, _) = text.char_indices().nth(max_chars)?;
+    let rest = text[cut..].chars().count();
+    Some(format!("{}…(+{rest} chars)", &text[..cut]))
+}
+
+/// Cut every string value of the records of a successful result to
+/// `max_chars` characters, marking how many were dropped, so one huge
+/// `NVARCHAR(MAX)` or `VARBINARY` (hex) value cannot blow out the response.
+///
+/// Only the top-level values of each record are cut; JSON parsed by
+/// `parse_json` is kept whole.  The text is rewritten from the structured
+/// content.
+pub fn truncate_cells(result: &mut CallToolResult, max_chars: Option<usize>) {
+    let Some(max_chars) = max_chars else {
+        return;
+    };
+    let Some(records) = record_list(result) else {
+        return;
+    };
+    let mut changed = false;
+    for value in records
+        .iter_mut()
+        .filter_map(Value::as_object_mut)
+        .flat_map(|record| record.values_mut())
+    {
+        if let Some(cut) = value.as_str().and_then(|s| truncate_text(s, max_chars)) {
+            *value = cut.into();
+            changed = true;
+        }
+    }
+    if changed {
+        refresh_text(result);
+    }
+}
+
+/// The record list in the structured content of a successful result.
+fn record_list(result: &mut CallToolResult) -> Option<&mut Vec<Value>> {
+    if result.is_error == Some(true) {
+        return None;
+    }
+    let Some(Value::Object(structured)) = &mut result.structured_content else {
+        return None;
+    };
+    let field = RECORD_FIELDS.iter().find(|f| structured[**f].is_array())?;
+    structured.get_mut(*field)?.as_array_mut()
+}
+
+/// Replace the JSON text of `result` with its (changed) structured content.
+fn refresh_text(result: &mut CallToolResult) {
+    let Some(structured) = &result.structured_content else {
+        return;
+    };
+    let text = structured.to_string();
+    if let Some(content) = result
+        .content
+        .iter_mut()
+        .find(|c| matches!(c.raw, RawContent::Text(_)))
+    {
+        content.raw = RawContent::text(text);
+    }
+}
+
+/// Rewrite the text of a successful record result in the requested format.
+///
+/// The JSON text of the result is replaced by its records in `format`,
+/// followed by the other non-null fields (e.g. `nextCursor`) as a JSON
+/// object.  The structured content is left as it is, so it still matches the
+/// tool's output schema.  Results without records are not changed; Arrow and
+/// Parquet are attached by [`crate::export::apply`].
+pub fn apply(result: &mut CallToolResult, options: FormatOptions) {
+    let write: Writer = match options.format.unwrap_or_default() {
+        Format::Json => return,
+        Format::Csv => csv,
+        Format::Markdown => markdown,
+        Format::Jsonl => jsonl,
+        Format::Arrow | Format::Parquet => return,
+    };
+    if result.is_error == Some(true) {
+        return;
+    }
+    let Some(Value::Object(structured)) = &result.structured_content else {
+        return;
+    };
+    let Some((field, records)) = records(structured) else {
+        return;
+    };
+    let declared = declared_columns(structured);
+    let mut text = write(&columns(&declared, &records), &records);
+    let rest = rest(structured, field);
+    if !rest.is_empty() {
+        text.push('\n');
+        text.push_str(&Value::Object(rest).to_string());
+    }
+
+    if let Some(content) = result
+        .content
+        .iter_mut()
+        .find(|c| matches!(c.raw, RawContent::Text(_)))
+    {
+        content.raw = RawContent::text(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    #[test]
+    fn csv_quotes_fields_and_writes_nested_values_as_json() {
+        let structured = serde_json::json!({
+            "rows": [
+                { "id": 1, "name": "Smith, Jane", "tags": ["a"] },
+                { "id": 2, "name": "say \"hi\"", "note": null, "extra": true },
+            ],
+            "nextCursor": "abc",
+        });
+        let mut result = CallToolResult::structured(structured.clone());
+        apply(
+            &mut result,
+            FormatOptions {
+                format: Some(Format::Csv),
+                ..Default::default()
+            },
+        );
+        let text = result.content[0].as_text().unwrap().text.clone();
+        assert_eq!(
+            text,
+            "id,name,tags,extra,note\r\n\
+             1,\"Smith, Jane\",\"[\"\"a\"\"]\",,\r\n\
+             2,\"say \"\"hi\"\"\",,true,\r\n\
+             \n{\"nextCursor\":\"abc\"}"
+        );
+        assert_eq!(result.structured_content, Some(structured));
+
+        let mut plain = CallToolResult::success(vec![Content::text("done")]);
+        apply(
+            &mut plain,
+            FormatOptions {
+                format: Some(Format::Csv),
+                ..Default::default()
+            },
+        );
+        assert_eq!(plain.content[0].as_text().unwrap().text, "done");
+    }
+
+    #[test]
+    fn columns_follow_the_select_list() {
+        let structured = serde_json::json!({
+            "columns": [
+                { "name": "name", "type": "nvarchar(50)", "nullable": false },
+                { "name": "id", "type": "int", "nullable": false },
+                { "name": "city", "type": "nvarchar(50)", "nullable": true },
+            ],
+            "rows": [{ "name": "Jane", "id": 1, "city": null }],
+        });
+        let text = |format| {
+            let mut result = CallToolResult::structured(structured.clone());
+            apply(
+                &mut result,
+                FormatOptions {
+                    format: Some(format),
+                    ..Default::default()
+                },
+            );
+            result.content[0].as_text().unwrap().text.clone()
+        };
+        assert!(text(Format::Csv).starts_with("name,id,city\r\nJane,1,\r\n"));
+        assert!(text(Format::Jsonl).starts_with("{\"name\":\"Jane\",\"id\":1,\"city\":null}\n"));
+    }
+
+    #[test]
+    fn json_columns_are_parsed_only_when_every_value_is_json() {
+        let structured = serde_json::json!({
+            "rows": [
+                { "id": 1, "doc": "{\"a\":1}", "note": "{not json" },
+                { "id": 2, "doc": null, "note": "[1]" },
+                { "id": 3, "doc": " [1, 2] ", "note": null },
+            ],
+        });
+        let mut result = CallToolResult::structured(structured);
+        parse_json_columns(
+            &mut result,
+            FormatOptions {
+                parse_json: Some(true),
+                ..Default::default()
+            },
+        );
+        let expected = serde_json::json!({
+            "rows": [
+                { "id": 1, "doc": { "a": 1 }, "note": "{not json" },
+                { "id": 2, "doc": null, "note": "[1]" },
+                { "id": 3, "doc": [1, 2], "note": null },
+            ],
+        });
+        assert_eq!(result.structured_content.as_ref(), Some(&expected));
+        let text: Value = serde_json::from_str(&result.content[0].as_text().unwrap().text).unwrap();
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn long_cells_are_cut_with_a_marker() {
+        assert_eq!(truncate_text("héllo wörld", 5).unwrap(), "héllo…(+6 chars)");
+        assert_eq!(truncate_text("short", 5), None);
+
+        let mut result = CallToolResult::structured(serde_json::json!({
+            "rows": [{ "id": 1, "body": "x".repeat(1_000), "doc": { "a": "y".repeat(50) } }],
+        }));
+        truncate_cells(&mut result, Some(10));
+        let row = &result.structured_content.as_ref().unwrap()["rows"][0];
+        assert_eq!(row["body"], "xxxxxxxxxx…(+990 chars)");
+        assert_eq!(row["doc"]["a"].as_str().unwrap().len(), 50);
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .text
+            .contains("(+990 chars)"));
+    }
+
+    #[test]
+    fn jsonl_writes_one_record_per_line() {
+        let options: FormatOptions =
+            serde_json::from_value(serde_json::json!({ "format": "ndjson" })).unwrap();
+        assert_eq!(options.format, Some(Format::Jsonl));
+        let records = vec![
+            Map::from_iter([("id".to_string(), Value::from(1))]),
+            Map::from_iter([("id".to_string(), Value::from(2))]),
+        ];
+        assert_eq!(jsonl(&["id"], &records), "{\"id\":1}\n{\"id\":2}\n");
+    }
+
+    #[test]
+    fn markdown_aligns_numbers_and_escapes_cells() {
+        let records = vec![
+            Map::from_iter([
+                ("city".to_string(), Value::from("A|B")),
+                ("total".to_string(), Value::from(12.5)),
+            ]),
+            Map::from_iter([
+                ("city".to_string(), Value::from("two\nlines")),
+                ("total".to_string(), Value::Null),
+            ]),
+        ];
+        assert_eq!(
+            markdown(&columns(&[], &records), &records),
+            "| city | total |\n\
+             | --- | ---: |\n\
+             | A\\|B | 12.5 |\n\
+             | two<br>lines |  |\n"
+        );
+
+        // The header follows the SELECT list, not the sorted keys.
+        let mut result = CallToolResult::structured(serde_json::json!({
+            "columns": [
+                { "name": "total", "type": "decimal(9,2)", "nullable": true },
+                { "name": "city", "type": "nvarchar(50)", "nullable": false },
+            ],
+            "rows": [{ "city": "Oslo", "total": 3.5 }],
+        }));
+        apply(
+            &mut result,
+            FormatOptions {
+                format: Some(Format::Markdown),
+                ..Default::default()
+            },
+        );
+        assert!(result.content[0]
+            .as_text()
+            .unwrap()
+            .text
+            .starts_with("| total | city |\n| ---: | --- |\n| 3.5 | Oslo |\n"));
+
+        let many: Vec<Map<String, Value>> = (0..150)
+            .map(|i| Map::from_iter([("n".to_string(), Value::from(i))]))
+            .collect();
+        let table = markdown(&["n"], &many);
+        assert_eq!(table.lines().count(), 2 + MAX_MARKDOWN_ROWS + 2);
+        assert!(table.ends_with("_50 more records not shown._\n"));
+    }
+}
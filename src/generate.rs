@@ -0,0 +1,143 @@
+use anyhow::{anyhow, bail, Result};
+use rmcp::{
+    model::{CreateMessageRequestParams, SamplingMessage},
+    Peer, RoleServer,
+};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::cosmos::{self, CosmosAccount};
+use crate::mssql;
+use crate::prompts::QueryTarget;
+use crate::readonly::{self, Dialect};
+
+/// Upper bound on the length of a drafted query, in tokens.
+const MAX_QUERY_TOKENS: u32 = 1_000;
+
+/// Take the query out of a model reply, dropping a surrounding Markdown code
+/// fence and a trailing semicolon.
+fn extract_query(reply: &str) -> String {
+    let mut query = reply.trim();
+    if let Some(fenced) = query.strip_prefix("```") {
+        // Skip the info string (e.g. `sql`) on the opening fence line.
+        let body = fenced.split_once('\n').map_or("", |(_, body)| body);
+        query = body.split("```").next().unwrap_or_default().trim();
+    }
+    query.trim_end_matches(';').trim_end().to_string()
+}
+
+/// Ask the client's model, through MCP sampling, to write a read-only query
+/// answering `question` against `target`, whose live schema is included in
+/// the request.
+///
+/// The draft is checked with [`readonly::ensure_read_only`] but not run.
+/// Returns `{"query", "dialect", "run_with", "model"}`, where `run_with`
+/// names the tool that executes the query.
+pub async fn generate_query(
+    peer: &Peer<RoleServer>,
+    config: &Config,
+    cosmos: Result<&CosmosAccount, String>,
+    question: &str,
+    target: QueryTarget<'_>,
+) -> Result<Value> {
+    if peer
+        .peer_info()
+        .is_none_or(|info| info.capabilities.sampling.is_none())
+    {
+        bail!("The client does not support sampling, so it cannot draft queries");
+    }
+
+    let (dialect, run_with, language, schema) = match target {
+        QueryTarget::Table { schema, table } => {
+            let cfg = config.require_mssql()?;
+            (
+                Dialect::TSql,
+                "mssql_execute_query",
+                format!("T-SQL (Azure SQL) query against the table [{schema}].[{table}]"),
+                mssql::describe_table(cfg, schema, table).await?,
+            )
+        }
+        QueryTarget::Container {
+            database,
+            container,
+        } => {
+            let account = cosmos.map_err(anyhow::Error::msg)?;
+            (
+                Dialect::CosmosSql,
+                "cosmos_query_items",
+                format!(
+                    "Cosmos DB SQL API query against the container {database}/{container}, \
+                     aliased as `c`"
+                ),
+                cosmos::describe_container(account, database, container).await?,
+            )
+        }
+        QueryTarget::Any => bail!("Either table or container is required"),
+    };
+
+    let schema = serde_json::to_string_pretty(&schema)?;
+    let result = peer
+        .create_message(CreateMessageRequestParams {
+            meta: None,
+            task: None,
+            messages: vec![SamplingMessage::user_text(format!(
+                "Schema:\n{schema}\n\nQuestion: {question}"
+            ))],
+            model_preferences: None,
+            system_prompt: Some(format!(
+                "Write one read-only {language} that answers the user's question, using \
+                 only the columns or properties in the schema.  Reply with the query alone: \
+                 no explanation, no Markdown."
+            )),
+            include_context: None,
+            temperature: Some(0.0),
+            max_tokens: MAX_QUERY_TOKENS,
+            stop_sequences: None,
+            metadata: None,
+            tools: None,
+            tool_choice: None,
+        })
+        .await
+        .map_err(|e| anyhow!("Sampling request failed: {e}"))?;
+
+    let reply = result
+        .message
+        .content
+        .iter()
+        .filter_map(|c| c.as_text())
+        .map(|t| t.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let query = extract_query(&reply);
+    readonly::ensure_read_only(dialect, &query)
+        .map_err(|e| anyhow!("The drafted query was rejected ({e}): {query}"))?;
+
+    Ok(serde_json::json!({
+        "query": query,
+        "dialect": match dialect {
+            Dialect::TSql => "tsql",
+            Dialect::CosmosSql => "cosmos_sql",
+            Dialect::Cql => "cql",
+        },
+        "run_with": run_with,
+        "model": result.model,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_query_strips_fences_and_semicolons() {
+        assert_eq!(extract_query("  SELECT 1;\n"), "SELECT 1");
+        assert_eq!(
+            extract_query("```sql\nSELECT TOP 5 *\nFROM dbo.orders;\n```"),
+            "SELECT TOP 5 *\nFROM dbo.orders"
+        );
+        assert_eq!(
+            extract_query("```\nSELECT * FROM c\n```"),
+            "SELECT * FROM c"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use futures::{SinkExt, StreamExt};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::GremlinConfig;
+use crate::recording;
+
+/// Results returned when the caller gives no `max_results`.
+pub const DEFAULT_MAX_RESULTS: usize = 100;
+
+/// Upper bound on the results returned by one call.
+pub const MAX_RESULTS: usize = 1_000;
+
+/// How long a traversal may run before it is abandoned.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The only serializer Cosmos DB accepts.
+const MIME_TYPE: &str = "application/vnd.gremlin-v2.0+json";
+
+/// Traversal steps that add, change or remove vertices, edges or
+/// properties.
+const WRITE_STEPS: &[&str] = &["addE", "addV", "drop", "mergeE", "mergeV", "property"];
+
+/// Which edges of a vertex `gremlin_get_edges` returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeDirection {
+    /// Edges leaving the vertex.
+    #[default]
+    Out,
+    /// Edges arriving at the vertex.
+    In,
+    Both,
+}
+
+/// Check that `query` only reads the graph: none of [`WRITE_STEPS`] may be
+/// called outside string literals.
+///
+/// Unlike the T-SQL check of [`crate::readonly::ensure_read_only`] this is a
+/// conservative token check, not a parser.
+pub fn ensure_read_only(query: &str) -> Result<()> {
+    let mut chars = query.chars();
+    // The identifier before the current character, kept across whitespace.
+    let mut word = String::new();
+    let mut in_word = false;
+    while let Some(c) = chars.next() {
+        if c.is_alphanumeric() || c == '_' {
+            if !in_word {
+                word.clear();
+                in_word = true;
+            }
+            word.push(c);
+            continue;
+        }
+        in_word = false;
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '(' && WRITE_STEPS.contains(&word.as_str()) {
+            bail!("Only read-only traversals are allowed, found `{word}()`");
+        }
+        word.clear();
+        if c == '\'' || c == '"' {
+            while let Some(next) = chars.next() {
+                if next == '\\' {
+                    chars.next();
+                } else if next == c {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A request frame: the length-prefixed MIME type followed by the JSON
+/// request.
+fn frame(request: &Value) -> Result<Vec<u8>> {
+    let mut frame = vec![MIME_TYPE.len() as u8];
+    frame.extend_from_slice(MIME_TYPE.as_bytes());
+    frame.extend(serde_json::to_vec(request)?);
+    Ok(frame)
+}
+
+/// Collapse GraphSON `{"@type": ..., "@value": ...}` wrappers into their
+/// plain values.
+fn untype(value: Value) -> Value {
+    match value {
+        Value::Object(mut object) if object.len() == 2 && object.contains_key("@type") => {
+            untype(object.remove("@value").unwrap_or_default())
+        }
+        Value::Object(object) => {
+            Value::Object(object.into_iter().map(|(k, v)| (k, untype(v))).collect())
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(untype).collect()),
+        value => value,
+    }
+}
+
+/// Run `query` with `bindings` against `graph` (else `GREMLIN_GRAPH`),
+/// collecting at most `max_results` results across the response batches.
+///
+/// Connects per call: Cosmos DB authenticates a connection for a single
+/// graph, with the user name `/dbs/{database}/colls/{graph}` and the account
+/// key as password.
+///
+/// Returns `{results, request_charge, truncated}`.
+pub async fn query(
+    cfg: &GremlinConfig,
+    graph: Option<&str>,
+    query: &str,
+    bindings: Map<String, Value>,
+    max_results: usize,
+) -> Result<Value> {
+    let graph = graph.unwrap_or(&cfg.graph);
+    let max_results = max_results.clamp(1, MAX_RESULTS);
+    tokio::time::timeout(
+        QUERY_TIMEOUT,
+        execute(cfg, graph, query, bindings, max_results),
+    )
+    .await
+    .with_context(|| format!("Gremlin query timed out after {}s", QUERY_TIMEOUT.as_secs()))?
+    .with_context(|| format!("Gremlin query against graph {graph} failed"))
+}
+
+/// Send one `eval` request, answering the authentication challenge, and
+/// collect its results.
+async fn execute(
+    cfg: &GremlinConfig,
+    graph: &str,
+    query: &str,
+    bindings: Map<String, Value>,
+    max_results: usize,
+) -> Result<Value> {
+    recording::live("Gremlin (WebSocket)")?;
+    let (mut socket, _) = tokio_tungstenite::connect_async(cfg.endpoint.as_str())
+        .await
+        .context("Failed to connect to the Gremlin endpoint")?;
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let request = serde_json::json!({
+        "requestId": request_id,
+        "op": "eval",
+        "processor": "",
+        "args": { "gremlin": query, "bindings": bindings, "language": "gremlin-groovy" },
+    });
+    socket.send(Message::binary(frame(&request)?)).await?;
+
+    let mut results = Vec::new();
+    let mut truncated = false;
+    let mut request_charge = None;
+    while let Some(message) = socket.next().await {
+        let response: Value = match message? {
+            Message::Text(text) => serde_json::from_str(&text)?,
+            Message::Binary(bytes) => serde_json::from_slice(&bytes)?,
+            Message::Close(_) => bail!("The Gremlin endpoint closed the connection"),
+            _ => continue,
+        };
+        let status = &response["status"];
+        match status["code"].as_u64().unwrap_or_default() {
+            407 => {
+                let username = format!("/dbs/{}/colls/{graph}", cfg.database);
+                let sasl = BASE64.encode(format!("\0{username}\0{}", cfg.key));
+                let authentication = serde_json::json!({
+                    "requestId": request_id,
+                    "op": "authentication",
+                    "processor": "",
+                    "args": { "SASL": sasl },
+                });
+                socket
+                    .send(Message::binary(frame(&authentication)?))
+                    .await?;
+                continue;
+            }
+            200 | 204 | 206 => {}
+            code => bail!(
+                "Gremlin endpoint returned {code}: {}",
+                status["message"].as_str().unwrap_or_default()
+            ),
+        }
+
+        let attributes = &status["attributes"];
+        request_charge = attributes["x-ms-total-request-charge"]
+            .as_f64()
+            .or(request_charge);
+        if let Value::Array(data) = untype(response["result"]["data"].clone()) {
+            for item in data {
+                if results.len() == max_results {
+                    truncated = true;
+                    break;
+                }
+                results.push(item);
+            }
+        }
+        if truncated || status["code"] != 206 {
+            break;
+        }
+    }
+    let _ = socket.close(None).await;
+
+    Ok(serde_json::json!({
+        "results": results,
+        "request_charge": request_charge,
+        "truncated": truncated,
+    }))
+}
+
+/// Read the vertex `id` (in the logical partition `partition_key`, which
+/// partitioned graphs need for a point read) with its edge counts.
+///
+/// Returns `{vertex, out_degree, in_degree, request_charge}`, the vertex
+/// being `null` when it does not exist.
+pub async fn get_vertex(
+    cfg: &GremlinConfig,
+    graph: Option<&str>,
+    id: &str,
+    partition_key: Option<&str>,
+) -> Result<Value> {
+    let traversal = "g.V(vertex_id).project('vertex', 'out_degree', 'in_degree')\
+                     .by().by(outE().count()).by(inE().count())";
+    let mut result = query(cfg, graph, traversal, vertex_binding(id, partition_key), 1).await?;
+    let found = result["results"][0].take();
+    Ok(serde_json::json!({
+        "vertex": found["vertex"],
+        "out_degree": found["out_degree"],
+        "in_degree": found["in_degree"],
+        "request_charge": result["request_charge"],
+    }))
+}
+
+/// List up to `max_results` edges of the vertex `id` in `direction`,
+/// optionally only those with `label`.
+///
+/// Returns `{edges, request_charge, truncated}`.
+pub async fn get_edges(
+    cfg: &GremlinConfig,
+    graph: Option<&str>,
+    id: &str,
+    partition_key: Option<&str>,
+    direction: EdgeDirection,
+    label: Option<&str>,
+    max_results: usize,
+) -> Result<Value> {
+    let step = match direction {
+        EdgeDirection::Out => "outE",
+        EdgeDirection::In => "inE",
+        EdgeDirection::Both => "bothE",
+    };
+    let max_results = max_results.clamp(1, MAX_RESULTS);
+    let mut bindings = vertex_binding(id, partition_key);
+    let traversal = match label {
+        Some(label) => {
+            bindings.insert("edge_label".into(), label.into());
+            format!(
+                "g.V(vertex_id).{step}(edge_label).limit({})",
+                max_results + 1
+            )
+        }
+        None => format!("g.V(vertex_id).{step}().limit({})", max_results + 1),
+    };
+    let mut result = query(cfg, graph, &traversal, bindings, max_results).await?;
+    Ok(serde_json::json!({
+        "edges": result["results"].take(),
+        "request_charge": result["request_charge"],
+        "truncated": result["truncated"],
+    }))
+}
+
+/// Bind `vertex_id` to `id`, or to `[partition_key, id]` — the form Cosmos
+/// DB uses for point reads in partitioned graphs.
+fn vertex_binding(id: &str, partition_key: Option<&str>) -> Map<String, Value> {
+    let id = match partition_key {
+        Some(pk) => serde_json::json!([pk, id]),
+        None => id.into(),
+    };
+    Map::from_iter([("vertex_id".to_string(), id)])
+}
+
+/// Check the endpoint is reachable and accepts the key.
+pub async fn ping(cfg: &GremlinConfig) -> Result<()> {
+    query(cfg, None, "g.V().limit(1).count()", Map::new(), 1)
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_check_finds_write_steps_outside_strings() {
+        for query in [
+            "g.V().hasLabel('person').has('name', 'drop(me)').out('knows').values('name')",
+            "g.V('a').properties('email').valueMap(true)",
+            "g.E().hasLabel(\"property (x)\").count()",
+        ] {
+            assert!(ensure_read_only(query).is_ok(), "{query}");
+        }
+        for query in [
+            "g.V('a').drop()",
+            "g.addV('person').property('id', 'b')",
+            "g.V('a').addE ('knows').to(g.V('b'))",
+        ] {
+            assert!(ensure_read_only(query).is_err(), "{query}");
+        }
+    }
+
+    #[test]
+    fn untype_and_frame_speak_graphson_v2() {
+        let value = serde_json::json!({
+            "id": "a",
+            "count": { "@type": "g:Int64", "@value": 3 },
+            "list": [{ "@type": "g:Double", "@value": 1.5 }],
+        });
+        assert_eq!(
+            untype(value),
+            serde_json::json!({ "id": "a", "count": 3, "list": [1.5] })
+        );
+
+        let frame = frame(&serde_json::json!({ "op": "eval" })).unwrap();
+        assert_eq!(frame[0] as usize, MIME_TYPE.len());
+        assert_eq!(&frame[1..=MIME_TYPE.len()], MIME_TYPE.as_bytes());
+        assert_eq!(&frame[MIME_TYPE.len() + 1..], br#"{"op":"eval"}"#);
+    }
+}
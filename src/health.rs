@@ -0,0 +1,355 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::aad::TokenProvider;
+use crate::appconfig;
+use crate::cassandra;
+use crate::config::Config;
+use crate::cosmos::{self, CosmosAccount};
+use crate::cost;
+use crate::eventgrid;
+use crate::eventhubs;
+use crate::gremlin;
+use crate::keyvault;
+use crate::kusto;
+use crate::monitor;
+use crate::mssql;
+use crate::openai;
+use crate::servicebus::ServiceBusClient;
+use crate::storage::StorageClient;
+
+/// How often configured backends are probed for reachability.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A data store behind a group of tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    Mssql,
+    Cosmos,
+    /// Cosmos DB Gremlin (graph) API.
+    Gremlin,
+    /// Cosmos DB Cassandra API.
+    Cassandra,
+    /// The Synapse serverless endpoint used for Cosmos DB analytical queries.
+    Analytical,
+    /// A Synapse serverless SQL endpoint queried directly.
+    Synapse,
+    /// A Microsoft Fabric warehouse or lakehouse SQL endpoint, reached with
+    /// the Azure AD credential.
+    Fabric,
+    /// The Azure Storage account (blobs and tables).
+    Storage,
+    ServiceBus,
+    EventHubs,
+    /// Azure Monitor (Log Analytics, Application Insights), reached with the
+    /// Azure AD credential.
+    Monitor,
+    /// Azure Data Explorer, reached with the Azure AD credential.
+    Kusto,
+    /// Azure Cost Management, reached with the Azure AD credential.
+    CostManagement,
+    /// Azure Key Vault, reached with the Azure AD credential.
+    KeyVault,
+    /// Azure OpenAI embedding deployment.
+    OpenAi,
+    /// Azure App Configuration store.
+    AppConfig,
+    /// Azure Event Grid topics.
+    EventGrid,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 17] = [
+        Backend::Mssql,
+        Backend::Cosmos,
+        Backend::Gremlin,
+        Backend::Cassandra,
+        Backend::Analytical,
+        Backend::Synapse,
+        Backend::Fabric,
+        Backend::Storage,
+        Backend::ServiceBus,
+        Backend::EventHubs,
+        Backend::Monitor,
+        Backend::Kusto,
+        Backend::CostManagement,
+        Backend::KeyVault,
+        Backend::OpenAi,
+        Backend::AppConfig,
+        Backend::EventGrid,
+    ];
+
+    /// Short lowercase name of the backend, as reported to clients.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Mssql => "mssql",
+            Backend::Cosmos => "cosmos",
+            Backend::Gremlin => "gremlin",
+            Backend::Cassandra => "cassandra",
+            Backend::Analytical => "analytical",
+            Backend::Synapse => "synapse",
+            Backend::Fabric => "fabric",
+            Backend::Storage => "storage",
+            Backend::ServiceBus => "servicebus",
+            Backend::EventHubs => "eventhubs",
+            Backend::Monitor => "monitor",
+            Backend::Kusto => "kusto",
+            Backend::CostManagement => "cost",
+            Backend::KeyVault => "keyvault",
+            Backend::OpenAi => "openai",
+            Backend::AppConfig => "appconfig",
+            Backend::EventGrid => "eventgrid",
+        }
+    }
+
+    /// The backend the tool `name` needs, or `None` for tools that do not
+    /// depend on a single backend (e.g. `fetch_next_page`).
+    pub fn of_tool(name: &str) -> Option<Self> {
+        if name == "cosmos_analytical_query" {
+            Some(Backend::Analytical)
+        } else if name.starts_with("synapse_") {
+            Some(Backend::Synapse)
+        } else if name.starts_with("fabric_") {
+            Some(Backend::Fabric)
+        } else if name.starts_with("mssql_") {
+            Some(Backend::Mssql)
+        } else if name.starts_with("cosmos_") {
+            Some(Backend::Cosmos)
+        } else if name.starts_with("gremlin_") {
+            Some(Backend::Gremlin)
+        } else if name.starts_with("cassandra_") {
+            Some(Backend::Cassandra)
+        } else if name.starts_with("blob_")
+            || name.starts_with("tables_")
+            || name.starts_with("adls_")
+            || name.starts_with("files_")
+        {
+            Some(Backend::Storage)
+        } else if name.starts_with("servicebus_") {
+            Some(Backend::ServiceBus)
+        } else if name.starts_with("eventhub_") {
+            Some(Backend::EventHubs)
+        } else if name.starts_with("loganalytics_") || name.starts_with("appinsights_") {
+            Some(Backend::Monitor)
+        } else if name.starts_with("kusto_") {
+            Some(Backend::Kusto)
+        } else if name.starts_with("cost_") {
+            Some(Backend::CostManagement)
+        } else if name.starts_with("keyvault_") {
+            Some(Backend::KeyVault)
+        } else if name.starts_with("embed_") {
+            Some(Backend::OpenAi)
+        } else if name.starts_with("appconfig_") {
+            Some(Backend::AppConfig)
+        } else if name.starts_with("eventgrid_") {
+            Some(Backend::EventGrid)
+        } else {
+            None
+        }
+    }
+
+    /// The environment variables that configure the backend.
+    pub fn settings(self) -> &'static str {
+        match self {
+            Backend::Mssql => "MSSQL_CONNECTION_STRING",
+            Backend::Synapse => "SYNAPSE_CONNECTION_STRING",
+            Backend::Fabric => "FABRIC_SQL_ENDPOINT and an Azure AD credential",
+            Backend::Cosmos => "COSMOS_ENDPOINT and COSMOS_KEY",
+            Backend::Analytical => "COSMOS_ANALYTICAL_CONNECTION_STRING",
+            Backend::Storage => "STORAGE_ACCOUNT and STORAGE_KEY",
+            Backend::ServiceBus => "SERVICEBUS_CONNECTION_STRING",
+            Backend::EventHubs => "EVENTHUB_CONNECTION_STRING",
+            Backend::Monitor | Backend::CostManagement => {
+                "AZURE_TENANT_ID, AZURE_CLIENT_ID and AZURE_CLIENT_SECRET, \
+                 or AZURE_USE_MANAGED_IDENTITY=true"
+            }
+            Backend::Kusto => "KUSTO_CLUSTER_URL and an Azure AD credential",
+            Backend::KeyVault => "KEYVAULT_URL and an Azure AD credential",
+            Backend::Gremlin => "GREMLIN_ENDPOINT and GREMLIN_KEY",
+            Backend::Cassandra => {
+                "CASSANDRA_CONTACT_POINT, CASSANDRA_USERNAME and CASSANDRA_PASSWORD"
+            }
+            Backend::OpenAi => "AZURE_OPENAI_ENDPOINT and AZURE_OPENAI_EMBEDDING_DEPLOYMENT",
+            Backend::AppConfig => "APPCONFIG_CONNECTION_STRING or APPCONFIG_ENDPOINT",
+            Backend::EventGrid => "EVENTGRID_TOPICS",
+        }
+    }
+
+    /// Whether the backend is configured at all.
+    pub fn configured(self, config: &Config) -> bool {
+        match self {
+            Backend::Mssql => config.mssql.is_some(),
+            Backend::Synapse => config.synapse.is_some(),
+            Backend::Fabric => config.fabric.is_some(),
+            Backend::Cosmos => config.cosmos.is_some(),
+            Backend::Analytical => config
+                .cosmos
+                .as_ref()
+                .is_some_and(|c| c.analytical.is_some()),
+            Backend::Storage => config.storage.is_some(),
+            Backend::ServiceBus => config.servicebus.is_some(),
+            Backend::EventHubs => config.eventhubs.is_some(),
+            Backend::Monitor => config.credential.is_some(),
+            Backend::Kusto => config.kusto.is_some(),
+            Backend::CostManagement => config.credential.is_some(),
+            Backend::KeyVault => config.keyvault.is_some(),
+            Backend::Gremlin => config.gremlin.is_some(),
+            Backend::Cassandra => config.cassandra.is_some(),
+            Backend::OpenAi => config.openai.is_some(),
+            Backend::AppConfig => config.appconfig.is_some(),
+            Backend::EventGrid => config.eventgrid.is_some(),
+        }
+    }
+
+    /// Make a cheap call to check the backend is reachable and accepts the
+    /// configured credentials.
+    pub async fn probe(
+        self,
+        config: &Config,
+        cosmos: Result<&CosmosAccount, String>,
+        storage: Result<&StorageClient, String>,
+    ) -> Result<()> {
+        match self {
+            Backend::Mssql => mssql::ping(config.require_mssql()?).await,
+            Backend::Synapse => mssql::ping(config.require_synapse()?).await,
+            Backend::Fabric => {
+                let token = TokenProvider::new(config.require_credential()?)
+                    .token(mssql::SQL_RESOURCE)
+                    .await?;
+                mssql::ping(&config.require_fabric()?.connection(token)).await
+            }
+            Backend::Cosmos => {
+                let account = cosmos.map_err(anyhow::Error::msg)?;
+                cosmos::list_databases(account).await.map(|_| ())
+            }
+            Backend::Analytical => {
+                mssql::ping(config.require_cosmos()?.require_analytical()?).await
+            }
+            Backend::Storage => storage.map_err(anyhow::Error::msg)?.ping().await,
+            Backend::ServiceBus => {
+                ServiceBusClient::new(config.require_servicebus()?)
+                    .ping()
+                    .await
+            }
+            Backend::EventHubs => eventhubs::ping(config.require_eventhubs()?).await,
+            Backend::Monitor => TokenProvider::new(config.require_credential()?)
+                .token(monitor::LOG_ANALYTICS_RESOURCE)
+                .await
+                .map(|_| ()),
+            Backend::Kusto => {
+                let tokens = TokenProvider::new(config.require_credential()?);
+                kusto::ping(&tokens, config.require_kusto()?).await
+            }
+            Backend::CostManagement => TokenProvider::new(config.require_credential()?)
+                .token(cost::MANAGEMENT_RESOURCE)
+                .await
+                .map(|_| ()),
+            Backend::KeyVault => {
+                let tokens = TokenProvider::new(config.require_credential()?);
+                keyvault::ping(&tokens, config.require_keyvault()?).await
+            }
+            Backend::Gremlin => gremlin::ping(config.require_gremlin()?).await,
+            Backend::Cassandra => cassandra::ping(config.require_cassandra()?).await,
+            Backend::OpenAi => {
+                let tokens = config.credential.as_ref().map(TokenProvider::new);
+                openai::ping(tokens.as_ref(), config.require_openai()?).await
+            }
+            Backend::AppConfig => {
+                let tokens = config.credential.as_ref().map(TokenProvider::new);
+                appconfig::ping(tokens.as_ref(), config.require_appconfig()?).await
+            }
+            // Publishing is the only call a topic accepts, so only check a
+            // token can be had for the topics without a key.
+            Backend::EventGrid => {
+                if config
+                    .require_eventgrid()?
+                    .topics
+                    .iter()
+                    .all(|t| t.key.is_some())
+                {
+                    return Ok(());
+                }
+                TokenProvider::new(config.require_credential()?)
+                    .token(eventgrid::EVENT_GRID_RESOURCE)
+                    .await
+                    .map(|_| ())
+            }
+        }
+    }
+}
+
+/// Which backends are currently usable.  Configured backends start out
+/// available and are updated by periodic probes.
+pub struct Availability {
+    flags: [AtomicBool; Backend::ALL.len()],
+}
+
+impl Availability {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            flags: Backend::ALL.map(|backend| AtomicBool::new(backend.configured(config))),
+        }
+    }
+
+    pub fn is_available(&self, backend: Backend) -> bool {
+        self.flags[backend as usize].load(Ordering::Relaxed)
+    }
+
+    /// Record whether `backend` is available, returning `true` if that
+    /// changed.
+    pub fn set(&self, backend: Backend, available: bool) -> bool {
+        self.flags[backend as usize].swap(available, Ordering::Relaxed) != available
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MssqlConfig;
+
+    #[test]
+    fn tools_map_to_their_backend() {
+        assert_eq!(
+            Backend::of_tool("mssql_execute_query"),
+            Some(Backend::Mssql)
+        );
+        assert_eq!(
+            Backend::of_tool("cosmos_query_items"),
+            Some(Backend::Cosmos)
+        );
+        assert_eq!(
+            Backend::of_tool("cosmos_analytical_query"),
+            Some(Backend::Analytical)
+        );
+        assert_eq!(
+            Backend::of_tool("fabric_execute_query"),
+            Some(Backend::Fabric)
+        );
+        assert_eq!(Backend::of_tool("blob_read"), Some(Backend::Storage));
+        assert_eq!(Backend::of_tool("tables_query"), Some(Backend::Storage));
+        assert_eq!(
+            Backend::of_tool("servicebus_send"),
+            Some(Backend::ServiceBus)
+        );
+        assert_eq!(Backend::of_tool("fetch_next_page"), None);
+        assert_eq!(Backend::of_tool("server_stats"), None);
+        assert_eq!(Backend::of_tool("server_status"), None);
+    }
+
+    #[test]
+    fn only_configured_backends_start_available() {
+        let config = Config {
+            mssql: Some(MssqlConfig::for_tests("server=tcp:localhost,1433")),
+            ..Config::for_tests()
+        };
+        let availability = Availability::new(&config);
+        assert!(availability.is_available(Backend::Mssql));
+        assert!(!availability.is_available(Backend::Cosmos));
+
+        assert!(availability.set(Backend::Mssql, false));
+        assert!(!availability.set(Backend::Mssql, false));
+        assert!(!availability.is_available(Backend::Mssql));
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::aad::TokenProvider;
+use crate::config::KeyVaultConfig;
+use crate::recording;
+
+/// Azure AD resource of every Key Vault.
+const VAULT_RESOURCE: &str = "https://vault.azure.net";
+
+/// Key Vault REST API version.
+const API_VERSION: &str = "7.4";
+
+/// GET `url` (below the vault, or a `nextLink`) with a vault token.
+async fn get(tokens: &TokenProvider, url: &str) -> Result<Value> {
+    let mut request = reqwest::Client::new().get(url);
+    // `nextLink`s already carry the API version.
+    if !url.contains("api-version=") {
+        request = request.query(&[("api-version", API_VERSION)]);
+    }
+    let response = recording::send(request.bearer_auth(tokens.token(VAULT_RESOURCE).await?))
+        .await
+        .context("Key Vault request failed")?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .context("Key Vault returned invalid JSON")?;
+    if !status.is_success() {
+        let message = body["error"]["message"].as_str().unwrap_or_default();
+        bail!("Key Vault returned {status}: {message}");
+    }
+    Ok(body)
+}
+
+/// GET every page of a Key Vault listing starting at `url`.
+async fn get_all(tokens: &TokenProvider, url: String) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut next = Some(url);
+    while let Some(url) = next {
+        let mut page = get(tokens, &url).await?;
+        if let Value::Array(values) = page["value"].take() {
+            items.extend(values);
+        }
+        next = page["nextLink"].as_str().map(str::to_string);
+    }
+    Ok(items)
+}
+
+/// A Unix timestamp from the `attributes` of a secret, as RFC 3339.
+fn timestamp(value: &Value) -> Option<String> {
+    chrono::DateTime::from_timestamp(value.as_i64()?, 0).map(|t| t.to_rfc3339())
+}
+
+/// The last segment of a secret `id`: its name for a secret, its version for
+/// a secret version.
+fn last_segment(id: &Value) -> Option<&str> {
+    id.as_str()?.rsplit('/').next()
+}
+
+/// Convert a secret item (`{id, attributes, contentType, tags}`) to its
+/// metadata, keyed by `key` (`name` or `version`).
+fn metadata(item: &Value, key: &str) -> Value {
+    let attributes = &item["attributes"];
+    serde_json::json!({
+        key: last_segment(&item["id"]),
+        "enabled": attributes["enabled"],
+        "content_type": item["contentType"],
+        "tags": item["tags"],
+        "created": timestamp(&attributes["created"]),
+        "updated": timestamp(&attributes["updated"]),
+        "expires": timestamp(&attributes["exp"]),
+        "not_before": timestamp(&attributes["nbf"]),
+        "managed": item["managed"].as_bool().unwrap_or(false),
+    })
+}
+
+/// List the secrets of the vault: names and attributes, never values.
+pub async fn list_secrets(tokens: &TokenProvider, cfg: &KeyVaultConfig) -> Result<Value> {
+    let items = get_all(tokens, format!("{}/secrets", cfg.url))
+        .await
+        .context("Failed to list Key Vault secrets")?;
+    let mut secrets: Vec<Value> = items.iter().map(|item| metadata(item, "name")).collect();
+    secrets.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+    Ok(serde_json::json!({ "vault": cfg.url, "secrets": secrets }))
+}
+
+/// Read the metadata of the secret `name` and of each of its versions,
+/// newest first.
+///
+/// The versions listing never carries values, so the secret itself is only
+/// read — and its value returned — when `include_value` is set, which needs
+/// `KEYVAULT_ALLOW_SECRET_VALUES`.
+pub async fn get_secret_metadata(
+    tokens: &TokenProvider,
+    cfg: &KeyVaultConfig,
+    name: &str,
+    include_value: bool,
+) -> Result<Value> {
+    if include_value {
+        cfg.require_secret_values()?;
+    }
+    let encoded = urlencoding::encode(name);
+    let items = get_all(tokens, format!("{}/secrets/{encoded}/versions", cfg.url))
+        .await
+        .with_context(|| format!("Failed to read versions of secret {name}"))?;
+    let mut versions: Vec<Value> = items.iter().map(|item| metadata(item, "version")).collect();
+    versions.sort_by(|a, b| b["updated"].as_str().cmp(&a["updated"].as_str()));
+
+    let mut result = serde_json::json!({
+        "name": name,
+        "versions": versions,
+        "value": null,
+    });
+    if include_value {
+        let secret = get(tokens, &format!("{}/secrets/{encoded}", cfg.url))
+            .await
+            .with_context(|| format!("Failed to read secret {name}"))?;
+        result["current_version"] = last_segment(&secret["id"]).into();
+        result["value"] = secret["value"].clone();
+    }
+    Ok(result)
+}
+
+/// Check the vault is reachable and the identity may list its secrets.
+pub async fn ping(tokens: &TokenProvider, cfg: &KeyVaultConfig) -> Result<()> {
+    get(tokens, &format!("{}/secrets?maxresults=1", cfg.url))
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_names_secrets_and_converts_timestamps() {
+        let item = serde_json::json!({
+            "id": "https://myvault.vault.azure.net/secrets/sql-password",
+            "attributes": { "enabled": true, "created": 1767225600, "updated": 1767225600 },
+            "contentType": "text/plain",
+        });
+        let secret = metadata(&item, "name");
+        assert_eq!(secret["name"], "sql-password");
+        assert_eq!(secret["enabled"], true);
+        assert_eq!(secret["created"], "2026-01-01T00:00:00+00:00");
+        assert_eq!(secret["expires"], Value::Null);
+        assert!(secret.get("value").is_none());
+    }
+}
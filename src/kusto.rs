@@ -0,0 +1,183 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::aad::TokenProvider;
+use crate::config::KustoConfig;
+use crate::monitor::MAX_ROWS;
+use crate::recording;
+
+/// Database used for commands that do not target one (`.show version`).
+const NO_DATABASE: &str = "NetDefaultDB";
+
+/// Which REST endpoint a request goes to: queries, or control commands
+/// (`.show ...`).
+#[derive(Debug, Clone, Copy)]
+enum Endpoint {
+    Query,
+    Mgmt,
+}
+
+/// POST `csl` to the cluster's v1 REST endpoint with a token for the cluster
+/// and return the parsed response (`{"Tables": [...]}`).
+async fn execute(
+    tokens: &TokenProvider,
+    cfg: &KustoConfig,
+    endpoint: Endpoint,
+    database: &str,
+    csl: &str,
+) -> Result<Value> {
+    let path = match endpoint {
+        Endpoint::Query => "query",
+        Endpoint::Mgmt => "mgmt",
+    };
+    let request = reqwest::Client::new()
+        .post(format!("{}/v1/rest/{path}", cfg.cluster_url))
+        .bearer_auth(tokens.token(&cfg.cluster_url).await?)
+        .header("accept", "application/json")
+        .json(&serde_json::json!({ "db": database, "csl": csl }));
+    let response = recording::send(request)
+        .await
+        .context("Kusto request failed")?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .context("Kusto returned invalid JSON")?;
+    if !status.is_success() {
+        let error = &body["error"];
+        let detail = error["@message"]
+            .as_str()
+            .or(error["message"].as_str())
+            .unwrap_or_default();
+        bail!("Kusto returned {status}: {detail}");
+    }
+    Ok(body)
+}
+
+/// Turn the first table of a v1 response into `{"columns": [{"name",
+/// "type"}], "rows": [{column: value}]}`, keeping at most `max_rows` rows —
+/// the same shape as the Azure Monitor tools return.
+fn tabulate(mut response: Value, max_rows: usize) -> Result<Value> {
+    let Some(table) = response["Tables"].get_mut(0).map(Value::take) else {
+        bail!("The query returned no result table");
+    };
+    let columns: Vec<(String, Value)> = table["Columns"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|c| {
+            let name = c["ColumnName"].as_str().unwrap_or_default().to_string();
+            let kind = c["ColumnType"].as_str().or(c["DataType"].as_str());
+            (name, kind.unwrap_or_default().into())
+        })
+        .collect();
+    let all_rows = table["Rows"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let rows: Vec<Map<String, Value>> = all_rows
+        .iter()
+        .take(max_rows)
+        .map(|row| {
+            columns
+                .iter()
+                .map(|(name, _)| name.clone())
+                .zip(row.as_array().into_iter().flatten().cloned())
+                .collect()
+        })
+        .collect();
+
+    Ok(serde_json::json!({
+        "columns": columns
+            .iter()
+            .map(|(name, kind)| serde_json::json!({ "name": name, "type": kind }))
+            .collect::<Vec<_>>(),
+        "rows": rows,
+        "truncated": all_rows.len() > max_rows,
+    }))
+}
+
+/// List the tables of `database` with their folder and description.
+pub async fn list_tables(
+    tokens: &TokenProvider,
+    cfg: &KustoConfig,
+    database: &str,
+) -> Result<Value> {
+    let response = execute(tokens, cfg, Endpoint::Mgmt, database, ".show tables")
+        .await
+        .with_context(|| format!("Failed to list the tables of Kusto database {database}"))?;
+    let result = tabulate(response, usize::MAX)?;
+    let tables: Vec<Value> = result["rows"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|row| {
+            serde_json::json!({
+                "name": row["TableName"],
+                "folder": row["Folder"].as_str().filter(|f| !f.is_empty()),
+                "description": row["DocString"].as_str().filter(|d| !d.is_empty()),
+            })
+        })
+        .collect();
+    Ok(serde_json::json!({ "database": database, "tables": tables }))
+}
+
+/// Run a KQL query against `database` and return at most `max_rows` rows of
+/// its primary result.
+///
+/// Queries go to the query endpoint, which refuses control commands, so
+/// nothing run here can change the cluster.
+pub async fn query(
+    tokens: &TokenProvider,
+    cfg: &KustoConfig,
+    database: &str,
+    kql: &str,
+    max_rows: usize,
+) -> Result<Value> {
+    if kql.trim_start().starts_with('.') {
+        bail!("Control commands (starting with '.') cannot be run; send a KQL query");
+    }
+    let response = execute(tokens, cfg, Endpoint::Query, database, kql)
+        .await
+        .with_context(|| format!("Kusto query against database {database} failed"))?;
+    tabulate(response, max_rows.clamp(1, MAX_ROWS))
+}
+
+/// Check the cluster is reachable and accepts the credential.
+pub async fn ping(tokens: &TokenProvider, cfg: &KustoConfig) -> Result<()> {
+    execute(tokens, cfg, Endpoint::Mgmt, NO_DATABASE, ".show version")
+        .await
+        .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabulate_reads_v1_tables() {
+        let response = serde_json::json!({
+            "Tables": [{
+                "TableName": "Table_0",
+                "Columns": [
+                    { "ColumnName": "Level", "DataType": "String", "ColumnType": "string" },
+                    { "ColumnName": "Count", "DataType": "Int64", "ColumnType": "long" },
+                ],
+                "Rows": [["Error", 12], ["Warning", 40]],
+            }],
+        });
+        let result = tabulate(response, 1).unwrap();
+        assert_eq!(
+            result["columns"],
+            serde_json::json!([
+                { "name": "Level", "type": "string" },
+                { "name": "Count", "type": "long" },
+            ])
+        );
+        assert_eq!(
+            result["rows"],
+            serde_json::json!([{ "Level": "Error", "Count": 12 }])
+        );
+        assert_eq!(result["truncated"], true);
+    }
+}
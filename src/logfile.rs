@@ -0,0 +1,165 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::config::{LogFileConfig, LogRotation};
+
+/// The period a line written at `time` belongs to; the file is rotated when
+/// it changes.
+fn period(rotation: LogRotation, time: DateTime<Utc>) -> Option<String> {
+    match rotation {
+        LogRotation::Never => None,
+        LogRotation::Hourly => Some(time.format("%Y-%m-%dT%H").to_string()),
+        LogRotation::Daily => Some(time.format("%Y-%m-%d").to_string()),
+    }
+}
+
+struct State {
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+/// A log file rotated when it reaches `LOG_FILE_MAX_BYTES` or a new hour or
+/// day begins.
+///
+/// The current file keeps its configured name; rotated files get the UTC
+/// time of the rotation appended (`server.log.20261016T080000`), and only
+/// the newest `LOG_FILE_MAX_FILES` of them are kept.
+pub struct RollingFile {
+    config: LogFileConfig,
+    state: Mutex<State>,
+}
+
+impl RollingFile {
+    /// Open (or create) the log file.  An existing file last written in an
+    /// earlier period is rotated on the first line.
+    pub fn open(config: LogFileConfig) -> Result<Self> {
+        let path = Path::new(&config.path);
+        let file = open(path).with_context(|| format!("Cannot open LOG_FILE '{}'", config.path))?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().map(DateTime::<Utc>::from);
+        let state = State {
+            size: metadata.len(),
+            period: period(config.rotation, modified.unwrap_or_else(|_| Utc::now())),
+            file,
+        };
+        Ok(Self {
+            config,
+            state: Mutex::new(state),
+        })
+    }
+
+    /// Move the current file aside, start a new one for `period` and remove
+    /// the rotated files beyond `max_files`.
+    fn rotate(&self, state: &mut State, period: Option<String>) -> io::Result<()> {
+        let path = Path::new(&self.config.path);
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S").to_string();
+        let mut rotated = suffixed(path, &stamp);
+        let mut n = 1;
+        while rotated.exists() {
+            rotated = suffixed(path, &format!("{stamp}-{n}"));
+            n += 1;
+        }
+        fs::rename(path, &rotated)?;
+        state.file = open(path)?;
+        state.size = 0;
+        state.period = period;
+
+        let mut old = self.rotated_files()?;
+        old.sort();
+        let excess = old.len().saturating_sub(self.config.max_files);
+        for file in &old[..excess] {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    /// The rotated files next to the log file.
+    fn rotated_files(&self) -> io::Result<Vec<PathBuf>> {
+        let path = Path::new(&self.config.path);
+        let prefix = format!(
+            "{}.",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+            .map(|entry| entry.path())
+            .collect())
+    }
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// `path` with `.{suffix}` appended to its file name.
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{suffix}"));
+    PathBuf::from(name)
+}
+
+impl Write for &RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let period = period(self.config.rotation, Utc::now());
+        let full = self
+            .config
+            .max_bytes
+            .is_some_and(|max| state.size > 0 && state.size + buf.len() as u64 > max);
+        if full || period != state.period {
+            self.rotate(&mut state, period)?;
+        }
+        state.file.write_all(buf)?;
+        state.size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.state.lock().unwrap().file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_files_are_rotated_and_old_ones_removed() {
+        let dir = std::env::temp_dir().join(format!("logs-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let path = dir.join("server.log");
+        let log = RollingFile::open(LogFileConfig {
+            path: path.to_string_lossy().into_owned(),
+            rotation: LogRotation::Never,
+            max_bytes: Some(10),
+            max_files: 2,
+        })
+        .unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            (&log).write_all(line.as_bytes()).unwrap();
+        }
+
+        let current = fs::read_to_string(&path).unwrap();
+        let mut rotated: Vec<String> = log
+            .rotated_files()
+            .unwrap()
+            .iter()
+            .map(|p| fs::read_to_string(p).unwrap())
+            .collect();
+        rotated.sort();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(current, "fourth\n");
+        assert_eq!(rotated, ["second\n", "third\n"]);
+    }
+}
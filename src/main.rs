@@ -1,28 +1,173 @@
+mod aad;
+mod adls;
+mod annotations;
+mod appconfig;
+mod audit;
+mod backoff;
+mod blob;
+mod budget;
+mod cassandra;
+mod catalog;
+mod charts;
+mod check;
+mod cli;
+mod concurrency;
 mod config;
+mod confirm;
+mod correlation;
 mod cosmos;
+mod cosmos_rest;
+mod cost;
+mod emulator;
+mod eventgrid;
+mod eventhubs;
+mod export;
+mod files;
+mod formats;
+mod generate;
+mod gremlin;
+mod health;
+mod keyvault;
+mod kusto;
+mod logfile;
+mod mock;
+mod monitor;
 mod mssql;
+mod openai;
+mod output;
+mod pagination;
+mod prompts;
+mod readonly;
+mod recording;
+mod redact;
+mod resources;
+mod results;
+mod retry;
+mod roles;
 mod server;
+mod servicebus;
+mod session_context;
+mod slowlog;
+mod stats;
+mod status;
+mod storage;
+mod subscriptions;
+mod tables;
+mod transit;
 
+use std::sync::Arc;
+
+use anyhow::Context;
+use config::LogFormat;
+use logfile::RollingFile;
+use redact::Redacting;
 use rmcp::transport::stdio;
 use rmcp::ServiceExt;
 use server::AzureMcpServer;
+use tracing::Subscriber;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// A layer writing log lines in `format` to `writer`.
+fn fmt_layer<S, W>(format: LogFormat, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_ansi(ansi)
+        .with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+    }
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Write structured logs to stderr so stdout stays clean for MCP JSON-RPC.
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_env("RUST_LOG")
-                .add_directive("azure_mcp_server=info".parse().unwrap()),
-        )
-        .with_writer(std::io::stderr)
+    // Write structured logs to stderr so stdout stays clean for MCP JSON-RPC,
+    // and to LOG_FILE when set, with secrets masked.
+    redact::register_environment();
+    let filter = tracing_subscriber::EnvFilter::from_env("RUST_LOG")
+        .add_directive("azure_mcp_server=info".parse().unwrap());
+    let format = LogFormat::from_env()?;
+    let file = match config::LogFileConfig::from_env()? {
+        Some(cfg) => Some(Arc::new(RollingFile::open(cfg)?)),
+        None => None,
+    };
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(format, Redacting(std::io::stderr), true))
+        .with(file.map(|file| fmt_layer(format, Redacting(file), false)))
         .init();
 
+    // `--emulator[=sql,cosmos,azurite]`: point the backends not configured
+    // otherwise at the local emulators of docker-compose.yml.  Combines with
+    // the commands below.
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(i) = args
+        .iter()
+        .position(|arg| arg == "--emulator" || arg.starts_with("--emulator="))
+    {
+        let arg = args.remove(i);
+        let names = arg.strip_prefix("--emulator=").unwrap_or_default();
+        emulator::apply(&emulator::parse(names)?);
+    }
+
+    // `--check`: probe every configured backend, print the report on stdout
+    // and exit non-zero unless all of them answered.
+    if args.first().map(String::as_str) == Some("--check") {
+        let report = check::run().await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        std::process::exit(if report["ok"] == true { 0 } else { 1 });
+    }
+
+    // `--verify-audit [path]`: check the hash chain of the audit log (by
+    // default AUDIT_LOG_FILE), print the report on stdout and exit non-zero
+    // unless it is intact.
+    if args.first().map(String::as_str) == Some("--verify-audit") {
+        let path = args
+            .get(1)
+            .cloned()
+            .or_else(|| std::env::var("AUDIT_LOG_FILE").ok())
+            .filter(|path| !path.trim().is_empty())
+            .context("Pass the audit log path or set AUDIT_LOG_FILE")?;
+        let key = std::env::var("AUDIT_CHAIN_KEY")
+            .ok()
+            .filter(|key| !key.trim().is_empty());
+        let report = audit::verify(&path, key.as_deref());
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        std::process::exit(if report["ok"] == true { 0 } else { 1 });
+    }
+
+    // `list-tools [<tool>...] [--json]`: print the registered tools with
+    // their input and output schemas, without any backend configured.
+    if args.first().map(String::as_str) == Some("list-tools") {
+        cli::list_tools(AzureMcpServer::registered_tools(), &args[1..])?;
+        return Ok(());
+    }
+
     tracing::info!("Starting azure-mcp-server v{}", env!("CARGO_PKG_VERSION"));
 
     let config = config::Config::from_env()?;
+    transit::warn_insecure(&config);
+    recording::install(config.recording.as_ref())?;
     let server = AzureMcpServer::new(config);
 
+    // `call <tool> --json '<arguments>'`: run one tool through an
+    // in-process MCP session, print its result and exit.
+    if args.first().map(String::as_str) == Some("call") {
+        std::process::exit(cli::call(server, &args[1..]).await?);
+    }
+
     let transport = stdio();
 
     tracing::info!("MCP server listening on stdio");
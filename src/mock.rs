@@ -0,0 +1,73 @@
+use rmcp::model::{CallToolResult, Content};
+use serde_json::Value;
+
+use crate::config::MockConfig;
+
+/// The result of a call of `tool` in mock mode, read from
+/// `<MOCK_FIXTURES_DIR>/<tool>.json`.
+///
+/// A fixture holding a `content` array is a whole `CallToolResult`, e.g.
+/// `{"content": [{"type": "text", "text": "Login failed"}], "isError":
+/// true}` to mock a failure; any other JSON is the tool's structured
+/// output, and should match its output schema.  A missing or invalid
+/// fixture makes an error result saying so.  Arguments are not looked at:
+/// every call of a tool returns the same fixture.
+pub async fn call(config: &MockConfig, tool: &str) -> CallToolResult {
+    let path = config.fixtures.join(format!("{tool}.json"));
+    let fixture = match tokio::fs::read(&path).await {
+        Ok(bytes) => serde_json::from_slice::<Value>(&bytes)
+            .map_err(|e| format!("The fixture {} is not JSON: {e}", path.display())),
+        Err(e) => Err(format!(
+            "MOCK_MODE is on and there is no fixture for {tool}: create {} ({e})",
+            path.display()
+        )),
+    };
+    match fixture {
+        Ok(fixture) if fixture.get("content").is_some_and(Value::is_array) => {
+            serde_json::from_value(fixture).unwrap_or_else(|e| {
+                CallToolResult::error(vec![Content::text(format!(
+                    "The fixture {} is not a tool result: {e}",
+                    path.display()
+                ))])
+            })
+        }
+        Ok(fixture) => CallToolResult::structured(fixture),
+        Err(message) => CallToolResult::error(vec![Content::text(message)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_MOCK_FIXTURES_DIR;
+    use crate::output::{DatabaseList, ItemsPage, RowsPage, TableList};
+
+    #[tokio::test]
+    async fn fixtures_become_tool_results() {
+        let config = MockConfig {
+            fixtures: std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join(DEFAULT_MOCK_FIXTURES_DIR),
+        };
+        let structured = |result: CallToolResult| {
+            assert_ne!(result.is_error, Some(true), "{result:?}");
+            result.structured_content.unwrap()
+        };
+
+        // The shipped fixtures match the tools' output types.
+        let tables = structured(call(&config, "mssql_list_tables").await);
+        serde_json::from_value::<TableList>(tables).unwrap();
+        let rows = structured(call(&config, "mssql_execute_query").await);
+        serde_json::from_value::<RowsPage>(rows).unwrap();
+        let databases = structured(call(&config, "cosmos_list_databases").await);
+        serde_json::from_value::<DatabaseList>(databases).unwrap();
+        let items = structured(call(&config, "cosmos_query_items").await);
+        serde_json::from_value::<ItemsPage>(items).unwrap();
+
+        let failed = call(&config, "blob_write").await;
+        assert_eq!(failed.is_error, Some(true));
+        let missing = call(&config, "no_such_tool").await;
+        assert_eq!(missing.is_error, Some(true));
+        let message = &missing.content[0].as_text().unwrap().text;
+        assert!(message.contains("no_such_tool.json"), "{message}");
+    }
+}
@@ -0,0 +1,179 @@
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::aad::TokenProvider;
+use crate::recording;
+
+/// Azure AD resource of the Log Analytics query API.
+pub const LOG_ANALYTICS_RESOURCE: &str = "https://api.loganalytics.io";
+
+/// Azure AD resource of the Application Insights query API.
+pub const APP_INSIGHTS_RESOURCE: &str = "https://api.applicationinsights.io";
+
+/// Rows returned when the caller gives no `max_rows`.
+pub const DEFAULT_MAX_ROWS: usize = 500;
+
+/// Upper bound on `max_rows`.
+pub const MAX_ROWS: usize = 10_000;
+
+/// Turn the primary table of a Log Analytics / Application Insights query
+/// response into `{"columns": [{"name", "type"}], "rows": [{column: value}]}`,
+/// keeping at most `max_rows` rows.
+///
+/// Sets `truncated` when rows were dropped and `partial_error` when the
+/// service returned partial results with an error.
+pub fn tabulate(mut response: Value, max_rows: usize) -> Result<Value> {
+    let Some(table) = response["tables"].get_mut(0).map(Value::take) else {
+        bail!("The query returned no result table");
+    };
+    let columns: Vec<String> = table["columns"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|c| c["name"].as_str().unwrap_or_default().to_string())
+        .collect();
+    let all_rows = table["rows"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let rows: Vec<Map<String, Value>> = all_rows
+        .iter()
+        .take(max_rows)
+        .map(|row| {
+            columns
+                .iter()
+                .cloned()
+                .zip(row.as_array().into_iter().flatten().cloned())
+                .collect()
+        })
+        .collect();
+
+    let mut result = serde_json::json!({
+        "columns": table["columns"],
+        "rows": rows,
+        "truncated": all_rows.len() > max_rows,
+    });
+    if let Some(message) = response["error"]["message"].as_str() {
+        result["partial_error"] = message.into();
+    }
+    Ok(result)
+}
+
+/// POST a query to `url` with a token for `resource` and tabulate the
+/// primary result (see [`tabulate`]).
+pub async fn query(
+    tokens: &TokenProvider,
+    resource: &str,
+    url: &str,
+    kql: &str,
+    timespan: Option<&str>,
+    max_rows: usize,
+) -> Result<Value> {
+    let mut body = serde_json::json!({ "query": kql });
+    if let Some(timespan) = timespan {
+        body["timespan"] = timespan.into();
+    }
+    let request = reqwest::Client::new()
+        .post(url)
+        .bearer_auth(tokens.token(resource).await?)
+        .json(&body);
+    let response = recording::send(request)
+        .await
+        .context("Query request failed")?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .context("Query API returned invalid JSON")?;
+    if !status.is_success() {
+        let error = &body["error"];
+        let detail = error["innererror"]["message"]
+            .as_str()
+            .or(error["message"].as_str())
+            .unwrap_or_default();
+        bail!("Query failed with {status}: {detail}");
+    }
+    tabulate(body, max_rows.clamp(1, MAX_ROWS))
+}
+
+/// Run a KQL query against the Log Analytics workspace `workspace_id` over
+/// `timespan` (an ISO 8601 duration such as `PT1H` or an interval
+/// `start/end`; without it the query's own filters apply).
+pub async fn loganalytics_query(
+    tokens: &TokenProvider,
+    workspace_id: &str,
+    kql: &str,
+    timespan: Option<&str>,
+    max_rows: usize,
+) -> Result<Value> {
+    query(
+        tokens,
+        LOG_ANALYTICS_RESOURCE,
+        &format!(
+            "{LOG_ANALYTICS_RESOURCE}/v1/workspaces/{}/query",
+            urlencoding::encode(workspace_id)
+        ),
+        kql,
+        timespan,
+        max_rows,
+    )
+    .await
+    .with_context(|| format!("Log Analytics query against workspace {workspace_id} failed"))
+}
+
+/// Run a KQL query against the Application Insights resource `app_id` (its
+/// Application ID, from the resource's API Access blade), over the
+/// `requests`, `traces`, `exceptions`, `dependencies`, ... tables.
+pub async fn appinsights_query(
+    tokens: &TokenProvider,
+    app_id: &str,
+    kql: &str,
+    timespan: Option<&str>,
+    max_rows: usize,
+) -> Result<Value> {
+    query(
+        tokens,
+        APP_INSIGHTS_RESOURCE,
+        &format!(
+            "{APP_INSIGHTS_RESOURCE}/v1/apps/{}/query",
+            urlencoding::encode(app_id)
+        ),
+        kql,
+        timespan,
+        max_rows,
+    )
+    .await
+    .with_context(|| format!("Application Insights query against app {app_id} failed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tabulate_zips_columns_and_caps_rows() {
+        let response = serde_json::json!({
+            "tables": [{
+                "name": "PrimaryResult",
+                "columns": [
+                    { "name": "TimeGenerated", "type": "datetime" },
+                    { "name": "Count", "type": "long" },
+                ],
+                "rows": [
+                    ["2026-01-01T00:00:00Z", 3],
+                    ["2026-01-01T01:00:00Z", 5],
+                ],
+            }],
+            "error": { "message": "Partial query failure" },
+        });
+        let result = tabulate(response, 1).unwrap();
+        assert_eq!(
+            result["rows"],
+            serde_json::json!([{ "TimeGenerated": "2026-01-01T00:00:00Z", "Count": 3 }])
+        );
+        assert_eq!(result["columns"][1]["type"], "long");
+        assert_eq!(result["truncated"], true);
+        assert_eq!(result["partial_error"], "Partial query failure");
+        assert!(tabulate(serde_json::json!({ "tables": [] }), 10).is_err());
+    }
+}
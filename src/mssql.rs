@@ -1,20 +1,67 @@
+use std::collections::HashSet;
+
 use anyhow::{Context, Result};
+use futures::TryStreamExt;
 use serde_json::{Map, Value};
-use tiberius::{Client, ColumnData, Config, Query};
+use sqlparser::ast::Statement;
+use sqlparser::dialect::MsSqlDialect;
+use sqlparser::parser::Parser;
+use tiberius::{
+    AuthMethod, Client, ColumnData, ColumnType, Config, Query, QueryItem, QueryStream, Row,
+};
 use tokio::net::TcpStream;
 use tokio_util::compat::TokioAsyncWriteCompatExt;
 
-use crate::config::MssqlConfig;
+use crate::budget::json_size;
+use crate::config::{MssqlConfig, PlanLimits};
+use crate::correlation;
+use crate::recording;
+use crate::retry;
+use crate::session_context;
 
 /// Default number of rows returned when the caller does not specify `max_rows`.
 pub const DEFAULT_MAX_ROWS: u64 = 500;
 /// Hard upper limit on rows to prevent runaway reads.
 pub const HARD_MAX_ROWS: u64 = 10_000;
 
-/// Open a new tiberius client from an ADO.NET connection string.
-async fn connect(cfg: &MssqlConfig) -> Result<Client<tokio_util::compat::Compat<TcpStream>>> {
-    let config = Config::from_ado_string(&cfg.connection_string)
+/// Azure AD resource of Azure SQL, Synapse and Fabric SQL endpoints.
+pub const SQL_RESOURCE: &str = "https://database.windows.net";
+
+type TdsClient = Client<tokio_util::compat::Compat<TcpStream>>;
+
+/// One page of a query's result.
+pub struct QueryPage {
+    /// The result columns in order, as `{name, type, nullable}`.
+    pub columns: Vec<Value>,
+    /// The rows, keyed by column name.
+    pub rows: Vec<Value>,
+    /// Whether more rows follow.
+    pub more: bool,
+    /// Whether the page was cut short at `MAX_RESULT_BYTES` rather than
+    /// `max_rows`.
+    pub memory_budget_exhausted: bool,
+}
+
+/// Open a new tiberius client from an ADO.NET connection string, logging in
+/// with the access token instead when one is set.
+///
+/// Every call opens its own connection; there is no pool.  Statements are
+/// therefore not prepared (`sp_prepare` handles only live as long as the
+/// connection), and repeated queries rely on SQL Server's plan cache
+/// instead, which already reuses the plans of identical batches and of
+/// `sp_executesql` calls.  Caching prepared handles needs a pool first.
+///
+/// Because there is no pool there are no idle connections to go stale, but a
+/// failover or a database resuming from auto-pause still refuses new
+/// connections for a while: the public functions below therefore retry
+/// connecting and querying together per `cfg.retry` (see [`retry::run`]).
+async fn connect(cfg: &MssqlConfig) -> Result<TdsClient> {
+    recording::live("MSSQL (TDS)")?;
+    let mut config = Config::from_ado_string(&cfg.connection_string)
         .context("Failed to parse MSSQL connection string")?;
+    if let Some(token) = &cfg.access_token {
+        config.authentication(AuthMethod::aad_token(token));
+    }
 
     let tcp = TcpStream::connect(config.get_addr())
         .await
@@ -23,10 +70,38 @@ async fn connect(cfg: &MssqlConfig) -> Result<Client<tokio_util::compat::Compat<
     tcp.set_nodelay(true)
         .context("Failed to set TCP_NODELAY on MSSQL connection")?;
 
-    let client = Client::connect(config, tcp.compat_write())
+    let mut client = Client::connect(config, tcp.compat_write())
         .await
         .context("MSSQL handshake/login failed")?;
 
+    // Tag the session with the tool call's correlation id, visible as
+    // `context_info` in sys.dm_exec_sessions and sys.dm_exec_requests.
+    if let Some(id) = correlation::current().and_then(|id| uuid::Uuid::parse_str(&id).ok()) {
+        let tagged = async {
+            client
+                .simple_query(format!("SET CONTEXT_INFO 0x{}", id.simple()))
+                .await?
+                .into_results()
+                .await
+        };
+        if let Err(e) = tagged.await {
+            tracing::debug!("Failed to set CONTEXT_INFO: {e}");
+        }
+    }
+
+    // Set the values row-level security predicates read with
+    // SESSION_CONTEXT(N'key').  Unlike CONTEXT_INFO this must succeed: a
+    // query running without them could see rows it should not.
+    for (key, value) in session_context::pairs(&cfg.session_context) {
+        client
+            .execute(
+                "EXEC sp_set_session_context @key = @P1, @value = @P2, @read_only = 1",
+                &[&key.as_str(), &value.as_str()],
+            )
+            .await
+            .with_context(|| format!("Failed to set SESSION_CONTEXT key '{key}'"))?;
+    }
+
     Ok(client)
 }
 
@@ -101,18 +176,661 @@ fn column_data_to_json(data: &ColumnData<'static>) -> Value {
     }
 }
 
+/// The SQL type name of a TDS column type, without length or precision.
+fn column_type_name(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Null => "null",
+        ColumnType::Bit | ColumnType::Bitn => "bit",
+        ColumnType::Int1 => "tinyint",
+        ColumnType::Int2 => "smallint",
+        ColumnType::Int4 | ColumnType::Intn => "int",
+        ColumnType::Int8 => "bigint",
+        ColumnType::Datetime4 => "smalldatetime",
+        ColumnType::Float4 => "real",
+        ColumnType::Float8 | ColumnType::Floatn => "float",
+        ColumnType::Money => "money",
+        ColumnType::Money4 => "smallmoney",
+        ColumnType::Datetime | ColumnType::Datetimen => "datetime",
+        ColumnType::Guid => "uniqueidentifier",
+        ColumnType::Decimaln => "decimal",
+        ColumnType::Numericn => "numeric",
+        ColumnType::Daten => "date",
+        ColumnType::Timen => "time",
+        ColumnType::Datetime2 => "datetime2",
+        ColumnType::DatetimeOffsetn => "datetimeoffset",
+        ColumnType::BigVarBin => "varbinary",
+        ColumnType::BigVarChar => "varchar",
+        ColumnType::BigBinary => "binary",
+        ColumnType::BigChar => "char",
+        ColumnType::NVarchar => "nvarchar",
+        ColumnType::NChar => "nchar",
+        ColumnType::Xml => "xml",
+        ColumnType::Udt => "udt",
+        ColumnType::Text => "text",
+        ColumnType::Image => "image",
+        ColumnType::NText => "ntext",
+        ColumnType::SSVariant => "sql_variant",
+    }
+}
+
+/// Describe the first result set of `sql` with
+/// `sys.dm_exec_describe_first_result_set`: the full type name (e.g.
+/// `nvarchar(50)`) and nullability of each visible column, in order.
+///
+/// With `labels`, columns read straight from a table column that has a
+/// sensitivity classification also carry its `sensitivity_label`, and
+/// computed columns (which the server does not trace to a source column)
+/// carry `derived_label` when it is given.
+async fn describe_result(
+    client: &mut TdsClient,
+    sql: &str,
+    labels: bool,
+    derived_label: Option<&str>,
+) -> Result<Vec<Value>> {
+    let mut query = Query::new(if labels {
+        "SELECT r.name, r.system_type_name, r.is_nullable, c.label, r.source_column \
+         FROM sys.dm_exec_describe_first_result_set(@P1, NULL, 1) AS r \
+         OUTER APPLY (SELECT TOP (1) sc.label FROM sys.sensitivity_classifications AS sc \
+             WHERE sc.major_id = OBJECT_ID(QUOTENAME(r.source_database) + N'.' \
+                 + QUOTENAME(r.source_schema) + N'.' + QUOTENAME(r.source_table)) \
+             AND sc.minor_id = COLUMNPROPERTY(sc.major_id, r.source_column, 'ColumnId')) AS c \
+         WHERE r.is_hidden = 0 ORDER BY r.column_ordinal"
+    } else {
+        "SELECT name, system_type_name, is_nullable \
+         FROM sys.dm_exec_describe_first_result_set(@P1, NULL, 0) \
+         WHERE is_hidden = 0 ORDER BY column_ordinal"
+    });
+    query.bind(sql);
+    let rows = query.query(client).await?.into_first_result().await?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let mut column = serde_json::json!({
+                "name": row.get::<&str, _>("name").unwrap_or_default(),
+                "type": row.get::<&str, _>("system_type_name").unwrap_or_default(),
+                "nullable": row.get::<bool, _>("is_nullable"),
+            });
+            let label = labels
+                .then(|| match row.get::<&str, _>("source_column") {
+                    Some(_) => row.get::<&str, _>("label"),
+                    None => derived_label,
+                })
+                .flatten();
+            if let Some(label) = label {
+                column["sensitivity_label"] = label.into();
+            }
+            column
+        })
+        .collect())
+}
+
+/// A table column classified with a label [`is_masked_label`] masks.
+#[derive(Debug, Clone, PartialEq)]
+struct MaskedColumn {
+    schema: String,
+    table: String,
+    column: String,
+    label: String,
+}
+
+/// The columns the database classifies with a label [`is_masked_label`]
+/// masks.  Empty when it has no classifications.
+async fn masked_columns(client: &mut TdsClient) -> Vec<MaskedColumn> {
+    let classified = async {
+        client
+            .simple_query(
+                "SELECT OBJECT_SCHEMA_NAME(major_id), OBJECT_NAME(major_id), \
+                 COL_NAME(major_id, minor_id), label \
+                 FROM sys.sensitivity_classifications WHERE class = 1",
+            )
+            .await?
+            .into_first_result()
+            .await
+    };
+    match classified.await {
+        Ok(rows) => rows
+            .iter()
+            .filter_map(|row| {
+                let label = row
+                    .get::<&str, _>(3)
+                    .filter(|label| is_masked_label(label))?;
+                Some(MaskedColumn {
+                    schema: row.get::<&str, _>(0)?.to_lowercase(),
+                    table: row.get::<&str, _>(1)?.to_lowercase(),
+                    column: row.get::<&str, _>(2)?.to_lowercase(),
+                    label: label.to_string(),
+                })
+            })
+            .collect(),
+        Err(e) => {
+            tracing::debug!("Could not read the sensitivity classifications: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// The label to mask the computed columns of `sql` with: that of a
+/// `masked` column its estimated plan reads, or of any of them when the
+/// plan cannot be read.  `None` when it reads none of them.
+async fn derived_label(
+    client: &mut TdsClient,
+    sql: &str,
+    masked: &[MaskedColumn],
+) -> Option<String> {
+    let read = match showplan(client, sql).await {
+        Ok(Some(plan)) => plan_columns(&plan),
+        Ok(None) => return masked.first().map(|c| c.label.clone()),
+        Err(e) => {
+            tracing::debug!("Could not read the plan to trace computed columns: {e:#}");
+            return masked.first().map(|c| c.label.clone());
+        }
+    };
+    masked
+        .iter()
+        .find(|c| read.contains(&(c.schema.clone(), c.table.clone(), c.column.clone())))
+        .map(|c| c.label.clone())
+}
+
+/// The `(schema, table, column)` of each table column a showplan XML
+/// document reads, lowercased and unquoted.  Views are expanded in plans,
+/// so these are the base table columns.
+fn plan_columns(xml: &str) -> HashSet<(String, String, String)> {
+    let attribute = |tag: &str, name: &str| {
+        let pattern = format!(" {name}=\"");
+        let start = tag.find(&pattern)? + pattern.len();
+        let value = &tag[start..start + tag[start..].find('"')?];
+        Some(
+            value
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_lowercase(),
+        )
+    };
+    xml.split("<ColumnReference ")
+        .skip(1)
+        .filter_map(|rest| {
+            let tag = &rest[..rest.find('>')?];
+            Some((
+                attribute(tag, "Schema")?,
+                attribute(tag, "Table")?,
+                attribute(tag, "Column")?,
+            ))
+        })
+        .collect()
+}
+
+/// Describe the result of `sql` (see [`describe_result`]), with sensitivity
+/// labels when `cfg.mask_classified` is set.  `None` when the server cannot
+/// describe it.
+///
+/// Computed columns are labelled like a masked column the query's plan
+/// reads, so that expressions over them are masked too.
+/// When the labels cannot be read the result is unchecked, so a database
+/// that classifies columns as confidential refuses the query instead.
+async fn describe_result_for(
+    client: &mut TdsClient,
+    cfg: &MssqlConfig,
+    sql: &str,
+) -> Result<Option<Vec<Value>>> {
+    if cfg.mask_classified {
+        let masked = masked_columns(client).await;
+        let derived = if masked.is_empty() {
+            None
+        } else {
+            derived_label(client, sql, &masked).await
+        };
+        match describe_result(client, sql, true, derived.as_deref()).await {
+            Ok(columns) => return Ok(Some(columns)),
+            Err(e) => {
+                tracing::debug!("Could not read the sensitivity labels of the result: {e}");
+                anyhow::ensure!(
+                    masked.is_empty(),
+                    "The query was not run: the database classifies columns as confidential \
+                     and the server cannot tell which columns of this query's result come \
+                     from them.  Select table columns directly rather than through \
+                     temporary tables or dynamic SQL."
+                );
+            }
+        }
+    }
+    Ok(describe_result(client, sql, false, None)
+        .await
+        .inspect_err(|e| tracing::debug!("Could not describe the result set: {e:#}"))
+        .ok())
+}
+
+/// Whether values of columns classified with `label` are masked:
+/// `Confidential` and `Highly Confidential`, including variants such as
+/// `Confidential - GDPR`.
+fn is_masked_label(label: &str) -> bool {
+    let label = label.trim().to_ascii_lowercase();
+    label.starts_with("confidential") || label.starts_with("highly confidential")
+}
+
+/// Replace the non-null values of `columns` whose `sensitivity_label` is
+/// masked with `***`.
+fn mask_classified(columns: &[Value], rows: &mut [Value]) {
+    for column in columns {
+        if !column["sensitivity_label"]
+            .as_str()
+            .is_some_and(is_masked_label)
+        {
+            continue;
+        }
+        let Some(name) = column["name"].as_str() else {
+            continue;
+        };
+        for value in rows.iter_mut().filter_map(|row| row.get_mut(name)) {
+            if !value.is_null() {
+                *value = "***".into();
+            }
+        }
+    }
+}
+
+/// What the optimizer expects of a statement, read from its estimated
+/// (SHOWPLAN_XML) plan.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PlanEstimate {
+    /// The most rows any operator of the plan is estimated to produce.
+    rows: f64,
+    /// The estimated subtree cost of the statement.
+    cost: f64,
+}
+
+impl PlanEstimate {
+    /// Read the largest `EstimateRows` and `StatementSubTreeCost` of a
+    /// showplan XML document.
+    fn from_xml(xml: &str) -> Self {
+        let max = |name: &str| {
+            let pattern = format!(" {name}=\"");
+            xml.match_indices(&pattern)
+                .filter_map(|(i, _)| {
+                    let value = &xml[i + pattern.len()..];
+                    value[..value.find('"')?].parse::<f64>().ok()
+                })
+                .fold(0.0, f64::max)
+        };
+        PlanEstimate {
+            rows: max("EstimateRows"),
+            cost: max("StatementSubTreeCost"),
+        }
+    }
+
+    /// Why the plan is over `limits`, phrased so that the model can narrow
+    /// the query; `None` when it is within them.
+    fn refusal(&self, limits: &PlanLimits) -> Option<String> {
+        let mut over = Vec::new();
+        if let Some(max) = limits.max_rows.filter(|max| self.rows > *max) {
+            over.push(format!(
+                "a step of the plan processes about {:.0} rows (MSSQL_MAX_ESTIMATED_ROWS is {max:.0})",
+                self.rows
+            ));
+        }
+        if let Some(max) = limits.max_cost.filter(|max| self.cost > *max) {
+            over.push(format!(
+                "its estimated cost is {:.1} (MSSQL_MAX_ESTIMATED_COST is {max})",
+                self.cost
+            ));
+        }
+        (!over.is_empty()).then(|| {
+            format!(
+                "The query was not run: {}.  Narrow it first: filter on indexed columns \
+                 in WHERE, aggregate with GROUP BY instead of returning detail rows, join \
+                 fewer tables, or add TOP (n) with an ORDER BY an index supports.",
+                over.join(" and ")
+            )
+        })
+    }
+}
+
+/// Fetch the showplan XML document of `sql` without running it.  Fails when
+/// the server cannot compile `sql`; `None` when it returns no plan.
+async fn showplan(client: &mut TdsClient, sql: &str) -> Result<Option<String>> {
+    client
+        .simple_query("SET SHOWPLAN_XML ON")
+        .await?
+        .into_results()
+        .await
+        .context("Failed to request the estimated plan (the login needs SHOWPLAN permission)")?;
+    let plan = async { client.simple_query(sql).await?.into_first_result().await }.await;
+    // Until SHOWPLAN_XML is off again every statement returns its plan
+    // instead of running, so the connection is unusable if this fails.
+    client
+        .simple_query("SET SHOWPLAN_XML OFF")
+        .await?
+        .into_results()
+        .await
+        .context("Failed to turn SHOWPLAN_XML off")?;
+    let rows = plan.context("The server could not compile the query")?;
+    Ok(rows
+        .first()
+        .and_then(|row| row.get::<&str, _>(0))
+        .map(str::to_string))
+}
+
+/// Estimate the plan of `sql` without running it.  `None` when the server
+/// returns no plan.
+async fn estimate_plan(client: &mut TdsClient, sql: &str) -> Result<Option<PlanEstimate>> {
+    Ok(showplan(client, sql)
+        .await?
+        .as_deref()
+        .map(PlanEstimate::from_xml))
+}
+
+/// Refuse `sql` when `cfg.plan_limits` is set and its estimated plan is over
+/// them, or cannot be estimated, before running it.
+async fn check_plan(client: &mut TdsClient, cfg: &MssqlConfig, sql: &str) -> Result<()> {
+    let Some(limits) = &cfg.plan_limits else {
+        return Ok(());
+    };
+    let estimate = estimate_plan(client, sql)
+        .await
+        .and_then(|estimate| estimate.context("the server returned no plan"))
+        .map_err(|e| {
+            let cause = format!("{e:#}");
+            e.context(format!(
+                "The query was not run: its estimated plan, which MSSQL_MAX_ESTIMATED_ROWS \
+                 and MSSQL_MAX_ESTIMATED_COST are checked against, could not be read: {cause}"
+            ))
+        })?;
+    match estimate.refusal(limits) {
+        Some(refusal) => {
+            tracing::info!(
+                rows = estimate.rows,
+                cost = estimate.cost,
+                "Refused a query over the plan limits"
+            );
+            Err(anyhow::anyhow!(refusal))
+        }
+        None => Ok(()),
+    }
+}
+
+/// The columns of a result: as `described` by the server when it could
+/// describe the query (and agrees on the column count), else the TDS column
+/// types with unknown nullability.
+fn result_columns(described: Option<Vec<Value>>, received: &[(String, ColumnType)]) -> Vec<Value> {
+    match described {
+        Some(described) if described.len() == received.len() => described,
+        _ => received_columns(received),
+    }
+}
+
+/// Quote `name` as a T-SQL identifier.
+fn quote_identifier(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Base types (as named by `system_type_name`, without length) the TDS
+/// client decodes itself.
+const DECODED_TYPES: &[&str] = &[
+    "bit",
+    "tinyint",
+    "smallint",
+    "int",
+    "bigint",
+    "real",
+    "float",
+    "money",
+    "smallmoney",
+    "decimal",
+    "numeric",
+    "datetime",
+    "smalldatetime",
+    "datetime2",
+    "date",
+    "time",
+    "datetimeoffset",
+    "uniqueidentifier",
+    "char",
+    "varchar",
+    "nchar",
+    "nvarchar",
+    "text",
+    "ntext",
+    "binary",
+    "varbinary",
+    "image",
+    "timestamp",
+    "xml",
+];
+
+/// How a column of a type the TDS client cannot decode is selected instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Conversion {
+    /// Spatial types, as WKT text.
+    Wkt,
+    /// `sql_variant`, as its base type and value as text.
+    Variant,
+    /// `hierarchyid` and CLR user-defined types, as their binary form.
+    Binary,
+}
+
+/// The conversion a column of SQL type `type_name` needs, if any.
+fn conversion(type_name: &str) -> Option<Conversion> {
+    let base = type_name.split('(').next().unwrap_or_default().trim();
+    match base {
+        "geography" | "geometry" => Some(Conversion::Wkt),
+        "sql_variant" => Some(Conversion::Variant),
+        _ if DECODED_TYPES.contains(&base) => None,
+        _ => Some(Conversion::Binary),
+    }
+}
+
+/// The select list of the paging query over `columns`: `*`, unless a column
+/// has a type the TDS client cannot decode.  Such columns are then selected
+/// as text or binary (see [`Conversion`]) and tagged by [`tag_values`].
+fn select_list(columns: &[Value]) -> String {
+    let convert = |column: &Value| conversion(column["type"].as_str().unwrap_or_default());
+    if !columns.iter().any(|c| convert(c).is_some()) {
+        return "*".into();
+    }
+    columns
+        .iter()
+        .map(|column| {
+            let name = quote_identifier(column["name"].as_str().unwrap_or_default());
+            match convert(column) {
+                Some(Conversion::Wkt) => format!("{name}.STAsText() AS {name}"),
+                Some(Conversion::Variant) => format!(
+                    "CASE WHEN {name} IS NULL THEN NULL ELSE CONCAT(\
+                     CAST(SQL_VARIANT_PROPERTY({name}, 'BaseType') AS nvarchar(128)), \
+                     N':', CAST({name} AS nvarchar(4000))) END AS {name}"
+                ),
+                Some(Conversion::Binary) => format!("CAST({name} AS varbinary(max)) AS {name}"),
+                None => name,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Replace the converted values of `columns` in `rows` with tagged values:
+/// `{"type": "sql_variant", "base_type": ..., "value": ...}` and
+/// `{"type": ..., "hex": ...}`.  NULLs stay `null`.
+fn tag_values(columns: &[Value], rows: &mut [Value]) {
+    for column in columns {
+        let type_name = column["type"].as_str().unwrap_or_default();
+        let Some(name) = column["name"].as_str() else {
+            continue;
+        };
+        let conversion = conversion(type_name);
+        if !matches!(conversion, Some(Conversion::Variant | Conversion::Binary)) {
+            continue;
+        }
+        for value in rows.iter_mut().filter_map(|row| row.get_mut(name)) {
+            let Some(text) = value.as_str() else {
+                continue;
+            };
+            *value = match conversion {
+                Some(Conversion::Variant) => {
+                    let (base_type, text) = text.split_once(':').unwrap_or(("", text));
+                    serde_json::json!({
+                        "type": "sql_variant",
+                        "base_type": base_type,
+                        "value": text,
+                    })
+                }
+                _ => serde_json::json!({ "type": type_name, "hex": text }),
+            };
+        }
+    }
+}
+
+fn received_columns(received: &[(String, ColumnType)]) -> Vec<Value> {
+    received
+        .iter()
+        .map(|(name, column_type)| {
+            serde_json::json!({
+                "name": name,
+                "type": column_type_name(*column_type),
+                "nullable": null,
+            })
+        })
+        .collect()
+}
+
+/// Convert a result row to a JSON object keyed by column name.
+fn row_to_json(row: &Row) -> Value {
+    let mut obj = Map::new();
+    for (col, data) in row.cells() {
+        obj.insert(col.name().to_string(), column_data_to_json(data));
+    }
+    Value::Object(obj)
+}
+
+/// Convert the rows of the first result set of `stream` to JSON as they
+/// arrive, stopping after `limit` rows or once the rows converted reach
+/// `max_bytes` (see [`json_size`]), whichever comes first.  Also returns
+/// whether the byte budget stopped it; further rows may then remain.
+///
+/// Each row's TDS buffers are released once it is converted, so only the
+/// JSON rows are held in memory rather than the whole result set twice; rows
+/// past the limit and any further result sets are never read (the
+/// connection is dropped with the rest of the stream).
+async fn collect_rows(
+    mut stream: QueryStream<'_>,
+    limit: usize,
+    max_bytes: usize,
+) -> Result<(Vec<Value>, bool)> {
+    let mut rows = Vec::new();
+    let mut bytes = 0;
+    while rows.len() < limit {
+        if bytes >= max_bytes {
+            return Ok((rows, true));
+        }
+        match stream.try_next().await? {
+            Some(QueryItem::Row(row)) => {
+                let row = row_to_json(&row);
+                bytes += json_size(&row);
+                rows.push(row);
+            }
+            Some(QueryItem::Metadata(metadata)) if metadata.result_index() > 0 => break,
+            Some(QueryItem::Metadata(_)) => {}
+            None => break,
+        }
+    }
+    Ok((rows, false))
+}
+
+/// Check that the server is reachable and the credentials are accepted.
+pub async fn ping(cfg: &MssqlConfig) -> Result<()> {
+    retry::run(cfg.retry, "MSSQL ping", || ping_once(cfg)).await
+}
+
+async fn ping_once(cfg: &MssqlConfig) -> Result<()> {
+    let mut client = connect(cfg).await?;
+    client
+        .simple_query("SELECT 1")
+        .await
+        .context("Failed to query MSSQL")?
+        .into_results()
+        .await
+        .context("Failed to query MSSQL")?;
+    Ok(())
+}
+
+/// The transport settings the server negotiated for a new connection, from
+/// `CONNECTIONPROPERTY`: `encrypt_option` (`TRUE` when the whole session is
+/// encrypted), `net_transport`, `protocol_type` and `auth_scheme`.
+pub async fn connection_properties(cfg: &MssqlConfig) -> Result<Map<String, Value>> {
+    retry::run(cfg.retry, "MSSQL connection properties", || {
+        connection_properties_once(cfg)
+    })
+    .await
+}
+
+async fn connection_properties_once(cfg: &MssqlConfig) -> Result<Map<String, Value>> {
+    const PROPERTIES: [&str; 4] = [
+        "encrypt_option",
+        "net_transport",
+        "protocol_type",
+        "auth_scheme",
+    ];
+    let mut client = connect(cfg).await?;
+    let select = PROPERTIES
+        .map(|p| format!("CAST(CONNECTIONPROPERTY('{p}') AS nvarchar(60)) AS {p}"))
+        .join(", ");
+    let row = client
+        .simple_query(format!("SELECT {select}"))
+        .await?
+        .into_row()
+        .await
+        .context("Failed to read the connection properties")?
+        .context("No connection properties returned")?;
+    Ok(PROPERTIES
+        .into_iter()
+        .map(|p| {
+            (
+                p.to_string(),
+                row.get::<&str, _>(p)
+                    .map(Value::from)
+                    .unwrap_or(Value::Null),
+            )
+        })
+        .collect())
+}
+
 /// List all user tables in the connected database.
 ///
 /// Returns a JSON array of objects with `schema` and `table_name` fields.
 pub async fn list_tables(cfg: &MssqlConfig) -> Result<Value> {
+    list_objects(cfg, false).await
+}
+
+/// List all user tables and views in the connected database, as on a Synapse
+/// serverless endpoint where lake data is usually exposed through views and
+/// external tables.
+///
+/// Returns a JSON array of objects with `schema`, `table_name` and `kind`
+/// (`table` or `view`) fields.
+pub async fn list_tables_and_views(cfg: &MssqlConfig) -> Result<Value> {
+    list_objects(cfg, true).await
+}
+
+async fn list_objects(cfg: &MssqlConfig, include_views: bool) -> Result<Value> {
+    retry::run(cfg.retry, "MSSQL table listing", || {
+        list_objects_once(cfg, include_views)
+    })
+    .await
+}
+
+async fn list_objects_once(cfg: &MssqlConfig, include_views: bool) -> Result<Value> {
     let mut client = connect(cfg).await?;
 
+    let types = if include_views {
+        "('BASE TABLE', 'VIEW')"
+    } else {
+        "('BASE TABLE')"
+    };
     let rows = client
         .query(
-            "SELECT TABLE_SCHEMA, TABLE_NAME \
-             FROM INFORMATION_SCHEMA.TABLES \
-             WHERE TABLE_TYPE = 'BASE TABLE' \
-             ORDER BY TABLE_SCHEMA, TABLE_NAME",
+            format!(
+                "SELECT TABLE_SCHEMA, TABLE_NAME, TABLE_TYPE \
+                 FROM INFORMATION_SCHEMA.TABLES \
+                 WHERE TABLE_TYPE IN {types} \
+                 ORDER BY TABLE_SCHEMA, TABLE_NAME"
+            ),
             &[],
         )
         .await
@@ -126,7 +844,15 @@ pub async fn list_tables(cfg: &MssqlConfig) -> Result<Value> {
         .map(|row| {
             let schema: &str = row.get("TABLE_SCHEMA").unwrap_or("");
             let name: &str = row.get("TABLE_NAME").unwrap_or("");
-            serde_json::json!({ "schema": schema, "table_name": name })
+            let mut table = serde_json::json!({ "schema": schema, "table_name": name });
+            if include_views {
+                let kind = match row.get("TABLE_TYPE") {
+                    Some("VIEW") => "view",
+                    _ => "table",
+                };
+                table["kind"] = kind.into();
+            }
+            table
         })
         .collect();
 
@@ -145,33 +871,262 @@ pub async fn list_tables(cfg: &MssqlConfig) -> Result<Value> {
 /// database user configured via `MSSQL_CONNECTION_STRING` should use the
 /// principle of least privilege (read-only where possible).
 pub async fn execute_query(cfg: &MssqlConfig, sql: &str, max_rows: u64) -> Result<Value> {
+    execute_query_with(cfg, "", sql, max_rows).await
+}
+
+/// Like [`execute_query`], but with `prelude` (typically a `WITH …` clause
+/// defining tables the query refers to) placed before the limiting `SELECT`.
+pub async fn execute_query_with(
+    cfg: &MssqlConfig,
+    prelude: &str,
+    sql: &str,
+    max_rows: u64,
+) -> Result<Value> {
+    retry::run(cfg.retry, "MSSQL query", || {
+        execute_query_with_once(cfg, prelude, sql, max_rows)
+    })
+    .await
+}
+
+async fn execute_query_with_once(
+    cfg: &MssqlConfig,
+    prelude: &str,
+    sql: &str,
+    max_rows: u64,
+) -> Result<Value> {
     let max_rows = max_rows.min(HARD_MAX_ROWS);
 
     let mut client = connect(cfg).await?;
 
     // Wrap the caller-supplied query in a TOP to prevent reading millions of rows.
-    let limited_sql = format!("SELECT TOP ({max_rows}) * FROM ({sql}) AS __mcp_query__");
+    let limited_sql = format!("{prelude}SELECT TOP ({max_rows}) * FROM ({sql}) AS __mcp_query__");
+    check_plan(&mut client, cfg, &limited_sql).await?;
+    let described = if cfg.mask_classified {
+        describe_result_for(&mut client, cfg, &limited_sql).await?
+    } else {
+        None
+    };
 
-    let rows = Query::new(limited_sql)
+    let stream = Query::new(limited_sql)
         .query(&mut client)
         .await
-        .context("Failed to execute SQL query")?
-        .into_first_result()
+        .context("Failed to execute SQL query")?;
+    let (mut result, cut_short) = collect_rows(stream, max_rows as usize, cfg.max_result_bytes)
         .await
         .context("Failed to collect query results")?;
+    if let Some(described) = &described {
+        mask_classified(described, &mut result);
+    }
+    if cut_short {
+        tracing::warn!(
+            "Query result cut to {} rows at the MAX_RESULT_BYTES budget of {} bytes",
+            result.len(),
+            cfg.max_result_bytes
+        );
+    }
 
-    let result: Vec<Value> = rows
-        .iter()
-        .map(|row| {
-            let mut obj = Map::new();
-            for (col, data) in row.cells() {
-                obj.insert(col.name().to_string(), column_data_to_json(data));
+    Ok(Value::Array(result))
+}
+
+/// `sql` with `OFFSET {offset} ROWS FETCH NEXT {fetch} ROWS ONLY` added to
+/// its own top-level `ORDER BY`, so that pages follow its order.  `None`
+/// unless it is a single query ordered without `OFFSET`, `FETCH` or `TOP`
+/// limits, or with just `OFFSET 0 ROWS`.
+fn ordered_page(sql: &str, offset: u64, fetch: u64) -> Option<String> {
+    let statements = Parser::parse_sql(&MsSqlDialect {}, sql).ok()?;
+    let [Statement::Query(query)] = statements.as_slice() else {
+        return None;
+    };
+    if query.order_by.is_none() || query.limit.is_some() || query.fetch.is_some() {
+        return None;
+    }
+    let sql = sql.trim().trim_end_matches(';').trim_end();
+    let sql = match &query.offset {
+        None => sql,
+        Some(existing) if existing.value.to_string() == "0" => {
+            // Drop the trailing `OFFSET 0 ROWS`.
+            let mut words = sql.rsplitn(4, char::is_whitespace);
+            let unit = words.next()?;
+            let zero = words.next()?;
+            let keyword = words.next()?;
+            let rest = words.next()?;
+            let dropped = keyword.eq_ignore_ascii_case("OFFSET")
+                && zero == "0"
+                && (unit.eq_ignore_ascii_case("ROWS") || unit.eq_ignore_ascii_case("ROW"));
+            if !dropped {
+                return None;
             }
-            Value::Object(obj)
-        })
+            rest.trim_end()
+        }
+        Some(_) => return None,
+    };
+    // On a new line, so that a trailing `--` comment does not swallow it.
+    Some(format!(
+        "{sql}\nOFFSET {offset} ROWS FETCH NEXT {fetch} ROWS ONLY"
+    ))
+}
+
+/// Wrap `sql` so that it skips `offset` rows and returns the next
+/// `max_rows + 1` with the `select` list; the extra row tells the caller
+/// whether another page follows.
+///
+/// A query with its own `ORDER BY` is paged in that order (see
+/// [`ordered_page`]).  Any other query is paged in no particular order, so
+/// its pages may overlap or miss rows.
+fn page_sql(select: &str, sql: &str, offset: u64, max_rows: u64) -> String {
+    match ordered_page(sql, offset, max_rows + 1) {
+        Some(page) => format!("SELECT {select} FROM ({page}) AS __mcp_query__"),
+        None => format!(
+            "SELECT {select} FROM ({sql}) AS __mcp_query__ ORDER BY (SELECT NULL) \
+             OFFSET {offset} ROWS FETCH NEXT {} ROWS ONLY",
+            max_rows + 1
+        ),
+    }
+}
+
+/// Execute one page of an arbitrary SQL query: up to `max_rows` rows (capped
+/// at [`HARD_MAX_ROWS`]) after skipping the first `offset`.
+///
+/// Spatial columns are returned as WKT (e.g. `POINT (-122.35 47.65)`) and
+/// `sql_variant`, `hierarchyid` and user-defined types as tagged values by
+/// rewriting the select list, which needs the server to describe `sql` first.
+///
+/// Returns the columns (kept even when every value, or every row, is
+/// missing), the rows and whether more rows follow.  Pages are only stable
+/// across calls when `sql` orders its rows deterministically with its own
+/// `ORDER BY` (see [`page_sql`]).  The security note on [`execute_query`]
+/// applies equally here.
+pub async fn execute_query_page(
+    cfg: &MssqlConfig,
+    sql: &str,
+    offset: u64,
+    max_rows: u64,
+) -> Result<QueryPage> {
+    retry::run(cfg.retry, "MSSQL query", || {
+        execute_query_page_once(cfg, sql, offset, max_rows)
+    })
+    .await
+}
+
+async fn execute_query_page_once(
+    cfg: &MssqlConfig,
+    sql: &str,
+    offset: u64,
+    max_rows: u64,
+) -> Result<QueryPage> {
+    let max_rows = max_rows.min(HARD_MAX_ROWS);
+
+    let mut client = connect(cfg).await?;
+
+    let described = describe_result_for(&mut client, cfg, sql).await?;
+    let select = described.as_deref().map_or("*".into(), select_list);
+    let paged_sql = page_sql(&select, sql, offset, max_rows);
+    check_plan(&mut client, cfg, &paged_sql).await?;
+    let mut stream = Query::new(paged_sql)
+        .query(&mut client)
+        .await
+        .context("Failed to execute SQL query")?;
+    let received: Vec<(String, ColumnType)> = stream
+        .columns()
+        .await
+        .context("Failed to read the result columns")?
+        .unwrap_or_default()
+        .iter()
+        .map(|c| (c.name().to_string(), c.column_type()))
         .collect();
+    let (mut result, cut_short) = collect_rows(stream, max_rows as usize + 1, cfg.max_result_bytes)
+        .await
+        .context("Failed to collect query results")?;
+    let more = cut_short || result.len() as u64 > max_rows;
+    result.truncate(max_rows as usize);
+    if let Some(described) = &described {
+        tag_values(described, &mut result);
+        mask_classified(described, &mut result);
+    }
 
-    Ok(Value::Array(result))
+    Ok(QueryPage {
+        columns: result_columns(described, &received),
+        rows: result,
+        more,
+        memory_budget_exhausted: cut_short,
+    })
+}
+
+/// The batch [`execute_statement`] sends: `sql` as it is, since an `INSERT`,
+/// `UPDATE`, `DELETE`, `EXEC` or DDL statement cannot be wrapped in the
+/// paging `SELECT` of [`page_sql`].  `SET NOCOUNT OFF` first, so that the
+/// rows affected are reported even where the server's user options turn
+/// them off.
+fn statement_batch(sql: &str) -> String {
+    format!("SET NOCOUNT OFF;\n{}", sql.trim())
+}
+
+/// Run `sql`, a statement that may modify data, as it is and return the
+/// number of rows it affected.
+///
+/// Unlike queries it is neither paged nor checked against the estimated plan
+/// limits, and it is not retried: a statement that failed on the way back
+/// may already have modified data.
+pub async fn execute_statement(cfg: &MssqlConfig, sql: &str) -> Result<u64> {
+    let mut client = connect(cfg).await?;
+    let result = Query::new(statement_batch(sql))
+        .execute(&mut client)
+        .await
+        .context("Failed to execute SQL statement")?;
+    Ok(result.total())
+}
+
+/// Describe the columns of `schema.table`: name, type, length / precision,
+/// nullability, default and primary key membership, in ordinal order.
+pub async fn describe_table(cfg: &MssqlConfig, schema: &str, table: &str) -> Result<Value> {
+    retry::run(cfg.retry, "MSSQL table description", || {
+        describe_table_once(cfg, schema, table)
+    })
+    .await
+}
+
+async fn describe_table_once(cfg: &MssqlConfig, schema: &str, table: &str) -> Result<Value> {
+    let mut client = connect(cfg).await?;
+
+    let rows = client
+        .query(
+            "SELECT c.COLUMN_NAME AS name, c.DATA_TYPE AS data_type, \
+                    c.CHARACTER_MAXIMUM_LENGTH AS max_length, \
+                    c.NUMERIC_PRECISION AS numeric_precision, \
+                    c.NUMERIC_SCALE AS numeric_scale, \
+                    CAST(CASE WHEN c.IS_NULLABLE = 'YES' THEN 1 ELSE 0 END AS bit) AS nullable, \
+                    c.COLUMN_DEFAULT AS column_default, \
+                    CAST(CASE WHEN pk.COLUMN_NAME IS NULL THEN 0 ELSE 1 END AS bit) AS primary_key \
+             FROM INFORMATION_SCHEMA.COLUMNS c \
+             LEFT JOIN ( \
+                 SELECT ku.TABLE_SCHEMA, ku.TABLE_NAME, ku.COLUMN_NAME \
+                 FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS tc \
+                 JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE ku \
+                   ON tc.CONSTRAINT_NAME = ku.CONSTRAINT_NAME \
+                  AND tc.TABLE_SCHEMA = ku.TABLE_SCHEMA \
+                 WHERE tc.CONSTRAINT_TYPE = 'PRIMARY KEY' \
+             ) pk ON pk.TABLE_SCHEMA = c.TABLE_SCHEMA \
+                 AND pk.TABLE_NAME = c.TABLE_NAME \
+                 AND pk.COLUMN_NAME = c.COLUMN_NAME \
+             WHERE c.TABLE_SCHEMA = @P1 AND c.TABLE_NAME = @P2 \
+             ORDER BY c.ORDINAL_POSITION",
+            &[&schema, &table],
+        )
+        .await
+        .context("Failed to query INFORMATION_SCHEMA.COLUMNS")?
+        .into_first_result()
+        .await
+        .context("Failed to collect column results")?;
+
+    if rows.is_empty() {
+        anyhow::bail!("Table {schema}.{table} does not exist or has no visible columns");
+    }
+
+    Ok(serde_json::json!({
+        "schema": schema,
+        "table": table,
+        "columns": rows.iter().map(row_to_json).collect::<Vec<_>>(),
+    }))
 }
 
 #[cfg(test)]
@@ -179,6 +1134,165 @@ mod tests {
     use super::*;
     use tiberius::{numeric::Numeric, ColumnData};
 
+    #[test]
+    fn statements_are_sent_unwrapped() {
+        let sql = "UPDATE dbo.orders SET status = 'shipped' WHERE id = 7";
+        let batch = statement_batch(&format!("  {sql}\n"));
+        assert_eq!(batch, format!("SET NOCOUNT OFF;\n{sql}"));
+        assert!(!batch.contains("__mcp_query__"));
+    }
+
+    #[test]
+    fn page_sql_fetches_one_extra_row_after_offset() {
+        assert_eq!(
+            page_sql("*", "SELECT id FROM t", 50, 25),
+            "SELECT * FROM (SELECT id FROM t) AS __mcp_query__ ORDER BY (SELECT NULL) \
+             OFFSET 50 ROWS FETCH NEXT 26 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn ordered_queries_are_paged_in_their_own_order() {
+        assert_eq!(
+            page_sql(
+                "[id]",
+                "SELECT id FROM t ORDER BY id DESC -- newest",
+                50,
+                25
+            ),
+            "SELECT [id] FROM (SELECT id FROM t ORDER BY id DESC -- newest\n\
+             OFFSET 50 ROWS FETCH NEXT 26 ROWS ONLY) AS __mcp_query__"
+        );
+        assert_eq!(
+            page_sql("*", "SELECT id FROM t ORDER BY id OFFSET 0 ROWS;", 0, 9),
+            "SELECT * FROM (SELECT id FROM t ORDER BY id\n\
+             OFFSET 0 ROWS FETCH NEXT 10 ROWS ONLY) AS __mcp_query__"
+        );
+        // Limits of the query's own are kept by paging over it.
+        assert!(
+            page_sql("*", "SELECT id FROM t ORDER BY id OFFSET 5 ROWS", 0, 9)
+                .contains("ORDER BY (SELECT NULL)")
+        );
+    }
+
+    #[test]
+    fn plans_over_the_limits_are_refused() {
+        let xml = r#"<ShowPlanXML><BatchSequence><Batch><Statements>
+            <StmtSimple StatementText="SELECT" StatementEstRows="26" StatementSubTreeCost="412.5">
+              <QueryPlan><RelOp EstimateRows="26" EstimateRowsWithoutRowGoal="9000000">
+                <RelOp EstimateRows="48000000" EstimatedTotalSubtreeCost="400">
+              </RelOp></RelOp></QueryPlan>
+            </StmtSimple></Statements></Batch></BatchSequence></ShowPlanXML>"#;
+        let estimate = PlanEstimate::from_xml(xml);
+        assert_eq!(
+            estimate,
+            PlanEstimate {
+                rows: 48_000_000.0,
+                cost: 412.5
+            }
+        );
+
+        let rows_only = PlanLimits {
+            max_rows: Some(1_000_000.0),
+            max_cost: None,
+        };
+        let refusal = estimate.refusal(&rows_only).unwrap();
+        assert!(refusal.contains("about 48000000 rows"), "{refusal}");
+        assert!(!refusal.contains("MSSQL_MAX_ESTIMATED_COST"), "{refusal}");
+        assert!(refusal.contains("TOP (n)"), "{refusal}");
+
+        let generous = PlanLimits {
+            max_rows: Some(1e9),
+            max_cost: Some(1000.0),
+        };
+        assert_eq!(estimate.refusal(&generous), None);
+    }
+
+    #[test]
+    fn confidential_columns_are_masked() {
+        let columns = [
+            serde_json::json!({ "name": "id", "type": "int", "nullable": false }),
+            serde_json::json!({
+                "name": "email",
+                "type": "nvarchar(200)",
+                "nullable": true,
+                "sensitivity_label": "Confidential - GDPR",
+            }),
+            serde_json::json!({
+                "name": "city",
+                "type": "nvarchar(50)",
+                "nullable": true,
+                "sensitivity_label": "General",
+            }),
+        ];
+        let mut rows = vec![
+            serde_json::json!({ "id": 1, "email": "a@example.com", "city": "Oslo" }),
+            serde_json::json!({ "id": 2, "email": null, "city": "Bergen" }),
+        ];
+        mask_classified(&columns, &mut rows);
+        assert_eq!(
+            rows,
+            [
+                serde_json::json!({ "id": 1, "email": "***", "city": "Oslo" }),
+                serde_json::json!({ "id": 2, "email": null, "city": "Bergen" }),
+            ]
+        );
+        assert!(is_masked_label("Highly Confidential"));
+        assert!(!is_masked_label("Public"));
+    }
+
+    #[test]
+    fn plan_columns_lists_the_table_columns_read() {
+        let plan = r#"<ShowPlanXML><OutputList>
+            <ColumnReference Database="[shop]" Schema="[dbo]" Table="[Customers]" Alias="[c]" Column="Email" />
+            <ColumnReference Column="Expr1002" />
+            </OutputList></ShowPlanXML>"#;
+        let columns = plan_columns(plan);
+        assert_eq!(columns.len(), 1);
+        assert!(columns.contains(&("dbo".into(), "customers".into(), "email".into())));
+    }
+
+    #[test]
+    fn spatial_columns_are_selected_as_wkt() {
+        let columns = [
+            serde_json::json!({ "name": "id", "type": "int", "nullable": false }),
+            serde_json::json!({ "name": "odd]name", "type": "geography", "nullable": true }),
+        ];
+        assert_eq!(
+            select_list(&columns),
+            "[id], [odd]]name].STAsText() AS [odd]]name]"
+        );
+        assert_eq!(select_list(&columns[..1]), "*");
+    }
+
+    #[test]
+    fn variant_and_binary_types_are_tagged() {
+        let columns = [
+            serde_json::json!({ "name": "v", "type": "sql_variant", "nullable": true }),
+            serde_json::json!({ "name": "node", "type": "hierarchyid", "nullable": true }),
+            serde_json::json!({ "name": "name", "type": "nvarchar(50)", "nullable": true }),
+        ];
+        let select = select_list(&columns);
+        assert!(select.contains("SQL_VARIANT_PROPERTY([v], 'BaseType')"));
+        assert!(select.contains("CAST([node] AS varbinary(max)) AS [node]"));
+        assert!(select.ends_with(", [name]"));
+
+        let mut rows = vec![
+            serde_json::json!({ "v": "int:42", "node": "5ac0", "name": "a:b" }),
+            serde_json::json!({ "v": null, "node": null, "name": null }),
+        ];
+        tag_values(&columns, &mut rows);
+        assert_eq!(
+            rows[0],
+            serde_json::json!({
+                "v": { "type": "sql_variant", "base_type": "int", "value": "42" },
+                "node": { "type": "hierarchyid", "hex": "5ac0" },
+                "name": "a:b",
+            })
+        );
+        assert_eq!(rows[1]["v"], Value::Null);
+    }
+
     #[test]
     fn column_data_null_variants_become_json_null() {
         assert_eq!(column_data_to_json(&ColumnData::I32(None)), Value::Null);
@@ -204,6 +1318,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn received_columns_keep_order_and_leave_nullability_unknown() {
+        let columns = received_columns(&[
+            ("id".into(), ColumnType::Int4),
+            ("name".into(), ColumnType::NVarchar),
+            ("deleted".into(), ColumnType::Null),
+        ]);
+        assert_eq!(
+            columns,
+            [
+                serde_json::json!({ "name": "id", "type": "int", "nullable": null }),
+                serde_json::json!({ "name": "name", "type": "nvarchar", "nullable": null }),
+                serde_json::json!({ "name": "deleted", "type": "null", "nullable": null }),
+            ]
+        );
+    }
+
     #[test]
     fn column_data_numeric_becomes_string() {
         // Numeric implements Display; we just verify it does not panic.
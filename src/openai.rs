@@ -0,0 +1,98 @@
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+use crate::aad::TokenProvider;
+use crate::config::OpenAiConfig;
+use crate::recording;
+
+/// Azure AD resource of Azure OpenAI (and the other Azure AI services).
+const COGNITIVE_SERVICES_RESOURCE: &str = "https://cognitiveservices.azure.com";
+
+/// Upper bound on the characters embedded at once; the models accept about
+/// 8 000 tokens.
+pub const MAX_INPUT_CHARS: usize = 32_000;
+
+/// Embed `text` with the configured embedding deployment, authenticating
+/// with `AZURE_OPENAI_API_KEY` when set and an Azure AD token from `tokens`
+/// otherwise.
+///
+/// `dimensions` (else `AZURE_OPENAI_EMBEDDING_DIMENSIONS`) shortens the
+/// vector; only `text-embedding-3` models support it.
+///
+/// Returns `{deployment, model, dimensions, embedding, prompt_tokens}`.
+pub async fn embed(
+    tokens: Option<&TokenProvider>,
+    cfg: &OpenAiConfig,
+    text: &str,
+    dimensions: Option<u32>,
+) -> Result<Value> {
+    if text.trim().is_empty() {
+        bail!("text must not be empty");
+    }
+    if text.chars().count() > MAX_INPUT_CHARS {
+        bail!("text is longer than {MAX_INPUT_CHARS} characters; embed it in chunks");
+    }
+
+    let mut body = serde_json::json!({ "input": text });
+    if let Some(dimensions) = dimensions.or(cfg.dimensions) {
+        body["dimensions"] = dimensions.into();
+    }
+    let request = reqwest::Client::new()
+        .post(format!(
+            "{}/openai/deployments/{}/embeddings",
+            cfg.endpoint,
+            urlencoding::encode(&cfg.deployment)
+        ))
+        .query(&[("api-version", cfg.api_version.as_str())])
+        .json(&body);
+    let request = match (&cfg.api_key, tokens) {
+        (Some(key), _) => request.header("api-key", key),
+        (None, Some(tokens)) => {
+            request.bearer_auth(tokens.token(COGNITIVE_SERVICES_RESOURCE).await?)
+        }
+        (None, None) => bail!("Azure OpenAI needs an API key or an Azure AD credential"),
+    };
+    let response = recording::send(request)
+        .await
+        .context("Azure OpenAI request failed")?;
+    let status = response.status();
+    let mut result: Value = response
+        .json()
+        .await
+        .context("Azure OpenAI returned invalid JSON")?;
+    if !status.is_success() {
+        let message = result["error"]["message"].as_str().unwrap_or_default();
+        bail!("Azure OpenAI returned {status}: {message}");
+    }
+
+    let Some(embedding) = result["data"][0]["embedding"]
+        .as_array_mut()
+        .map(std::mem::take)
+    else {
+        bail!("Azure OpenAI returned no embedding");
+    };
+    Ok(serde_json::json!({
+        "deployment": cfg.deployment,
+        "model": result["model"],
+        "dimensions": embedding.len(),
+        "embedding": embedding,
+        "prompt_tokens": result["usage"]["prompt_tokens"],
+    }))
+}
+
+/// Embed `text` and return only the vector, e.g. to search with it.
+pub async fn embed_vector(
+    tokens: Option<&TokenProvider>,
+    cfg: &OpenAiConfig,
+    text: &str,
+) -> Result<Vec<f64>> {
+    let result = embed(tokens, cfg, text, None).await?;
+    serde_json::from_value(result["embedding"].clone())
+        .context("Azure OpenAI returned a non-numeric embedding")
+}
+
+/// Check the deployment is reachable and accepts the credential by
+/// embedding a single word.
+pub async fn ping(tokens: Option<&TokenProvider>, cfg: &OpenAiConfig) -> Result<()> {
+    embed(tokens, cfg, "ping", None).await.map(|_| ())
+}
@@ -0,0 +1,1088 @@
+use std::collections::BTreeMap;
+
+use rmcp::Json;
+use schemars::JsonSchema;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::cosmos::QueryMetadata;
+
+/// One result row, keyed by column name.
+pub type Row = Map<String, Value>;
+
+/// Check a backend result against the tool's output type `T` and return it
+/// as structured content.
+///
+/// The backends build their results as JSON; converting them here means the
+/// advertised output schema and the actual response cannot drift apart.
+pub fn structured<T: DeserializeOwned>(result: anyhow::Result<Value>) -> Result<Json<T>, String> {
+    let value = result.map_err(|e| e.to_string())?;
+    serde_json::from_value(value)
+        .map(Json)
+        .map_err(|e| format!("Unexpected result shape: {e}"))
+}
+
+// ---------------------------------------------------------------------------
+// MSSQL
+// ---------------------------------------------------------------------------
+
+/// Output of `mssql_list_tables`, `synapse_list_tables` and
+/// `fabric_list_tables`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TableList {
+    /// Every user table (and, on Synapse and Fabric, view), ordered by schema
+    /// and name.
+    pub tables: Vec<TableName>,
+}
+
+/// A schema-qualified table name.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TableName {
+    pub schema: String,
+    pub table_name: String,
+    /// `table` or `view`; only reported by `synapse_list_tables` and
+    /// `fabric_list_tables`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+/// Output of `mssql_execute_query`, `synapse_execute_query`,
+/// `fabric_execute_query` and `cassandra_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RowsPage {
+    /// The result columns in order; not reported by `cassandra_query`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<ResultColumn>>,
+    /// The rows of this page.
+    pub rows: Vec<Row>,
+    /// `true` when the page stopped short of `max_rows` because its rows
+    /// reached `MAX_RESULT_BYTES`; absent otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_budget_exhausted: Option<bool>,
+    /// Cursor for `fetch_next_page`; `null` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+    /// The rows modified by a statement that may modify data, which returns
+    /// no `rows`; absent for queries.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows_affected: Option<u64>,
+}
+
+/// A column of a SQL result.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ResultColumn {
+    pub name: String,
+    /// SQL type, e.g. `nvarchar(50)` or `decimal(18,2)`; only the base type
+    /// (e.g. `nvarchar`) when the server cannot describe the query.
+    #[serde(rename = "type")]
+    pub sql_type: String,
+    /// Whether the column allows NULL; `null` when unknown.
+    pub nullable: Option<bool>,
+}
+
+/// Output of `cosmos_analytical_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RowList {
+    /// The result rows, capped at `max_rows`.
+    pub rows: Vec<Row>,
+}
+
+// ---------------------------------------------------------------------------
+// Cosmos DB
+// ---------------------------------------------------------------------------
+
+/// Output of `cosmos_list_databases`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DatabaseList {
+    /// Database names.
+    pub databases: Vec<String>,
+}
+
+/// Output of `cosmos_list_containers`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerList {
+    /// Container names.
+    pub containers: Vec<String>,
+}
+
+/// Output of `cosmos_container_stats`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerStatsList {
+    /// One entry per container, largest first.
+    pub containers: Vec<ContainerStats>,
+}
+
+/// Document count and storage usage of one container.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerStats {
+    pub container: String,
+    pub document_count: Option<i64>,
+    pub documents_size_kb: Option<i64>,
+    pub total_size_kb: Option<i64>,
+    pub quota_size_kb: Option<i64>,
+}
+
+/// Output of `cosmos_list_programmability`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Programmability {
+    pub stored_procedures: Vec<ScriptSummary>,
+    pub user_defined_functions: Vec<ScriptSummary>,
+    pub triggers: Vec<ScriptSummary>,
+}
+
+/// A stored procedure, user-defined function or trigger.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ScriptSummary {
+    pub id: String,
+    /// How to call a UDF from a query, e.g. `udf.tax(...)`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_syntax: Option<String>,
+    /// `Pre` or `Post` (triggers only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger_type: Option<String>,
+    /// Operation the trigger fires on (triggers only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger_operation: Option<String>,
+    /// JavaScript source, when `include_body` was set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
+
+/// Output of `cosmos_read_conflicts`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConflictFeed {
+    /// The container's conflict resolution policy.
+    pub conflict_resolution_policy: Value,
+    /// `true` when the feed holds more conflicts than were returned.
+    pub truncated: bool,
+    pub conflicts: Vec<Conflict>,
+}
+
+/// One entry of a conflicts feed.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Conflict {
+    pub id: String,
+    pub operation_type: Option<String>,
+    pub resource_type: Option<String>,
+    pub source_resource_id: Option<String>,
+    pub conflict_lsn: Option<i64>,
+    /// Epoch seconds at which the conflict was recorded.
+    pub timestamp: Option<i64>,
+    /// The losing version of the document.
+    pub conflicting_version: Value,
+    /// The version that currently won; `null` when not requested or when
+    /// the document no longer exists.
+    #[serde(default)]
+    pub current_version: Value,
+}
+
+/// Output of `cosmos_get_ttl`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TtlInfo {
+    pub container: String,
+    /// Container default TTL: `null` disabled, `-1` no default, otherwise
+    /// seconds.
+    pub default_ttl: Option<i64>,
+    pub description: String,
+    /// The requested document's TTL, when `id` was given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item: Option<ItemTtl>,
+}
+
+/// The TTL of one document.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ItemTtl {
+    pub id: String,
+    /// The document's own `ttl`, if set.
+    pub ttl: Option<i64>,
+    /// The TTL that applies after combining the item and container settings.
+    pub effective_ttl: Option<i64>,
+    /// Epoch seconds of the document's last modification (`_ts`).
+    pub last_modified: Option<i64>,
+    pub description: String,
+}
+
+/// Output of `cosmos_query_items`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ItemsPage {
+    /// The documents of this page.
+    pub items: Vec<Value>,
+    pub metadata: QueryMetadata,
+    /// Cursor for `fetch_next_page`; `null` once the query is exhausted.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+/// Output of `fetch_next_page`: `rows` for an MSSQL cursor, `items` and
+/// `metadata` for a Cosmos DB cursor, `entities` for a Table Storage cursor.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct NextPage {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<Vec<ResultColumn>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<Vec<Row>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory_budget_exhausted: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<QueryMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<Vec<Row>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paths: Option<Vec<LakePath>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entries: Option<Vec<FileEntry>>,
+    /// Cursor for the following page; `null` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+/// Output of `cosmos_read_item`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ItemRead {
+    /// The document, or `null` when it does not exist.
+    pub item: Option<Value>,
+    pub metadata: QueryMetadata,
+}
+
+/// Output of `cosmos_vector_search`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct VectorSearchResults {
+    /// Nearest documents first.
+    pub results: Vec<ScoredDocument>,
+    /// `cosine`, `dotproduct` or `euclidean`.
+    pub distance_function: String,
+    /// Whether the container has a vector embedding policy for the path.
+    pub vector_policy_found: bool,
+    pub metadata: QueryMetadata,
+}
+
+/// A document with its vector distance score.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ScoredDocument {
+    pub document: Value,
+    pub score: f64,
+}
+
+/// Output of `cosmos_text_search`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TextSearchResults {
+    /// Matching documents, best first for the ranked modes.
+    pub results: Vec<Value>,
+    pub metadata: QueryMetadata,
+}
+
+/// Output of `cosmos_read_change_feed`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ChangeFeedPage {
+    /// Documents changed since the start position, oldest first per range.
+    pub items: Vec<Value>,
+    /// Token to pass as `continuation` to read the following changes.
+    pub continuation: String,
+    pub metadata: QueryMetadata,
+}
+
+/// Output of `cosmos_patch_item`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PatchResult {
+    pub id: String,
+    pub partition_key: String,
+    pub operations_applied: usize,
+}
+
+/// Output of `cosmos_execute_stored_procedure`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StoredProcedureResult {
+    /// The procedure's response body.
+    pub result: Value,
+    /// `console.log` output, if any.
+    pub logs: Option<String>,
+    pub request_charge: f64,
+}
+
+/// Output of `cosmos_set_item_ttl`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ItemTtlChange {
+    pub id: String,
+    /// The new `ttl`, or `null` when it was removed.
+    pub ttl: Option<i64>,
+}
+
+/// Output of `cosmos_bulk_import`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ImportReport {
+    pub succeeded: usize,
+    pub failed: usize,
+    pub request_charge: f64,
+    /// One entry per input document, in input order.
+    pub results: Vec<ImportResult>,
+}
+
+/// The outcome of writing one document.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ImportResult {
+    /// Position of the document in the input.
+    pub index: usize,
+    /// The document's `id`, when it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// HTTP status of the write, when it reached the service.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u64>,
+    /// Why the document was not written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Output of `cosmos_create_database`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedDatabase {
+    pub database: String,
+    pub throughput: Option<u32>,
+    pub autoscale_max_throughput: Option<u32>,
+}
+
+/// Output of `cosmos_create_container`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedContainer {
+    pub database: String,
+    pub container: String,
+    pub partition_key_path: String,
+    pub throughput: Option<u32>,
+    pub autoscale_max_throughput: Option<u32>,
+}
+
+/// Output of `cosmos_set_container_ttl`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ContainerTtlChange {
+    pub container: String,
+    pub previous_default_ttl: Option<i64>,
+    pub default_ttl: Option<i64>,
+    pub description: String,
+}
+
+// ---------------------------------------------------------------------------
+// Cosmos DB Gremlin
+// ---------------------------------------------------------------------------
+
+/// Output of `gremlin_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GremlinResult {
+    /// What the traversal returned: vertices, edges, values, paths, ...
+    pub results: Vec<Value>,
+    /// Request units charged, when reported.
+    pub request_charge: Option<f64>,
+    /// Whether more than `max_results` results were returned.
+    pub truncated: bool,
+}
+
+/// Output of `gremlin_get_vertex`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GremlinVertex {
+    /// `{id, label, type, properties}`, or `null` when not found.
+    pub vertex: Option<Value>,
+    pub out_degree: Option<u64>,
+    pub in_degree: Option<u64>,
+    pub request_charge: Option<f64>,
+}
+
+/// Output of `gremlin_get_edges`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GremlinEdges {
+    /// `{id, label, type, inV, inVLabel, outV, outVLabel, properties}` each.
+    pub edges: Vec<Value>,
+    pub request_charge: Option<f64>,
+    pub truncated: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Cosmos DB Cassandra
+// ---------------------------------------------------------------------------
+
+/// Output of `cassandra_list_keyspaces`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyspaceList {
+    pub keyspaces: Vec<String>,
+}
+
+/// Output of `cassandra_list_tables`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CassandraTableList {
+    pub keyspace: String,
+    pub tables: Vec<CassandraTable>,
+}
+
+/// A Cassandra table with its columns, key columns first.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CassandraTable {
+    pub name: String,
+    pub columns: Vec<CassandraColumn>,
+}
+
+/// A column of a Cassandra table.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CassandraColumn {
+    pub name: String,
+    /// CQL type, e.g. `text` or `map<text, int>`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// `partition_key`, `clustering` or `regular`.
+    pub role: String,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Storage
+// ---------------------------------------------------------------------------
+
+/// Output of `blob_read`, `adls_read_file` and `files_read_file` (where
+/// `container` is the filesystem or share).
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BlobContent {
+    pub container: String,
+    pub path: String,
+    /// Offset of the first returned byte.
+    pub offset: u64,
+    /// Number of bytes returned.
+    pub length: u64,
+    /// Total size of the blob in bytes.
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// `text` or `base64`.
+    pub encoding: String,
+    pub content: String,
+    /// Offset to pass to read the rest; `null` at the end of the blob.
+    pub next_offset: Option<u64>,
+}
+
+/// Output of `files_list_shares`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FileShareList {
+    pub shares: Vec<FileShare>,
+}
+
+/// An Azure Files share.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FileShare {
+    pub name: String,
+    /// Provisioned size in GiB.
+    pub quota_gib: Option<u64>,
+    pub last_modified: Option<String>,
+}
+
+/// Output of `files_list_directory`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FileDirectoryListing {
+    pub share: String,
+    /// Directory listed; empty for the share root.
+    pub directory: String,
+    /// Subdirectories first, then files.
+    pub entries: Vec<FileEntry>,
+    /// Cursor for `fetch_next_page`; `null` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+/// A file or subdirectory of an Azure Files directory.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FileEntry {
+    /// Name within the listed directory.
+    pub name: String,
+    pub is_directory: bool,
+    /// Size in bytes; `null` for directories.
+    pub size: Option<u64>,
+}
+
+/// Output of `adls_list_paths`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LakePathList {
+    pub filesystem: String,
+    /// Directory listed; `null` for the filesystem root.
+    pub directory: Option<String>,
+    pub paths: Vec<LakePath>,
+    /// Cursor for `fetch_next_page`; `null` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+/// A file or directory of a Data Lake filesystem.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LakePath {
+    /// Path from the filesystem root.
+    pub name: String,
+    pub is_directory: bool,
+    /// Size in bytes; `null` for directories.
+    pub size: Option<u64>,
+    pub last_modified: Option<String>,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    /// POSIX permissions such as `rwxr-x---`.
+    pub permissions: Option<String>,
+}
+
+/// Output of `adls_get_properties`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct LakePathProperties {
+    pub filesystem: String,
+    pub path: String,
+    pub is_directory: bool,
+    /// Size in bytes; `null` for directories.
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub created: Option<String>,
+    /// User-defined properties (metadata).
+    pub properties: Map<String, Value>,
+    /// Whether the account has a hierarchical namespace; owner, group,
+    /// permissions and ACL are only reported when it does.
+    pub hierarchical_namespace: bool,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub permissions: Option<String>,
+    /// Access control list, e.g. `user::rwx,group::r-x,other::---`.
+    pub acl: Option<String>,
+}
+
+/// Output of `blob_write`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BlobWritten {
+    pub container: String,
+    pub path: String,
+    /// Size of the uploaded blob in bytes.
+    pub size: u64,
+    pub content_type: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Output of `blob_share_url`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BlobShareUrl {
+    pub container: String,
+    pub path: String,
+    /// Read-only HTTPS URL carrying a SAS token.
+    pub url: String,
+    /// When the URL stops working (RFC 3339, UTC).
+    pub expires: String,
+    pub size: Option<u64>,
+    pub content_type: Option<String>,
+}
+
+/// Output of `tables_list`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct StorageTableList {
+    /// Table names.
+    pub tables: Vec<String>,
+}
+
+/// Output of `tables_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EntitiesPage {
+    /// The entities of this page, with their `PartitionKey`, `RowKey`,
+    /// `Timestamp` and properties.
+    pub entities: Vec<Row>,
+    /// Cursor for `fetch_next_page`; `null` on the last page.
+    #[serde(rename = "nextCursor")]
+    pub next_cursor: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Service Bus
+// ---------------------------------------------------------------------------
+
+/// Output of `servicebus_send`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SentMessage {
+    pub entity: String,
+    pub message_id: Option<String>,
+    pub sent: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Event Grid
+// ---------------------------------------------------------------------------
+
+/// Output of `eventgrid_publish`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PublishedEvents {
+    pub topic: String,
+    pub published: usize,
+    /// Ids of the published events, in order.
+    pub ids: Vec<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Event Hubs
+// ---------------------------------------------------------------------------
+
+/// Output of `eventhub_read_events`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct EventBatch {
+    pub hub: String,
+    pub partition: i32,
+    /// Offset of the oldest event the partition retains.
+    pub first_offset: i64,
+    /// Offset the next event written to the partition will get.
+    pub end_offset: i64,
+    /// The events read, oldest first.
+    pub events: Vec<HubEvent>,
+    /// Offset to pass as `starting_position` to read on; `null` when the
+    /// newest event was reached.
+    pub next_offset: Option<i64>,
+}
+
+/// One event read by `eventhub_read_events`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HubEvent {
+    pub partition: i32,
+    pub offset: i64,
+    pub enqueued_time: Option<String>,
+    /// Partition key, if the producer set one.
+    pub key: Option<String>,
+    /// Application properties.
+    pub headers: Map<String, Value>,
+    /// `text` or `base64`.
+    pub encoding: String,
+    pub body: String,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Monitor
+// ---------------------------------------------------------------------------
+
+/// Output of `loganalytics_query`, `appinsights_query` and `kusto_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KqlResult {
+    /// Columns of the primary result table, with their KQL types.
+    pub columns: Vec<KqlColumn>,
+    /// Rows of the primary result table, keyed by column name.
+    pub rows: Vec<Row>,
+    /// Whether rows were dropped to honour `max_rows`.
+    pub truncated: bool,
+    /// Set when the service returned partial results with an error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial_error: Option<String>,
+}
+
+/// A result column.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KqlColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+/// Output of `kusto_list_tables`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KustoTableList {
+    pub database: String,
+    pub tables: Vec<KustoTable>,
+}
+
+/// A table of a Kusto database.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KustoTable {
+    pub name: String,
+    /// Folder the table is organised under, if any.
+    pub folder: Option<String>,
+    /// The table's docstring, if any.
+    pub description: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Cost Management
+// ---------------------------------------------------------------------------
+
+/// Output of `cost_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CostReport {
+    pub scope: String,
+    /// First day of the period.
+    pub from: String,
+    /// Last day of the period.
+    pub to: String,
+    pub currency: Option<String>,
+    /// Sum of the `Cost` column over the returned rows.
+    pub total_cost: f64,
+    /// Result columns with their types, e.g. `Cost`, `UsageDate`,
+    /// `ServiceName`, `Currency`.
+    pub columns: Vec<Value>,
+    /// Rows keyed by column name.
+    pub rows: Vec<Row>,
+    /// Whether rows were dropped after the first 5 000.
+    pub truncated: bool,
+}
+
+// ---------------------------------------------------------------------------
+// Azure Key Vault
+// ---------------------------------------------------------------------------
+
+/// Output of `keyvault_list_secrets`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct KeyVaultSecrets {
+    pub vault: String,
+    /// Secrets ordered by name.
+    pub secrets: Vec<SecretAttributes>,
+}
+
+/// Attributes of a secret or secret version; never its value.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SecretAttributes {
+    /// Secret name (in listings) ...
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// ... or version id (in a secret's versions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub enabled: Option<bool>,
+    pub content_type: Option<String>,
+    pub tags: Option<Map<String, Value>>,
+    pub created: Option<String>,
+    pub updated: Option<String>,
+    pub expires: Option<String>,
+    pub not_before: Option<String>,
+    /// Whether the secret backs a Key Vault certificate.
+    pub managed: bool,
+}
+
+/// Output of `keyvault_get_secret_metadata`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SecretMetadata {
+    pub name: String,
+    /// Every version, most recently updated first.
+    pub versions: Vec<SecretAttributes>,
+    /// Version the value was read from; only set with `include_value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_version: Option<String>,
+    /// The secret value; `null` unless requested and allowed.
+    pub value: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Azure App Configuration
+// ---------------------------------------------------------------------------
+
+/// Output of `appconfig_list_keys` and `appconfig_get_values`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AppConfigSettings {
+    pub store: String,
+    pub settings: Vec<AppConfigSetting>,
+    /// Whether more settings matched than were returned.
+    pub truncated: bool,
+}
+
+/// A key-value of an App Configuration store.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AppConfigSetting {
+    pub key: String,
+    /// `null` for settings without a label.
+    pub label: Option<String>,
+    /// The value; only returned by `appconfig_get_values`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub content_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Map<String, Value>>,
+    pub last_modified: Option<String>,
+    pub locked: Option<bool>,
+}
+
+/// Output of `appconfig_list_feature_flags`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FeatureFlagList {
+    pub store: String,
+    pub flags: Vec<FeatureFlag>,
+    /// Whether more flags matched than were returned.
+    pub truncated: bool,
+}
+
+/// A feature flag under one label.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub label: Option<String>,
+    pub enabled: bool,
+    pub description: Option<String>,
+    /// Client filters (targeting, time windows, ...) deciding who sees the
+    /// flag when it is enabled.
+    pub conditions: Option<Value>,
+    pub last_modified: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Azure OpenAI
+// ---------------------------------------------------------------------------
+
+/// Output of `embed_text`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Embedding {
+    pub deployment: String,
+    /// Model behind the deployment, as reported by the service.
+    pub model: Option<String>,
+    pub dimensions: usize,
+    pub embedding: Vec<f64>,
+    pub prompt_tokens: Option<u64>,
+}
+
+// ---------------------------------------------------------------------------
+// Query drafting
+// ---------------------------------------------------------------------------
+
+/// Output of `generate_query`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct GeneratedQuery {
+    /// The drafted query, checked to be a single read-only statement.
+    pub query: String,
+    /// `tsql` or `cosmos_sql`.
+    pub dialect: String,
+    /// The tool that runs the query.
+    pub run_with: String,
+    /// The model that drafted it, as reported by the client.
+    pub model: String,
+}
+
+// ---------------------------------------------------------------------------
+// Server
+// ---------------------------------------------------------------------------
+
+/// How one connection protects data in transit.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectionSecurity {
+    /// The backend, e.g. `mssql` or `cosmos`.
+    pub backend: String,
+    /// The server or endpoint, without credentials.
+    pub target: String,
+    pub encrypted: bool,
+    /// Whether the server certificate is validated against trusted roots.
+    pub certificate_validated: bool,
+    /// What the connection negotiated: `encrypt_option`, `net_transport`,
+    /// `protocol_type` and `auth_scheme` for SQL, `tls_version` and
+    /// `cipher_suite` for HTTPS.
+    pub negotiated: Map<String, Value>,
+    pub ok: bool,
+    /// What leaves data in transit unprotected, or could not be verified.
+    pub issues: Vec<String>,
+}
+
+/// Output of `server_connection_security`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConnectionSecurityReport {
+    /// Whether every connection is encrypted and validated.
+    pub ok: bool,
+    pub connections: Vec<ConnectionSecurity>,
+}
+
+/// Output of `server_status`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ServerStatus {
+    pub version: String,
+    /// The effective configuration per backend (`null` when not
+    /// configured), with secrets reduced to whether they are set.
+    pub configuration: Map<String, Value>,
+    pub backends: Vec<BackendStatus>,
+    /// Tools not offered, with the reason.
+    pub hidden_tools: Vec<HiddenTool>,
+    /// Flags that refuse tools, or options of them, unless set.
+    pub policies: Vec<ToolPolicy>,
+    pub limits: Limits,
+}
+
+/// Whether a backend is configured and reachable.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BackendStatus {
+    pub name: String,
+    pub configured: bool,
+    /// `false` when not configured or the last probe failed.
+    pub available: bool,
+    /// The environment variables that configure it.
+    pub settings: String,
+}
+
+/// A tool left out of `tools/list`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct HiddenTool {
+    pub tool: String,
+    /// `None` for backend-independent tools.
+    pub backend: Option<String>,
+    pub reason: String,
+}
+
+/// A flag that refuses tools unless set.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolPolicy {
+    /// The environment variable, e.g. `COSMOS_ALLOW_WRITES`.
+    pub flag: String,
+    pub enabled: bool,
+    pub tools: Vec<String>,
+    /// What happens while the flag is not set.
+    pub effect: String,
+}
+
+/// The limits applied to tool calls.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct Limits {
+    /// Rows returned by SQL queries when `max_rows` is not given.
+    pub sql_default_max_rows: u64,
+    /// Documents returned by Cosmos DB queries when `max_items` is not given.
+    pub cosmos_default_max_items: u32,
+    /// `COSMOS_MAX_REQUEST_UNITS`.
+    pub cosmos_max_request_units: Option<f64>,
+    /// `COSMOS_SESSION_MAX_REQUEST_UNITS`.
+    pub cosmos_session_max_request_units: Option<f64>,
+    /// `MSSQL_MAX_ESTIMATED_ROWS`.
+    pub mssql_max_estimated_rows: Option<f64>,
+    /// `MSSQL_MAX_ESTIMATED_COST`.
+    pub mssql_max_estimated_cost: Option<f64>,
+    /// `MAX_CELL_CHARS`.
+    pub max_cell_chars: Option<usize>,
+    /// `SLOW_QUERY_MS`.
+    pub slow_query_ms: Option<u64>,
+    /// `RETRY_MAX_ATTEMPTS`: attempts per SQL or Cosmos DB call, the first
+    /// included.
+    pub retry_max_attempts: u32,
+    /// `RETRY_MAX_WAIT_MS`.
+    pub retry_max_wait_ms: u64,
+    /// `MAX_RESULT_BYTES`: approximate size at which SQL rows and Cosmos DB
+    /// items stop being collected.
+    pub max_result_bytes: usize,
+    /// `SESSION_MAX_QUERIES`.
+    pub session_max_queries: Option<u64>,
+    /// `SESSION_MAX_ROWS`.
+    pub session_max_rows: Option<u64>,
+    /// `SESSION_MAX_RESULT_BYTES`.
+    pub session_max_result_bytes: Option<u64>,
+    /// Calls allowed to run at once, per configured backend with a
+    /// `MAX_CONCURRENT_QUERIES` limit.
+    pub max_concurrent_queries: BTreeMap<String, usize>,
+    /// Results larger than this are returned as a preview and a
+    /// `result://` link.
+    pub max_inline_result_bytes: usize,
+    /// How long a `result://` resource can be read.
+    pub stored_result_ttl_seconds: u64,
+}
+
+/// Output of `server_stats`.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ServerStats {
+    /// The MCP session this server serves.
+    pub session: String,
+    pub uptime_seconds: u64,
+    /// When the server started (RFC 3339).
+    pub started_at: String,
+    /// Tool calls running now, this one included.
+    pub in_flight: usize,
+    /// Most tool calls that have run at once.
+    pub peak_in_flight: usize,
+    /// Tool calls finished since the server started.
+    pub calls: u64,
+    /// Finished tool calls that failed.
+    pub errors: u64,
+    /// Counts per tool, for every tool called so far.
+    pub tools: BTreeMap<String, ToolStats>,
+    /// Whether each configured backend is currently available.
+    pub backends: BTreeMap<String, bool>,
+    /// Whether each shared client (`cosmos`, `storage`, `servicebus`,
+    /// `tokens`) has been built.  SQL backends open a connection per call.
+    pub clients: BTreeMap<String, bool>,
+    /// Azure AD `tokens`, completion `catalog` names and stored `results`.
+    pub caches: BTreeMap<String, CacheStats>,
+    pub request_units: RequestUnitStats,
+    /// Queries, records and bytes returned per MCP session, counted against
+    /// the `SESSION_MAX_*` quotas.
+    pub usage: BTreeMap<String, SessionUsageStats>,
+    /// Calls running and waiting per backend with a
+    /// `MAX_CONCURRENT_QUERIES` limit.
+    pub concurrency: BTreeMap<String, ConcurrencyStats>,
+}
+
+/// Calls of one backend with a concurrency limit.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ConcurrencyStats {
+    pub limit: usize,
+    /// Calls running now.
+    pub running: usize,
+    /// Calls waiting for one of the running calls to finish.
+    pub waiting: usize,
+}
+
+/// Cosmos DB request units charged since the server started.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RequestUnitStats {
+    pub total: f64,
+    /// Request units charged per MCP session.
+    pub sessions: BTreeMap<String, f64>,
+    /// `COSMOS_SESSION_MAX_REQUEST_UNITS`, if set.
+    pub session_limit: Option<f64>,
+}
+
+/// What one MCP session has used.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct SessionUsageStats {
+    /// Calls of tools reaching a backend, `fetch_next_page` included.
+    pub queries: u64,
+    /// Rows, documents and other records returned.
+    pub rows: u64,
+    /// Approximate bytes of JSON returned.
+    pub bytes: u64,
+}
+
+/// Calls of one tool.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// `errors / calls`.
+    pub error_rate: Option<f64>,
+    /// Mean duration in milliseconds.
+    pub mean_ms: u64,
+    pub max_ms: u64,
+    /// Cosmos DB request units charged.
+    pub request_units: f64,
+}
+
+/// Lookups of one cache.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// `hits / (hits + misses)`; `null` before the first lookup.
+    pub hit_rate: Option<f64>,
+    /// Entries currently held; only reported for `results`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entries: Option<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_keeps_optional_fields_absent() {
+        let result = serde_json::json!({
+            "succeeded": 1,
+            "failed": 1,
+            "request_charge": 10.5,
+            "results": [
+                { "index": 0, "status": 201, "id": "a" },
+                { "index": 1, "error": "write failed" },
+            ],
+        });
+        let Json(report) = structured::<ImportReport>(Ok(result.clone())).unwrap();
+        assert_eq!(serde_json::to_value(report).unwrap(), result);
+    }
+
+    #[test]
+    fn structured_rejects_unexpected_shapes() {
+        let err = structured::<TableList>(Ok(serde_json::json!([])))
+            .err()
+            .unwrap();
+        assert!(err.starts_with("Unexpected result shape"), "{err}");
+        let err = structured::<TableList>(Err(anyhow::anyhow!("boom")))
+            .err()
+            .unwrap();
+        assert_eq!(err, "boom");
+    }
+}
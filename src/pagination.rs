@@ -0,0 +1,613 @@
+use std::sync::LazyLock;
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::aad::TokenProvider;
+use crate::adls;
+use crate::cassandra;
+use crate::config::{CassandraConfig, Config, MssqlConfig};
+use crate::cosmos::{self, CosmosAccount, QueryResume, QuerySettings};
+use crate::files;
+use crate::mssql;
+use crate::storage::StorageClient;
+use crate::tables::{self, TableResume};
+
+/// The key cursors are signed with, drawn when the process starts.
+static CURSOR_KEY: LazyLock<Vec<u8>> = LazyLock::new(|| {
+    [uuid::Uuid::new_v4(), uuid::Uuid::new_v4()]
+        .iter()
+        .flat_map(|id| id.into_bytes())
+        .collect()
+});
+
+/// Position of the next page of a query result, serialised into the opaque
+/// `nextCursor` handed back to callers and accepted by `fetch_next_page`.
+///
+/// Cursors are self-contained: the server keeps no state between pages.
+/// They carry the query they continue, so they are signed: a cursor the
+/// client altered, or one issued by another server process, is refused
+/// rather than running a query the originating tool never checked.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+enum Cursor {
+    /// The rows of an MSSQL query after the first `offset`.
+    Mssql {
+        query: String,
+        offset: u64,
+        max_rows: u64,
+    },
+    /// The rows of a Synapse serverless SQL query after the first `offset`.
+    Synapse {
+        query: String,
+        offset: u64,
+        max_rows: u64,
+    },
+    /// The rows of a Fabric SQL endpoint query after the first `offset`.
+    Fabric {
+        query: String,
+        offset: u64,
+        max_rows: u64,
+    },
+    /// The rows of a Cassandra query from the base64 `paging_state`.
+    Cassandra {
+        keyspace: Option<String>,
+        query: String,
+        max_rows: u64,
+        paging_state: String,
+    },
+    /// The items of a Cosmos DB query after `resume`.
+    Cosmos {
+        database: String,
+        container: String,
+        query: String,
+        partition_key: Option<String>,
+        settings: QuerySettings,
+        resume: QueryResume,
+    },
+    /// The paths of a Data Lake listing after `continuation`.
+    Adls {
+        filesystem: String,
+        directory: Option<String>,
+        recursive: bool,
+        max_results: u32,
+        continuation: String,
+    },
+    /// The entries of an Azure Files directory listing after `marker`.
+    Files {
+        share: String,
+        directory: String,
+        max_results: u32,
+        marker: String,
+    },
+    /// The entities of a Table Storage query after `resume`.
+    Table {
+        table: String,
+        filter: Option<String>,
+        select: Vec<String>,
+        top: u32,
+        resume: TableResume,
+    },
+}
+
+impl Cursor {
+    fn mac() -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(&CURSOR_KEY).expect("HMAC accepts keys of any length")
+    }
+
+    fn decode(cursor: &str) -> Result<Self> {
+        let (payload, signature) = cursor.trim().split_once('.').context("Invalid cursor")?;
+        let bytes = BASE64.decode(payload).context("Invalid cursor")?;
+        let signature = BASE64.decode(signature).context("Invalid cursor")?;
+        let mut mac = Self::mac();
+        mac.update(&bytes);
+        mac.verify_slice(&signature).map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid cursor: it was altered, or issued before the server restarted; \
+                 run the query again"
+            )
+        })?;
+        serde_json::from_slice(&bytes).context("Invalid cursor")
+    }
+
+    /// `base64(JSON).base64(HMAC-SHA256)`.
+    fn encode(&self) -> String {
+        let bytes = serde_json::to_vec(self).unwrap_or_default();
+        let mut mac = Self::mac();
+        mac.update(&bytes);
+        format!(
+            "{}.{}",
+            BASE64.encode(&bytes),
+            BASE64.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    /// The tool whose results the cursor continues.
+    fn tool(&self) -> &'static str {
+        match self {
+            Cursor::Mssql { .. } => "mssql_execute_query",
+            Cursor::Synapse { .. } => "synapse_execute_query",
+            Cursor::Fabric { .. } => "fabric_execute_query",
+            Cursor::Cassandra { .. } => "cassandra_query",
+            Cursor::Cosmos { .. } => "cosmos_query_items",
+            Cursor::Adls { .. } => "adls_list_paths",
+            Cursor::Files { .. } => "files_list_directory",
+            Cursor::Table { .. } => "tables_query",
+        }
+    }
+}
+
+/// A signed cursor for the rows of the MSSQL `query` after the first 500.
+#[cfg(test)]
+pub fn mssql_cursor(query: &str) -> String {
+    Cursor::Mssql {
+        query: query.to_string(),
+        offset: 500,
+        max_rows: 500,
+    }
+    .encode()
+}
+
+/// Run one page of an MSSQL query: up to `max_rows` rows after the first
+/// `offset`.
+///
+/// Returns `{"rows": [...], "nextCursor": ...}`, where `nextCursor` is `null`
+/// on the last page.
+pub async fn mssql_page(
+    cfg: &MssqlConfig,
+    query: &str,
+    offset: u64,
+    max_rows: u64,
+) -> Result<Value> {
+    sql_page(cfg, query, offset, max_rows, |query, offset, max_rows| {
+        Cursor::Mssql {
+            query,
+            offset,
+            max_rows,
+        }
+    })
+    .await
+}
+
+/// Like [`mssql_page`], for the Synapse serverless SQL endpoint.
+pub async fn synapse_page(
+    cfg: &MssqlConfig,
+    query: &str,
+    offset: u64,
+    max_rows: u64,
+) -> Result<Value> {
+    sql_page(cfg, query, offset, max_rows, |query, offset, max_rows| {
+        Cursor::Synapse {
+            query,
+            offset,
+            max_rows,
+        }
+    })
+    .await
+}
+
+/// Like [`mssql_page`], for a Fabric SQL endpoint (`cfg` carrying the access
+/// token).
+pub async fn fabric_page(
+    cfg: &MssqlConfig,
+    query: &str,
+    offset: u64,
+    max_rows: u64,
+) -> Result<Value> {
+    sql_page(cfg, query, offset, max_rows, |query, offset, max_rows| {
+        Cursor::Fabric {
+            query,
+            offset,
+            max_rows,
+        }
+    })
+    .await
+}
+
+/// Run one page of a TDS query, building the next page's cursor from the
+/// query, the next offset and `max_rows` with `cursor`.
+async fn sql_page(
+    cfg: &MssqlConfig,
+    query: &str,
+    offset: u64,
+    max_rows: u64,
+    cursor: impl FnOnce(String, u64, u64) -> Cursor,
+) -> Result<Value> {
+    let page = mssql::execute_query_page(cfg, query, offset, max_rows).await?;
+    let next = page
+        .more
+        .then(|| cursor(query.to_string(), offset + page.rows.len() as u64, max_rows).encode());
+    let mut result = serde_json::json!({
+        "columns": page.columns,
+        "memory_budget_exhausted": page.memory_budget_exhausted.then_some(true),
+        "nextCursor": next,
+    });
+    // `json!` would serialize a copy of every row; move them in instead.
+    result["rows"] = Value::Array(page.rows);
+    Ok(result)
+}
+
+/// Run one page of a Cassandra query (see
+/// [`cassandra::execute_query_page`]), continuing from `paging_state` when
+/// given.
+///
+/// Returns `{"rows": [...], "nextCursor": ...}`, where `nextCursor` is `null`
+/// on the last page.
+pub async fn cassandra_page(
+    cfg: &CassandraConfig,
+    keyspace: Option<&str>,
+    query: &str,
+    max_rows: u64,
+    paging_state: Option<&[u8]>,
+) -> Result<Value> {
+    let (rows, paging_state) =
+        cassandra::execute_query_page(cfg, keyspace, query, max_rows, paging_state).await?;
+    let next = paging_state.map(|state| {
+        Cursor::Cassandra {
+            keyspace: keyspace.map(str::to_string),
+            query: query.to_string(),
+            max_rows,
+            paging_state: BASE64.encode(state),
+        }
+        .encode()
+    });
+    Ok(serde_json::json!({ "rows": rows, "nextCursor": next }))
+}
+
+/// Run one page of a Cosmos DB query (see [`cosmos::query_items`]), starting
+/// from `resume` when given.
+///
+/// Returns `{"items": [...], "metadata": {...}, "nextCursor": ...}`, where
+/// `nextCursor` is `null` once the query is exhausted.
+pub async fn cosmos_page(
+    account: &CosmosAccount,
+    database: &str,
+    container: &str,
+    query: &str,
+    partition_key: Option<&str>,
+    settings: QuerySettings,
+    resume: Option<QueryResume>,
+) -> Result<Value> {
+    let (mut result, resume) = cosmos::query_items(
+        account,
+        database,
+        container,
+        query,
+        partition_key,
+        settings,
+        resume,
+    )
+    .await?;
+    let next = resume.map(|resume| {
+        Cursor::Cosmos {
+            database: database.to_string(),
+            container: container.to_string(),
+            query: query.to_string(),
+            partition_key: partition_key.map(str::to_string),
+            settings,
+            resume,
+        }
+        .encode()
+    });
+    result["nextCursor"] = next.into();
+    Ok(result)
+}
+
+/// Run one page of a Table Storage query (see [`tables::query_entities`]),
+/// starting from `resume` when given.
+///
+/// Returns `{"entities": [...], "nextCursor": ...}`, where `nextCursor` is
+/// `null` once no more entities match.
+pub async fn table_page(
+    client: &StorageClient,
+    table: &str,
+    filter: Option<&str>,
+    select: &[String],
+    top: u32,
+    resume: Option<&TableResume>,
+) -> Result<Value> {
+    let (entities, resume) =
+        tables::query_entities(client, table, filter, select, top, resume).await?;
+    let next = resume.map(|resume| {
+        Cursor::Table {
+            table: table.to_string(),
+            filter: filter.map(str::to_string),
+            select: select.to_vec(),
+            top,
+            resume,
+        }
+        .encode()
+    });
+    Ok(serde_json::json!({ "entities": entities, "nextCursor": next }))
+}
+
+/// List one page of Data Lake paths (see [`adls::list_paths`]), starting
+/// from `continuation` when given.
+///
+/// Returns `{"filesystem": ..., "paths": [...], "nextCursor": ...}`, where
+/// `nextCursor` is `null` once every path was listed.
+pub async fn adls_page(
+    client: &StorageClient,
+    filesystem: &str,
+    directory: Option<&str>,
+    recursive: bool,
+    max_results: u32,
+    continuation: Option<&str>,
+) -> Result<Value> {
+    let (paths, continuation) = adls::list_paths(
+        client,
+        filesystem,
+        directory,
+        recursive,
+        max_results,
+        continuation,
+    )
+    .await?;
+    let next = continuation.map(|continuation| {
+        Cursor::Adls {
+            filesystem: filesystem.to_string(),
+            directory: directory.map(str::to_string),
+            recursive,
+            max_results,
+            continuation,
+        }
+        .encode()
+    });
+    Ok(serde_json::json!({
+        "filesystem": filesystem,
+        "directory": directory,
+        "paths": paths,
+        "nextCursor": next,
+    }))
+}
+
+/// List one page of an Azure Files directory (see
+/// [`files::list_directory`]), starting from `marker` when given.
+///
+/// Returns `{"share": ..., "directory": ..., "entries": [...], "nextCursor":
+/// ...}`, where `nextCursor` is `null` once every entry was listed.
+pub async fn files_page(
+    client: &StorageClient,
+    share: &str,
+    directory: &str,
+    max_results: u32,
+    marker: Option<&str>,
+) -> Result<Value> {
+    let (entries, marker) =
+        files::list_directory(client, share, directory, max_results, marker).await?;
+    let next = marker.map(|marker| {
+        Cursor::Files {
+            share: share.to_string(),
+            directory: directory.to_string(),
+            max_results,
+            marker,
+        }
+        .encode()
+    });
+    Ok(serde_json::json!({
+        "share": share,
+        "directory": directory,
+        "entries": entries,
+        "nextCursor": next,
+    }))
+}
+
+/// Fetch the page a `nextCursor` points to, from whichever backend issued it.
+pub async fn fetch_next_page(
+    config: &Config,
+    tokens: Result<&TokenProvider, String>,
+    cosmos: Result<&CosmosAccount, String>,
+    storage: Result<&StorageClient, String>,
+    cursor: &str,
+) -> Result<Value> {
+    let cursor = Cursor::decode(cursor)?;
+    // A role that may page may still not run the query itself.
+    anyhow::ensure!(
+        config.role.allows(cursor.tool()),
+        "This cursor continues {}, which the {} role does not allow (MCP_ROLE)",
+        cursor.tool(),
+        config.role.name
+    );
+    match cursor {
+        Cursor::Mssql {
+            query,
+            offset,
+            max_rows,
+        } => mssql_page(config.require_mssql()?, &query, offset, max_rows).await,
+        Cursor::Synapse {
+            query,
+            offset,
+            max_rows,
+        } => synapse_page(config.require_synapse()?, &query, offset, max_rows).await,
+        Cursor::Fabric {
+            query,
+            offset,
+            max_rows,
+        } => {
+            let token = tokens
+                .map_err(anyhow::Error::msg)?
+                .token(mssql::SQL_RESOURCE)
+                .await?;
+            let cfg = config.require_fabric()?.connection(token);
+            fabric_page(&cfg, &query, offset, max_rows).await
+        }
+        Cursor::Cassandra {
+            keyspace,
+            query,
+            max_rows,
+            paging_state,
+        } => {
+            let paging_state = BASE64.decode(paging_state).context("Invalid cursor")?;
+            cassandra_page(
+                config.require_cassandra()?,
+                keyspace.as_deref(),
+                &query,
+                max_rows,
+                Some(&paging_state),
+            )
+            .await
+        }
+        Cursor::Cosmos {
+            database,
+            container,
+            query,
+            partition_key,
+            settings,
+            resume,
+        } => {
+            // Re-apply the server's RU limit in case it was lowered since the
+            // cursor was issued.
+            let settings = QuerySettings {
+                max_request_units: config
+                    .require_cosmos()?
+                    .request_unit_budget(settings.max_request_units),
+                ..settings
+            };
+            let account = cosmos.map_err(anyhow::Error::msg)?;
+            cosmos_page(
+                account,
+                &database,
+                &container,
+                &query,
+                partition_key.as_deref(),
+                settings,
+                Some(resume),
+            )
+            .await
+        }
+        Cursor::Adls {
+            filesystem,
+            directory,
+            recursive,
+            max_results,
+            continuation,
+        } => {
+            let client = storage.map_err(anyhow::Error::msg)?;
+            adls_page(
+                client,
+                &filesystem,
+                directory.as_deref(),
+                recursive,
+                max_results,
+                Some(&continuation),
+            )
+            .await
+        }
+        Cursor::Files {
+            share,
+            directory,
+            max_results,
+            marker,
+        } => {
+            let client = storage.map_err(anyhow::Error::msg)?;
+            files_page(client, &share, &directory, max_results, Some(&marker)).await
+        }
+        Cursor::Table {
+            table,
+            filter,
+            select,
+            top,
+            resume,
+        } => {
+            let client = storage.map_err(anyhow::Error::msg)?;
+            table_page(
+                client,
+                &table,
+                filter.as_deref(),
+                &select,
+                top,
+                Some(&resume),
+            )
+            .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor::Cosmos {
+            database: "shop".into(),
+            container: "orders".into(),
+            query: "SELECT * FROM c".into(),
+            partition_key: None,
+            settings: QuerySettings {
+                max_items: 100,
+                include_metrics: false,
+                max_request_units: Some(50.0),
+                consistency: None,
+            },
+            resume: QueryResume {
+                ranges: vec!["1".into(), "2".into()],
+                continuation: Some("token".into()),
+                remaining_top: None,
+            },
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let cursor = Cursor::Mssql {
+            query: "SELECT 1".into(),
+            offset: 500,
+            max_rows: 500,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let cursor = Cursor::Synapse {
+            query: "SELECT * FROM OPENROWSET(BULK 'raw/*.parquet') AS r".into(),
+            offset: 100,
+            max_rows: 100,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let cursor = Cursor::Fabric {
+            query: "SELECT * FROM dbo.sales".into(),
+            offset: 500,
+            max_rows: 500,
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let cursor = Cursor::Table {
+            table: "orders".into(),
+            filter: Some("PartitionKey eq 'eu'".into()),
+            select: vec!["RowKey".into()],
+            top: 100,
+            resume: TableResume {
+                partition_key: "1!8!ZXU-".into(),
+                row_key: Some("1!12!MDAwNDI-".into()),
+            },
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+
+        let cursor = Cursor::Adls {
+            filesystem: "lake".into(),
+            directory: Some("raw/sales".into()),
+            recursive: true,
+            max_results: 500,
+            continuation: "VBbzu86Yu".into(),
+        };
+        assert_eq!(Cursor::decode(&cursor.encode()).unwrap(), cursor);
+        assert!(Cursor::decode("not a cursor").is_err());
+
+        // Editing the query invalidates the signature.
+        let cursor = Cursor::Mssql {
+            query: "SELECT * FROM dbo.orders".into(),
+            offset: 500,
+            max_rows: 500,
+        }
+        .encode();
+        let (payload, signature) = cursor.split_once('.').unwrap();
+        let edited = String::from_utf8(BASE64.decode(payload).unwrap())
+            .unwrap()
+            .replace("dbo.orders", "dbo.users");
+        let forged = format!("{}.{signature}", BASE64.encode(edited));
+        assert!(Cursor::decode(&forged).is_err());
+        assert!(Cursor::decode(payload).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::config::{Config, MssqlConfig};
+use crate::cosmos::{self, CosmosAccount};
+use crate::mssql;
+
+/// Maximum number of tables whose columns are inlined by [`explain_schema`],
+/// to keep the prompt within a reasonable context size.
+const MAX_EXPLAINED_TABLES: usize = 25;
+
+/// Render `value` as a fenced JSON block for inclusion in a prompt.
+fn json_block(value: &Value) -> String {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+    format!("```json\n{pretty}\n```")
+}
+
+/// Build the "profile this table" prompt text from a table's column
+/// description (as returned by [`mssql::describe_table`]).
+fn profile_table_text(schema: &str, table: &str, description: &Value) -> String {
+    format!(
+        "Profile the MSSQL table [{schema}].[{table}].\n\n\
+         Its columns are:\n\n{}\n\n\
+         Using the mssql_execute_query tool, report:\n\
+         - the total row count;\n\
+         - for every column, the share of NULLs and the number of distinct values;\n\
+         - minimum, maximum and average of numeric columns, and the range of date columns;\n\
+         - the most frequent values of low-cardinality text columns.\n\
+         Prefer a few aggregate queries over many small ones, then summarise data quality \
+         issues (unexpected NULLs, outliers, suspicious defaults).",
+        json_block(description)
+    )
+}
+
+/// "Profile this table": column context for `schema.table` plus instructions
+/// for computing a data profile with the query tool.
+pub async fn profile_table(cfg: &MssqlConfig, schema: &str, table: &str) -> Result<String> {
+    let description = mssql::describe_table(cfg, schema, table).await?;
+    Ok(profile_table_text(schema, table, &description))
+}
+
+/// "Explain this schema": the columns of every table (optionally only those
+/// in `schema`), with a request to explain the data model.
+pub async fn explain_schema(cfg: &MssqlConfig, schema: Option<&str>) -> Result<String> {
+    let tables = mssql::list_tables(cfg).await?;
+    let tables: Vec<(String, String)> = tables
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|t| {
+            Some((
+                t["schema"].as_str()?.to_string(),
+                t["table_name"].as_str()?.to_string(),
+            ))
+        })
+        .filter(|(s, _)| schema.is_none_or(|wanted| s.eq_ignore_ascii_case(wanted)))
+        .collect();
+
+    let mut described = Vec::new();
+    for (schema, table) in tables.iter().take(MAX_EXPLAINED_TABLES) {
+        described.push(mssql::describe_table(cfg, schema, table).await?);
+    }
+    let omitted = tables.len().saturating_sub(MAX_EXPLAINED_TABLES);
+    let note = if omitted > 0 {
+        format!(
+            "\n\n{omitted} further tables were omitted; use the mssql://schema/... resources \
+             to inspect them."
+        )
+    } else {
+        String::new()
+    };
+
+    Ok(format!(
+        "Explain the following MSSQL schema: what each table represents, how the tables \
+         relate to each other (infer foreign keys from column names and primary keys), and \
+         which tables hold the core business entities.\n\n{}{note}",
+        json_block(&Value::Array(described))
+    ))
+}
+
+/// What a [`write_query`] prompt should be written against.
+pub enum QueryTarget<'a> {
+    /// An MSSQL table, `schema.table`.
+    Table { schema: &'a str, table: &'a str },
+    /// A Cosmos DB container.
+    Container {
+        database: &'a str,
+        container: &'a str,
+    },
+    /// No specific target: list what is available.
+    Any,
+}
+
+/// "Write a query for…": the schema context of `target` plus the user's
+/// `question`, asking for a query and for it to be run with the right tool.
+pub async fn write_query(
+    config: &Config,
+    cosmos: Result<&CosmosAccount, String>,
+    question: &str,
+    target: QueryTarget<'_>,
+) -> Result<String> {
+    let context = match target {
+        QueryTarget::Table { schema, table } => {
+            let cfg = config.require_mssql()?;
+            format!(
+                "Write a T-SQL query for MSSQL and run it with mssql_execute_query.  \
+                 The table [{schema}].[{table}] has these columns:\n\n{}",
+                json_block(&mssql::describe_table(cfg, schema, table).await?)
+            )
+        }
+        QueryTarget::Container {
+            database,
+            container,
+        } => {
+            let account = cosmos.map_err(anyhow::Error::msg)?;
+            format!(
+                "Write a Cosmos DB SQL query and run it with cosmos_query_items.  Supply the \
+                 partition key when the question concerns a single partition.  The container \
+                 {database}/{container} looks like this:\n\n{}",
+                json_block(&cosmos::describe_container(account, database, container).await?)
+            )
+        }
+        QueryTarget::Any => {
+            let mut available = serde_json::Map::new();
+            if let Some(cfg) = &config.mssql {
+                available.insert("mssql_tables".into(), mssql::list_tables(cfg).await?);
+            }
+            if config.cosmos.is_some() {
+                let account = cosmos.map_err(anyhow::Error::msg)?;
+                let containers = cosmos::all_containers(account)
+                    .await?
+                    .into_iter()
+                    .map(|(database, container)| format!("{database}/{container}"))
+                    .collect::<Vec<_>>();
+                available.insert("cosmos_containers".into(), containers.into());
+            }
+            format!(
+                "Pick the most relevant data source, inspect its schema (mssql://schema/... or \
+                 cosmos://... resources), then write and run a query with the matching tool.  \
+                 Available data:\n\n{}",
+                json_block(&Value::Object(available))
+            )
+        }
+    };
+
+    Ok(format!("{context}\n\nQuestion: {question}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_table_text_embeds_columns() {
+        let description = serde_json::json!({
+            "schema": "dbo",
+            "table": "orders",
+            "columns": [{ "name": "total", "data_type": "decimal" }],
+        });
+        let text = profile_table_text("dbo", "orders", &description);
+        assert!(text.starts_with("Profile the MSSQL table [dbo].[orders]."));
+        assert!(text.contains("\"data_type\": \"decimal\""));
+        assert!(text.contains("mssql_execute_query"));
+    }
+}
@@ -0,0 +1,368 @@
+use std::ops::ControlFlow;
+
+use anyhow::{anyhow, bail, Result};
+use sqlparser::ast::{ObjectName, Query, SetExpr, Statement, Visit, Visitor};
+use sqlparser::dialect::MsSqlDialect;
+use sqlparser::parser::Parser;
+
+use crate::config::CosmosQueryPolicy;
+
+/// Query language a statement is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// T-SQL, as run by `mssql_execute_query`, `synapse_execute_query` and
+    /// `fabric_execute_query`.
+    TSql,
+    /// Cosmos DB SQL API, as run by `cosmos_query_items`.
+    CosmosSql,
+    /// CQL, as run by `cassandra_query`.
+    Cql,
+}
+
+/// Rowset functions that read from other servers or from files.  A query
+/// using them only reads, but reaches past the configured database.
+const EXTERNAL_ROWSETS: &[&str] = &["OPENDATASOURCE", "OPENQUERY", "OPENROWSET"];
+
+/// The first word of a statement, e.g. `UPDATE`.
+fn keyword(statement: &Statement) -> String {
+    let text = statement.to_string();
+    text.split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Looks through a parsed T-SQL query, its subqueries and common table
+/// expressions for what makes it more than a read of the database.
+struct WriteFinder;
+
+impl Visitor for WriteFinder {
+    type Break = String;
+
+    /// A statement inside a query is a data change, as in
+    /// `WITH recent AS (...) UPDATE ...`.
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<String> {
+        ControlFlow::Break(keyword(statement))
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<String> {
+        if selects_into(&query.body) {
+            return ControlFlow::Break("INTO".into());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<String> {
+        match relation.0.first().map(|name| name.value.to_uppercase()) {
+            Some(name) if EXTERNAL_ROWSETS.contains(&name.as_str()) => ControlFlow::Break(name),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+/// Whether a query body creates a table with `SELECT ... INTO`.  Nested
+/// queries are checked by [`WriteFinder`] as it reaches them.
+fn selects_into(body: &SetExpr) -> bool {
+    match body {
+        SetExpr::Select(select) => select.into.is_some(),
+        SetExpr::SetOperation { left, right, .. } => selects_into(left) || selects_into(right),
+        _ => false,
+    }
+}
+
+/// Check that `sql` parses as a single T-SQL query (`SELECT`, optionally
+/// with common table expressions) that neither creates a table with `INTO`
+/// nor reads through [`EXTERNAL_ROWSETS`].
+fn ensure_tsql_read_only(sql: &str) -> Result<()> {
+    let statements = Parser::parse_sql(&MsSqlDialect {}, sql)
+        .map_err(|e| anyhow!("Could not verify that the query only reads: {e}"))?;
+    let [statement] = statements.as_slice() else {
+        bail!("Expected exactly one statement, found {}", statements.len());
+    };
+    let Statement::Query(query) = statement else {
+        bail!(
+            "Only SELECT queries are allowed, found `{}`",
+            keyword(statement)
+        );
+    };
+    if let ControlFlow::Break(found) = query.visit(&mut WriteFinder) {
+        bail!("Only read-only queries are allowed, found `{found}`");
+    }
+    Ok(())
+}
+
+/// Blank out comments, string literals and quoted identifiers so that only
+/// the statement's own words remain.
+fn strip_literals(dialect: Dialect, sql: &str) -> String {
+    let mut out = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '-' if chars.peek() == Some(&'-') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+                out.push('\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            quote @ ('\'' | '"') => {
+                while let Some(c) = chars.next() {
+                    if dialect == Dialect::CosmosSql && c == '\\' {
+                        chars.next();
+                    } else if c == quote {
+                        // CQL escapes a quote by doubling it.
+                        if chars.peek() == Some(&quote) {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                out.push_str(" _ ");
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Check that `sql` is a single statement that only reads data.
+///
+/// T-SQL is parsed (see [`ensure_tsql_read_only`]) rather than searched for
+/// keywords, so a write cannot hide behind unusual spacing, comments or
+/// quoting, and columns named like keywords need no quoting.  A query the
+/// parser does not understand is not verified and fails the check.  Cosmos
+/// DB SQL and CQL `SELECT`s cannot modify data, so for them only the shape of
+/// the statement is checked: a single statement starting with `SELECT`.
+pub fn ensure_read_only(dialect: Dialect, sql: &str) -> Result<()> {
+    if dialect == Dialect::TSql {
+        return ensure_tsql_read_only(sql);
+    }
+
+    let stripped = strip_literals(dialect, sql);
+    let statements: Vec<&str> = stripped
+        .split(';')
+        .filter(|s| !s.trim().is_empty())
+        .collect();
+    let [statement] = statements.as_slice() else {
+        bail!("Expected exactly one statement, found {}", statements.len());
+    };
+
+    let first = statement
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .find(|w| !w.is_empty())
+        .map(str::to_uppercase)
+        .unwrap_or_default();
+    if first != "SELECT" {
+        bail!("Only SELECT queries are allowed, found `{first}`");
+    }
+    Ok(())
+}
+
+/// The upper-cased words of `stripped` (see [`strip_literals`]) outside any
+/// parentheses, brackets or braces, or an error when those are unbalanced.
+fn top_level_words(stripped: &str) -> Result<Vec<String>> {
+    let mut open = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for c in stripped.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            if open.is_empty() {
+                word.push(c.to_ascii_uppercase());
+            }
+            continue;
+        }
+        if !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        match c {
+            '(' | '[' | '{' => open.push(c),
+            ')' | ']' | '}' => {
+                let expected = match c {
+                    ')' => '(',
+                    ']' => '[',
+                    _ => '{',
+                };
+                if open.pop() != Some(expected) {
+                    bail!("The query is malformed: unbalanced `{c}`");
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(c) = open.last() {
+        bail!("The query is malformed: unclosed `{c}`");
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Whether `c` can be part of a word checked by [`ensure_not_denied`]:
+/// T-SQL names may also contain `@`, `#` and `$`.
+pub fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$')
+}
+
+/// Check that `sql` contains none of the words of `denylist`
+/// (`QUERY_DENYLIST`), in any case.  The text is searched as written,
+/// comments and literals included, without parsing it, so the check holds
+/// for statements the parser does not understand or that run after a
+/// confirmation; `master..xp_cmdshell` and `[xp_cmdshell]` contain the word
+/// `xp_cmdshell`, `xp_cmdshell_log` does not.
+pub fn ensure_not_denied(denylist: &[String], sql: &str) -> Result<()> {
+    if denylist.is_empty() {
+        return Ok(());
+    }
+    let denied = sql
+        .split(|c: char| !is_word_char(c))
+        .find_map(|word| denylist.iter().find(|d| d.eq_ignore_ascii_case(word)));
+    if let Some(word) = denied {
+        bail!("The statement contains `{word}`, which the server refuses (QUERY_DENYLIST)");
+    }
+    Ok(())
+}
+
+/// Check a Cosmos DB SQL query for `cosmos_query_items` before it runs: a
+/// single `SELECT` with balanced parentheses, narrowed as `policy` demands
+/// on `database`/`container`.  A query given a partition key reads one
+/// partition and always satisfies the policy.
+pub fn ensure_cosmos_query_allowed(
+    policy: &CosmosQueryPolicy,
+    database: &str,
+    container: &str,
+    sql: &str,
+    partition_key: bool,
+) -> Result<()> {
+    ensure_read_only(Dialect::CosmosSql, sql)?;
+    let words = top_level_words(&strip_literals(Dialect::CosmosSql, sql))?;
+    if partition_key {
+        return Ok(());
+    }
+    if CosmosQueryPolicy::lists(&policy.require_partition_key, database, container) {
+        bail!(
+            "Queries on {database}/{container} must read a single partition \
+             (COSMOS_REQUIRE_PARTITION_KEY): pass `partition_key`"
+        );
+    }
+    if CosmosQueryPolicy::lists(&policy.require_filter, database, container)
+        && !words.iter().any(|w| w == "WHERE")
+    {
+        bail!(
+            "Queries on {database}/{container} must not read every document \
+             (COSMOS_REQUIRE_FILTER): add a WHERE clause, ideally on the partition key, \
+             or pass `partition_key`"
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denied_words_are_refused_anywhere() {
+        let denylist = vec!["xp_cmdshell".to_string(), "OPENROWSET".to_string()];
+        for sql in [
+            "EXEC master..XP_CMDSHELL 'dir'",
+            "EXEC [xp_cmdshell] 'dir'",
+            "SELECT * FROM openrowset(BULK 'x', SINGLE_CLOB) AS f",
+            "SELECT 1 -- xp_cmdshell",
+        ] {
+            assert!(ensure_not_denied(&denylist, sql).is_err(), "{sql}");
+        }
+        for sql in [
+            "SELECT xp_cmdshell_log FROM dbo.audit",
+            "SELECT * FROM c WHERE c.kind = 'rowset'",
+        ] {
+            assert!(ensure_not_denied(&denylist, sql).is_ok(), "{sql}");
+        }
+        assert!(ensure_not_denied(&[], "EXEC xp_cmdshell 'dir'").is_ok());
+    }
+
+    #[test]
+    fn accepts_read_only_queries() {
+        for sql in [
+            "SELECT TOP 10 * FROM dbo.orders ORDER BY created_at DESC;",
+            "WITH totals AS (SELECT customer_id, SUM(total) AS spent FROM dbo.orders \
+             GROUP BY customer_id) SELECT * FROM totals",
+            "SELECT [update], 'DROP TABLE x' AS note FROM dbo.audit -- DELETE everything",
+            "select last_update from dbo.t /* INSERT */",
+            "SELECT o.id, l.sku FROM dbo.orders AS o WITH (NOLOCK) \
+             CROSS APPLY (SELECT TOP 1 sku FROM dbo.lines WHERE order_id = o.id) AS l",
+            "SELECT id FROM dbo.a UNION ALL SELECT id FROM dbo.b",
+        ] {
+            ensure_read_only(Dialect::TSql, sql).unwrap();
+        }
+        ensure_read_only(
+            Dialect::CosmosSql,
+            r#"SELECT c.id FROM c WHERE c.note = "it\"s; fine""#,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_writes_and_multiple_statements() {
+        for sql in [
+            "DELETE FROM dbo.orders",
+            "SELECT * INTO dbo.copy FROM dbo.orders",
+            "WITH x AS (SELECT 1 AS a) UPDATE dbo.t SET a = 1",
+            "SELECT 1; DROP TABLE dbo.orders",
+            "EXEC sp_who",
+            "",
+            "SELECT id\nINTO/* copy */#t FROM dbo.orders",
+            "SELECT id FROM dbo.a UNION SELECT id INTO dbo.b FROM dbo.c",
+            "SELECT * FROM (SELECT id INTO dbo.b FROM dbo.c) AS x",
+            "SELECT * FROM OPENROWSET('SQLNCLI', 'Server=evil;', 'SELECT 1') AS r",
+            "WITH c AS (SELECT 1 AS a) SELECT * FROM c; WAITFOR DELAY '00:01'",
+        ] {
+            assert!(ensure_read_only(Dialect::TSql, sql).is_err(), "{sql}");
+        }
+        assert!(ensure_read_only(Dialect::CosmosSql, "WITH x SELECT 1").is_err());
+    }
+
+    #[test]
+    fn cosmos_queries_follow_the_container_policy() {
+        let policy = CosmosQueryPolicy {
+            require_filter: vec!["orders".into()],
+            require_partition_key: vec!["shop/events".into()],
+        };
+        let check = |container: &str, sql: &str, partition_key: bool| {
+            ensure_cosmos_query_allowed(&policy, "shop", container, sql, partition_key)
+        };
+        check("orders", "SELECT * FROM c WHERE c.customerId = 'a'", false).unwrap();
+        check("orders", "SELECT * FROM c", true).unwrap();
+        check("customers", "SELECT * FROM c", false).unwrap();
+        check("events", "SELECT * FROM c WHERE c.type = 'x'", true).unwrap();
+        ensure_cosmos_query_allowed(&policy, "other", "events", "SELECT * FROM c", false).unwrap();
+
+        for (container, sql) in [
+            ("orders", "SELECT * FROM c"),
+            (
+                "orders",
+                "SELECT * FROM c JOIN (SELECT VALUE t FROM t IN c.tags WHERE t = 'x')",
+            ),
+            ("orders", "SELECT * FROM c -- WHERE c.id = '1'"),
+            ("events", "SELECT * FROM c WHERE c.type = 'x'"),
+            ("customers", "SELECT * FROM c WHERE (c.a = 1"),
+            ("customers", "SELECT c.tags[0 FROM c"),
+            ("customers", "DELETE FROM c"),
+        ] {
+            assert!(check(container, sql, false).is_err(), "{container}: {sql}");
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::Recording;
+use crate::redact::redact;
+
+/// Response headers left out of recordings: they describe the bytes on the
+/// wire, which replay does not reproduce, or a client's cookies.
+const SKIPPED_HEADERS: &[&str] = &[
+    "content-length",
+    "transfer-encoding",
+    "content-encoding",
+    "connection",
+    "set-cookie",
+];
+
+/// One line of a recording: a request and the response it got.  Bodies are
+/// stored by [`body_value`], with secrets masked; request headers, where
+/// credentials travel, are not stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    request_body: Value,
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    body: Value,
+}
+
+impl Interaction {
+    /// Whether this is a recording of `other`'s request.
+    fn answers(&self, other: &Interaction) -> bool {
+        self.method == other.method
+            && self.url == other.url
+            && self.request_body == other.request_body
+    }
+
+    /// The recorded response.
+    fn response(&self) -> Result<Response> {
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name, value);
+        }
+        Ok(Response::from(response.body(body_bytes(&self.body))?))
+    }
+}
+
+/// `bytes` as stored in a recording: JSON objects and arrays as they are,
+/// other UTF-8 text as a string, anything else as `{"$base64": "..."}`.
+fn body_value(bytes: &[u8]) -> Value {
+    if bytes.is_empty() {
+        return Value::Null;
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return serde_json::json!({ "$base64": BASE64.encode(bytes) });
+    };
+    let text = redact(text);
+    match serde_json::from_str(&text) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => value,
+        _ => Value::String(text.into_owned()),
+    }
+}
+
+/// The bytes a [`body_value`] stands for.
+fn body_bytes(body: &Value) -> Vec<u8> {
+    if let Some(Value::String(encoded)) = body.get("$base64") {
+        if let Ok(bytes) = BASE64.decode(encoded) {
+            return bytes;
+        }
+    }
+    match body {
+        Value::Null => Vec::new(),
+        Value::String(text) => text.clone().into_bytes(),
+        other => other.to_string().into_bytes(),
+    }
+}
+
+/// Records backend traffic to a file, or answers requests from one.
+enum Recorder {
+    Record(Mutex<File>),
+    Replay {
+        path: PathBuf,
+        /// The recorded interactions, and whether each has been served.
+        interactions: Mutex<Vec<(Interaction, bool)>>,
+    },
+}
+
+impl Recorder {
+    fn record(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open BACKEND_RECORD_FILE {}", path.display()))?;
+        Ok(Recorder::Record(Mutex::new(file)))
+    }
+
+    fn replay(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read BACKEND_REPLAY_FILE {}", path.display()))?;
+        let interactions = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let interaction = serde_json::from_str(line).with_context(|| {
+                    format!("Line {} of {} is not an interaction", i + 1, path.display())
+                })?;
+                Ok((interaction, false))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Recorder::Replay {
+            path: path.to_path_buf(),
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let mut interaction = Interaction {
+            method: request.method().to_string(),
+            url: redact(request.url().as_str()).into_owned(),
+            request_body: body_value(
+                request
+                    .body()
+                    .and_then(|b| b.as_bytes())
+                    .unwrap_or_default(),
+            ),
+            status: 0,
+            headers: BTreeMap::new(),
+            body: Value::Null,
+        };
+        match self {
+            Recorder::Record(file) => {
+                let response = client.execute(request).await?;
+                let status = response.status();
+                let headers = response.headers().clone();
+                let bytes = response.bytes().await?;
+                interaction.status = status.as_u16();
+                interaction.headers = recorded_headers(&headers);
+                interaction.body = body_value(&bytes);
+                let line = serde_json::to_string(&interaction)?;
+                if let Err(e) = writeln!(file.lock().unwrap(), "{line}") {
+                    tracing::warn!("Failed to write to BACKEND_RECORD_FILE: {e}");
+                }
+                let mut response = http::Response::new(bytes.to_vec());
+                *response.status_mut() = status;
+                *response.headers_mut() = headers;
+                Ok(Response::from(response))
+            }
+            Recorder::Replay { path, interactions } => {
+                // The first recording of the request not served yet, or the
+                // last one again once all have been.
+                let mut interactions = interactions.lock().unwrap();
+                let index = interactions
+                    .iter()
+                    .position(|(recorded, served)| !served && recorded.answers(&interaction))
+                    .or_else(|| {
+                        interactions
+                            .iter()
+                            .rposition(|(recorded, _)| recorded.answers(&interaction))
+                    });
+                let Some(index) = index else {
+                    bail!(
+                        "{} has no response to {} {}",
+                        path.display(),
+                        interaction.method,
+                        interaction.url
+                    );
+                };
+                interactions[index].1 = true;
+                interactions[index].0.response()
+            }
+        }
+    }
+}
+
+/// The response headers worth replaying, the values of repeated ones
+/// joined with commas.
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    let mut recorded = BTreeMap::<String, String>::new();
+    for (name, value) in headers {
+        if SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        recorded
+            .entry(name.to_string())
+            .and_modify(|joined| {
+                joined.push_str(", ");
+                joined.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    recorded
+}
+
+/// The recorder installed by [`install`], if any.
+static RECORDER: OnceLock<Recorder> = OnceLock::new();
+
+/// Record the HTTP traffic of the backends to, or replay it from, the file
+/// of `recording`, for every later [`send`].
+pub fn install(recording: Option<&Recording>) -> Result<()> {
+    let recorder = match recording {
+        None => return Ok(()),
+        Some(Recording::Record(path)) => Recorder::record(path)?,
+        Some(Recording::Replay(path)) => Recorder::replay(path)?,
+    };
+    let _ = RECORDER.set(recorder);
+    Ok(())
+}
+
+/// Whether requests are answered from a recording, so that nothing needs
+/// credentials.
+pub fn replaying() -> bool {
+    matches!(RECORDER.get(), Some(Recorder::Replay { .. }))
+}
+
+/// Refuse to reach `backend` while replaying: it does not speak HTTP through
+/// [`send`], so it would connect to the live service instead of answering
+/// from the recording.
+pub fn live(backend: &str) -> Result<()> {
+    refuse_when_replaying(replaying(), backend)
+}
+
+fn refuse_when_replaying(replaying: bool, backend: &str) -> Result<()> {
+    if replaying {
+        bail!(
+            "BACKEND_REPLAY_FILE is set, but {backend} is not replayed: only HTTP \
+             requests are recorded, and the live service is not reached while replaying"
+        );
+    }
+    Ok(())
+}
+
+/// Send `request` to a backend, recording it and its response with
+/// `BACKEND_RECORD_FILE`, or answer it from `BACKEND_REPLAY_FILE` without
+/// sending it.  A replayed response with an error status is returned like a
+/// live one; a request missing from the recording fails.
+pub async fn send(request: RequestBuilder) -> Result<Response> {
+    match RECORDER.get() {
+        Some(recorder) => recorder.send(request).await,
+        None => Ok(request.send().await?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[tokio::test]
+    async fn recordings_answer_requests_in_order() {
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", uuid::Uuid::new_v4()));
+        let url = "https://account.documents.azure.com/dbs/shop/colls/orders/docs";
+        let query = serde_json::json!({ "query": "SELECT * FROM c" });
+        let lines = [
+            serde_json::json!({
+                "method": "POST", "url": url, "request_body": query,
+                "status": 429, "headers": { "x-ms-retry-after-ms": "10" },
+            }),
+            serde_json::json!({
+                "method": "POST", "url": url, "request_body": query,
+                "status": 200, "headers": { "x-ms-request-charge": "2.9" },
+                "body": { "Documents": [{ "id": "1" }] },
+            }),
+            serde_json::json!({
+                "method": "GET", "url": "https://account.blob.core.windows.net/c/logo.png",
+                "status": 200, "body": { "$base64": BASE64.encode([0x89, 0x50, 0x4e, 0x47]) },
+            }),
+        ];
+        let text: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        std::fs::write(&path, text).unwrap();
+        let recorder = Recorder::replay(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let http = reqwest::Client::new();
+        let request = || http.post(url).json(&query);
+        let throttled = recorder.send(request()).await.unwrap();
+        assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = recorder.send(request()).await.unwrap();
+        assert_eq!(response.headers()["x-ms-request-charge"], "2.9");
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["Documents"][0]["id"], "1");
+        // Used up: the last response is served again.
+        let again = recorder.send(request()).await.unwrap();
+        assert_eq!(again.status(), StatusCode::OK);
+
+        let image = recorder
+            .send(http.get("https://account.blob.core.windows.net/c/logo.png"))
+            .await
+            .unwrap();
+        assert_eq!(image.bytes().await.unwrap().as_ref(), b"\x89PNG");
+
+        let other = http
+            .post(url)
+            .json(&serde_json::json!({ "query": "SELECT 1" }));
+        assert!(recorder.send(other).await.is_err());
+    }
+
+    #[test]
+    fn backends_outside_the_recording_are_refused_while_replaying() {
+        assert!(refuse_when_replaying(false, "MSSQL (TDS)").is_ok());
+        let refused = refuse_when_replaying(true, "MSSQL (TDS)").unwrap_err();
+        assert!(refused
+            .to_string()
+            .starts_with("BACKEND_REPLAY_FILE is set, but MSSQL (TDS) is not replayed"));
+    }
+}
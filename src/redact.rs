@@ -0,0 +1,241 @@
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::RwLock;
+
+use rmcp::model::{CallToolResult, RawContent};
+use rmcp::ErrorData;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Connection string and URL parameters whose values are secrets, in lower
+/// case: SQL passwords, storage and Cosmos DB account keys, Service Bus and
+/// Event Hubs keys, App Configuration secrets and SAS signatures.
+const SECRET_PARAMETERS: &[&str] = &[
+    "accountkey",
+    "client_secret",
+    "password",
+    "pwd",
+    "secret",
+    "sharedaccesskey",
+    "sharedaccesssignature",
+    "sig",
+];
+
+/// What secret values are replaced with.
+const MASK: &str = "***";
+
+/// Environment variables shorter than this are not registered as secrets,
+/// so that short values such as `true` are not scrubbed everywhere.
+const MIN_SECRET_LEN: usize = 8;
+
+/// Secret values read from the environment (see [`register_environment`]).
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Whether the environment variable `name` holds a secret as a whole.
+fn is_secret_variable(name: &str) -> bool {
+    ["_KEY", "_PASSWORD", "_SECRET", "_TOKEN"]
+        .iter()
+        .any(|suffix| name.ends_with(suffix))
+}
+
+/// Remember the secrets in the environment, so that [`redact`] scrubs them
+/// wherever they appear: the values of `*_KEY`, `*_PASSWORD`, `*_SECRET`
+/// and `*_TOKEN` variables and the [`SECRET_PARAMETERS`] of every
+/// `*_CONNECTION_STRING`.
+pub fn register_environment() {
+    let mut secrets = Vec::new();
+    for (name, value) in std::env::vars() {
+        if is_secret_variable(&name) {
+            secrets.push(value.trim().to_string());
+        } else if name.ends_with("_CONNECTION_STRING") {
+            secrets.extend(secret_parameters(&value).map(str::to_string));
+        }
+    }
+    secrets.retain(|s| s.len() >= MIN_SECRET_LEN);
+    // Longest first, so that a secret containing another is masked whole.
+    secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    secrets.dedup();
+    *SECRETS.write().unwrap() = secrets;
+}
+
+/// The byte range of the value of each [`SECRET_PARAMETERS`] `name=value`
+/// pair in `text`.  A value is enclosed in braces or quotes, or runs to the
+/// next `;`, `&`, quote or line break; a URL's `sig` also ends at whitespace.
+fn secret_values(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut from = 0;
+    while let Some(offset) = text[from..].find('=') {
+        let equals = from + offset;
+        from = equals + 1;
+        let name_end = text[..equals].trim_end().len();
+        let name_start = text[..name_end]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+            .last()
+            .map_or(name_end, |(i, _)| i);
+        let name = text[name_start..name_end].to_ascii_lowercase();
+        if !SECRET_PARAMETERS.contains(&name.as_str()) {
+            continue;
+        }
+        let value = &text[equals + 1..];
+        let start = equals + 1 + (value.len() - value.trim_start_matches(' ').len());
+        let url = name == "sig";
+        let end = match text.as_bytes().get(start) {
+            Some(&open @ (b'{' | b'\'' | b'"')) => {
+                let close = if open == b'{' { '}' } else { open as char };
+                text[start + 1..]
+                    .find(close)
+                    .map_or(text.len(), |i| start + 1 + i + 1)
+            }
+            _ => text[start..]
+                .find(|c: char| {
+                    matches!(c, ';' | '&' | '"' | '\'' | '\n' | '\r') || (url && c.is_whitespace())
+                })
+                .map_or(text.len(), |i| start + i),
+        };
+        if end > start {
+            ranges.push((start, end));
+            from = end;
+        }
+    }
+    ranges
+}
+
+/// The secret values of a connection string.
+fn secret_parameters(connection_string: &str) -> impl Iterator<Item = &str> {
+    secret_values(connection_string)
+        .into_iter()
+        .map(|(start, end)| {
+            connection_string[start..end].trim_matches(|c| matches!(c, '{' | '}' | '\'' | '"'))
+        })
+}
+
+/// `text` with secrets masked: the values of [`SECRET_PARAMETERS`] in
+/// connection strings and URLs, and every secret registered from the
+/// environment.
+pub fn redact(text: &str) -> Cow<'_, str> {
+    let mut redacted = Cow::Borrowed(text);
+    let ranges = secret_values(text);
+    if !ranges.is_empty() {
+        let mut masked = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end) in ranges {
+            masked.push_str(&text[last..start]);
+            masked.push_str(MASK);
+            last = end;
+        }
+        masked.push_str(&text[last..]);
+        redacted = Cow::Owned(masked);
+    }
+    for secret in SECRETS.read().unwrap().iter() {
+        if redacted.contains(secret.as_str()) {
+            redacted = Cow::Owned(redacted.replace(secret.as_str(), MASK));
+        }
+    }
+    redacted
+}
+
+/// Mask secrets in the error of a failed tool call, whether it failed with
+/// a protocol error or an error result.
+pub fn tool_error(result: &mut Result<CallToolResult, ErrorData>) {
+    match result {
+        Ok(result) if result.is_error == Some(true) => {
+            for content in &mut result.content {
+                if let RawContent::Text(text) = &mut content.raw {
+                    if let Cow::Owned(masked) = redact(&text.text) {
+                        text.text = masked;
+                    }
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(error) => {
+            if let Cow::Owned(masked) = redact(&error.message) {
+                error.message = masked.into();
+            }
+        }
+    }
+}
+
+/// A [`MakeWriter`] masking secrets in every log line before it is written.
+pub struct Redacting<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+/// Writes through [`redact`].  Log events are formatted whole and written
+/// in one call, so a secret is never split across writes.
+pub struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.0.write_all(redact(text).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_string_secrets_are_masked() {
+        for (text, expected) in [
+            (
+                "Login failed: server=tcp:db,1433;User Id=app;Password=p@ss w0rd;Encrypt=true",
+                "Login failed: server=tcp:db,1433;User Id=app;Password=***;Encrypt=true",
+            ),
+            (
+                "Invalid connection string 'Server=db;PWD={a;b=c};Database=x'",
+                "Invalid connection string 'Server=db;PWD=***;Database=x'",
+            ),
+            (
+                "Endpoint=sb://ns.servicebus.windows.net/;SharedAccessKeyName=send;\
+                 SharedAccessKey=abc123==",
+                "Endpoint=sb://ns.servicebus.windows.net/;SharedAccessKeyName=send;\
+                 SharedAccessKey=***",
+            ),
+            (
+                "GET https://acct.blob.core.windows.net/c/b?sv=2022-11-02&sig=AbC%2Bd&se=x failed",
+                "GET https://acct.blob.core.windows.net/c/b?sv=2022-11-02&sig=***&se=x failed",
+            ),
+            ("the password = hunter2", "the password = ***"),
+            (
+                "no secrets here, key = partition",
+                "no secrets here, key = partition",
+            ),
+        ] {
+            assert_eq!(redact(text), expected);
+        }
+    }
+
+    #[test]
+    fn tool_errors_are_masked() {
+        let mut result = Ok(CallToolResult::error(vec![rmcp::model::Content::text(
+            "Failed to connect with AccountKey=c2VjcmV0a2V5;",
+        )]));
+        tool_error(&mut result);
+        let text = &result.unwrap().content[0];
+        assert_eq!(
+            text.as_text().unwrap().text,
+            "Failed to connect with AccountKey=***;"
+        );
+
+        let mut failed: Result<CallToolResult, _> =
+            Err(ErrorData::internal_error("bad client_secret=xyz", None));
+        tool_error(&mut failed);
+        assert_eq!(failed.unwrap_err().message, "bad client_secret=***");
+    }
+}
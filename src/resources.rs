@@ -0,0 +1,368 @@
+use anyhow::{Context, Result};
+use rmcp::model::{AnnotateAble, RawResource, RawResourceTemplate, Resource, ResourceTemplate};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::cosmos::{self, CosmosAccount};
+use crate::health::Backend;
+use crate::mssql::{self, DEFAULT_MAX_ROWS};
+
+/// URI prefix of MSSQL table schema resources: `mssql://schema/{schema}/{table}`.
+const MSSQL_SCHEMA_PREFIX: &str = "mssql://schema/";
+/// URI prefix of MSSQL row resources: `mssql://{schema}/{table}/rows?top={n}`.
+const MSSQL_PREFIX: &str = "mssql://";
+/// URI prefix of Cosmos DB resources: `cosmos://{database}/{container}` and
+/// `cosmos://{database}/{container}/{id}?pk={pk}`.
+const COSMOS_PREFIX: &str = "cosmos://";
+
+/// MIME type of every resource body (all are JSON documents).
+pub const RESOURCE_MIME_TYPE: &str = "application/json";
+
+/// A parsed resource URI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceUri {
+    /// Column definitions of one MSSQL table.
+    MssqlSchema { schema: String, table: String },
+    /// Metadata and sample document shape of one Cosmos DB container.
+    CosmosContainer { database: String, container: String },
+    /// The first `top` rows of an MSSQL table.
+    MssqlRows {
+        schema: String,
+        table: String,
+        top: Option<u64>,
+    },
+    /// One Cosmos DB document, addressed by `id` and partition key.
+    CosmosDocument {
+        database: String,
+        container: String,
+        id: String,
+        partition_key: String,
+    },
+}
+
+impl ResourceUri {
+    /// Parse a resource URI, returning `None` if it is not one this server
+    /// publishes.  Path segments and query values are percent-decoded.
+    pub fn parse(uri: &str) -> Option<Self> {
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+
+        if let Some([schema, table]) = path
+            .strip_prefix(MSSQL_SCHEMA_PREFIX)
+            .and_then(path_segments)
+        {
+            return Some(Self::MssqlSchema { schema, table });
+        }
+        if let Some(rest) = path.strip_prefix(MSSQL_PREFIX) {
+            let [schema, table, rows] = path_segments(rest)?;
+            if rows != "rows" {
+                return None;
+            }
+            let top = match query_param(query, "top") {
+                Some(top) => Some(top.parse().ok()?),
+                None => None,
+            };
+            return Some(Self::MssqlRows { schema, table, top });
+        }
+        if let Some(rest) = path.strip_prefix(COSMOS_PREFIX) {
+            if let Some([database, container]) = path_segments(rest) {
+                return Some(Self::CosmosContainer {
+                    database,
+                    container,
+                });
+            }
+            let [database, container, id] = path_segments(rest)?;
+            return Some(Self::CosmosDocument {
+                database,
+                container,
+                id,
+                partition_key: query_param(query, "pk")?,
+            });
+        }
+        None
+    }
+
+    /// The backend a read of this resource reaches.
+    pub fn backend(&self) -> Backend {
+        match self {
+            Self::MssqlSchema { .. } | Self::MssqlRows { .. } => Backend::Mssql,
+            Self::CosmosContainer { .. } | Self::CosmosDocument { .. } => Backend::Cosmos,
+        }
+    }
+
+    /// The records `body`, read from this resource, holds for the session
+    /// quotas: the rows of a table or the one document.
+    pub fn records(&self, body: &Value) -> u64 {
+        match self {
+            Self::MssqlRows { .. } => body.as_array().map_or(0, Vec::len) as u64,
+            Self::CosmosDocument { .. } => 1,
+            Self::MssqlSchema { .. } | Self::CosmosContainer { .. } => 0,
+        }
+    }
+
+    /// Format the resource as a URI, percent-encoding path segments.
+    pub fn to_uri(&self) -> String {
+        match self {
+            Self::MssqlSchema { schema, table } => format!(
+                "{MSSQL_SCHEMA_PREFIX}{}/{}",
+                urlencoding::encode(schema),
+                urlencoding::encode(table)
+            ),
+            Self::CosmosContainer {
+                database,
+                container,
+            } => format!(
+                "{COSMOS_PREFIX}{}/{}",
+                urlencoding::encode(database),
+                urlencoding::encode(container)
+            ),
+            Self::MssqlRows { schema, table, top } => {
+                let uri = format!(
+                    "{MSSQL_PREFIX}{}/{}/rows",
+                    urlencoding::encode(schema),
+                    urlencoding::encode(table)
+                );
+                match top {
+                    Some(top) => format!("{uri}?top={top}"),
+                    None => uri,
+                }
+            }
+            Self::CosmosDocument {
+                database,
+                container,
+                id,
+                partition_key,
+            } => format!(
+                "{COSMOS_PREFIX}{}/{}/{}?pk={}",
+                urlencoding::encode(database),
+                urlencoding::encode(container),
+                urlencoding::encode(id),
+                urlencoding::encode(partition_key)
+            ),
+        }
+    }
+}
+
+/// Find the percent-decoded value of `name` in a `k=v&k=v` query string.
+fn query_param(query: &str, name: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| urlencoding::decode(value).ok())
+        .map(|value| value.into_owned())
+}
+
+/// Build a resource template entry.
+fn template(uri_template: &str, name: &str, description: &str) -> ResourceTemplate {
+    RawResourceTemplate {
+        uri_template: uri_template.to_string(),
+        name: name.to_string(),
+        title: None,
+        description: Some(description.to_string()),
+        mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+        icons: None,
+    }
+    .no_annotation()
+}
+
+/// List the resource templates for the configured backends, which let
+/// clients fetch individual rows and documents through the resources API.
+pub fn templates(config: &Config) -> Vec<ResourceTemplate> {
+    let mut templates = Vec::new();
+    if config.mssql.is_some() {
+        templates.push(template(
+            "mssql://{schema}/{table}/rows?top={n}",
+            "MSSQL table rows",
+            "The first n rows of an MSSQL table (default 500, maximum 10 000)",
+        ));
+    }
+    if config.cosmos.is_some() {
+        templates.push(template(
+            "cosmos://{database}/{container}/{id}?pk={pk}",
+            "Cosmos DB document",
+            "One Cosmos DB document, read by id and partition key",
+        ));
+    }
+    templates
+}
+
+/// Split `path` into exactly `N` non-empty, percent-decoded segments.
+fn path_segments<const N: usize>(path: &str) -> Option<[String; N]> {
+    let segments = path
+        .split('/')
+        .map(|s| match s {
+            "" => None,
+            s => urlencoding::decode(s).ok().map(|s| s.into_owned()),
+        })
+        .collect::<Option<Vec<_>>>()?;
+    segments.try_into().ok()
+}
+
+/// Build a JSON resource entry.
+fn json_resource(uri: &ResourceUri, name: String, description: String) -> Resource {
+    let mut resource = RawResource::new(uri.to_uri(), name);
+    resource.description = Some(description);
+    resource.mime_type = Some(RESOURCE_MIME_TYPE.to_string());
+    resource.no_annotation()
+}
+
+/// List every resource published for the configured backends.
+///
+/// `cosmos` is the shared Cosmos DB account, or `None` when Cosmos DB is not
+/// usable.  A backend that cannot be reached is skipped with a warning, so
+/// one unavailable data store does not hide the others' resources.
+pub async fn list(config: &Config, cosmos: Option<&CosmosAccount>) -> Vec<Resource> {
+    let mut resources = Vec::new();
+
+    if let Some(cfg) = &config.mssql {
+        match mssql::list_tables(cfg).await {
+            Ok(tables) => {
+                resources.extend(tables.as_array().into_iter().flatten().map(|t| {
+                    let schema = t["schema"].as_str().unwrap_or_default();
+                    let table = t["table_name"].as_str().unwrap_or_default();
+                    let uri = ResourceUri::MssqlSchema {
+                        schema: schema.to_string(),
+                        table: table.to_string(),
+                    };
+                    json_resource(
+                        &uri,
+                        format!("{schema}.{table}"),
+                        format!("Column definitions of MSSQL table {schema}.{table}"),
+                    )
+                }));
+            }
+            Err(e) => tracing::warn!("Skipping MSSQL resources: {e:#}"),
+        }
+    }
+
+    if let Some(account) = cosmos {
+        match cosmos::all_containers(account).await {
+            Ok(containers) => {
+                resources.extend(containers.into_iter().map(|(database, container)| {
+                    let name = format!("{database}/{container}");
+                    let description = format!(
+                        "Partition key, policies and sample document shape of Cosmos DB \
+                         container {name}"
+                    );
+                    let uri = ResourceUri::CosmosContainer {
+                        database,
+                        container,
+                    };
+                    json_resource(&uri, name, description)
+                }));
+            }
+            Err(e) => tracing::warn!("Skipping Cosmos DB resources: {e:#}"),
+        }
+    }
+
+    resources
+}
+
+/// Read the body of a resource.
+pub async fn read(
+    config: &Config,
+    cosmos: Result<&CosmosAccount, String>,
+    uri: &ResourceUri,
+) -> Result<Value> {
+    match uri {
+        ResourceUri::MssqlSchema { schema, table } => {
+            let cfg = config.require_mssql()?;
+            mssql::describe_table(cfg, schema, table)
+                .await
+                .with_context(|| format!("Failed to read schema of {schema}.{table}"))
+        }
+        ResourceUri::CosmosContainer {
+            database,
+            container,
+        } => {
+            let account = cosmos.map_err(anyhow::Error::msg)?;
+            cosmos::describe_container(account, database, container)
+                .await
+                .with_context(|| format!("Failed to describe container {database}/{container}"))
+        }
+        ResourceUri::MssqlRows { schema, table, top } => {
+            let cfg = config.require_mssql()?;
+            let quote = |name: &str| format!("[{}]", name.replace(']', "]]"));
+            let sql = format!("SELECT * FROM {}.{}", quote(schema), quote(table));
+            mssql::execute_query(cfg, &sql, top.unwrap_or(DEFAULT_MAX_ROWS))
+                .await
+                .with_context(|| format!("Failed to read rows of {schema}.{table}"))
+        }
+        ResourceUri::CosmosDocument {
+            database,
+            container,
+            id,
+            partition_key,
+        } => {
+            let account = cosmos.map_err(anyhow::Error::msg)?;
+            let document =
+                cosmos::read_item(account, database, container, id, partition_key, None).await?;
+            match &document["item"] {
+                Value::Null => anyhow::bail!(
+                    "Document '{id}' (partition key {partition_key:?}) not found in \
+                     {database}/{container}"
+                ),
+                item => Ok(item.clone()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mssql_schema_uri_round_trips_with_encoding() {
+        let uri = ResourceUri::MssqlSchema {
+            schema: "dbo".into(),
+            table: "order lines".into(),
+        };
+        assert_eq!(uri.to_uri(), "mssql://schema/dbo/order%20lines");
+        assert_eq!(ResourceUri::parse(&uri.to_uri()), Some(uri));
+    }
+
+    #[test]
+    fn cosmos_container_uri_round_trips() {
+        let uri = ResourceUri::CosmosContainer {
+            database: "shop".into(),
+            container: "orders".into(),
+        };
+        assert_eq!(uri.to_uri(), "cosmos://shop/orders");
+        assert_eq!(ResourceUri::parse("cosmos://shop/orders"), Some(uri));
+        assert_eq!(ResourceUri::parse("cosmos://shop"), None);
+    }
+
+    #[test]
+    fn row_and_document_templates_parse() {
+        assert_eq!(
+            ResourceUri::parse("mssql://dbo/orders/rows?top=25"),
+            Some(ResourceUri::MssqlRows {
+                schema: "dbo".into(),
+                table: "orders".into(),
+                top: Some(25),
+            })
+        );
+        assert_eq!(ResourceUri::parse("mssql://dbo/orders/rows?top=x"), None);
+
+        let document = ResourceUri::CosmosDocument {
+            database: "shop".into(),
+            container: "orders".into(),
+            id: "order 1".into(),
+            partition_key: "tenant/a".into(),
+        };
+        assert_eq!(
+            document.to_uri(),
+            "cosmos://shop/orders/order%201?pk=tenant%2Fa"
+        );
+        assert_eq!(ResourceUri::parse(&document.to_uri()), Some(document));
+        assert_eq!(ResourceUri::parse("cosmos://shop/orders/order-1"), None);
+    }
+
+    #[test]
+    fn unknown_or_malformed_uris_are_rejected() {
+        assert_eq!(ResourceUri::parse("mssql://schema/dbo"), None);
+        assert_eq!(ResourceUri::parse("mssql://schema/dbo/a/b"), None);
+        assert_eq!(ResourceUri::parse("file:///etc/passwd"), None);
+    }
+}
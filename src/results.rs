@@ -0,0 +1,257 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use flate2::{write::GzEncoder, Compression};
+use rmcp::model::{CallToolResult, Content, Meta, RawContent, RawResource, ResourceContents};
+use serde_json::Value;
+
+use crate::annotations::RECORD_FIELDS;
+use crate::resources::RESOURCE_MIME_TYPE;
+use crate::stats::CacheCounter;
+
+/// URI prefix of stored tool results: `result://{uuid}`.
+pub const RESULT_PREFIX: &str = "result://";
+
+/// Results whose JSON is larger than this are stored and returned as a
+/// resource link plus a preview.
+pub const MAX_INLINE_BYTES: usize = 64 * 1024;
+
+/// Approximate size of the records kept in a preview.
+const PREVIEW_BYTES: usize = 8 * 1024;
+
+/// How long a stored result can be read.
+pub const RESULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Maximum number of stored results; the oldest is dropped first.
+const MAX_STORED_RESULTS: usize = 32;
+
+/// Experimental client capability announcing that the client can read
+/// gzip-compressed results.
+pub const GZIP_CAPABILITY: &str = "gzipResults";
+
+/// MIME type of a compressed result.
+const GZIP_MIME_TYPE: &str = "application/gzip";
+
+struct Stored {
+    uri: String,
+    body: String,
+    stored: Instant,
+}
+
+/// Oversized tool results, kept in memory for a while so clients can read
+/// them as `result://{uuid}` resources.
+#[derive(Default)]
+pub struct ResultStore {
+    results: Mutex<VecDeque<Stored>>,
+    /// Reads of stored results, missed when expired or unknown.
+    reads: CacheCounter,
+}
+
+/// Shrink the first record list of `value` to roughly [`PREVIEW_BYTES`].
+/// Returns the number of records kept and the original number, or `None`
+/// when `value` holds no records.
+fn shrink_records(value: &mut Value) -> Option<(usize, usize)> {
+    let field = RECORD_FIELDS.iter().find(|f| value[**f].is_array())?;
+    let records = value[*field].as_array_mut()?;
+    let total = records.len();
+    let mut size = 0;
+    let kept = records
+        .iter()
+        .take_while(|record| {
+            size += record.to_string().len() + 1;
+            size <= PREVIEW_BYTES
+        })
+        .count();
+    records.truncate(kept);
+    Some((kept, total))
+}
+
+/// `body` gzip-compressed and base64-encoded, unless compression fails.
+fn compress(body: &str) -> Option<String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(body.as_bytes()).ok()?;
+    Some(BASE64.encode(encoder.finish().ok()?))
+}
+
+impl ResultStore {
+    /// The body of the stored result at `uri`, unless it has expired.
+    pub fn get(&self, uri: &str) -> Option<String> {
+        let mut results = self.results.lock().unwrap();
+        results.retain(|r| r.stored.elapsed() < RESULT_TTL);
+        let body = results
+            .iter()
+            .find(|r| r.uri == uri)
+            .map(|r| r.body.clone());
+        match body {
+            Some(_) => self.reads.hit(),
+            None => self.reads.miss(),
+        }
+        body
+    }
+
+    /// `{hits, misses, hit_rate, entries}` of result reads, `entries` being
+    /// the results currently stored.
+    pub fn report(&self) -> Value {
+        let mut report = self.reads.report();
+        let mut results = self.results.lock().unwrap();
+        results.retain(|r| r.stored.elapsed() < RESULT_TTL);
+        report["entries"] = results.len().into();
+        report
+    }
+
+    fn insert(&self, body: String) -> String {
+        let uri = format!("{RESULT_PREFIX}{}", uuid::Uuid::new_v4());
+        let mut results = self.results.lock().unwrap();
+        results.retain(|r| r.stored.elapsed() < RESULT_TTL);
+        if results.len() >= MAX_STORED_RESULTS {
+            results.pop_front();
+        }
+        results.push_back(Stored {
+            uri: uri.clone(),
+            body,
+            stored: Instant::now(),
+        });
+        uri
+    }
+
+    /// Move an oversized record result out of line, or compress it when the
+    /// client accepts `gzip`.
+    ///
+    /// When the structured content of `result` is larger than
+    /// [`MAX_INLINE_BYTES`] and holds a record list (rows, items, ...), the
+    /// full JSON is stored as a `result://{uuid}` resource.  The result then
+    /// keeps only the first records as a preview, in the same shape so it
+    /// still matches the tool's output schema, followed by a link to the
+    /// resource and any attached export; `_meta` names the resource under
+    /// `fullResult`.  With `gzip` the full JSON is attached instead as a
+    /// gzip-compressed base64 blob and `_meta` carries
+    /// `contentEncoding: "gzip"` and the blob's URI under `compressedResult`.
+    /// Other results are left as they are.
+    pub fn offload(&self, result: &mut CallToolResult, gzip: bool) {
+        if result.is_error == Some(true) {
+            return;
+        }
+        let Some(structured) = &result.structured_content else {
+            return;
+        };
+        let body = structured.to_string();
+        if body.len() <= MAX_INLINE_BYTES {
+            return;
+        }
+        let mut preview = structured.clone();
+        let Some((kept, total)) = shrink_records(&mut preview) else {
+            return;
+        };
+
+        let size = body.len();
+        let compressed = if gzip { compress(&body) } else { None };
+        let (full, meta) = match compressed {
+            Some(blob) => {
+                let uri = format!("{RESULT_PREFIX}{}.json.gz", uuid::Uuid::new_v4());
+                let mut meta = Meta::new();
+                meta.insert("contentEncoding".into(), "gzip".into());
+                meta.insert("compressedResult".into(), uri.clone().into());
+                let full = Content::resource(ResourceContents::BlobResourceContents {
+                    uri,
+                    mime_type: Some(GZIP_MIME_TYPE.to_string()),
+                    blob,
+                    meta: None,
+                });
+                (full, meta)
+            }
+            None => {
+                let uri = self.insert(body);
+                let mut link = RawResource::new(&uri, "Full tool result");
+                link.description = Some(format!(
+                    "All {total} records; the inline result is a preview of the first {kept}"
+                ));
+                link.mime_type = Some(RESOURCE_MIME_TYPE.to_string());
+                link.size = u32::try_from(size).ok();
+                let mut meta = Meta::new();
+                meta.insert("fullResult".into(), uri.into());
+                (Content::resource_link(link), meta)
+            }
+        };
+
+        // Keep attached exports, which hold every record.
+        result
+            .content
+            .retain(|c| matches!(c.raw, RawContent::Resource(_)));
+        result
+            .content
+            .splice(0..0, [Content::text(preview.to_string()), full]);
+        result.structured_content = Some(preview);
+        result.meta.get_or_insert_with(Meta::new).extend(meta);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_results_are_stored_with_a_preview() {
+        let rows: Vec<Value> = (0..5_000)
+            .map(|i| serde_json::json!({ "id": i, "name": "x".repeat(20) }))
+            .collect();
+        let full = serde_json::json!({ "rows": rows, "nextCursor": null });
+        let mut result = CallToolResult::structured(full.clone());
+
+        let store = ResultStore::default();
+        store.offload(&mut result, false);
+
+        let uri = result.meta.as_ref().unwrap()["fullResult"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        assert!(uri.starts_with(RESULT_PREFIX));
+        let preview = result.structured_content.as_ref().unwrap();
+        let kept = preview["rows"].as_array().unwrap().len();
+        assert!(kept > 0 && kept < 5_000, "{kept} rows kept");
+        assert!(preview.to_string().len() <= PREVIEW_BYTES + 100);
+        assert_eq!(result.content.len(), 2);
+
+        let stored: Value = serde_json::from_str(&store.get(&uri).unwrap()).unwrap();
+        assert_eq!(stored, full);
+        assert!(store.get("result://unknown").is_none());
+    }
+
+    #[test]
+    fn small_results_stay_inline() {
+        let mut result = CallToolResult::structured(serde_json::json!({ "rows": [{ "id": 1 }] }));
+        ResultStore::default().offload(&mut result, true);
+        assert!(result.meta.is_none());
+        assert_eq!(result.content.len(), 1);
+    }
+
+    #[test]
+    fn oversized_results_are_compressed_for_clients_that_accept_gzip() {
+        let rows: Vec<Value> = (0..10_000)
+            .map(|i| serde_json::json!({ "id": i }))
+            .collect();
+        let full = serde_json::json!({ "rows": rows });
+        let mut result = CallToolResult::structured(full.clone());
+
+        let store = ResultStore::default();
+        store.offload(&mut result, true);
+
+        let meta = result.meta.as_ref().unwrap();
+        assert_eq!(meta["contentEncoding"], "gzip");
+        assert!(meta.get("fullResult").is_none());
+        assert!(store.results.lock().unwrap().is_empty());
+        let RawContent::Resource(resource) = &result.content[1].raw else {
+            panic!("expected an embedded resource");
+        };
+        let ResourceContents::BlobResourceContents { blob, .. } = &resource.resource else {
+            panic!("expected a blob");
+        };
+        let compressed = BASE64.decode(blob).unwrap();
+        assert!(compressed.len() < full.to_string().len() / 4);
+        let decoded: Value =
+            serde_json::from_reader(flate2::read::GzDecoder::new(&compressed[..])).unwrap();
+        assert_eq!(decoded, full);
+    }
+}
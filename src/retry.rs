@@ -0,0 +1,141 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+
+use crate::backoff::Backoff;
+use crate::config::RetryPolicy;
+use crate::cosmos_rest::ErrorResponse;
+
+/// Delays between attempts when the service does not say how long to wait.
+const BACKOFF: Backoff = Backoff {
+    base: Duration::from_millis(500),
+    max: Duration::from_secs(8),
+};
+
+/// Azure SQL errors worth retrying: the database is unavailable while it
+/// moves or resumes (40613), the service hit an error (40197) or is busy
+/// (40501), the elastic pool cannot be reached (49918–49920), a resource
+/// limit was hit (10928, 10929), the login timed out waiting for a
+/// replica (4221), or the statement was chosen as a deadlock victim (1205).
+const MSSQL_TRANSIENT_ERRORS: &[u32] = &[
+    40613, 40197, 40501, 49918, 49919, 49920, 10928, 10929, 4221, 1205,
+];
+
+/// Whether `error` is transient, and if so how long the service asked to
+/// wait before retrying (`Some(None)` when it did not say).
+fn transient(error: &anyhow::Error) -> Option<Option<Duration>> {
+    if let Some(response) = error.downcast_ref::<ErrorResponse>() {
+        return response.is_transient().then_some(response.retry_after);
+    }
+    if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        // The request never reached the service.
+        return e.is_connect().then_some(None);
+    }
+    if error.downcast_ref::<std::io::Error>().is_some() {
+        return Some(None);
+    }
+    match error.downcast_ref::<tiberius::error::Error>()? {
+        tiberius::error::Error::Io { .. } | tiberius::error::Error::Tls(_) => Some(None),
+        e => e
+            .code()
+            .filter(|code| MSSQL_TRANSIENT_ERRORS.contains(code))
+            .map(|_| None),
+    }
+}
+
+/// Run `attempt` until it succeeds, fails with an error that is not
+/// transient, or `policy` allows no further attempt.
+///
+/// Waits between attempts as long as the service asked (Cosmos DB's
+/// `x-ms-retry-after-ms`), else with jittered exponential backoff.  A retry
+/// that would take the time spent waiting past `policy.max_wait` is not
+/// made; the last error is returned instead.
+pub async fn run<T, F, Fut>(policy: RetryPolicy, service: &str, mut attempt: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut waited = Duration::ZERO;
+    let mut n = 1;
+    loop {
+        let error = match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) => e,
+        };
+        if n >= policy.max_attempts {
+            return Err(error);
+        }
+        let Some(retry_after) = transient(&error) else {
+            return Err(error);
+        };
+        let delay = retry_after.unwrap_or_else(|| BACKOFF.delay(n));
+        if waited + delay > policy.max_wait {
+            return Err(error);
+        }
+        tracing::warn!(
+            "{service} attempt {n} failed transiently, retrying in {} ms: {error:#}",
+            delay.as_millis()
+        );
+        tokio::time::sleep(delay).await;
+        waited += delay;
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn throttled(retry_after_ms: u64) -> anyhow::Error {
+        ErrorResponse {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            message: "Request rate is large".into(),
+            retry_after: Some(Duration::from_millis(retry_after_ms)),
+            idempotent: false,
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried_within_the_budget() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            max_wait: Duration::from_millis(100),
+        };
+        let calls = AtomicU32::new(0);
+        let result = run(policy, "Cosmos DB", || async {
+            match calls.fetch_add(1, Ordering::Relaxed) {
+                0 | 1 => Err(throttled(10)),
+                _ => Ok("done"),
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "done");
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // Waiting longer than the budget allows is not attempted.
+        calls.store(0, Ordering::Relaxed);
+        let result: Result<()> = run(policy, "Cosmos DB", || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            Err(throttled(500))
+        })
+        .await;
+        assert!(result.unwrap_err().to_string().contains("429"));
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+
+        // Nor is an error that is not transient.
+        calls.store(0, Ordering::Relaxed);
+        let result: Result<()> = run(policy, "Cosmos DB", || async {
+            calls.fetch_add(1, Ordering::Relaxed);
+            anyhow::bail!("Syntax error")
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+}
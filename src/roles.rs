@@ -0,0 +1,117 @@
+use crate::config::Role;
+
+/// Tools that list, describe or read named objects without running a query
+/// written by the caller.
+const READER_TOOLS: &[&str] = &[
+    "mssql_list_tables",
+    "synapse_list_tables",
+    "fabric_list_tables",
+    "cosmos_list_databases",
+    "cosmos_list_containers",
+    "cosmos_container_stats",
+    "cosmos_list_programmability",
+    "cosmos_read_conflicts",
+    "cosmos_get_ttl",
+    "cosmos_read_item",
+    "cosmos_read_change_feed",
+    "gremlin_get_vertex",
+    "gremlin_get_edges",
+    "cassandra_list_keyspaces",
+    "cassandra_list_tables",
+    "blob_read",
+    "tables_list",
+    "adls_list_paths",
+    "adls_read_file",
+    "adls_get_properties",
+    "files_list_shares",
+    "files_list_directory",
+    "files_read_file",
+    "eventhub_read_events",
+    "kusto_list_tables",
+    "keyvault_list_secrets",
+    "appconfig_list_keys",
+    "appconfig_get_values",
+    "appconfig_list_feature_flags",
+    "fetch_next_page",
+    "server_stats",
+    "server_status",
+    "server_connection_security",
+];
+
+/// Tools that run read-only queries written (or drafted) by the caller, or
+/// spend money per call, on top of [`READER_TOOLS`].
+const ANALYST_TOOLS: &[&str] = &[
+    "mssql_execute_query",
+    "synapse_execute_query",
+    "fabric_execute_query",
+    "cosmos_query_items",
+    "cosmos_vector_search",
+    "cosmos_text_search",
+    "cosmos_analytical_query",
+    "gremlin_query",
+    "cassandra_query",
+    "tables_query",
+    "loganalytics_query",
+    "appinsights_query",
+    "kusto_query",
+    "cost_query",
+    "generate_query",
+    "embed_text",
+];
+
+/// Tools only the `admin` role is given: they change data or resources,
+/// hand out access, or can reveal secret values.
+#[cfg(test)]
+pub const ADMIN_TOOLS: &[&str] = &[
+    "cosmos_patch_item",
+    "cosmos_execute_stored_procedure",
+    "cosmos_set_item_ttl",
+    "cosmos_bulk_import",
+    "cosmos_create_database",
+    "cosmos_create_container",
+    "cosmos_set_container_ttl",
+    "blob_write",
+    "blob_share_url",
+    "servicebus_send",
+    "eventgrid_publish",
+    "keyvault_get_secret_metadata",
+];
+
+/// The names of the built-in roles.
+pub const BUILT_IN: &[&str] = &["reader", "analyst", "admin"];
+
+/// The built-in role `name`: `reader` browses, `analyst` also runs
+/// read-only queries, `admin` may call every tool.  None limits results
+/// beyond the server-wide settings.
+pub fn built_in(name: &str) -> Option<Role> {
+    let tools = match name {
+        "reader" => Some(READER_TOOLS.to_vec()),
+        "analyst" => Some([READER_TOOLS, ANALYST_TOOLS].concat()),
+        "admin" => None,
+        _ => return None,
+    };
+    Some(Role {
+        name: name.to_string(),
+        tools: tools.map(|tools| tools.into_iter().map(str::to_string).collect()),
+        max_result_bytes: None,
+        session_max_request_units: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_widen_from_reader_to_admin() {
+        let reader = built_in("reader").unwrap();
+        let analyst = built_in("analyst").unwrap();
+        let admin = built_in("admin").unwrap();
+        assert!(reader.allows("cosmos_read_item"));
+        assert!(!reader.allows("mssql_execute_query"));
+        assert!(analyst.allows("mssql_execute_query"));
+        assert!(!analyst.allows("cosmos_patch_item"));
+        assert!(admin.allows("cosmos_patch_item"));
+        assert!(built_in("owner").is_none());
+    }
+}
@@ -1,22 +1,103 @@
 use rmcp::{
-    handler::server::{router::tool::ToolRouter, wrapper::Parameters},
-    model::{Implementation, ProtocolVersion, ServerCapabilities, ServerInfo},
-    tool, tool_handler, tool_router, ServerHandler,
+    handler::server::{
+        router::{prompt::PromptRouter, tool::ToolRouter},
+        tool::ToolCallContext,
+        wrapper::Parameters,
+    },
+    model::{
+        CallToolRequestParams, CallToolResult, CompleteRequestParams, CompleteResult,
+        CompletionInfo, Content, GetPromptRequestParams, GetPromptResult, Implementation,
+        JsonObject, ListPromptsResult, ListResourceTemplatesResult, ListResourcesResult,
+        ListToolsResult, Meta, PaginatedRequestParams, PromptMessage, PromptMessageRole,
+        ProtocolVersion, ReadResourceRequestParams, ReadResourceResult, ResourceContents,
+        ResourceUpdatedNotificationParam, ServerCapabilities, ServerInfo, SubscribeRequestParams,
+        Tool, UnsubscribeRequestParams,
+    },
+    prompt, prompt_handler, prompt_router,
+    service::{NotificationContext, RequestContext},
+    tool, tool_router, ErrorData, Json, Peer, RoleServer, ServerHandler,
 };
 use schemars::JsonSchema;
 use serde::Deserialize;
-use std::sync::Arc;
+use serde_json::{Map, Value};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tracing::Instrument;
 
-use crate::config::Config;
+use crate::config::{Config, CosmosConfig, MssqlConfig};
 use crate::{
-    cosmos::{self, DEFAULT_MAX_ITEMS},
+    aad::TokenProvider,
+    adls,
+    annotations::{self, CallInfo},
+    appconfig,
+    audit::{self, AuditLog},
+    blob::{self, BlobEncoding},
+    budget, cassandra,
+    catalog::Catalog,
+    charts::{self, RenderOptions},
+    concurrency::QueryLimiter,
+    confirm::confirm,
+    correlation,
+    cosmos::{
+        self, ConsistencyLevel, CosmosAccount, ImportMode, PatchOperation, QuerySettings,
+        TextSearchMode, TextSearchRequest, DEFAULT_IMPORT_CONCURRENCY, DEFAULT_MAX_ITEMS,
+        DEFAULT_TOP_K,
+    },
+    cost::{self, CostDimension, CostQuery, Granularity},
+    eventgrid::{self, EventInput},
+    eventhubs::{self, StartingPosition},
+    export, files,
+    formats::{self, FormatOptions},
+    generate,
+    gremlin::{self, EdgeDirection},
+    health::{Availability, Backend, PROBE_INTERVAL},
+    keyvault, kusto, mock, monitor,
     mssql::{self, DEFAULT_MAX_ROWS},
+    openai,
+    output::{
+        structured, AppConfigSettings, BlobContent, BlobShareUrl, BlobWritten, CassandraTableList,
+        ChangeFeedPage, ConflictFeed, ConnectionSecurityReport, ContainerList, ContainerStatsList,
+        ContainerTtlChange, CostReport, CreatedContainer, CreatedDatabase, DatabaseList, Embedding,
+        EntitiesPage, EventBatch, FeatureFlagList, FileDirectoryListing, FileShareList,
+        GeneratedQuery, GremlinEdges, GremlinResult, GremlinVertex, ImportReport, ItemRead,
+        ItemTtlChange, ItemsPage, KeyVaultSecrets, KeyspaceList, KqlResult, KustoTableList,
+        LakePathList, LakePathProperties, NextPage, PatchResult, Programmability, PublishedEvents,
+        RowList, RowsPage, SecretMetadata, SentMessage, ServerStats, ServerStatus,
+        StorageTableList, StoredProcedureResult, TableList, TextSearchResults, TtlInfo,
+        VectorSearchResults,
+    },
+    pagination,
+    prompts::{self, QueryTarget},
+    readonly::{self, Dialect},
+    redact,
+    resources::{self, ResourceUri, RESOURCE_MIME_TYPE},
+    results::{ResultStore, GZIP_CAPABILITY, RESULT_PREFIX},
+    servicebus::{self, ServiceBusClient},
+    session_context::{self, SESSION_CONTEXT_CAPABILITY},
+    slowlog::SlowQueryLog,
+    stats::{self, CacheCounter, Stats},
+    status,
+    storage::StorageClient,
+    subscriptions::{Subscriptions, POLL_INTERVAL},
+    tables, transit,
 };
 
 // ---------------------------------------------------------------------------
 // Tool parameter types
 // ---------------------------------------------------------------------------
 
+/// Optional chart and text format of the records a query tool returns.
+/// Only declared in its parameters for the input schema: `call_tool` reads
+/// them from the raw arguments, adds the chart and formats the records.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[allow(dead_code)]
+pub struct OutputOptions {
+    #[serde(flatten)]
+    pub render: RenderOptions,
+    #[serde(flatten)]
+    pub format: FormatOptions,
+}
+
 /// Parameters for `mssql_execute_query`.
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct MssqlExecuteQueryParams {
@@ -24,6 +105,36 @@ pub struct MssqlExecuteQueryParams {
     pub query: String,
     /// Maximum number of rows to return (default: 500, maximum: 10 000).
     pub max_rows: Option<u64>,
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub output: OutputOptions,
+}
+
+/// Parameters for `synapse_execute_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SynapseExecuteQueryParams {
+    /// T-SQL query to execute, e.g. over lake files with
+    /// `SELECT * FROM OPENROWSET(BULK 'https://<account>.dfs.core.windows.net/<fs>/curated/sales/*.parquet', FORMAT = 'PARQUET') AS r`.
+    /// Results are capped to `max_rows` rows.
+    pub query: String,
+    /// Maximum number of rows to return (default: 500, maximum: 10 000).
+    pub max_rows: Option<u64>,
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub output: OutputOptions,
+}
+
+/// Parameters for `fabric_execute_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FabricExecuteQueryParams {
+    /// T-SQL query to execute against the warehouse or the lakehouse's SQL
+    /// analytics endpoint.  Results are capped to `max_rows` rows.
+    pub query: String,
+    /// Maximum number of rows to return (default: 500, maximum: 10 000).
+    pub max_rows: Option<u64>,
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub output: OutputOptions,
 }
 
 /// Parameters for `cosmos_list_containers`.
@@ -45,29 +156,811 @@ pub struct CosmosQueryItemsParams {
     /// omitted.
     pub database: Option<String>,
     /// Partition key value for single-partition queries.  Omit (or set to
-    /// `null`) to issue a cross-partition query.
+    /// `null`) to issue a cross-partition query, which is executed against
+    /// every partition key range.  Cross-partition queries cannot use
+    /// `ORDER BY`, aggregates, `GROUP BY`, `DISTINCT` or `OFFSET`/`LIMIT`.
     pub partition_key: Option<String>,
     /// Maximum number of items to return (default: 100, maximum: 5 000).
     pub max_items: Option<u32>,
+    /// Include server-side query execution metrics (index hit ratio,
+    /// retrieved vs output document counts, execution time) under
+    /// `metadata.query_metrics`.  Defaults to `false`.
+    pub include_metrics: Option<bool>,
+    /// Request unit budget: stop fetching further pages once this many RUs
+    /// have been consumed and return the items read so far.  Cannot exceed
+    /// the server's `COSMOS_MAX_REQUEST_UNITS` limit.
+    pub max_request_units: Option<f64>,
+    /// Consistency level for this query, e.g. `Eventual` for cheaper
+    /// analytics reads.  Must not be stronger than the account default.
+    pub consistency: Option<ConsistencyLevel>,
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub output: OutputOptions,
+}
+
+/// Parameters for `cosmos_vector_search`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosVectorSearchParams {
+    /// Container to search.
+    pub container: String,
+    /// Query vector; must match the dimensions of the container's vector
+    /// embedding policy.  Give either `embedding` or `text`.
+    pub embedding: Option<Vec<f64>>,
+    /// Text to embed with the Azure OpenAI embedding deployment and search
+    /// with, instead of `embedding`.
+    pub text: Option<String>,
+    /// Path of the vector property, e.g. `/embedding`.
+    pub path: String,
+    /// Number of nearest documents to return (default: 10, maximum: 100).
+    pub top_k: Option<u32>,
+    /// Restrict the search to one logical partition.
+    pub partition_key: Option<String>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_text_search`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosTextSearchParams {
+    /// Container to search; it needs a full-text policy on `path`.
+    pub container: String,
+    /// Path of the text property, e.g. `/description`.
+    pub path: String,
+    /// Search terms.
+    pub terms: Vec<String>,
+    /// `contains_all`, `contains_any`, `rank` (BM25 relevance, the default) or
+    /// `hybrid` (relevance fused with vector similarity).
+    pub mode: Option<TextSearchMode>,
+    /// Query vector for `hybrid` mode.
+    pub embedding: Option<Vec<f64>>,
+    /// Path of the vector property for `hybrid` mode, e.g. `/embedding`.
+    pub vector_path: Option<String>,
+    /// Number of documents to return (default: 10, maximum: 100).
+    pub top_k: Option<u32>,
+    /// Restrict the search to one logical partition.  Required for `rank` and
+    /// `hybrid` on containers with more than one physical partition.
+    pub partition_key: Option<String>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_analytical_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosAnalyticalQueryParams {
+    /// Container (with analytical store enabled) to query.
+    pub container: String,
+    /// T-SQL query that refers to the container as the table `docs`, e.g.
+    /// `SELECT status, COUNT(*) AS n FROM docs GROUP BY status`.
+    pub query: String,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Maximum number of rows to return (default: 500, maximum: 10 000).
+    pub max_rows: Option<u64>,
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub output: OutputOptions,
+}
+
+/// Parameters for `cosmos_read_item`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosReadItemParams {
+    /// Container holding the document.
+    pub container: String,
+    /// `id` of the document to read.
+    pub id: String,
+    /// Partition key value of the document.
+    pub partition_key: String,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Consistency level for this read.  Must not be stronger than the
+    /// account default.
+    pub consistency: Option<ConsistencyLevel>,
+}
+
+/// Parameters for `cosmos_container_stats`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosContainerStatsParams {
+    /// Container to report on.  Omit to report every container in the database.
+    pub container: Option<String>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_list_programmability`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosListProgrammabilityParams {
+    /// Container to inspect.
+    pub container: String,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Include the JavaScript source of each artifact (default: `false`).
+    pub include_body: Option<bool>,
+}
+
+/// Parameters for `cosmos_read_conflicts`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosReadConflictsParams {
+    /// Container whose conflicts feed is read.
+    pub container: String,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Maximum number of conflicts to return (default: 100, maximum: 5 000).
+    pub max_items: Option<u32>,
+    /// Also read the version of each document that currently won, for
+    /// side-by-side comparison (default: `true`).
+    pub include_current: Option<bool>,
+}
+
+/// Parameters for `cosmos_read_change_feed`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosReadChangeFeedParams {
+    /// Container whose change feed is read.
+    pub container: String,
+    /// RFC 3339 timestamp, e.g. `"2026-01-01T12:00:00Z"`.  Only changes made
+    /// at or after this time are returned.  Ignored when `continuation` is set.
+    pub since: Option<String>,
+    /// Continuation token returned by a previous call, to read only changes
+    /// made since then.
+    pub continuation: Option<String>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Maximum number of changed documents to return (default: 100, maximum: 5 000).
+    pub max_items: Option<u32>,
+    /// Request unit budget: stop reading once this many RUs have been
+    /// consumed.  Cannot exceed the server's `COSMOS_MAX_REQUEST_UNITS` limit.
+    pub max_request_units: Option<f64>,
+}
+
+/// Parameters for `cosmos_patch_item`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosPatchItemParams {
+    /// Container holding the document.
+    pub container: String,
+    /// Document `id`.
+    pub id: String,
+    /// Partition key value of the document.
+    pub partition_key: String,
+    /// JSON Patch operations applied in order, e.g.
+    /// `[{"op": "set", "path": "/status", "value": "shipped"}]`.
+    pub operations: Vec<PatchOperation>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_execute_stored_procedure`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosExecuteStoredProcedureParams {
+    /// Container the stored procedure is defined on.
+    pub container: String,
+    /// Stored procedure name (`id`).
+    pub procedure: String,
+    /// Partition key value of the logical partition the procedure runs in.
+    pub partition_key: String,
+    /// Positional arguments passed to the procedure.
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_get_ttl`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosGetTtlParams {
+    /// Container to inspect.
+    pub container: String,
+    /// Optional document `id`; when set, that item's `ttl` is also reported.
+    pub id: Option<String>,
+    /// Partition key value of the document.  Required when `id` is set.
+    pub partition_key: Option<String>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_set_item_ttl`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosSetItemTtlParams {
+    /// Container holding the document.
+    pub container: String,
+    /// Document `id`.
+    pub id: String,
+    /// Partition key value of the document.
+    pub partition_key: String,
+    /// Lifetime in seconds after the document's last write, or `-1` to never
+    /// expire.  Omit (or `null`) to remove the item's own `ttl` and fall back
+    /// to the container default.
+    pub ttl: Option<i64>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_bulk_import`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosBulkImportParams {
+    /// Container to write to.
+    pub container: String,
+    /// Documents to write.  Each must have a string `id` and its partition key
+    /// property.  Provide either `documents` or `file_path`.
+    pub documents: Option<Vec<serde_json::Value>>,
+    /// Path, relative to the server's `COSMOS_IMPORT_DIR`, of a file holding
+    /// a JSON array or newline-delimited JSON documents.  Provide either
+    /// `documents` or `file_path`.
+    pub file_path: Option<String>,
+    /// `upsert` (default) to insert or replace, `create` to insert only.
+    pub mode: Option<ImportMode>,
+    /// Number of transactional batches written concurrently (default: 4,
+    /// maximum: 16).
+    pub concurrency: Option<usize>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_create_database`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosCreateDatabaseParams {
+    /// Name of the database to create.
+    pub database: String,
+    /// Fixed provisioned throughput in RU/s (e.g. 400).  Omit for serverless
+    /// accounts or when containers provision their own throughput.
+    pub throughput: Option<u32>,
+    /// Maximum RU/s for autoscale throughput (e.g. 4000).  Mutually exclusive
+    /// with `throughput`.
+    pub autoscale_max_throughput: Option<u32>,
+}
+
+/// Parameters for `cosmos_set_container_ttl`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosSetContainerTtlParams {
+    /// Container to update.
+    pub container: String,
+    /// Default lifetime of items in seconds, or `-1` to enable TTL without a
+    /// default (only items with their own `ttl` expire).  Omit (or `null`) to
+    /// disable TTL.
+    pub default_ttl: Option<i64>,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `cosmos_create_container`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CosmosCreateContainerParams {
+    /// Name of the container to create.
+    pub container: String,
+    /// Partition key path, e.g. `"/tenantId"`.
+    pub partition_key_path: String,
+    /// Cosmos DB database name.  Falls back to `COSMOS_DEFAULT_DATABASE` when
+    /// omitted.
+    pub database: Option<String>,
+    /// Fixed provisioned throughput in RU/s (e.g. 400).  Omit to share the
+    /// database's throughput or on serverless accounts.
+    pub throughput: Option<u32>,
+    /// Maximum RU/s for autoscale throughput (e.g. 4000).  Mutually exclusive
+    /// with `throughput`.
+    pub autoscale_max_throughput: Option<u32>,
+}
+
+/// Parameters for `gremlin_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GremlinQueryParams {
+    /// Gremlin traversal, e.g. `g.V().hasLabel('person').out('knows').values('name')`.
+    pub query: String,
+    /// Values for the traversal's parameters, e.g. `{"name": "alice"}` for
+    /// `g.V().has('name', name)`.
+    #[serde(default)]
+    pub bindings: Map<String, Value>,
+    /// Graph to query.  Falls back to `GREMLIN_GRAPH` when omitted.
+    pub graph: Option<String>,
+    /// Maximum number of results to return (default: 100, maximum: 1 000).
+    pub max_results: Option<usize>,
+}
+
+/// Parameters for `gremlin_get_vertex`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GremlinGetVertexParams {
+    /// Vertex `id`.
+    pub id: String,
+    /// Partition key value of the vertex; needed for a cheap point read on
+    /// partitioned graphs.
+    pub partition_key: Option<String>,
+    /// Graph to read.  Falls back to `GREMLIN_GRAPH` when omitted.
+    pub graph: Option<String>,
+}
+
+/// Parameters for `gremlin_get_edges`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GremlinGetEdgesParams {
+    /// `id` of the vertex whose edges to list.
+    pub vertex_id: String,
+    /// Partition key value of the vertex.
+    pub partition_key: Option<String>,
+    /// `out` (default), `in` or `both`.
+    #[serde(default)]
+    pub direction: EdgeDirection,
+    /// Only return edges with this label.
+    pub label: Option<String>,
+    /// Graph to read.  Falls back to `GREMLIN_GRAPH` when omitted.
+    pub graph: Option<String>,
+    /// Maximum number of edges to return (default: 100, maximum: 1 000).
+    pub max_results: Option<usize>,
+}
+
+/// Parameters for `cassandra_list_tables`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CassandraListTablesParams {
+    /// Keyspace whose tables to list.  Falls back to `CASSANDRA_KEYSPACE`
+    /// when omitted.
+    pub keyspace: Option<String>,
+}
+
+/// Parameters for `cassandra_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CassandraQueryParams {
+    /// CQL `SELECT` statement, e.g.
+    /// `SELECT * FROM orders WHERE customer_id = 'c-42' LIMIT 20`.
+    pub query: String,
+    /// Keyspace the query runs in.  Falls back to `CASSANDRA_KEYSPACE` when
+    /// omitted; not needed when the query names `keyspace.table`.
+    pub keyspace: Option<String>,
+    /// Maximum number of rows to return (default: 500, maximum: 10 000).
+    pub max_rows: Option<u64>,
+}
+
+/// Parameters for `fetch_next_page`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FetchNextPageParams {
+    /// The `nextCursor` of a previous `mssql_execute_query`,
+    /// `synapse_execute_query`, `fabric_execute_query`, `cassandra_query`,
+    /// `cosmos_query_items`, `tables_query`, `adls_list_paths`,
+    /// `files_list_directory` or `fetch_next_page` result.
+    pub cursor: String,
+    /// Optional text format of the records, as for the original query.
+    #[serde(flatten)]
+    #[allow(dead_code)]
+    pub format: FormatOptions,
+}
+
+/// Parameters for `blob_read`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlobReadParams {
+    /// Blob container name.
+    pub container: String,
+    /// Path of the blob within the container, e.g. `exports/2026/orders.csv`.
+    pub path: String,
+    /// Offset of the first byte to read (default 0).
+    pub offset: Option<u64>,
+    /// Number of bytes to read (default 65 536, maximum 1 048 576).
+    pub length: Option<u64>,
+    /// `auto` (default: text when the bytes are UTF-8, base64 otherwise),
+    /// `text` or `base64`.
+    pub encoding: Option<BlobEncoding>,
+}
+
+/// Parameters for `blob_share_url`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlobShareUrlParams {
+    /// Blob container name.
+    pub container: String,
+    /// Path of the blob within the container, e.g. `exports/2026/orders.csv`.
+    pub path: String,
+    /// How long the URL stays valid (default 60, maximum 1 440 = 24 hours).
+    pub expires_in_minutes: Option<u32>,
+}
+
+/// Parameters for `blob_write`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlobWriteParams {
+    /// Blob container name.
+    pub container: String,
+    /// Path of the blob within the container, e.g. `exports/2026/orders.csv`.
+    pub path: String,
+    /// Content to upload (at most 16 MiB once decoded).
+    pub content: String,
+    /// `text` (default) or `base64` for binary content.
+    pub encoding: Option<BlobEncoding>,
+    /// MIME type stored with the blob.  Guessed from the path's extension
+    /// when omitted.
+    pub content_type: Option<String>,
+    /// Replace the blob if it already exists (default false: fail instead).
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// Parameters for `adls_list_paths`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AdlsListPathsParams {
+    /// Filesystem (container) name.
+    pub filesystem: String,
+    /// Directory to list, e.g. `raw/sales`; the filesystem root when omitted.
+    pub directory: Option<String>,
+    /// Also list everything below subdirectories (default false).
+    #[serde(default)]
+    pub recursive: bool,
+    /// Maximum number of paths per page (default 500, maximum 5 000).
+    pub max_results: Option<u32>,
+}
+
+/// Parameters for `adls_read_file`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AdlsReadFileParams {
+    /// Filesystem (container) name.
+    pub filesystem: String,
+    /// Path of the file, e.g. `curated/sales/2026/summary.json`.
+    pub path: String,
+    /// Offset of the first byte to read (default 0).
+    pub offset: Option<u64>,
+    /// Number of bytes to read (default 65 536, maximum 1 048 576).
+    pub length: Option<u64>,
+    /// `auto` (default: text when the bytes are UTF-8, base64 otherwise),
+    /// `text` or `base64`.
+    pub encoding: Option<BlobEncoding>,
+}
+
+/// Parameters for `adls_get_properties`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AdlsGetPropertiesParams {
+    /// Filesystem (container) name.
+    pub filesystem: String,
+    /// Path of the file or directory.
+    pub path: String,
+}
+
+/// Parameters for `files_list_directory`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilesListDirectoryParams {
+    /// File share name.
+    pub share: String,
+    /// Directory to list, e.g. `exports/2026`; the share root when omitted.
+    pub directory: Option<String>,
+    /// Maximum number of entries per page (default 500, maximum 5 000).
+    pub max_results: Option<u32>,
+}
+
+/// Parameters for `files_read_file`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FilesReadFileParams {
+    /// File share name.
+    pub share: String,
+    /// Path of the file within the share, e.g. `exports/2026/orders.csv`.
+    pub path: String,
+    /// Offset of the first byte to read (default 0).
+    pub offset: Option<u64>,
+    /// Number of bytes to read (default 65 536, maximum 1 048 576).
+    pub length: Option<u64>,
+    /// `auto` (default: text when the bytes are UTF-8, base64 otherwise),
+    /// `text` or `base64`.
+    pub encoding: Option<BlobEncoding>,
+}
+
+/// Parameters for `tables_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TablesQueryParams {
+    /// Table name.
+    pub table: String,
+    /// OData filter, e.g. `PartitionKey eq 'eu' and Amount gt 100`.  Filter
+    /// on `PartitionKey` (and `RowKey`) wherever possible: other filters scan
+    /// the table.
+    pub filter: Option<String>,
+    /// Properties to return; all when omitted.
+    pub select: Option<Vec<String>>,
+    /// Maximum number of entities per page (default 100, maximum 1 000).
+    pub top: Option<u32>,
+}
+
+/// Parameters for `servicebus_send`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ServiceBusSendParams {
+    /// Queue or topic name.
+    pub entity: String,
+    /// Message body.
+    pub body: String,
+    /// MIME type of the body (default `application/json`).
+    pub content_type: Option<String>,
+    /// Service Bus system properties, e.g. `{"MessageId": "...",
+    /// "CorrelationId": "...", "SessionId": "...", "Label": "..."}`.
+    pub broker_properties: Option<Map<String, Value>>,
+    /// Custom application properties.
+    pub properties: Option<Map<String, Value>>,
+}
+
+/// Parameters for `eventgrid_publish`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EventGridPublishParams {
+    /// Name of a topic in `EVENTGRID_TOPICS` (the first label of its host).
+    pub topic: String,
+    /// Events to publish (at most 100).
+    pub events: Vec<EventInput>,
+}
+
+/// Parameters for `eventhub_read_events`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EventHubReadEventsParams {
+    /// Event hub name.
+    pub hub: String,
+    /// Partition to read (default 0).
+    pub partition: Option<i32>,
+    /// `latest` (default: the most recent `max_events` events), `earliest`,
+    /// an offset from a previous result, or an RFC 3339 enqueue time.
+    pub starting_position: Option<String>,
+    /// Maximum number of events to return (default 10, maximum 100).
+    pub max_events: Option<u32>,
+}
+
+/// Parameters for `loganalytics_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LogAnalyticsQueryParams {
+    /// KQL query, e.g. `AppRequests | where Success == false | take 50`.
+    pub kql: String,
+    /// Workspace (customer) ID.  Falls back to `LOGANALYTICS_WORKSPACE_ID`
+    /// when omitted.
+    pub workspace_id: Option<String>,
+    /// Time range: an ISO 8601 duration such as `PT1H` or `P7D`, or an
+    /// interval `2026-01-01T00:00:00Z/2026-01-02T00:00:00Z`.  Without it
+    /// only the query's own time filters apply.
+    pub timespan: Option<String>,
+    /// Maximum number of rows to return (default 500, maximum 10 000).
+    pub max_rows: Option<usize>,
+}
+
+/// Parameters for `appinsights_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AppInsightsQueryParams {
+    /// KQL query over `requests`, `traces`, `exceptions`, `dependencies`,
+    /// ..., e.g. `exceptions | summarize count() by type, bin(timestamp, 5m)`.
+    pub kql: String,
+    /// Application ID of the Application Insights resource.  Falls back to
+    /// `APPINSIGHTS_APP_ID` when omitted.
+    pub app_id: Option<String>,
+    /// Time range: an ISO 8601 duration such as `PT1H` or `P7D`, or an
+    /// interval `start/end`.  Without it the service applies its default of
+    /// the last 24 hours unless the query filters on `timestamp`.
+    pub timespan: Option<String>,
+    /// Maximum number of rows to return (default 500, maximum 10 000).
+    pub max_rows: Option<usize>,
+}
+
+/// Parameters for `kusto_list_tables`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct KustoListTablesParams {
+    /// Database name.  Falls back to `KUSTO_DATABASE` when omitted.
+    pub database: Option<String>,
+}
+
+/// Parameters for `kusto_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct KustoQueryParams {
+    /// KQL query, e.g. `Traces | where Level == "Error" | summarize count() by bin(Timestamp, 5m)`.
+    /// Control commands (starting with `.`) are rejected.
+    pub kql: String,
+    /// Database name.  Falls back to `KUSTO_DATABASE` when omitted.
+    pub database: Option<String>,
+    /// Maximum number of rows to return (default 500, maximum 10 000).
+    pub max_rows: Option<usize>,
+}
+
+/// Parameters for `cost_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CostQueryParams {
+    /// First day of the period, `YYYY-MM-DD`.
+    pub from: String,
+    /// Last day of the period, `YYYY-MM-DD` (default today).
+    pub to: Option<String>,
+    /// Dimensions to break the cost down by, e.g. `["service"]` or
+    /// `["resource_group", "service"]`.  A single total when omitted.
+    #[serde(default)]
+    pub group_by: Vec<CostDimension>,
+    /// `none` (default: one total per group), `daily` or `monthly`.
+    #[serde(default)]
+    pub granularity: Granularity,
+    /// Only count these services, e.g. `["Azure Cosmos DB"]`.
+    #[serde(default)]
+    pub service_names: Vec<String>,
+    /// Only count these resource groups.
+    #[serde(default)]
+    pub resource_groups: Vec<String>,
+    /// Report amortized cost, spreading reservation purchases over their
+    /// term (default false: actual cost).
+    #[serde(default)]
+    pub amortized: bool,
+    /// Scope to report on, e.g. `/subscriptions/{id}/resourceGroups/{name}`
+    /// or a billing account.  Defaults to the subscription in
+    /// `AZURE_SUBSCRIPTION_ID`.
+    pub scope: Option<String>,
+}
+
+/// Parameters for `keyvault_get_secret_metadata`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct KeyVaultSecretParams {
+    /// Secret name.
+    pub name: String,
+    /// Also return the current value (default false).  Only honoured when
+    /// `KEYVAULT_ALLOW_SECRET_VALUES=true`.
+    #[serde(default)]
+    pub include_value: bool,
+}
+
+/// Parameters for `appconfig_list_keys`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AppConfigListKeysParams {
+    /// Key filter, e.g. `Checkout:*`; a trailing `*` matches any suffix and
+    /// commas separate alternatives.  Default: every key.
+    pub key: Option<String>,
+    /// Label filter, e.g. `prod` or `prod,staging`; `\0` matches settings
+    /// without a label.  Default: every label.
+    pub label: Option<String>,
+}
+
+/// Parameters for `appconfig_get_values`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AppConfigGetValuesParams {
+    /// Key or key filter, e.g. `Checkout:Timeout` or `Checkout:*`.
+    pub key: String,
+    /// Label filter, e.g. `prod` or `prod,staging`; `\0` matches settings
+    /// without a label.  Default: every label, to compare environments.
+    pub label: Option<String>,
+}
+
+/// Parameters for `appconfig_list_feature_flags`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AppConfigFeatureFlagsParams {
+    /// Flag name filter, e.g. `Beta*`.  Default: every flag.
+    pub name: Option<String>,
+    /// Label filter, e.g. `prod`.  Default: every label.
+    pub label: Option<String>,
+}
+
+/// Parameters for `embed_text`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EmbedTextParams {
+    /// Text to embed, e.g. a question to search documents with.
+    pub text: String,
+    /// Number of dimensions to shorten the vector to (`text-embedding-3`
+    /// models only).  Defaults to `AZURE_OPENAI_EMBEDDING_DIMENSIONS`, else
+    /// the model's native size.
+    pub dimensions: Option<u32>,
+}
+
+/// Parameters for `generate_query`.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateQueryParams {
+    /// The question the query should answer.
+    pub question: String,
+    /// MSSQL table to query, as `schema.table`.
+    pub table: Option<String>,
+    /// Cosmos DB container to query.
+    pub container: Option<String>,
+    /// Cosmos DB database of `container`.  Falls back to
+    /// `COSMOS_DEFAULT_DATABASE` when omitted.
+    pub database: Option<String>,
+}
+
+// ---------------------------------------------------------------------------
+// Prompt parameter types
+// ---------------------------------------------------------------------------
+
+/// Arguments of the `profile_table` prompt.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ProfileTableArgs {
+    /// Schema of the table, e.g. `dbo`.
+    pub schema: String,
+    /// Name of the table to profile.
+    pub table: String,
+}
+
+/// Arguments of the `explain_schema` prompt.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExplainSchemaArgs {
+    /// Only explain tables in this schema.
+    pub schema: Option<String>,
+}
+
+/// Arguments of the `write_query` prompt.
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WriteQueryArgs {
+    /// The question the query should answer.
+    pub question: String,
+    /// MSSQL table to query, as `schema.table`.
+    pub table: Option<String>,
+    /// Cosmos DB container to query.
+    pub container: Option<String>,
+    /// Cosmos DB database of `container`.  Falls back to
+    /// `COSMOS_DEFAULT_DATABASE` when omitted.
+    pub database: Option<String>,
 }
 
 // ---------------------------------------------------------------------------
 // Server
 // ---------------------------------------------------------------------------
 
+/// Resolve the Cosmos database for a tool call, falling back to
+/// `COSMOS_DEFAULT_DATABASE` when the caller omits it.
+fn resolve_database(cfg: &CosmosConfig, database: Option<&str>) -> Result<String, String> {
+    database
+        .or(cfg.default_database.as_deref())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            "database parameter is required when COSMOS_DEFAULT_DATABASE is not set".to_string()
+        })
+}
+
+/// The page of a SQL statement that may modify data: no rows, just the
+/// number it affected.
+fn statement_result(rows_affected: u64) -> Value {
+    serde_json::json!({ "rows": [], "rows_affected": rows_affected, "nextCursor": null })
+}
+
+/// Render `value` as indented JSON for a confirmation request.
+fn pretty_json(value: &impl serde::Serialize) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+/// Server instructions sent on initialization unless `MCP_INSTRUCTIONS` or
+/// `MCP_INSTRUCTIONS_FILE` replaces them.
+const DEFAULT_INSTRUCTIONS: &str = "This MCP server provides tools for querying Azure MSSQL and \
+     Azure Cosmos DB data stores.  Use the mssql_* tools for relational data and the cosmos_* \
+     tools for document data.  Table schemas and container descriptions are also published as \
+     mssql://schema/... and cosmos://... resources.";
+
 /// MCP server that exposes Azure MSSQL and Cosmos DB as tools.
 #[derive(Clone)]
 pub struct AzureMcpServer {
     config: Arc<Config>,
+    /// Identifies the MCP session this server serves.
+    session: Arc<String>,
+    /// Cosmos DB clients, built on first use and shared by every tool call.
+    cosmos: Arc<OnceLock<CosmosAccount>>,
+    /// Azure Storage client, built on first use.
+    storage: Arc<OnceLock<StorageClient>>,
+    /// Service Bus client, built on first use.
+    servicebus: Arc<OnceLock<ServiceBusClient>>,
+    /// Azure AD tokens, acquired on first use and cached per resource.
+    tokens: Arc<OnceLock<TokenProvider>>,
+    /// Cached table and container names for argument completion.
+    catalog: Arc<Catalog>,
+    /// Schema resources the client subscribed to.
+    subscriptions: Arc<Subscriptions>,
+    /// Which backends are reachable; tools of the others are not offered.
+    availability: Arc<Availability>,
+    /// Oversized tool results, readable as `result://` resources.
+    results: Arc<ResultStore>,
+    /// Where every tool call is recorded, if anywhere.
+    audit: Arc<AuditLog>,
+    /// Where calls slower than `SLOW_QUERY_MS` are recorded.
+    slow_queries: Arc<SlowQueryLog>,
+    /// Call counts and durations, reported by `server_stats`.
+    stats: Arc<Stats>,
+    /// Per-backend limits on the tool calls running at once.
+    limiter: Arc<QueryLimiter>,
     tool_router: ToolRouter<Self>,
+    prompt_router: PromptRouter<Self>,
 }
 
-#[tool_handler(router = self.tool_router)]
+#[prompt_handler(router = self.prompt_router)]
 impl ServerHandler for AzureMcpServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_tool_list_changed()
+                .enable_resources()
+                .enable_resources_subscribe()
+                .enable_resources_list_changed()
+                .enable_prompts()
+                .enable_completions()
+                .build(),
             server_info: Implementation {
                 name: "azure-mcp-server".into(),
                 version: env!("CARGO_PKG_VERSION").into(),
@@ -77,169 +970,2875 @@ impl ServerHandler for AzureMcpServer {
                 website_url: None,
             },
             instructions: Some(
-                "This MCP server provides tools for querying Azure MSSQL and \
-                 Azure Cosmos DB data stores.  Use the mssql_* tools for \
-                 relational data and the cosmos_* tools for document data."
-                    .into(),
+                self.config
+                    .customization
+                    .instructions
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_INSTRUCTIONS.into()),
             ),
         }
     }
-}
-
-#[tool_router]
-impl AzureMcpServer {
-    // ------------------------------------------------------------------
-    // MSSQL tools
-    // ------------------------------------------------------------------
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let render = RenderOptions::from_arguments(request.arguments.as_ref());
+        let format = FormatOptions::from_arguments(request.arguments.as_ref());
+        let gzip = context
+            .peer
+            .peer_info()
+            .and_then(|info| info.capabilities.experimental.as_ref())
+            .is_some_and(|experimental| experimental.contains_key(GZIP_CAPABILITY));
+        let session_context = context
+            .peer
+            .peer_info()
+            .and_then(|info| info.capabilities.experimental.as_ref())
+            .and_then(|experimental| experimental.get(SESSION_CONTEXT_CAPABILITY))
+            .cloned()
+            .unwrap_or_default();
+        let tool = request.name.clone();
+        let client = context
+            .peer
+            .peer_info()
+            .map(|info| info.client_info.clone());
+        let arguments = request.arguments.clone();
+        let started = Instant::now();
+        let call = ToolCallContext::new(self, request, context);
+        let correlation_id = correlation::new_id();
+        let span = tracing::info_span!(
+            "tool_call",
+            tool = %tool,
+            session = %self.session,
+            correlation_id = %correlation_id,
+        );
+        let in_flight = self.stats.begin();
+        let refusal = self
+            .request_unit_ceiling(&tool)
+            .or_else(|| self.session_quota(&tool));
+        let refused = refusal.is_some();
+        let mut result = match refusal {
+            Some(refusal) => Ok(CallToolResult::error(vec![Content::text(refusal)])),
+            None => {
+                let tools = self.available_tools();
+                let run = async {
+                    let _permit = match Backend::of_tool(&tool) {
+                        Some(backend) => self.limiter.acquire(backend).await,
+                        None => None,
+                    };
+                    match &self.config.mock {
+                        Some(mock) if tools.has_route(&tool) => Ok(mock::call(mock, &tool).await),
+                        _ => tools.call(call).await,
+                    }
+                };
+                let run = session_context::scope(session_context, run);
+                correlation::scope(correlation_id.clone(), run)
+                    .instrument(span.clone())
+                    .await
+            }
+        };
+        drop(in_flight);
+        let duration = started.elapsed();
+        redact::tool_error(&mut result);
+        let (failed, request_units) = match &result {
+            Ok(result) => (result.is_error == Some(true), stats::request_charge(result)),
+            Err(_) => (true, 0.0),
+        };
+        span.in_scope(|| {
+            tracing::info!(
+                duration_ms = duration.as_millis() as u64,
+                outcome = if failed { "error" } else { "ok" },
+                request_units,
+                "Tool call finished"
+            )
+        });
+        self.stats
+            .record(&self.session, &tool, duration, failed, request_units);
+        if stats::is_query(&tool) && !refused {
+            let (rows, bytes) = match &result {
+                Ok(result) if !failed => stats::result_size(result),
+                _ => (0, 0),
+            };
+            self.stats.record_query(&self.session, rows, bytes);
+        }
+        let result = result.map(|mut result| {
+            let call = CallInfo {
+                backend: Backend::of_tool(&tool),
+                duration,
+                arguments: arguments.as_ref(),
+            };
+            formats::parse_json_columns(&mut result, format);
+            formats::truncate_cells(
+                &mut result,
+                format.max_cell_chars.or(self.config.max_cell_chars),
+            );
+            export::apply(&mut result, format);
+            self.results.offload(&mut result, gzip);
+            annotations::annotate(&mut result, &call);
+            charts::render(&mut result, render);
+            formats::apply(&mut result, format);
+            result
+                .meta
+                .get_or_insert_with(Meta::new)
+                .insert(correlation::META_KEY.into(), correlation_id.clone().into());
+            result
+        });
+        let result = result.map_err(|mut error| {
+            let mut data = match error.data.take() {
+                Some(Value::Object(data)) => data,
+                Some(other) => Map::from_iter([("data".to_string(), other)]),
+                None => Map::new(),
+            };
+            data.insert(correlation::META_KEY.into(), correlation_id.clone().into());
+            error.data = Some(Value::Object(data));
+            error
+        });
+        self.slow_queries
+            .record(
+                &correlation_id,
+                &tool,
+                arguments.as_ref(),
+                duration,
+                &result,
+            )
+            .await;
+        if self.audit.enabled() {
+            let entry = audit::entry(
+                &correlation_id,
+                self.audit.session(),
+                client.as_ref(),
+                &tool,
+                arguments.as_ref(),
+                duration,
+                &result,
+            );
+            self.record_audit(&entry).await;
+        }
+        result
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        Ok(ListToolsResult {
+            tools: self.available_tools().list_all(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    fn get_tool(&self, name: &str) -> Option<Tool> {
+        self.available_tools().get(name).cloned()
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, ErrorData> {
+        let cosmos = match &self.config.cosmos {
+            Some(_) => self
+                .cosmos()
+                .inspect_err(|e| tracing::warn!("Skipping Cosmos DB resources: {e}"))
+                .ok(),
+            None => None,
+        };
+        Ok(ListResourcesResult::with_all_items(
+            resources::list(&self.config, cosmos).await,
+        ))
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParams>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, ErrorData> {
+        Ok(ListResourceTemplatesResult::with_all_items(
+            resources::templates(&self.config),
+        ))
+    }
+
+    async fn complete(
+        &self,
+        request: CompleteRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<CompleteResult, ErrorData> {
+        let context = request
+            .context
+            .and_then(|c| c.arguments)
+            .unwrap_or_default();
+        let argument = request.argument;
+        let (values, total) = self
+            .catalog
+            .complete(
+                &self.config,
+                self.cosmos(),
+                &argument.name,
+                &argument.value,
+                &context,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("No completions for '{}': {e:#}", argument.name);
+                (Vec::new(), 0)
+            });
+
+        let has_more = total > values.len();
+        let completion = CompletionInfo::with_pagination(values, Some(total as u32), has_more)
+            .map_err(|e| ErrorData::internal_error(e, None))?;
+        Ok(CompleteResult { completion })
+    }
+
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        tracing::info!("client initialized");
+        if self.config.mock.is_none() {
+            tokio::spawn(self.clone().watch_backends(context.peer));
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        request: SubscribeRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        let uri = ResourceUri::parse(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("Unknown resource '{}'", request.uri), None)
+        })?;
+        if !Subscriptions::supports(&uri) {
+            return Err(ErrorData::invalid_params(
+                "Only schema resources (mssql://schema/... and cosmos://{database}/{container}) \
+                 support subscriptions",
+                None,
+            ));
+        }
+        let body = resources::read(&self.config, self.cosmos(), &uri)
+            .await
+            .map_err(|e| ErrorData::internal_error(format!("{e:#}"), None))?;
+
+        if self.subscriptions.subscribe(request.uri, Some(&body)) {
+            tokio::spawn(self.clone().watch_resources(context.peer));
+        }
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        request: UnsubscribeRequestParams,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), ErrorData> {
+        self.subscriptions.unsubscribe(&request.uri);
+        Ok(())
+    }
+
+    async fn read_resource(
+        &self,
+        request: ReadResourceRequestParams,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ReadResourceResult, ErrorData> {
+        if request.uri.starts_with(RESULT_PREFIX) {
+            let text = self.results.get(&request.uri).ok_or_else(|| {
+                ErrorData::resource_not_found(
+                    format!("Result '{}' has expired or does not exist", request.uri),
+                    None,
+                )
+            })?;
+            return Ok(ReadResourceResult {
+                contents: vec![ResourceContents::TextResourceContents {
+                    uri: request.uri,
+                    mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+                    text,
+                    meta: None,
+                }],
+            });
+        }
+        let uri = ResourceUri::parse(&request.uri).ok_or_else(|| {
+            ErrorData::resource_not_found(format!("Unknown resource '{}'", request.uri), None)
+        })?;
+        if let Some(refusal) = self.quota_exceeded() {
+            return Err(ErrorData::invalid_request(refusal, None));
+        }
+        let correlation_id = correlation::new_id();
+        let started = Instant::now();
+        let read = async {
+            let _permit = self.limiter.acquire(uri.backend()).await;
+            resources::read(&self.config, self.cosmos(), &uri).await
+        };
+        let body = correlation::scope(correlation_id.clone(), read)
+            .await
+            .map_err(|e| {
+                ErrorData::internal_error(redact::redact(&format!("{e:#}")).into_owned(), None)
+            });
+        let (rows, bytes) = match &body {
+            Ok(body) => (uri.records(body), budget::json_size(body) as u64),
+            Err(_) => (0, 0),
+        };
+        self.stats.record_query(&self.session, rows, bytes);
+        if self.audit.enabled() {
+            // Recorded like a tool call, with the URI as its argument.
+            let client = context
+                .peer
+                .peer_info()
+                .map(|info| info.client_info.clone());
+            let arguments =
+                JsonObject::from_iter([("uri".to_string(), request.uri.clone().into())]);
+            let outcome = match &body {
+                Ok(_) => Ok(CallToolResult::success(Vec::new())),
+                Err(e) => Err(e.clone()),
+            };
+            let entry = audit::entry(
+                &correlation_id,
+                self.audit.session(),
+                client.as_ref(),
+                "resources/read",
+                Some(&arguments),
+                started.elapsed(),
+                &outcome,
+            );
+            self.record_audit(&entry).await;
+        }
+        let body = body?;
+
+        Ok(ReadResourceResult {
+            contents: vec![ResourceContents::TextResourceContents {
+                uri: request.uri,
+                mime_type: Some(RESOURCE_MIME_TYPE.to_string()),
+                text: body.to_string(),
+                meta: None,
+            }],
+        })
+    }
+}
+
+#[tool_router]
+impl AzureMcpServer {
+    // ------------------------------------------------------------------
+    // MSSQL tools
+    // ------------------------------------------------------------------
 
     /// List all user tables in the Azure MSSQL database.
     ///
-    /// Returns a JSON array of objects with `schema` and `table_name` fields.
+    /// Returns the tables under `tables`, each with `schema` and `table_name`.
     #[tool(description = "List all user tables in the Azure MSSQL database.")]
-    async fn mssql_list_tables(&self) -> Result<String, String> {
+    async fn mssql_list_tables(&self) -> Result<Json<TableList>, String> {
         let cfg = self.config.require_mssql().map_err(|e| e.to_string())?;
 
-        mssql::list_tables(cfg)
-            .await
-            .map_err(|e| e.to_string())
-            .map(|v| v.to_string())
+        structured(
+            mssql::list_tables(cfg)
+                .await
+                .map(|tables| serde_json::json!({ "tables": tables })),
+        )
     }
 
     /// Execute a SQL query against Azure MSSQL and return the results as JSON.
     ///
-    /// Results are wrapped in a TOP clause to prevent runaway reads.
-    #[tool(description = "Execute a SQL query against Azure MSSQL.  Results are \
-                          returned as a JSON array of row objects.  Results are \
-                          capped at max_rows (default 500, maximum 10 000).")]
+    /// Results are paged with OFFSET / FETCH to prevent runaway reads.
+    #[tool(description = "Execute a SQL query against Azure MSSQL.  Returns row \
+                          objects under `rows`, at most max_rows (default 500, \
+                          maximum 10 000) per page, and the ordered `columns` \
+                          with their SQL type and nullability.  When more rows remain, pass \
+                          `nextCursor` to fetch_next_page; for stable pages the \
+                          query should order its rows with ORDER BY.  A \
+                          confirmed statement that may modify data returns \
+                          `rows_affected` instead of rows.")]
     async fn mssql_execute_query(
         &self,
         Parameters(params): Parameters<MssqlExecuteQueryParams>,
-    ) -> Result<String, String> {
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<RowsPage>, String> {
         let cfg = self.config.require_mssql().map_err(|e| e.to_string())?;
 
+        readonly::ensure_not_denied(&self.config.query_denylist, &params.query)
+            .map_err(|e| e.to_string())?;
+        if let Err(e) = readonly::ensure_read_only(Dialect::TSql, &params.query) {
+            cfg.require_writes("MSSQL_ALLOW_WRITES")
+                .map_err(|denied| format!("{e}.  {denied}"))?;
+            confirm(
+                &peer,
+                "run a statement that may modify data",
+                "the Azure MSSQL database",
+                &params.query,
+            )
+            .await?;
+            return structured(
+                mssql::execute_statement(cfg, &params.query)
+                    .await
+                    .map(statement_result),
+            );
+        }
+
+        let max_rows = params.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+
+        structured(pagination::mssql_page(cfg, &params.query, 0, max_rows).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Synapse serverless SQL tools
+    // ------------------------------------------------------------------
+
+    /// List the tables and views of the Synapse serverless SQL database.
+    #[tool(description = "List the tables, external tables and views of the \
+                          Synapse serverless SQL database, with their `kind` \
+                          (table or view).")]
+    async fn synapse_list_tables(&self) -> Result<Json<TableList>, String> {
+        let cfg = self.config.require_synapse().map_err(|e| e.to_string())?;
+
+        structured(
+            mssql::list_tables_and_views(cfg)
+                .await
+                .map(|tables| serde_json::json!({ "tables": tables })),
+        )
+    }
+
+    /// Execute a T-SQL query against the Synapse serverless SQL endpoint.
+    #[tool(description = "Execute a T-SQL query against the Synapse serverless \
+                          SQL endpoint, e.g. over parquet, CSV or Delta files in \
+                          the data lake with OPENROWSET(BULK '<url>', FORMAT = \
+                          'PARQUET').  Returns row objects under `rows`, at most \
+                          max_rows (default 500, maximum 10 000) per page, plus a \
+                          `nextCursor` for fetch_next_page.")]
+    async fn synapse_execute_query(
+        &self,
+        Parameters(params): Parameters<SynapseExecuteQueryParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<RowsPage>, String> {
+        let cfg = self.config.require_synapse().map_err(|e| e.to_string())?;
+
+        readonly::ensure_not_denied(&self.config.query_denylist, &params.query)
+            .map_err(|e| e.to_string())?;
+        if let Err(e) = readonly::ensure_read_only(Dialect::TSql, &params.query) {
+            cfg.require_writes("SYNAPSE_ALLOW_WRITES")
+                .map_err(|denied| format!("{e}.  {denied}"))?;
+            confirm(
+                &peer,
+                "run a statement that may modify data",
+                "the Synapse serverless SQL database",
+                &params.query,
+            )
+            .await?;
+            return structured(
+                mssql::execute_statement(cfg, &params.query)
+                    .await
+                    .map(statement_result),
+            );
+        }
+
+        let max_rows = params.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
+
+        structured(pagination::synapse_page(cfg, &params.query, 0, max_rows).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Microsoft Fabric SQL tools
+    // ------------------------------------------------------------------
+
+    /// List the tables and views of the Fabric warehouse or lakehouse.
+    #[tool(description = "List the tables and views of the Microsoft Fabric \
+                          warehouse or lakehouse SQL analytics endpoint, with \
+                          their `kind` (table or view).")]
+    async fn fabric_list_tables(&self) -> Result<Json<TableList>, String> {
+        let cfg = self.fabric().await?;
+
+        structured(
+            mssql::list_tables_and_views(&cfg)
+                .await
+                .map(|tables| serde_json::json!({ "tables": tables })),
+        )
+    }
+
+    /// Execute a T-SQL query against the Fabric SQL endpoint.
+    #[tool(description = "Execute a T-SQL query against the Microsoft Fabric \
+                          warehouse or lakehouse SQL analytics endpoint.  \
+                          Returns row objects under `rows`, at most max_rows \
+                          (default 500, maximum 10 000) per page, plus a \
+                          `nextCursor` for fetch_next_page.")]
+    async fn fabric_execute_query(
+        &self,
+        Parameters(params): Parameters<FabricExecuteQueryParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<RowsPage>, String> {
+        let cfg = self.fabric().await?;
+
+        readonly::ensure_not_denied(&self.config.query_denylist, &params.query)
+            .map_err(|e| e.to_string())?;
+        if let Err(e) = readonly::ensure_read_only(Dialect::TSql, &params.query) {
+            cfg.require_writes("FABRIC_ALLOW_WRITES")
+                .map_err(|denied| format!("{e}.  {denied}"))?;
+            confirm(
+                &peer,
+                "run a statement that may modify data",
+                "the Fabric warehouse",
+                &params.query,
+            )
+            .await?;
+            return structured(
+                mssql::execute_statement(&cfg, &params.query)
+                    .await
+                    .map(statement_result),
+            );
+        }
+
         let max_rows = params.max_rows.unwrap_or(DEFAULT_MAX_ROWS);
 
-        mssql::execute_query(cfg, &params.query, max_rows)
-            .await
-            .map_err(|e| e.to_string())
-            .map(|v| v.to_string())
+        structured(pagination::fabric_page(&cfg, &params.query, 0, max_rows).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Cosmos DB tools
+    // ------------------------------------------------------------------
+
+    /// List all databases in the Azure Cosmos DB account.
+    ///
+    /// Returns the database names under `databases`.
+    #[tool(description = "List all databases in the Azure Cosmos DB account.")]
+    async fn cosmos_list_databases(&self) -> Result<Json<DatabaseList>, String> {
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::list_databases(account)
+                .await
+                .map(|databases| serde_json::json!({ "databases": databases })),
+        )
+    }
+
+    /// List all containers in an Azure Cosmos DB database.
+    ///
+    /// Returns the container names under `containers`.
+    #[tool(description = "List all containers in an Azure Cosmos DB database.  \
+                          `database` defaults to COSMOS_DEFAULT_DATABASE when omitted.")]
+    async fn cosmos_list_containers(
+        &self,
+        Parameters(params): Parameters<CosmosListContainersParams>,
+    ) -> Result<Json<ContainerList>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::list_containers(account, &database)
+                .await
+                .map(|containers| serde_json::json!({ "containers": containers })),
+        )
+    }
+
+    /// Report document count and storage usage for Cosmos DB containers.
+    ///
+    /// Returns per-container statistics under `containers`, largest first.
+    #[tool(
+        description = "Report document count and storage usage (KB) for a Cosmos \
+                          DB container, or for every container in the database \
+                          (largest first) when `container` is omitted.  `database` \
+                          defaults to COSMOS_DEFAULT_DATABASE."
+    )]
+    async fn cosmos_container_stats(
+        &self,
+        Parameters(params): Parameters<CosmosContainerStatsParams>,
+    ) -> Result<Json<ContainerStatsList>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::container_stats(account, &database, params.container.as_deref())
+                .await
+                .map(|containers| serde_json::json!({ "containers": containers })),
+        )
+    }
+
+    /// List stored procedures, UDFs and triggers defined on a container.
+    #[tool(
+        description = "List the stored procedures, user-defined functions (UDFs) \
+                          and triggers defined on a Cosmos DB container.  UDFs are \
+                          called in queries as `udf.<name>(...)`.  Set include_body \
+                          to also return their JavaScript source."
+    )]
+    async fn cosmos_list_programmability(
+        &self,
+        Parameters(params): Parameters<CosmosListProgrammabilityParams>,
+    ) -> Result<Json<Programmability>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::list_programmability(
+                account,
+                &database,
+                &params.container,
+                params.include_body.unwrap_or(false),
+            )
+            .await,
+        )
+    }
+
+    /// Read the conflicts feed of a container.
+    #[tool(description = "Read the conflicts feed of a Cosmos DB container in a \
+                          multi-region write account.  Each entry shows the \
+                          conflicting (losing) document version and, unless \
+                          include_current is false, the version that currently \
+                          won.  Conflicts are only recorded under a Custom \
+                          conflict resolution policy; the container's policy is \
+                          returned too.  Results are capped at max_items \
+                          (default 100, maximum 5 000).")]
+    async fn cosmos_read_conflicts(
+        &self,
+        Parameters(params): Parameters<CosmosReadConflictsParams>,
+    ) -> Result<Json<ConflictFeed>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::read_conflicts(
+                account,
+                &database,
+                &params.container,
+                params.max_items.unwrap_or(DEFAULT_MAX_ITEMS),
+                params.include_current.unwrap_or(true),
+            )
+            .await,
+        )
+    }
+
+    /// Read the time-to-live settings of a container (and optionally an item).
+    #[tool(description = "Read the default time-to-live (TTL) of a Cosmos DB \
+                          container and, when `id` and `partition_key` are given, \
+                          the `ttl` and effective expiry of that document.")]
+    async fn cosmos_get_ttl(
+        &self,
+        Parameters(params): Parameters<CosmosGetTtlParams>,
+    ) -> Result<Json<TtlInfo>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+        let item = match (params.id.as_deref(), params.partition_key.as_deref()) {
+            (Some(id), Some(pk)) => Some((id, pk)),
+            (Some(_), None) => return Err("partition_key is required when id is set".to_string()),
+            (None, _) => None,
+        };
+
+        let account = self.cosmos()?;
+
+        structured(cosmos::get_ttl(account, &database, &params.container, item).await)
+    }
+
+    /// Query items in an Azure Cosmos DB container using a SQL-API query.
+    ///
+    /// Returns a JSON object with the matching documents under `items` and the
+    /// aggregate request charge under `metadata`.
+    #[tool(description = "Query items in an Azure Cosmos DB container using a \
+                          Cosmos SQL-API query string.  Returns matching documents \
+                          under `items` and the total RU charge and activity IDs \
+                          under `metadata`.  Set include_metrics to also return \
+                          execution metrics (index hit ratio, retrieved vs output \
+                          documents).  Set max_request_units to stop once an RU \
+                          budget is spent, and consistency (e.g. Eventual) to \
+                          relax the account default.  Results are capped at \
+                          max_items (default 100, maximum 5 000) per page; when \
+                          more remain, pass `nextCursor` to fetch_next_page.  The \
+                          query must be a single SELECT; on some large containers \
+                          it must also have a WHERE clause or a partition_key.")]
+    async fn cosmos_query_items(
+        &self,
+        Parameters(params): Parameters<CosmosQueryItemsParams>,
+    ) -> Result<Json<ItemsPage>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+        readonly::ensure_not_denied(&self.config.query_denylist, &params.query)
+            .map_err(|e| e.to_string())?;
+        readonly::ensure_cosmos_query_allowed(
+            &cfg.query_policy,
+            &database,
+            &params.container,
+            &params.query,
+            params.partition_key.is_some(),
+        )
+        .map_err(|e| e.to_string())?;
+
+        let settings = QuerySettings {
+            max_items: params.max_items.unwrap_or(DEFAULT_MAX_ITEMS),
+            include_metrics: params.include_metrics.unwrap_or(false),
+            max_request_units: self.request_unit_budget(cfg, params.max_request_units),
+            consistency: params.consistency,
+        };
+
+        let account = self.cosmos()?;
+
+        structured(
+            pagination::cosmos_page(
+                account,
+                &database,
+                &params.container,
+                &params.query,
+                params.partition_key.as_deref(),
+                settings,
+                None,
+            )
+            .await,
+        )
+    }
+
+    /// Vector similarity search over a Cosmos DB container.
+    #[tool(description = "Find the top_k documents whose vector at `path` is \
+                          closest to `embedding`, using Cosmos DB VectorDistance \
+                          (the container's vector embedding policy decides the \
+                          distance function).  Each result has the document (with \
+                          the vector stripped) and its score.  Pass `text` instead \
+                          of `embedding` to have it embedded with Azure OpenAI \
+                          first.  Searches every partition unless partition_key \
+                          is given.")]
+    async fn cosmos_vector_search(
+        &self,
+        Parameters(params): Parameters<CosmosVectorSearchParams>,
+    ) -> Result<Json<VectorSearchResults>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let embedding = match (params.embedding, params.text) {
+            (Some(embedding), None) => embedding,
+            (None, Some(text)) => {
+                let openai = self.config.require_openai().map_err(|e| e.to_string())?;
+                openai::embed_vector(self.openai_tokens()?, openai, &text)
+                    .await
+                    .map_err(|e| format!("{e:#}"))?
+            }
+            _ => return Err("Give exactly one of `embedding` and `text`".into()),
+        };
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::vector_search(
+                account,
+                &database,
+                &params.container,
+                &params.path,
+                &embedding,
+                params.top_k.unwrap_or(DEFAULT_TOP_K),
+                params.partition_key.as_deref(),
+            )
+            .await,
+        )
+    }
+
+    /// Full-text and hybrid search over a Cosmos DB container.
+    #[tool(
+        description = "Full-text search a Cosmos DB container's text property \
+                          at `path` for `terms`.  mode: contains_all / contains_any \
+                          filter documents; rank (default) orders by BM25 relevance \
+                          (FullTextScore); hybrid fuses relevance with vector \
+                          similarity of `embedding` at `vector_path` (RRF).  rank \
+                          and hybrid need partition_key on multi-partition \
+                          containers."
+    )]
+    async fn cosmos_text_search(
+        &self,
+        Parameters(params): Parameters<CosmosTextSearchParams>,
+    ) -> Result<Json<TextSearchResults>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let mode = params.mode.unwrap_or(TextSearchMode::Rank);
+        let vector = match (&params.vector_path, &params.embedding) {
+            (Some(path), Some(embedding)) => Some((path.as_str(), embedding.as_slice())),
+            _ => None,
+        };
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::text_search(
+                account,
+                &database,
+                &params.container,
+                TextSearchRequest {
+                    path: &params.path,
+                    terms: &params.terms,
+                    mode,
+                    vector,
+                    top_k: params.top_k.unwrap_or(DEFAULT_TOP_K),
+                    partition_key: params.partition_key.as_deref(),
+                },
+            )
+            .await,
+        )
+    }
+
+    /// Query a container's analytical store via Synapse serverless SQL.
+    #[tool(description = "Run a T-SQL query against the analytical store of a \
+                          Cosmos DB container (Synapse Link) through the Synapse \
+                          serverless SQL endpoint, without consuming the \
+                          container's RUs.  Refer to the container as the table \
+                          `docs`.  Prefer this over cosmos_query_items for large \
+                          aggregations; data may lag writes by a few minutes.  \
+                          Results are capped at max_rows (default 500, maximum \
+                          10 000).")]
+    async fn cosmos_analytical_query(
+        &self,
+        Parameters(params): Parameters<CosmosAnalyticalQueryParams>,
+    ) -> Result<Json<RowList>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+        readonly::ensure_not_denied(&self.config.query_denylist, &params.query)
+            .map_err(|e| e.to_string())?;
+        readonly::ensure_read_only(Dialect::TSql, &params.query).map_err(|e| e.to_string())?;
+
+        structured(
+            cosmos::analytical_query(
+                cfg,
+                &database,
+                &params.container,
+                &params.query,
+                params.max_rows.unwrap_or(DEFAULT_MAX_ROWS),
+            )
+            .await
+            .map(|rows| serde_json::json!({ "rows": rows })),
+        )
+    }
+
+    /// Read a single document from an Azure Cosmos DB container.
+    #[tool(
+        description = "Read one Cosmos DB document by id and partition key (a \
+                          point read, the cheapest way to fetch a known item).  \
+                          Optionally set consistency (e.g. Eventual) to relax the \
+                          account default.  Returns the item (null if missing) \
+                          and the RU charge under metadata."
+    )]
+    async fn cosmos_read_item(
+        &self,
+        Parameters(params): Parameters<CosmosReadItemParams>,
+    ) -> Result<Json<ItemRead>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::read_item(
+                account,
+                &database,
+                &params.container,
+                &params.id,
+                &params.partition_key,
+                params.consistency,
+            )
+            .await,
+        )
+    }
+
+    /// Read the change feed of an Azure Cosmos DB container.
+    ///
+    /// Returns changed documents plus a continuation token for the next read.
+    #[tool(
+        description = "Read the change feed of a Cosmos DB container: documents \
+                          inserted or updated since `since` (RFC 3339 timestamp) or \
+                          since a previous call's `continuation` token.  Deletions \
+                          are not reported.  Results are capped at max_items \
+                          (default 100, maximum 5 000)."
+    )]
+    async fn cosmos_read_change_feed(
+        &self,
+        Parameters(params): Parameters<CosmosReadChangeFeedParams>,
+    ) -> Result<Json<ChangeFeedPage>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+        let max_items = params.max_items.unwrap_or(DEFAULT_MAX_ITEMS);
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::read_change_feed(
+                account,
+                &database,
+                &params.container,
+                params.since.as_deref(),
+                params.continuation.as_deref(),
+                max_items,
+                self.request_unit_budget(cfg, params.max_request_units),
+            )
+            .await,
+        )
+    }
+
+    /// Apply JSON Patch operations to a single Cosmos DB document.
+    ///
+    /// Requires `COSMOS_ALLOW_WRITES=true`.
+    #[tool(description = "Partially update a Cosmos DB document with JSON Patch \
+                          operations (set, add, replace, remove, increment) \
+                          without rewriting the whole document.  Requires \
+                          COSMOS_ALLOW_WRITES=true.")]
+    async fn cosmos_patch_item(
+        &self,
+        Parameters(params): Parameters<CosmosPatchItemParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<PatchResult>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_writes().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        confirm(
+            &peer,
+            "patch a document",
+            &format!(
+                "document `{}` (partition key `{}`) in {database}/{}",
+                params.id, params.partition_key, params.container
+            ),
+            &pretty_json(&params.operations),
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::patch_item(
+                account,
+                &database,
+                &params.container,
+                &params.id,
+                &params.partition_key,
+                &params.operations,
+            )
+            .await,
+        )
+    }
+
+    /// Execute a stored procedure in an Azure Cosmos DB container.
+    ///
+    /// Requires `COSMOS_ALLOW_WRITES=true`, since procedures may modify data.
+    #[tool(
+        description = "Execute a Cosmos DB stored procedure within one logical \
+                          partition, passing `parameters` as its arguments, and \
+                          return its response body and console.log output.  \
+                          Requires COSMOS_ALLOW_WRITES=true."
+    )]
+    async fn cosmos_execute_stored_procedure(
+        &self,
+        Parameters(params): Parameters<CosmosExecuteStoredProcedureParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<StoredProcedureResult>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_writes().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        confirm(
+            &peer,
+            "execute a stored procedure",
+            &format!(
+                "partition key `{}` of {database}/{}",
+                params.partition_key, params.container
+            ),
+            &format!("{}({})", params.procedure, pretty_json(&params.parameters)),
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::execute_stored_procedure(
+                account,
+                &database,
+                &params.container,
+                &params.procedure,
+                &params.partition_key,
+                &params.parameters,
+            )
+            .await,
+        )
+    }
+
+    /// Set or remove the `ttl` of a single Cosmos DB document.
+    ///
+    /// Requires `COSMOS_ALLOW_WRITES=true`.
+    #[tool(description = "Set the `ttl` (seconds, or -1 for never) of a single \
+                          Cosmos DB document, or remove it when `ttl` is omitted so \
+                          the container default applies.  Requires \
+                          COSMOS_ALLOW_WRITES=true.")]
+    async fn cosmos_set_item_ttl(
+        &self,
+        Parameters(params): Parameters<CosmosSetItemTtlParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<ItemTtlChange>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_writes().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+
+        confirm(
+            &peer,
+            "change the time-to-live of a document",
+            &format!(
+                "document `{}` (partition key `{}`) in {database}/{}",
+                params.id, params.partition_key, params.container
+            ),
+            &match params.ttl {
+                Some(ttl) => format!("ttl = {ttl}"),
+                None => "remove ttl".to_string(),
+            },
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::set_item_ttl(
+                account,
+                &database,
+                &params.container,
+                &params.id,
+                &params.partition_key,
+                params.ttl,
+            )
+            .await,
+        )
+    }
+
+    /// Write many documents to an Azure Cosmos DB container.
+    ///
+    /// Requires `COSMOS_ALLOW_WRITES=true`.
+    #[tool(description = "Bulk-write documents (inline `documents` array or a \
+                          JSON/JSONL `file_path` in the server's import \
+                          directory) to a Cosmos DB \
+                          container.  Documents are written in atomic \
+                          transactional batches per partition key with bounded \
+                          concurrency; a per-item success/failure result is \
+                          returned.  Requires COSMOS_ALLOW_WRITES=true.")]
+    async fn cosmos_bulk_import(
+        &self,
+        Parameters(params): Parameters<CosmosBulkImportParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<ImportReport>, String> {
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_writes().map_err(|e| e.to_string())?;
+
+        let database = resolve_database(cfg, params.database.as_deref())?;
+        let documents = match (params.documents, params.file_path.as_deref()) {
+            (Some(documents), None) => documents,
+            (None, Some(path)) => cosmos::load_import_file(cfg.import_dir.as_deref(), path)
+                .await
+                .map_err(|e| e.to_string())?,
+            _ => return Err("Provide exactly one of `documents` or `file_path`".to_string()),
+        };
+
+        let mode = params.mode.unwrap_or_default();
+        confirm(
+            &peer,
+            "bulk-write documents",
+            &format!("{database}/{}", params.container),
+            &format!("{mode:?} {} documents", documents.len()),
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::bulk_import(
+                account,
+                &database,
+                &params.container,
+                documents,
+                mode,
+                params.concurrency.unwrap_or(DEFAULT_IMPORT_CONCURRENCY),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Cosmos DB Gremlin tools
+    // ------------------------------------------------------------------
+
+    /// Run a Gremlin traversal against a Cosmos DB graph.
+    #[tool(description = "Run a Gremlin traversal against a Cosmos DB graph \
+                          (`graph`, default GREMLIN_GRAPH), with optional \
+                          parameter `bindings`.  Returns up to max_results \
+                          (default 100, maximum 1 000) results and the RU charge.  \
+                          Traversals that add, change or drop vertices, edges or \
+                          properties need GREMLIN_ALLOW_WRITES=true and are \
+                          confirmed with the user first.")]
+    async fn gremlin_query(
+        &self,
+        Parameters(params): Parameters<GremlinQueryParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<GremlinResult>, String> {
+        let cfg = self.config.require_gremlin().map_err(|e| e.to_string())?;
+        let graph = params.graph.as_deref().unwrap_or(&cfg.graph);
+
+        if gremlin::ensure_read_only(&params.query).is_err() {
+            cfg.require_writes().map_err(|e| e.to_string())?;
+            confirm(
+                &peer,
+                "run a traversal that modifies the graph",
+                &format!("Gremlin graph {graph}"),
+                &params.query,
+            )
+            .await?;
+        }
+
+        structured(
+            gremlin::query(
+                cfg,
+                Some(graph),
+                &params.query,
+                params.bindings,
+                params.max_results.unwrap_or(gremlin::DEFAULT_MAX_RESULTS),
+            )
+            .await,
+        )
+    }
+
+    /// Read one vertex of a Cosmos DB graph with its edge counts.
+    #[tool(description = "Read a vertex of a Cosmos DB graph by `id` (pass \
+                          partition_key on partitioned graphs for a cheap point \
+                          read), with its label, properties and number of \
+                          outgoing and incoming edges.  `vertex` is null when it \
+                          does not exist.")]
+    async fn gremlin_get_vertex(
+        &self,
+        Parameters(params): Parameters<GremlinGetVertexParams>,
+    ) -> Result<Json<GremlinVertex>, String> {
+        let cfg = self.config.require_gremlin().map_err(|e| e.to_string())?;
+
+        structured(
+            gremlin::get_vertex(
+                cfg,
+                params.graph.as_deref(),
+                &params.id,
+                params.partition_key.as_deref(),
+            )
+            .await,
+        )
+    }
+
+    /// List the edges of a vertex.
+    #[tool(description = "List the edges of a vertex in a Cosmos DB graph: \
+                          outgoing (default), incoming or both, optionally only \
+                          those with `label`.  Each edge has its id, label, \
+                          properties and the ids of the vertices it connects \
+                          (outV -> inV).  Returns up to max_results (default \
+                          100, maximum 1 000).")]
+    async fn gremlin_get_edges(
+        &self,
+        Parameters(params): Parameters<GremlinGetEdgesParams>,
+    ) -> Result<Json<GremlinEdges>, String> {
+        let cfg = self.config.require_gremlin().map_err(|e| e.to_string())?;
+
+        structured(
+            gremlin::get_edges(
+                cfg,
+                params.graph.as_deref(),
+                &params.vertex_id,
+                params.partition_key.as_deref(),
+                params.direction,
+                params.label.as_deref(),
+                params.max_results.unwrap_or(gremlin::DEFAULT_MAX_RESULTS),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Cosmos DB Cassandra tools
+    // ------------------------------------------------------------------
+
+    /// List the keyspaces of the Cassandra API account.
+    #[tool(description = "List the keyspaces of the Cosmos DB Cassandra API \
+                          account (system keyspaces excluded).")]
+    async fn cassandra_list_keyspaces(&self) -> Result<Json<KeyspaceList>, String> {
+        let cfg = self.config.require_cassandra().map_err(|e| e.to_string())?;
+
+        structured(
+            cassandra::list_keyspaces(cfg)
+                .await
+                .map(|keyspaces| serde_json::json!({ "keyspaces": keyspaces })),
+        )
+    }
+
+    /// List the tables of a keyspace with their columns.
+    #[tool(description = "List the tables of a Cassandra keyspace (`keyspace`, \
+                          default CASSANDRA_KEYSPACE) with their columns: name, \
+                          CQL type and role (partition_key, clustering or \
+                          regular).  Efficient queries filter on the full \
+                          partition key.")]
+    async fn cassandra_list_tables(
+        &self,
+        Parameters(params): Parameters<CassandraListTablesParams>,
+    ) -> Result<Json<CassandraTableList>, String> {
+        let cfg = self.config.require_cassandra().map_err(|e| e.to_string())?;
+        let keyspace = params
+            .keyspace
+            .or_else(|| cfg.default_keyspace.clone())
+            .ok_or("No keyspace given and CASSANDRA_KEYSPACE is not set")?;
+
+        structured(cassandra::list_tables(cfg, &keyspace).await)
+    }
+
+    /// Run a bounded CQL SELECT.
+    #[tool(description = "Run a CQL SELECT against the Cosmos DB Cassandra API \
+                          (`keyspace`, default CASSANDRA_KEYSPACE).  Returns row \
+                          objects under `rows`, at most max_rows (default 500, \
+                          maximum 10 000) per page.  When more rows remain, pass \
+                          `nextCursor` to fetch_next_page.  Only single SELECT \
+                          statements are accepted.")]
+    async fn cassandra_query(
+        &self,
+        Parameters(params): Parameters<CassandraQueryParams>,
+    ) -> Result<Json<RowsPage>, String> {
+        let cfg = self.config.require_cassandra().map_err(|e| e.to_string())?;
+        let keyspace = params.keyspace.or_else(|| cfg.default_keyspace.clone());
+        let max_rows = params.max_rows.unwrap_or(cassandra::DEFAULT_MAX_ROWS);
+
+        structured(
+            pagination::cassandra_page(cfg, keyspace.as_deref(), &params.query, max_rows, None)
+                .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Storage tools
+    // ------------------------------------------------------------------
+
+    /// Read part of a blob as text or base64.
+    #[tool(
+        description = "Read a blob from Azure Storage.  Returns up to `length` \
+                          bytes (default 64 KiB, maximum 1 MiB) starting at `offset` \
+                          as UTF-8 text, or base64 for binary data.  When the blob \
+                          is longer, call again with `next_offset`."
+    )]
+    async fn blob_read(
+        &self,
+        Parameters(params): Parameters<BlobReadParams>,
+    ) -> Result<Json<BlobContent>, String> {
+        let client = self.storage()?;
+
+        structured(
+            blob::read(
+                client,
+                &params.container,
+                &params.path,
+                params.offset.unwrap_or(0),
+                params.length,
+                params.encoding.unwrap_or_default(),
+            )
+            .await,
+        )
+    }
+
+    /// Upload a blob.
+    ///
+    /// Requires `STORAGE_ALLOW_WRITES=true`.
+    #[tool(description = "Write a blob to Azure Storage from text or base64 \
+                          `content` (at most 16 MiB), e.g. to save a query export.  \
+                          `content_type` is guessed from the extension when omitted.  \
+                          Fails if the blob exists unless `overwrite` is true.  \
+                          Requires STORAGE_ALLOW_WRITES=true.")]
+    async fn blob_write(
+        &self,
+        Parameters(params): Parameters<BlobWriteParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<BlobWritten>, String> {
+        let cfg = self.config.require_storage().map_err(|e| e.to_string())?;
+        cfg.require_writes().map_err(|e| e.to_string())?;
+
+        let encoding = params.encoding.unwrap_or_default();
+        let bytes = blob::decode_content(&params.content, encoding).map_err(|e| e.to_string())?;
+        let content_type = params
+            .content_type
+            .unwrap_or_else(|| blob::guess_content_type(&params.path, encoding).to_string());
+
+        confirm(
+            &peer,
+            match params.overwrite {
+                true => "write a blob, replacing it if it exists",
+                false => "write a new blob",
+            },
+            &format!("{}/{}", params.container, params.path),
+            &format!("{} bytes of {content_type}", bytes.len()),
+        )
+        .await?;
+
+        let client = self.storage()?;
+
+        structured(
+            blob::write(
+                client,
+                &params.container,
+                &params.path,
+                bytes,
+                &content_type,
+                params.overwrite,
+            )
+            .await,
+        )
+    }
+
+    /// Generate a time-limited read-only URL for a blob.
+    #[tool(description = "Generate a read-only HTTPS URL to a blob, e.g. a \
+                          query export written with blob_write, that anyone \
+                          holding it can download until it expires (default \
+                          60 minutes, maximum 24 hours).  Signed with a service \
+                          SAS; no account key is shared.  Requires \
+                          STORAGE_ALLOW_SHARE=true, and only shares blobs of \
+                          STORAGE_SHARE_CONTAINER.")]
+    async fn blob_share_url(
+        &self,
+        Parameters(params): Parameters<BlobShareUrlParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<BlobShareUrl>, String> {
+        let cfg = self.config.require_storage().map_err(|e| e.to_string())?;
+        cfg.require_share(&params.container)
+            .map_err(|e| e.to_string())?;
+        let minutes = params
+            .expires_in_minutes
+            .unwrap_or(blob::DEFAULT_SHARE_MINUTES)
+            .clamp(1, blob::MAX_SHARE_MINUTES);
+
+        confirm(
+            &peer,
+            "share a download URL of a blob",
+            &format!("{}/{}", params.container, params.path),
+            &format!("anyone holding the URL can read the blob for {minutes} minutes"),
+        )
+        .await?;
+
+        let client = self.storage()?;
+
+        structured(blob::share_url(client, &params.container, &params.path, minutes).await)
+    }
+
+    /// List the tables of the Azure Table Storage account.
+    #[tool(description = "List all tables in Azure Table Storage (or the Cosmos \
+                          DB Table API account set by STORAGE_TABLE_ENDPOINT).")]
+    async fn tables_list(&self) -> Result<Json<StorageTableList>, String> {
+        let client = self.storage()?;
+
+        structured(
+            tables::list_tables(client)
+                .await
+                .map(|tables| serde_json::json!({ "tables": tables })),
+        )
+    }
+
+    /// Query the entities of a table.
+    #[tool(
+        description = "Query entities of an Azure Table Storage table with an \
+                          optional OData `filter` (e.g. PartitionKey eq 'eu' and \
+                          Amount gt 100) and `select`ed properties.  Returns at most \
+                          `top` entities (default 100, maximum 1 000) under \
+                          `entities`; when more match, pass `nextCursor` to \
+                          fetch_next_page."
+    )]
+    async fn tables_query(
+        &self,
+        Parameters(params): Parameters<TablesQueryParams>,
+    ) -> Result<Json<EntitiesPage>, String> {
+        let client = self.storage()?;
+
+        structured(
+            pagination::table_page(
+                client,
+                &params.table,
+                params.filter.as_deref(),
+                &params.select.unwrap_or_default(),
+                params.top.unwrap_or(tables::DEFAULT_TOP),
+                None,
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Data Lake Storage Gen2 tools
+    // ------------------------------------------------------------------
+
+    /// List the files and directories of a Data Lake filesystem.
+    #[tool(description = "List the files and directories of an Azure Data Lake \
+                          Storage Gen2 filesystem below `directory` (the root when \
+                          omitted), recursively when asked, e.g. to browse the \
+                          raw/curated zones of a lake.  Returns `paths` with size, \
+                          owner and POSIX permissions, at most max_results (default \
+                          500, maximum 5 000) per page, plus a `nextCursor`.")]
+    async fn adls_list_paths(
+        &self,
+        Parameters(params): Parameters<AdlsListPathsParams>,
+    ) -> Result<Json<LakePathList>, String> {
+        let client = self.storage()?;
+
+        structured(
+            pagination::adls_page(
+                client,
+                &params.filesystem,
+                params.directory.as_deref(),
+                params.recursive,
+                params.max_results.unwrap_or(adls::DEFAULT_MAX_RESULTS),
+                None,
+            )
+            .await,
+        )
+    }
+
+    /// Read part of a Data Lake file as text or base64.
+    #[tool(description = "Read a Data Lake Storage Gen2 file's bytes from \
+                          `offset` (default 0), up to `length` (default 64 KiB, \
+                          maximum 1 MiB), as UTF-8 text or base64.  Returns the \
+                          file's total `size` and a `next_offset` to continue \
+                          from (null at the end).")]
+    async fn adls_read_file(
+        &self,
+        Parameters(params): Parameters<AdlsReadFileParams>,
+    ) -> Result<Json<BlobContent>, String> {
+        let client = self.storage()?;
+
+        structured(
+            adls::read_file(
+                client,
+                &params.filesystem,
+                &params.path,
+                params.offset.unwrap_or(0),
+                params.length,
+                params.encoding.unwrap_or_default(),
+            )
+            .await,
+        )
+    }
+
+    /// Read the properties of a Data Lake file or directory.
+    #[tool(
+        description = "Read the properties of a Data Lake Storage Gen2 file or \
+                          directory: whether it is a directory, size, content \
+                          type, timestamps and user-defined properties, plus \
+                          owner, group, POSIX permissions and ACL when the account \
+                          has a hierarchical namespace."
+    )]
+    async fn adls_get_properties(
+        &self,
+        Parameters(params): Parameters<AdlsGetPropertiesParams>,
+    ) -> Result<Json<LakePathProperties>, String> {
+        let client = self.storage()?;
+
+        structured(adls::get_properties(client, &params.filesystem, &params.path).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Files tools
+    // ------------------------------------------------------------------
+
+    /// List the file shares of the storage account.
+    #[tool(description = "List the Azure Files shares of the storage account \
+                          with their quota (GiB).")]
+    async fn files_list_shares(&self) -> Result<Json<FileShareList>, String> {
+        let client = self.storage()?;
+
+        structured(
+            files::list_shares(client)
+                .await
+                .map(|shares| serde_json::json!({ "shares": shares })),
+        )
+    }
+
+    /// List the files and subdirectories of an Azure Files directory.
+    #[tool(description = "List the files (with size) and subdirectories of a \
+                          directory in an Azure Files share (the share root when \
+                          `directory` is omitted).  Returns at most max_results \
+                          (default 500, maximum 5 000) `entries` per page, plus a \
+                          `nextCursor`.")]
+    async fn files_list_directory(
+        &self,
+        Parameters(params): Parameters<FilesListDirectoryParams>,
+    ) -> Result<Json<FileDirectoryListing>, String> {
+        let client = self.storage()?;
+
+        structured(
+            pagination::files_page(
+                client,
+                &params.share,
+                params.directory.as_deref().unwrap_or_default(),
+                params.max_results.unwrap_or(files::DEFAULT_MAX_RESULTS),
+                None,
+            )
+            .await,
+        )
+    }
+
+    /// Read part of an Azure Files file as text or base64.
+    #[tool(description = "Read an Azure Files file's bytes from `offset` \
+                          (default 0), up to `length` (default 64 KiB, maximum \
+                          1 MiB), as UTF-8 text or base64.  Returns the file's \
+                          total `size` and a `next_offset` to continue from (null \
+                          at the end).")]
+    async fn files_read_file(
+        &self,
+        Parameters(params): Parameters<FilesReadFileParams>,
+    ) -> Result<Json<BlobContent>, String> {
+        let client = self.storage()?;
+
+        structured(
+            files::read_file(
+                client,
+                &params.share,
+                &params.path,
+                params.offset.unwrap_or(0),
+                params.length,
+                params.encoding.unwrap_or_default(),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Service Bus tools
+    // ------------------------------------------------------------------
+
+    /// Send a message to a queue or topic.
+    ///
+    /// Requires `SERVICEBUS_ALLOW_SEND=true`.
+    #[tool(
+        description = "Send one message to a Service Bus queue or topic, with \
+                          optional `broker_properties` (MessageId, CorrelationId, \
+                          SessionId, Label, ...) and custom `properties`.  Requires \
+                          SERVICEBUS_ALLOW_SEND=true."
+    )]
+    async fn servicebus_send(
+        &self,
+        Parameters(params): Parameters<ServiceBusSendParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<SentMessage>, String> {
+        let cfg = self
+            .config
+            .require_servicebus()
+            .map_err(|e| e.to_string())?;
+        cfg.require_send().map_err(|e| e.to_string())?;
+
+        confirm(
+            &peer,
+            "send a message",
+            &format!("Service Bus entity {}", params.entity),
+            &params.body,
+        )
+        .await?;
+
+        let client = self.servicebus()?;
+
+        structured(
+            servicebus::send(
+                client,
+                &params.entity,
+                params.body,
+                params.content_type.as_deref().unwrap_or("application/json"),
+                &params.broker_properties.unwrap_or_default(),
+                &params.properties.unwrap_or_default(),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Event Grid tools
+    // ------------------------------------------------------------------
+
+    /// Publish events to an Event Grid topic.
+    ///
+    /// Requires `EVENTGRID_ALLOW_PUBLISH=true`.
+    #[tool(description = "Publish up to 100 events (`event_type`, `subject`, \
+                          `data`) to one of the configured Event Grid topics, \
+                          e.g. to announce that a report is ready.  Ids, event \
+                          times and data versions are filled in.  Requires \
+                          EVENTGRID_ALLOW_PUBLISH=true.")]
+    async fn eventgrid_publish(
+        &self,
+        Parameters(params): Parameters<EventGridPublishParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<PublishedEvents>, String> {
+        let cfg = self.config.require_eventgrid().map_err(|e| e.to_string())?;
+        cfg.require_publish().map_err(|e| e.to_string())?;
+        let topic = cfg.topic(&params.topic).map_err(|e| e.to_string())?;
+
+        let summary = params
+            .events
+            .iter()
+            .map(|e| format!("{} {}", e.event_type, e.subject))
+            .collect::<Vec<_>>()
+            .join("\n");
+        confirm(
+            &peer,
+            &format!("publish {} event(s)", params.events.len()),
+            &format!("Event Grid topic {}", topic.name),
+            &summary,
+        )
+        .await?;
+
+        let tokens = match topic.key {
+            Some(_) => None,
+            None => Some(self.tokens()?),
+        };
+
+        structured(eventgrid::publish(tokens, topic, params.events).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Event Hubs tools
+    // ------------------------------------------------------------------
+
+    /// Read a bounded sample of events from an event hub partition.
+    #[tool(
+        description = "Read up to `max_events` events (default 10, maximum 100) \
+                          from one partition of an Azure event hub, starting at \
+                          `starting_position`: `latest` (the most recent events, the \
+                          default), `earliest`, an offset, or an RFC 3339 enqueue \
+                          time.  Nothing is consumed or committed; pass `next_offset` \
+                          as `starting_position` to read on."
+    )]
+    async fn eventhub_read_events(
+        &self,
+        Parameters(params): Parameters<EventHubReadEventsParams>,
+    ) -> Result<Json<EventBatch>, String> {
+        let cfg = self.config.require_eventhubs().map_err(|e| e.to_string())?;
+        let start = match params.starting_position.as_deref() {
+            Some(position) => StartingPosition::parse(position).map_err(|e| e.to_string())?,
+            None => StartingPosition::Latest,
+        };
+
+        structured(
+            eventhubs::read_events(
+                cfg,
+                &params.hub,
+                params.partition.unwrap_or(0),
+                start,
+                params.max_events.unwrap_or(eventhubs::DEFAULT_MAX_EVENTS),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Monitor tools
+    // ------------------------------------------------------------------
+
+    /// Run a KQL query against a Log Analytics workspace.
+    #[tool(
+        description = "Run a KQL query against an Azure Log Analytics workspace \
+                          (`workspace_id`, default LOGANALYTICS_WORKSPACE_ID) over an \
+                          optional `timespan` (ISO 8601 duration like PT1H, or \
+                          start/end).  Returns the primary table's `columns` and \
+                          `rows`, at most max_rows (default 500, maximum 10 000)."
+    )]
+    async fn loganalytics_query(
+        &self,
+        Parameters(params): Parameters<LogAnalyticsQueryParams>,
+    ) -> Result<Json<KqlResult>, String> {
+        let workspace_id = params
+            .workspace_id
+            .or_else(|| self.config.monitor.default_workspace.clone())
+            .ok_or_else(|| {
+                "workspace_id is required when LOGANALYTICS_WORKSPACE_ID is not set".to_string()
+            })?;
+        let tokens = self.tokens()?;
+
+        structured(
+            monitor::loganalytics_query(
+                tokens,
+                &workspace_id,
+                &params.kql,
+                params.timespan.as_deref(),
+                params.max_rows.unwrap_or(monitor::DEFAULT_MAX_ROWS),
+            )
+            .await,
+        )
+    }
+
+    /// Run a KQL query against an Application Insights resource.
+    #[tool(description = "Run a KQL query against Azure Application Insights \
+                          (`app_id`, default APPINSIGHTS_APP_ID) over its requests, \
+                          traces, exceptions and dependencies tables, e.g. to see \
+                          why errors are spiking.  `timespan` is an ISO 8601 \
+                          duration like PT1H, or start/end.  Returns the primary \
+                          table's `columns` and `rows`, at most max_rows (default \
+                          500, maximum 10 000).")]
+    async fn appinsights_query(
+        &self,
+        Parameters(params): Parameters<AppInsightsQueryParams>,
+    ) -> Result<Json<KqlResult>, String> {
+        let app_id = params
+            .app_id
+            .or_else(|| self.config.monitor.default_app.clone())
+            .ok_or_else(|| "app_id is required when APPINSIGHTS_APP_ID is not set".to_string())?;
+        let tokens = self.tokens()?;
+
+        structured(
+            monitor::appinsights_query(
+                tokens,
+                &app_id,
+                &params.kql,
+                params.timespan.as_deref(),
+                params.max_rows.unwrap_or(monitor::DEFAULT_MAX_ROWS),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Data Explorer tools
+    // ------------------------------------------------------------------
+
+    /// List the tables of a Kusto database.
+    #[tool(description = "List the tables of an Azure Data Explorer (Kusto) \
+                          database (`database`, default KUSTO_DATABASE), with \
+                          their folder and description.")]
+    async fn kusto_list_tables(
+        &self,
+        Parameters(params): Parameters<KustoListTablesParams>,
+    ) -> Result<Json<KustoTableList>, String> {
+        let database = self.kusto_database(params.database)?;
+        let cfg = self.config.require_kusto().map_err(|e| e.to_string())?;
+        structured(kusto::list_tables(self.tokens()?, cfg, &database).await)
+    }
+
+    /// Run a KQL query against a Kusto database.
+    #[tool(description = "Run a read-only KQL query against an Azure Data \
+                          Explorer (Kusto) database (`database`, default \
+                          KUSTO_DATABASE).  Control commands are rejected.  \
+                          Returns the primary result's `columns` and `rows`, at \
+                          most max_rows (default 500, maximum 10 000).")]
+    async fn kusto_query(
+        &self,
+        Parameters(params): Parameters<KustoQueryParams>,
+    ) -> Result<Json<KqlResult>, String> {
+        let database = self.kusto_database(params.database)?;
+        let cfg = self.config.require_kusto().map_err(|e| e.to_string())?;
+        structured(
+            kusto::query(
+                self.tokens()?,
+                cfg,
+                &database,
+                &params.kql,
+                params.max_rows.unwrap_or(monitor::DEFAULT_MAX_ROWS),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Cost Management tools
+    // ------------------------------------------------------------------
+
+    /// Query cost and usage over a time window.
+    #[tool(description = "Query Azure cost between `from` and `to` (YYYY-MM-DD, \
+                          default today), optionally broken down by resource_group, \
+                          service, resource, meter_category or location, per day or \
+                          month, and filtered to service_names (e.g. \"Azure Cosmos \
+                          DB\") or resource_groups.  Reports the subscription in \
+                          AZURE_SUBSCRIPTION_ID unless `scope` is given.  Returns \
+                          `rows` with a `Cost` column, the `currency` and \
+                          `total_cost`.")]
+    async fn cost_query(
+        &self,
+        Parameters(params): Parameters<CostQueryParams>,
+    ) -> Result<Json<CostReport>, String> {
+        let scope = match (params.scope, &self.config.subscription_id) {
+            (Some(scope), _) => scope,
+            (None, Some(id)) => format!("/subscriptions/{id}"),
+            (None, None) => {
+                return Err("scope is required when AZURE_SUBSCRIPTION_ID is not set".into())
+            }
+        };
+        let from = cost::parse_date(&params.from).map_err(|e| e.to_string())?;
+        let to = match params.to.as_deref() {
+            Some(to) => cost::parse_date(to).map_err(|e| e.to_string())?,
+            None => chrono::Utc::now().date_naive(),
+        };
+        let query = CostQuery {
+            from,
+            to,
+            group_by: params.group_by,
+            granularity: params.granularity,
+            service_names: params.service_names,
+            resource_groups: params.resource_groups,
+            amortized: params.amortized,
+        };
+
+        structured(cost::query(self.tokens()?, &scope, &query).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Azure Key Vault tools
+    // ------------------------------------------------------------------
+
+    /// List the secrets of the Key Vault without their values.
+    #[tool(description = "List the secrets of the configured Azure Key Vault \
+                          with their attributes (enabled, content type, tags, \
+                          created/updated/expiry times).  Never returns values.")]
+    async fn keyvault_list_secrets(&self) -> Result<Json<KeyVaultSecrets>, String> {
+        let cfg = self.config.require_keyvault().map_err(|e| e.to_string())?;
+
+        structured(keyvault::list_secrets(self.tokens()?, cfg).await)
+    }
+
+    /// Read the metadata of one secret and its versions.
+    #[tool(
+        description = "Read the metadata of a Key Vault secret and of each of \
+                          its versions (newest first), e.g. to check whether it \
+                          expired or was rotated.  The value is only returned with \
+                          include_value when KEYVAULT_ALLOW_SECRET_VALUES=true."
+    )]
+    async fn keyvault_get_secret_metadata(
+        &self,
+        Parameters(params): Parameters<KeyVaultSecretParams>,
+    ) -> Result<Json<SecretMetadata>, String> {
+        let cfg = self.config.require_keyvault().map_err(|e| e.to_string())?;
+
+        structured(
+            keyvault::get_secret_metadata(self.tokens()?, cfg, &params.name, params.include_value)
+                .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure App Configuration tools
+    // ------------------------------------------------------------------
+
+    /// List the keys of the App Configuration store.
+    #[tool(description = "List the keys of the configured Azure App \
+                          Configuration store with their labels, content types \
+                          and last modification times, without values.  Filter \
+                          with `key` (e.g. `Checkout:*`) and `label` (e.g. \
+                          `prod`).")]
+    async fn appconfig_list_keys(
+        &self,
+        Parameters(params): Parameters<AppConfigListKeysParams>,
+    ) -> Result<Json<AppConfigSettings>, String> {
+        let cfg = self.config.require_appconfig().map_err(|e| e.to_string())?;
+
+        structured(
+            appconfig::list_keys(
+                self.appconfig_tokens()?,
+                cfg,
+                params.key.as_deref(),
+                params.label.as_deref(),
+            )
+            .await,
+        )
+    }
+
+    /// Read App Configuration values.
+    #[tool(description = "Read the values of the App Configuration settings \
+                          matching `key` (e.g. `Checkout:Timeout` or \
+                          `Checkout:*`), under every label unless `label` is \
+                          given — e.g. to compare a setting between environments.  \
+                          Key Vault references are returned as stored, never \
+                          resolved.")]
+    async fn appconfig_get_values(
+        &self,
+        Parameters(params): Parameters<AppConfigGetValuesParams>,
+    ) -> Result<Json<AppConfigSettings>, String> {
+        let cfg = self.config.require_appconfig().map_err(|e| e.to_string())?;
+
+        structured(
+            appconfig::get_values(
+                self.appconfig_tokens()?,
+                cfg,
+                &params.key,
+                params.label.as_deref(),
+            )
+            .await,
+        )
+    }
+
+    /// List App Configuration feature flags.
+    #[tool(description = "List the feature flags of the App Configuration \
+                          store with whether they are enabled and their client \
+                          filters (conditions), per label.  Filter with `name` \
+                          (e.g. `Beta*`) and `label`.")]
+    async fn appconfig_list_feature_flags(
+        &self,
+        Parameters(params): Parameters<AppConfigFeatureFlagsParams>,
+    ) -> Result<Json<FeatureFlagList>, String> {
+        let cfg = self.config.require_appconfig().map_err(|e| e.to_string())?;
+
+        structured(
+            appconfig::list_feature_flags(
+                self.appconfig_tokens()?,
+                cfg,
+                params.name.as_deref(),
+                params.label.as_deref(),
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Azure OpenAI tools
+    // ------------------------------------------------------------------
+
+    /// Embed text with the Azure OpenAI embedding deployment.
+    #[tool(
+        description = "Turn text into an embedding vector with the configured \
+                          Azure OpenAI embedding deployment, e.g. to compare \
+                          texts or to pass to cosmos_vector_search / \
+                          cosmos_text_search (hybrid).  To search with a \
+                          question, prefer cosmos_vector_search with `text`, \
+                          which embeds it without returning the vector."
+    )]
+    async fn embed_text(
+        &self,
+        Parameters(params): Parameters<EmbedTextParams>,
+    ) -> Result<Json<Embedding>, String> {
+        let cfg = self.config.require_openai().map_err(|e| e.to_string())?;
+
+        structured(openai::embed(self.openai_tokens()?, cfg, &params.text, params.dimensions).await)
+    }
+
+    // ------------------------------------------------------------------
+    // Pagination
+    // ------------------------------------------------------------------
+
+    /// Fetch the next page of a paged query result.
+    #[tool(description = "Fetch the next page of an mssql_execute_query, \
+                          synapse_execute_query, fabric_execute_query, \
+                          cassandra_query, \
+                          cosmos_query_items, tables_query, adls_list_paths or \
+                          files_list_directory result, given its `nextCursor`.  \
+                          Returns the same shape as the original tool, with a new \
+                          `nextCursor` that is null on the last page.")]
+    async fn fetch_next_page(
+        &self,
+        Parameters(params): Parameters<FetchNextPageParams>,
+    ) -> Result<Json<NextPage>, String> {
+        structured(
+            pagination::fetch_next_page(
+                &self.config,
+                self.tokens(),
+                self.cosmos(),
+                self.storage(),
+                &params.cursor,
+            )
+            .await,
+        )
+    }
+
+    // ------------------------------------------------------------------
+    // Server
+    // ------------------------------------------------------------------
+
+    /// Report runtime statistics of this server.
+    #[tool(description = "Report this server's runtime statistics: uptime, tool \
+                          calls in flight, per-tool call counts, error rates and \
+                          durations, whether each configured backend is \
+                          available, which shared clients are built and the hit \
+                          rates of the token, catalog and stored result caches.")]
+    async fn server_stats(&self) -> Result<Json<ServerStats>, String> {
+        let mut report = self.stats.report();
+        report["backends"] = Backend::ALL
+            .into_iter()
+            .filter(|backend| backend.configured(&self.config))
+            .map(|backend| {
+                let available = self.availability.is_available(backend);
+                (backend.name().to_string(), Value::from(available))
+            })
+            .collect::<Map<_, _>>()
+            .into();
+        report["clients"] = serde_json::json!({
+            "cosmos": self.cosmos.get().is_some(),
+            "storage": self.storage.get().is_some(),
+            "servicebus": self.servicebus.get().is_some(),
+            "tokens": self.tokens.get().is_some(),
+        });
+        let tokens = match self.tokens.get() {
+            Some(tokens) => tokens.cache().report(),
+            None => CacheCounter::default().report(),
+        };
+        report["session"] = self.session.as_str().into();
+        report["request_units"]["session_limit"] = self
+            .config
+            .cosmos
+            .as_ref()
+            .and_then(|cfg| cfg.session_max_request_units)
+            .into();
+        report["caches"] = serde_json::json!({
+            "tokens": tokens,
+            "catalog": self.catalog.cache().report(),
+            "results": self.results.report(),
+        });
+        report["concurrency"] = self.limiter.report();
+        structured(Ok(report))
+    }
+
+    /// Report the server's effective configuration and why tools are
+    /// missing or refused.
+    #[tool(description = "Report this server's effective configuration \
+                          (without secrets), which backends are configured and \
+                          reachable, which tools are hidden and why, which tools \
+                          are refused until a flag is set, and the limits applied \
+                          to calls.  Use it to find out why a tool is missing or \
+                          refused.")]
+    async fn server_status(&self) -> Result<Json<ServerStatus>, String> {
+        let backends: Vec<Value> = Backend::ALL
+            .into_iter()
+            .map(|backend| {
+                serde_json::json!({
+                    "name": backend.name(),
+                    "configured": backend.configured(&self.config),
+                    "available": self.availability.is_available(backend),
+                    "settings": backend.settings(),
+                })
+            })
+            .collect();
+        let hidden_tools: Vec<Value> = self
+            .tool_router
+            .list_all()
+            .into_iter()
+            .filter_map(|tool| {
+                let backend = Backend::of_tool(&tool.name);
+                if !self.config.role.allows(&tool.name) {
+                    return Some(serde_json::json!({
+                        "tool": tool.name,
+                        "backend": backend.map(Backend::name),
+                        "reason": format!(
+                            "the {} role does not allow it (MCP_ROLE)",
+                            self.config.role.name
+                        ),
+                    }));
+                }
+                let backend = backend?;
+                let reason = if !backend.configured(&self.config) {
+                    format!(
+                        "{} is not configured (set {})",
+                        backend.name(),
+                        backend.settings()
+                    )
+                } else if !self.availability.is_available(backend) {
+                    format!(
+                        "{} is unreachable or rejects the credentials",
+                        backend.name()
+                    )
+                } else {
+                    return None;
+                };
+                Some(serde_json::json!({
+                    "tool": tool.name,
+                    "backend": backend.name(),
+                    "reason": reason,
+                }))
+            })
+            .collect();
+        structured(Ok(serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "configuration": status::configuration(&self.config),
+            "backends": backends,
+            "hidden_tools": hidden_tools,
+            "policies": status::policies(&self.config),
+            "limits": status::limits(&self.config),
+        })))
+    }
+
+    /// Verify that data in transit to the SQL endpoints and Cosmos DB is
+    /// protected.
+    #[tool(
+        description = "Verify that connections to the configured SQL endpoints \
+                          (MSSQL, Synapse, Fabric, Cosmos DB analytical) are \
+                          encrypted with a validated certificate and that Cosmos DB \
+                          endpoints use HTTPS.  Opens a connection to each and \
+                          reports what was negotiated (SQL encrypt option and \
+                          transport, TLS version and cipher suite) and any issue, \
+                          e.g. TrustServerCertificate=true."
+    )]
+    async fn server_connection_security(&self) -> Result<Json<ConnectionSecurityReport>, String> {
+        structured(Ok(transit::check(&self.config).await))
+    }
+
+    // ------------------------------------------------------------------
+    // Query drafting
+    // ------------------------------------------------------------------
+
+    /// Draft a read-only query with the client's model.
+    #[tool(
+        description = "Draft a read-only query answering `question` for an MSSQL \
+                          `table` (schema.table) or a Cosmos DB `container`.  The \
+                          server reads the live schema and asks the client's model \
+                          to write the query (MCP sampling), then rejects drafts \
+                          that are not a single read-only SELECT.  The query is \
+                          returned, not run; `run_with` names the tool to run it."
+    )]
+    async fn generate_query(
+        &self,
+        Parameters(params): Parameters<GenerateQueryParams>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<Json<GeneratedQuery>, String> {
+        let database = match (&params.table, &params.container) {
+            (None, Some(_)) => {
+                let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+                Some(resolve_database(cfg, params.database.as_deref())?)
+            }
+            _ => None,
+        };
+        let target = match (&params.table, &params.container) {
+            (Some(table), _) => {
+                let (schema, table) = table.split_once('.').unwrap_or(("dbo", table));
+                QueryTarget::Table { schema, table }
+            }
+            (None, Some(container)) => QueryTarget::Container {
+                database: database.as_deref().unwrap_or_default(),
+                container,
+            },
+            (None, None) => QueryTarget::Any,
+        };
+
+        structured(
+            generate::generate_query(
+                &context.peer,
+                &self.config,
+                self.cosmos(),
+                &params.question,
+                target,
+            )
+            .await,
+        )
     }
 
     // ------------------------------------------------------------------
-    // Cosmos DB tools
+    // Cosmos DB management tools (COSMOS_ALLOW_MANAGEMENT)
     // ------------------------------------------------------------------
 
-    /// List all databases in the Azure Cosmos DB account.
+    /// Create a database in the Azure Cosmos DB account.
     ///
-    /// Returns a JSON array of database name strings.
-    #[tool(description = "List all databases in the Azure Cosmos DB account.")]
-    async fn cosmos_list_databases(&self) -> Result<String, String> {
+    /// Requires `COSMOS_ALLOW_MANAGEMENT=true`.
+    #[tool(description = "Create a Cosmos DB database, optionally with manual \
+                          (`throughput`) or autoscale (`autoscale_max_throughput`) \
+                          provisioned RU/s.  Requires COSMOS_ALLOW_MANAGEMENT=true.")]
+    async fn cosmos_create_database(
+        &self,
+        Parameters(params): Parameters<CosmosCreateDatabaseParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<CreatedDatabase>, String> {
         let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_management().map_err(|e| e.to_string())?;
 
-        cosmos::list_databases(cfg)
-            .await
-            .map_err(|e| e.to_string())
-            .map(|v| v.to_string())
+        confirm(
+            &peer,
+            "create a database",
+            &cfg.endpoint,
+            &pretty_json(&serde_json::json!({
+                "database": params.database,
+                "throughput": params.throughput,
+                "autoscale_max_throughput": params.autoscale_max_throughput,
+            })),
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::create_database(
+                account,
+                &params.database,
+                params.throughput,
+                params.autoscale_max_throughput,
+            )
+            .await,
+        )
     }
 
-    /// List all containers in an Azure Cosmos DB database.
+    /// Create a container in an Azure Cosmos DB database.
     ///
-    /// Returns a JSON array of container name strings.
-    #[tool(description = "List all containers in an Azure Cosmos DB database.  \
-                          `database` defaults to COSMOS_DEFAULT_DATABASE when omitted.")]
-    async fn cosmos_list_containers(
+    /// Requires `COSMOS_ALLOW_MANAGEMENT=true`.
+    #[tool(
+        description = "Create a Cosmos DB container with the given partition key \
+                          path, optionally with manual (`throughput`) or autoscale \
+                          (`autoscale_max_throughput`) provisioned RU/s.  `database` \
+                          defaults to COSMOS_DEFAULT_DATABASE.  Requires \
+                          COSMOS_ALLOW_MANAGEMENT=true."
+    )]
+    async fn cosmos_create_container(
         &self,
-        Parameters(params): Parameters<CosmosListContainersParams>,
-    ) -> Result<String, String> {
+        Parameters(params): Parameters<CosmosCreateContainerParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<CreatedContainer>, String> {
         let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_management().map_err(|e| e.to_string())?;
 
-        let database = params
-            .database
-            .as_deref()
-            .or(cfg.default_database.as_deref())
-            .ok_or_else(|| {
-                "database parameter is required when COSMOS_DEFAULT_DATABASE is not set".to_string()
-            })?
-            .to_string();
+        let database = resolve_database(cfg, params.database.as_deref())?;
 
-        cosmos::list_containers(cfg, &database)
-            .await
-            .map_err(|e| e.to_string())
-            .map(|v| v.to_string())
+        confirm(
+            &peer,
+            "create a container",
+            &format!("database {database}"),
+            &pretty_json(&serde_json::json!({
+                "container": params.container,
+                "partition_key_path": params.partition_key_path,
+                "throughput": params.throughput,
+                "autoscale_max_throughput": params.autoscale_max_throughput,
+            })),
+        )
+        .await?;
+
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::create_container(
+                account,
+                &database,
+                &params.container,
+                &params.partition_key_path,
+                params.throughput,
+                params.autoscale_max_throughput,
+            )
+            .await,
+        )
     }
 
-    /// Query items in an Azure Cosmos DB container using a SQL-API query.
+    /// Set or disable the default time-to-live of a Cosmos DB container.
     ///
-    /// Returns a JSON array of matching document objects.
-    #[tool(description = "Query items in an Azure Cosmos DB container using a \
-                          Cosmos SQL-API query string.  Results are capped at \
-                          max_items (default 100, maximum 5 000).")]
-    async fn cosmos_query_items(
+    /// Requires `COSMOS_ALLOW_MANAGEMENT=true`.
+    #[tool(description = "Set the default time-to-live of a Cosmos DB container \
+                          in seconds (-1 enables TTL with no default), or disable \
+                          TTL when `default_ttl` is omitted.  Requires \
+                          COSMOS_ALLOW_MANAGEMENT=true.")]
+    async fn cosmos_set_container_ttl(
         &self,
-        Parameters(params): Parameters<CosmosQueryItemsParams>,
-    ) -> Result<String, String> {
+        Parameters(params): Parameters<CosmosSetContainerTtlParams>,
+        peer: Peer<RoleServer>,
+    ) -> Result<Json<ContainerTtlChange>, String> {
         let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        cfg.require_management().map_err(|e| e.to_string())?;
 
-        let database = params
-            .database
-            .as_deref()
-            .or(cfg.default_database.as_deref())
-            .ok_or_else(|| {
-                "database parameter is required when COSMOS_DEFAULT_DATABASE is not set".to_string()
-            })?
-            .to_string();
+        let database = resolve_database(cfg, params.database.as_deref())?;
 
-        let max_items = params.max_items.unwrap_or(DEFAULT_MAX_ITEMS);
+        confirm(
+            &peer,
+            "change the default time-to-live of a container",
+            &format!("{database}/{}", params.container),
+            &match params.default_ttl {
+                Some(ttl) => format!("default_ttl = {ttl}"),
+                None => "disable TTL".to_string(),
+            },
+        )
+        .await?;
 
-        cosmos::query_items(
-            cfg,
-            &database,
-            &params.container,
-            &params.query,
-            params.partition_key.as_deref(),
-            max_items,
+        let account = self.cosmos()?;
+
+        structured(
+            cosmos::set_container_ttl(account, &database, &params.container, params.default_ttl)
+                .await,
         )
-        .await
-        .map_err(|e| e.to_string())
-        .map(|v| v.to_string())
+    }
+}
+
+/// Wrap prompt text built from backend context as a single user message.
+fn user_prompt(
+    description: &str,
+    text: anyhow::Result<String>,
+) -> Result<GetPromptResult, ErrorData> {
+    let text = text.map_err(|e| ErrorData::internal_error(format!("{e:#}"), None))?;
+    Ok(GetPromptResult {
+        description: Some(description.to_string()),
+        messages: vec![PromptMessage::new_text(PromptMessageRole::User, text)],
+    })
+}
+
+#[prompt_router]
+impl AzureMcpServer {
+    /// Profile an MSSQL table's data.
+    #[prompt(
+        description = "Profile an MSSQL table: row count, NULL rates, distinct \
+                            counts and value ranges per column, with the table's \
+                            columns pre-filled."
+    )]
+    async fn profile_table(
+        &self,
+        Parameters(args): Parameters<ProfileTableArgs>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let text = match self.config.require_mssql() {
+            Ok(cfg) => prompts::profile_table(cfg, &args.schema, &args.table).await,
+            Err(e) => Err(e),
+        };
+        user_prompt(&format!("Profile {}.{}", args.schema, args.table), text)
+    }
+
+    /// Explain the MSSQL data model.
+    #[prompt(
+        description = "Explain the MSSQL schema: what each table holds and how \
+                            the tables relate, with every table's columns \
+                            pre-filled (optionally limited to one schema)."
+    )]
+    async fn explain_schema(
+        &self,
+        Parameters(args): Parameters<ExplainSchemaArgs>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let text = match self.config.require_mssql() {
+            Ok(cfg) => prompts::explain_schema(cfg, args.schema.as_deref()).await,
+            Err(e) => Err(e),
+        };
+        user_prompt("Explain the MSSQL schema", text)
+    }
+
+    /// Write (and run) a query answering a question.
+    #[prompt(
+        description = "Write and run a query that answers a question, with the \
+                            schema of the given MSSQL `table` (schema.table) or Cosmos \
+                            DB `container` pre-filled; without either, lists the \
+                            available tables and containers."
+    )]
+    async fn write_query(
+        &self,
+        Parameters(args): Parameters<WriteQueryArgs>,
+    ) -> Result<GetPromptResult, ErrorData> {
+        let database = match (&args.container, &self.config.cosmos) {
+            (Some(_), Some(cfg)) => Some(
+                resolve_database(cfg, args.database.as_deref())
+                    .map_err(|e| ErrorData::invalid_params(e, None))?,
+            ),
+            _ => None,
+        };
+        let target = match (&args.table, &args.container) {
+            (Some(table), _) => {
+                let (schema, table) = table.split_once('.').unwrap_or(("dbo", table));
+                QueryTarget::Table { schema, table }
+            }
+            (None, Some(container)) => QueryTarget::Container {
+                database: database.as_deref().unwrap_or_default(),
+                container,
+            },
+            (None, None) => QueryTarget::Any,
+        };
+        let text = prompts::write_query(&self.config, self.cosmos(), &args.question, target).await;
+        user_prompt("Write a query", text)
     }
 }
 
 impl AzureMcpServer {
     /// Create a new server instance.
     pub fn new(config: Config) -> Self {
+        let mut tool_router = Self::tool_router();
+        for (name, description) in &config.customization.tool_descriptions {
+            match tool_router.map.get_mut(name.as_str()) {
+                Some(route) => route.attr.description = Some(description.clone().into()),
+                None => tracing::warn!(
+                    "MCP_TOOL_DESCRIPTIONS_FILE describes unknown tool '{name}'; ignoring it"
+                ),
+            }
+        }
+        let session = uuid::Uuid::new_v4().to_string();
         Self {
+            availability: Arc::new(Availability::new(&config)),
+            audit: Arc::new(AuditLog::new(config.audit.clone(), session.clone())),
+            slow_queries: Arc::new(SlowQueryLog::new(config.slow_queries.clone())),
+            limiter: Arc::new(QueryLimiter::new(&config.concurrency)),
             config: Arc::new(config),
-            tool_router: Self::tool_router(),
+            cosmos: Arc::new(OnceLock::new()),
+            storage: Arc::new(OnceLock::new()),
+            servicebus: Arc::new(OnceLock::new()),
+            tokens: Arc::new(OnceLock::new()),
+            catalog: Arc::new(Catalog::default()),
+            subscriptions: Arc::new(Subscriptions::default()),
+            results: Arc::new(ResultStore::default()),
+            stats: Arc::new(Stats::default()),
+            session: Arc::new(session),
+            tool_router,
+            prompt_router: Self::prompt_router(),
+        }
+    }
+
+    /// Every tool the server registers, sorted by name, with its built-in
+    /// description, whatever the configuration.
+    pub fn registered_tools() -> Vec<Tool> {
+        let mut tools = Self::tool_router().list_all();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
+    /// The tools of the backends that are currently available, plus the
+    /// backend-independent ones, that the client's role allows.  In mock
+    /// mode every tool the role allows is offered.
+    fn available_tools(&self) -> ToolRouter<Self> {
+        let mut router = self.tool_router.clone();
+        for tool in self.tool_router.list_all() {
+            if !self.config.role.allows(&tool.name)
+                || (self.config.mock.is_none()
+                    && Backend::of_tool(&tool.name)
+                        .is_some_and(|b| !self.availability.is_available(b)))
+            {
+                router.remove_route(&tool.name);
+            }
+        }
+        router
+    }
+
+    /// Probe the configured backends every [`PROBE_INTERVAL`] and send
+    /// `notifications/tools/list_changed` to `peer` when one becomes
+    /// reachable or unreachable (e.g. an expired credential), so that the
+    /// client stops offering tools that cannot work.  Runs until the client
+    /// goes away.
+    async fn watch_backends(self, peer: Peer<RoleServer>) {
+        let mut interval = tokio::time::interval(PROBE_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let mut changed = false;
+            for backend in Backend::ALL {
+                if !backend.configured(&self.config) {
+                    continue;
+                }
+                let probe = backend
+                    .probe(&self.config, self.cosmos(), self.storage())
+                    .await;
+                if let Err(e) = &probe {
+                    tracing::debug!("{backend:?} probe failed: {e:#}");
+                }
+                if self.availability.set(backend, probe.is_ok()) {
+                    match &probe {
+                        Ok(()) => tracing::info!("{backend:?} is available again"),
+                        Err(e) => tracing::warn!("{backend:?} is unavailable: {e:#}"),
+                    }
+                    changed = true;
+                }
+            }
+            if changed {
+                if let Err(e) = peer.notify_tool_list_changed().await {
+                    tracing::warn!("Stopping backend probes: {e}");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Re-read subscribed resources every [`POLL_INTERVAL`] and notify `peer`
+    /// when one changes or when tables or containers are added or dropped.
+    /// Runs until no subscriptions remain or the client goes away.
+    async fn watch_resources(self, peer: Peer<RoleServer>) {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        // The first tick completes immediately; subscribers already have the
+        // current state.
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let watched = self.subscriptions.watched();
+            if watched.is_empty() {
+                return;
+            }
+
+            let cosmos = match &self.config.cosmos {
+                Some(_) => self.cosmos().ok(),
+                None => None,
+            };
+            let listing: Vec<String> = resources::list(&self.config, cosmos)
+                .await
+                .into_iter()
+                .map(|r| r.raw.uri)
+                .collect();
+            let mut sent = Ok(());
+            if self.subscriptions.update_listing(&listing) {
+                sent = peer.notify_resource_list_changed().await;
+            }
+
+            for uri in watched {
+                if sent.is_err() {
+                    break;
+                }
+                let Some(resource) = ResourceUri::parse(&uri) else {
+                    continue;
+                };
+                let body = resources::read(&self.config, self.cosmos(), &resource)
+                    .await
+                    .inspect_err(|e| tracing::warn!("Failed to re-read {uri}: {e:#}"))
+                    .ok();
+                if self.subscriptions.update(&uri, body.as_ref()) {
+                    tracing::info!("Subscribed resource {uri} changed");
+                    sent = peer
+                        .notify_resource_updated(ResourceUpdatedNotificationParam { uri })
+                        .await;
+                }
+            }
+
+            if let Err(e) = sent {
+                tracing::warn!("Stopping resource subscriptions: {e}");
+                self.subscriptions.clear();
+                return;
+            }
+        }
+    }
+
+    /// Return the shared Cosmos DB clients, building them on first use.
+    ///
+    /// Construction is cheap and does not touch the network, so a racing
+    /// first call may build a second copy; only one is ever kept.
+    fn cosmos(&self) -> Result<&CosmosAccount, String> {
+        if let Some(account) = self.cosmos.get() {
+            return Ok(account);
+        }
+        let cfg = self.config.require_cosmos().map_err(|e| e.to_string())?;
+        let account = CosmosAccount::new(cfg).map_err(|e| e.to_string())?;
+        Ok(self.cosmos.get_or_init(|| account))
+    }
+
+    /// The request unit budget of a Cosmos DB read: the caller's, capped by
+    /// `COSMOS_MAX_REQUEST_UNITS` and by what is left of the session's
+    /// `COSMOS_SESSION_MAX_REQUEST_UNITS`.
+    fn request_unit_budget(&self, cfg: &CosmosConfig, requested: Option<f64>) -> Option<f64> {
+        let budget = cfg.request_unit_budget(requested);
+        let Some(limit) = cfg.session_max_request_units else {
+            return budget;
+        };
+        let left = (limit - self.stats.session_request_units(&self.session)).max(0.0);
+        Some(budget.map_or(left, |budget| budget.min(left)))
+    }
+
+    /// Why a call of `tool` is refused, if it could charge request units
+    /// and the session has spent `COSMOS_SESSION_MAX_REQUEST_UNITS`.
+    /// `fetch_next_page` counts, as it may continue a Cosmos DB query.
+    fn request_unit_ceiling(&self, tool: &str) -> Option<String> {
+        let limit = self.config.cosmos.as_ref()?.session_max_request_units?;
+        let charges = matches!(
+            Backend::of_tool(tool),
+            Some(Backend::Cosmos | Backend::Gremlin)
+        ) || tool == "fetch_next_page";
+        let spent = self.stats.session_request_units(&self.session);
+        (charges && spent >= limit).then(|| {
+            format!(
+                "Quota exceeded: this session has spent {spent:.1} of its {limit} request units \
+                 (COSMOS_SESSION_MAX_REQUEST_UNITS); Cosmos DB tools are refused \
+                 for the rest of the session"
+            )
+        })
+    }
+
+    /// Write `entry` to the audit log.
+    async fn record_audit(&self, entry: &Value) {
+        let cosmos = match self.config.audit.cosmos_container {
+            Some(_) => self.cosmos(),
+            None => Err("not needed".into()),
+        };
+        self.audit.record(entry, cosmos).await;
+    }
+
+    /// Why a call of `tool` is refused, if it reaches a backend and the
+    /// session has used up one of its `SESSION_MAX_*` quotas.  The call
+    /// crossing a quota completes, so usage can end up above it.
+    fn session_quota(&self, tool: &str) -> Option<String> {
+        if !stats::is_query(tool) {
+            return None;
+        }
+        self.quota_exceeded()
+    }
+
+    /// Why calls and resource reads reaching a backend are refused, if the
+    /// session has used up one of its `SESSION_MAX_*` quotas.
+    fn quota_exceeded(&self) -> Option<String> {
+        let quotas = &self.config.quotas;
+        let used = self.stats.session_usage(&self.session);
+        [
+            (
+                used.queries,
+                quotas.max_queries,
+                "queries",
+                "SESSION_MAX_QUERIES",
+            ),
+            (used.rows, quotas.max_rows, "rows", "SESSION_MAX_ROWS"),
+            (
+                used.bytes,
+                quotas.max_bytes,
+                "bytes of results",
+                "SESSION_MAX_RESULT_BYTES",
+            ),
+        ]
+        .into_iter()
+        .find_map(|(used, limit, what, variable)| {
+            let limit = limit?;
+            (used >= limit).then(|| {
+                format!(
+                    "Quota exceeded: this session has used {used} of its {limit} {what} \
+                     ({variable}); tools and resources reaching a backend are refused for \
+                     the rest of the session"
+                )
+            })
+        })
+    }
+
+    /// Return the shared Azure Storage client, creating it on first use.
+    fn storage(&self) -> Result<&StorageClient, String> {
+        if let Some(client) = self.storage.get() {
+            return Ok(client);
+        }
+        let cfg = self.config.require_storage().map_err(|e| e.to_string())?;
+        let client = StorageClient::new(cfg).map_err(|e| e.to_string())?;
+        Ok(self.storage.get_or_init(|| client))
+    }
+
+    /// Return the shared Service Bus client, creating it on first use.
+    fn servicebus(&self) -> Result<&ServiceBusClient, String> {
+        let cfg = self
+            .config
+            .require_servicebus()
+            .map_err(|e| e.to_string())?;
+        Ok(self.servicebus.get_or_init(|| ServiceBusClient::new(cfg)))
+    }
+
+    /// Return the shared Azure AD token provider, creating it on first use.
+    fn tokens(&self) -> Result<&TokenProvider, String> {
+        let credential = self
+            .config
+            .require_credential()
+            .map_err(|e| e.to_string())?;
+        Ok(self.tokens.get_or_init(|| TokenProvider::new(credential)))
+    }
+
+    /// The connection to the Fabric SQL endpoint, with a fresh access token.
+    async fn fabric(&self) -> Result<MssqlConfig, String> {
+        let cfg = self.config.require_fabric().map_err(|e| e.to_string())?;
+        let token = self
+            .tokens()?
+            .token(mssql::SQL_RESOURCE)
+            .await
+            .map_err(|e| format!("{e:#}"))?;
+        Ok(cfg.connection(token))
+    }
+
+    /// The Azure AD token provider for Azure OpenAI, or `None` when
+    /// `AZURE_OPENAI_API_KEY` is used instead.
+    fn openai_tokens(&self) -> Result<Option<&TokenProvider>, String> {
+        let cfg = self.config.require_openai().map_err(|e| e.to_string())?;
+        match cfg.api_key {
+            Some(_) => Ok(None),
+            None => self.tokens().map(Some),
+        }
+    }
+
+    /// The Azure AD token provider for App Configuration, or `None` when an
+    /// access key from `APPCONFIG_CONNECTION_STRING` is used instead.
+    fn appconfig_tokens(&self) -> Result<Option<&TokenProvider>, String> {
+        let cfg = self.config.require_appconfig().map_err(|e| e.to_string())?;
+        match cfg.access_key {
+            Some(_) => Ok(None),
+            None => self.tokens().map(Some),
         }
     }
+
+    /// The database a Kusto tool targets: `database`, else `KUSTO_DATABASE`.
+    fn kusto_database(&self, database: Option<String>) -> Result<String, String> {
+        let cfg = self.config.require_kusto().map_err(|e| e.to_string())?;
+        database
+            .or_else(|| cfg.default_database.clone())
+            .ok_or_else(|| "database is required when KUSTO_DATABASE is not set".to_string())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{CosmosConfig, MssqlConfig};
+    use crate::config::{
+        ConcurrencyConfig, CosmosConfig, CosmosQueryPolicy, Customization, MockConfig, RetryPolicy,
+        SessionQuotas, DEFAULT_MAX_RESULT_BYTES, DEFAULT_QUERY_PARALLELISM,
+    };
+    use rmcp::{service::RunningService, ServiceExt};
+    use serde_json::json;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    fn mssql_only_config() -> Config {
+        Config {
+            mssql: Some(MssqlConfig::for_tests("server=localhost;database=test")),
+            ..Config::for_tests()
+        }
+    }
 
     fn make_server_mssql_only() -> AzureMcpServer {
-        AzureMcpServer::new(Config {
-            mssql: Some(MssqlConfig {
-                connection_string: "server=localhost;database=test".into(),
-            }),
-            cosmos: None,
-        })
+        AzureMcpServer::new(mssql_only_config())
     }
 
     fn make_server_cosmos_only() -> AzureMcpServer {
         AzureMcpServer::new(Config {
-            mssql: None,
             cosmos: Some(CosmosConfig {
                 endpoint: "https://example.documents.azure.com:443/".into(),
                 key: Some("dGVzdGtleQ==".into()),
                 default_database: Some("mydb".into()),
+                allow_writes: false,
+                allow_management: false,
+                max_request_units: None,
+                session_max_request_units: None,
+                preferred_regions: Vec::new(),
+                query_parallelism: DEFAULT_QUERY_PARALLELISM,
+                integrated_cache: None,
+                query_policy: CosmosQueryPolicy::default(),
+                analytical: None,
+                analytical_credential: None,
+                import_dir: None,
+                retry: RetryPolicy::default(),
+                max_result_bytes: DEFAULT_MAX_RESULT_BYTES,
             }),
+            ..Config::for_tests()
         })
     }
 
+    /// Serve `server` over an in-memory pipe to a scripted client that
+    /// declares `capabilities` and answers every elicitation request with
+    /// `answer`.
+    async fn connect(
+        server: AzureMcpServer,
+        capabilities: Value,
+        answer: Value,
+    ) -> RunningService<RoleServer, AzureMcpServer> {
+        let (server_io, client_io) = tokio::io::duplex(64 * 1024);
+        let (client_read, mut client_write) = tokio::io::split(client_io);
+        let initialize = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-06-18",
+                "capabilities": capabilities,
+                "clientInfo": { "name": "test", "version": "0" },
+            },
+        });
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        client_write
+            .write_all(format!("{initialize}\n{initialized}\n").as_bytes())
+            .await
+            .unwrap();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(client_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message: Value = serde_json::from_str(&line).unwrap();
+                if message["method"] == "elicitation/create" {
+                    let reply = json!({ "jsonrpc": "2.0", "id": message["id"], "result": answer });
+                    if client_write
+                        .write_all(format!("{reply}\n").as_bytes())
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+        server.serve(tokio::io::split(server_io)).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn resource_reads_are_audited() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let mut config = mssql_only_config();
+        // Nothing listens on port 1, so the read fails at once.
+        config.mssql = Some(MssqlConfig {
+            retry: RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            },
+            ..MssqlConfig::for_tests("server=tcp:127.0.0.1,1")
+        });
+        config.audit.file = Some(path.display().to_string());
+        let mut client = crate::cli::Client::connect(AzureMcpServer::new(config), false)
+            .await
+            .unwrap();
+        let uri = "mssql://schema/dbo/orders";
+        let read = client
+            .request("resources/read", json!({ "uri": uri }))
+            .await;
+        assert!(read.is_err());
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let entry: Value = serde_json::from_str(log.lines().next().unwrap()).unwrap();
+        assert_eq!(entry["tool"], "resources/read");
+        assert_eq!(entry["arguments"]["uri"], uri);
+        assert_eq!(entry["outcome"], "error");
+    }
+
+    #[tokio::test]
+    async fn resource_reads_count_against_the_session_quotas() {
+        let mut config = mssql_only_config();
+        config.mssql = Some(MssqlConfig {
+            retry: RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            },
+            ..MssqlConfig::for_tests("server=tcp:127.0.0.1,1")
+        });
+        config.quotas = SessionQuotas {
+            max_queries: Some(1),
+            max_rows: None,
+            max_bytes: None,
+        };
+        let server = AzureMcpServer::new(config);
+        let mut client = crate::cli::Client::connect(server.clone(), false)
+            .await
+            .unwrap();
+        let read = json!({ "uri": "mssql://dbo/orders/rows" });
+
+        let failed = client.request("resources/read", read.clone()).await;
+        assert!(!failed
+            .unwrap_err()
+            .to_string()
+            .starts_with("Quota exceeded"));
+        assert_eq!(server.stats.session_usage(&server.session).queries, 1);
+        let refused = client.request("resources/read", read).await.unwrap_err();
+        assert!(
+            refused.to_string().starts_with("Quota exceeded"),
+            "{refused}"
+        );
+    }
+
+    #[tokio::test]
+    async fn resource_reads_wait_for_the_backend_limit() {
+        let mut config = mssql_only_config();
+        config.concurrency = ConcurrencyConfig {
+            default: Some(1),
+            ..ConcurrencyConfig::default()
+        };
+        let server = AzureMcpServer::new(config);
+        let mut client = crate::cli::Client::connect(server.clone(), false)
+            .await
+            .unwrap();
+        let permit = server.limiter.acquire(Backend::Mssql).await;
+        assert!(permit.is_some());
+
+        let read = client.request(
+            "resources/read",
+            json!({ "uri": "mssql://dbo/orders/rows" }),
+        );
+        let waited = tokio::time::timeout(Duration::from_millis(200), read).await;
+        assert!(waited.is_err(), "the read did not wait for the permit");
+        assert_eq!(server.limiter.report()["mssql"]["running"], 1);
+    }
+
+    #[tokio::test]
+    async fn the_command_line_client_calls_tools_in_process() {
+        let mut config = (*make_server_mssql_only().config).clone();
+        config.mssql = None;
+        config.mock = Some(MockConfig {
+            fixtures: std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures"),
+        });
+        let mut client = crate::cli::Client::connect(AzureMcpServer::new(config), false)
+            .await
+            .unwrap();
+        let result = client
+            .request(
+                "tools/call",
+                json!({ "name": "mssql_list_tables", "arguments": {} }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            result["structuredContent"]["tables"][0]["table_name"],
+            "customers"
+        );
+        assert!(result["_meta"]["correlationId"].is_string(), "{result}");
+    }
+
     #[test]
     fn server_info_contains_correct_name() {
         let server = make_server_mssql_only();
@@ -255,6 +3854,137 @@ mod tests {
             info.capabilities.tools.is_some(),
             "tools capability must be present"
         );
+        assert!(
+            info.capabilities.resources.is_some(),
+            "resources capability must be present"
+        );
+        assert!(
+            info.capabilities.prompts.is_some(),
+            "prompts capability must be present"
+        );
+        assert!(
+            info.capabilities.completions.is_some(),
+            "completions capability must be present"
+        );
+        assert_eq!(info.capabilities.tools.unwrap().list_changed, Some(true));
+        let resources = info.capabilities.resources.unwrap();
+        assert_eq!(resources.subscribe, Some(true));
+        assert_eq!(resources.list_changed, Some(true));
+    }
+
+    #[test]
+    fn tools_of_unconfigured_backends_are_not_offered() {
+        let server = make_server_mssql_only();
+        let tools = server.available_tools();
+        assert!(tools.has_route("mssql_execute_query"));
+        assert!(tools.has_route("fetch_next_page"));
+        assert!(!tools.has_route("cosmos_query_items"));
+
+        server.availability.set(Backend::Mssql, false);
+        assert!(!server.available_tools().has_route("mssql_execute_query"));
+    }
+
+    #[tokio::test]
+    async fn tools_outside_the_role_are_not_offered() {
+        let mut config = (*make_server_mssql_only().config).clone();
+        config.role = crate::roles::built_in("reader").unwrap();
+        let server = AzureMcpServer::new(config);
+        let tools = server.available_tools();
+        assert!(tools.has_route("mssql_list_tables"));
+        assert!(!tools.has_route("mssql_execute_query"));
+
+        let Json(status) = server.server_status().await.unwrap();
+        let hidden = status
+            .hidden_tools
+            .iter()
+            .find(|t| t.tool == "mssql_execute_query")
+            .expect("mssql_execute_query should be hidden");
+        assert!(hidden.reason.contains("reader role"), "{}", hidden.reason);
+
+        // Paging is allowed, but not through a query the role may not run.
+        let cursor = crate::pagination::mssql_cursor("SELECT * FROM dbo.users");
+        let Err(error) = server
+            .fetch_next_page(Parameters(FetchNextPageParams {
+                cursor,
+                format: Default::default(),
+            }))
+            .await
+        else {
+            panic!("a reader paged through an MSSQL query");
+        };
+        assert!(error.contains("reader role"), "{error}");
+    }
+
+    #[test]
+    fn every_tool_belongs_to_a_built_in_role() {
+        let analyst = crate::roles::built_in("analyst").unwrap();
+        let server = make_server_mssql_only();
+        for tool in server.tool_router.list_all() {
+            assert!(
+                analyst.allows(&tool.name) || crate::roles::ADMIN_TOOLS.contains(&&*tool.name),
+                "{} is in no role's tool list",
+                tool.name
+            );
+        }
+    }
+
+    #[test]
+    fn prompt_router_lists_expected_prompts() {
+        let server = make_server_mssql_only();
+        let prompts = server.prompt_router.list_all();
+        let names: Vec<_> = prompts.iter().map(|p| p.name.as_str()).collect();
+        for expected in ["profile_table", "explain_schema", "write_query"] {
+            assert!(names.contains(&expected), "{expected} missing");
+        }
+    }
+
+    #[test]
+    fn every_tool_declares_an_output_schema() {
+        let server = make_server_mssql_only();
+        for tool in server.tool_router.list_all() {
+            assert!(
+                tool.output_schema.is_some(),
+                "{} has no output schema",
+                tool.name
+            );
+        }
+    }
+
+    #[test]
+    fn customization_replaces_instructions_and_tool_descriptions() {
+        let server = AzureMcpServer::new(Config {
+            mssql: Some(MssqlConfig::for_tests("server=localhost;database=test")),
+            customization: Customization {
+                instructions: Some("Tables in the sales schema are owned by finance.".into()),
+                tool_descriptions: [
+                    (
+                        "mssql_list_tables".to_string(),
+                        "List warehouse tables.".to_string(),
+                    ),
+                    ("no_such_tool".to_string(), "Ignored.".to_string()),
+                ]
+                .into(),
+            },
+            ..Config::for_tests()
+        });
+        assert_eq!(
+            server.get_info().instructions.as_deref(),
+            Some("Tables in the sales schema are owned by finance.")
+        );
+        assert_eq!(
+            server
+                .get_tool("mssql_list_tables")
+                .unwrap()
+                .description
+                .as_deref(),
+            Some("List warehouse tables.")
+        );
+
+        let default = make_server_mssql_only();
+        assert_eq!(
+            default.get_info().instructions.as_deref(),
+            Some(DEFAULT_INSTRUCTIONS)
+        );
     }
 
     #[test]
@@ -271,6 +4001,13 @@ mod tests {
             names.contains(&"mssql_execute_query"),
             "mssql_execute_query missing"
         );
+        assert!(
+            names.contains(&"fetch_next_page"),
+            "fetch_next_page missing"
+        );
+        assert!(names.contains(&"generate_query"), "generate_query missing");
+        assert!(names.contains(&"server_stats"), "server_stats missing");
+        assert!(names.contains(&"server_status"), "server_status missing");
         assert!(
             names.contains(&"cosmos_list_databases"),
             "cosmos_list_databases missing"
@@ -283,5 +4020,268 @@ mod tests {
             names.contains(&"cosmos_query_items"),
             "cosmos_query_items missing"
         );
+        assert!(
+            names.contains(&"cosmos_container_stats"),
+            "cosmos_container_stats missing"
+        );
+        assert!(
+            names.contains(&"cosmos_list_programmability"),
+            "cosmos_list_programmability missing"
+        );
+        assert!(
+            names.contains(&"cosmos_read_conflicts"),
+            "cosmos_read_conflicts missing"
+        );
+        assert!(
+            names.contains(&"cosmos_vector_search"),
+            "cosmos_vector_search missing"
+        );
+        assert!(
+            names.contains(&"cosmos_text_search"),
+            "cosmos_text_search missing"
+        );
+        assert!(
+            names.contains(&"cosmos_analytical_query"),
+            "cosmos_analytical_query missing"
+        );
+        assert!(
+            names.contains(&"cosmos_read_item"),
+            "cosmos_read_item missing"
+        );
+        assert!(
+            names.contains(&"cosmos_read_change_feed"),
+            "cosmos_read_change_feed missing"
+        );
+        assert!(
+            names.contains(&"cosmos_patch_item"),
+            "cosmos_patch_item missing"
+        );
+        assert!(
+            names.contains(&"cosmos_execute_stored_procedure"),
+            "cosmos_execute_stored_procedure missing"
+        );
+        assert!(names.contains(&"cosmos_get_ttl"), "cosmos_get_ttl missing");
+        assert!(
+            names.contains(&"cosmos_set_item_ttl"),
+            "cosmos_set_item_ttl missing"
+        );
+        assert!(
+            names.contains(&"cosmos_set_container_ttl"),
+            "cosmos_set_container_ttl missing"
+        );
+        assert!(
+            names.contains(&"cosmos_bulk_import"),
+            "cosmos_bulk_import missing"
+        );
+        assert!(
+            names.contains(&"cosmos_create_database"),
+            "cosmos_create_database missing"
+        );
+        assert!(
+            names.contains(&"cosmos_create_container"),
+            "cosmos_create_container missing"
+        );
+    }
+
+    #[tokio::test]
+    async fn cosmos_patch_item_is_refused_without_write_flag() {
+        let server = make_server_cosmos_only();
+        let client = connect(server.clone(), json!({}), Value::Null).await;
+        let err = server
+            .cosmos_patch_item(
+                Parameters(CosmosPatchItemParams {
+                    container: "orders".into(),
+                    id: "1".into(),
+                    partition_key: "1".into(),
+                    operations: vec![],
+                    database: None,
+                }),
+                client.peer().clone(),
+            )
+            .await
+            .err()
+            .expect("tool should be refused");
+        assert!(
+            err.contains("COSMOS_ALLOW_WRITES"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn cosmos_management_tools_are_refused_without_flag() {
+        let server = make_server_cosmos_only();
+        let client = connect(server.clone(), json!({}), Value::Null).await;
+        let err = server
+            .cosmos_create_database(
+                Parameters(CosmosCreateDatabaseParams {
+                    database: "sandbox".into(),
+                    throughput: None,
+                    autoscale_max_throughput: None,
+                }),
+                client.peer().clone(),
+            )
+            .await
+            .err()
+            .expect("tool should be refused");
+        assert!(
+            err.contains("COSMOS_ALLOW_MANAGEMENT"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn output_options_are_top_level_parameters() {
+        let schema = serde_json::to_value(schemars::schema_for!(MssqlExecuteQueryParams)).unwrap();
+        for name in ["query", "render", "chart", "format", "parse_json"] {
+            assert!(
+                schema["properties"].get(name).is_some(),
+                "{name} missing from {schema}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn writes_are_refused_without_write_flag() {
+        let server = make_server_mssql_only();
+        let client = connect(server.clone(), json!({}), Value::Null).await;
+        let err = server
+            .mssql_execute_query(
+                Parameters(MssqlExecuteQueryParams {
+                    query: "DELETE FROM dbo.orders".into(),
+                    max_rows: None,
+                    output: OutputOptions::default(),
+                }),
+                client.peer().clone(),
+            )
+            .await
+            .err()
+            .expect("statement should be refused");
+        assert!(
+            err.contains("MSSQL_ALLOW_WRITES"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_are_not_run_when_the_user_declines() {
+        let mut config = mssql_only_config();
+        config.mssql.as_mut().unwrap().allow_writes = true;
+        let server = AzureMcpServer::new(config);
+        let client = connect(
+            server.clone(),
+            json!({ "elicitation": {} }),
+            json!({ "action": "decline" }),
+        )
+        .await;
+        let err = server
+            .mssql_execute_query(
+                Parameters(MssqlExecuteQueryParams {
+                    query: "DELETE FROM dbo.orders".into(),
+                    max_rows: None,
+                    output: OutputOptions::default(),
+                }),
+                client.peer().clone(),
+            )
+            .await
+            .err()
+            .expect("statement should not run");
+        assert!(err.contains("did not confirm"), "unexpected error: {err}");
+    }
+
+    #[test]
+    fn cosmos_clients_are_built_once_and_shared() {
+        let server = make_server_cosmos_only();
+        let first = server.cosmos().expect("clients should build") as *const CosmosAccount;
+        let clone = server.clone();
+        let second = clone.cosmos().expect("clients should build") as *const CosmosAccount;
+        assert_eq!(first, second, "clones must share the cached clients");
+
+        assert!(make_server_mssql_only().cosmos().is_err());
+    }
+
+    #[tokio::test]
+    async fn server_status_explains_hidden_and_refused_tools() {
+        let server = make_server_mssql_only();
+        let Json(status) = server.server_status().await.unwrap();
+        assert_eq!(
+            status.configuration["mssql"],
+            json!({ "server": "localhost", "database": "test" })
+        );
+        assert_eq!(status.configuration["cosmos"], Value::Null);
+        let hidden = status
+            .hidden_tools
+            .iter()
+            .find(|t| t.tool == "cosmos_query_items")
+            .expect("cosmos_query_items should be hidden");
+        assert!(
+            hidden.reason.contains("COSMOS_ENDPOINT"),
+            "{}",
+            hidden.reason
+        );
+        assert!(!status
+            .hidden_tools
+            .iter()
+            .any(|t| t.tool == "mssql_execute_query"));
+        let writes = status
+            .policies
+            .iter()
+            .find(|p| p.flag == "COSMOS_ALLOW_WRITES")
+            .unwrap();
+        assert!(!writes.enabled);
+        assert_eq!(status.limits.sql_default_max_rows, DEFAULT_MAX_ROWS);
+    }
+
+    #[test]
+    fn session_request_unit_limit_caps_and_then_refuses_cosmos_tools() {
+        let mut config = (*make_server_cosmos_only().config).clone();
+        let cosmos = config.cosmos.as_mut().unwrap();
+        cosmos.session_max_request_units = Some(100.0);
+        let server = AzureMcpServer::new(config);
+        let cfg = server.config.require_cosmos().unwrap();
+
+        server.stats.record(
+            &server.session,
+            "cosmos_query_items",
+            Duration::from_millis(5),
+            false,
+            70.0,
+        );
+        assert_eq!(server.request_unit_budget(cfg, None), Some(30.0));
+        assert_eq!(server.request_unit_budget(cfg, Some(10.0)), Some(10.0));
+        assert!(server.request_unit_ceiling("cosmos_query_items").is_none());
+
+        server.stats.record(
+            &server.session,
+            "cosmos_query_items",
+            Duration::from_millis(5),
+            false,
+            45.0,
+        );
+        let refusal = server.request_unit_ceiling("cosmos_read_item").unwrap();
+        assert!(
+            refusal.contains("COSMOS_SESSION_MAX_REQUEST_UNITS"),
+            "{refusal}"
+        );
+        assert!(server.request_unit_ceiling("fetch_next_page").is_some());
+        assert!(server.request_unit_ceiling("blob_read").is_none());
+    }
+
+    #[test]
+    fn session_quotas_refuse_backend_tools_once_used_up() {
+        let mut config = (*make_server_cosmos_only().config).clone();
+        config.quotas = SessionQuotas {
+            max_queries: Some(3),
+            max_rows: Some(100),
+            max_bytes: None,
+        };
+        let server = AzureMcpServer::new(config);
+
+        server.stats.record_query(&server.session, 60, 4096);
+        assert!(server.session_quota("cosmos_query_items").is_none());
+        server.stats.record_query(&server.session, 60, 4096);
+        let refusal = server.session_quota("fetch_next_page").unwrap();
+        assert!(refusal.starts_with("Quota exceeded"), "{refusal}");
+        assert!(refusal.contains("SESSION_MAX_ROWS"), "{refusal}");
+        assert!(server.session_quota("server_stats").is_none());
     }
 }
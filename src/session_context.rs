@@ -0,0 +1,76 @@
+use std::future::Future;
+
+use serde_json::{Map, Value};
+
+use crate::config::SessionContextConfig;
+
+/// Experimental client capability carrying the session's own
+/// `SESSION_CONTEXT` values, e.g. `{"sessionContext": {"tenant_id": "42"}}`.
+pub const SESSION_CONTEXT_CAPABILITY: &str = "sessionContext";
+
+tokio::task_local! {
+    /// The `SESSION_CONTEXT` values the client of the tool call being
+    /// handled passed in its capabilities.
+    static CLIENT_VALUES: Map<String, Value>;
+}
+
+/// Run `future` with `values` as the client's `SESSION_CONTEXT` values.
+/// Tasks it spawns do not inherit them.
+pub async fn scope<F: Future>(values: Map<String, Value>, future: F) -> F::Output {
+    CLIENT_VALUES.scope(values, future).await
+}
+
+/// The key/value pairs to set with `sp_set_session_context` on a new
+/// connection: the configured values, then the client's values for the keys
+/// listed in `MSSQL_SESSION_CONTEXT_CLIENT_KEYS`.  A client cannot replace a
+/// configured value, and other keys it passes are ignored.
+pub fn pairs(config: &SessionContextConfig) -> Vec<(String, String)> {
+    let mut pairs = config.values.clone();
+    if config.client_keys.is_empty() {
+        return pairs;
+    }
+    let _ = CLIENT_VALUES.try_with(|client| {
+        for (key, value) in client {
+            if !config.client_keys.contains(key) {
+                tracing::debug!("Ignoring SESSION_CONTEXT key '{key}' from the client");
+                continue;
+            }
+            if pairs.iter().any(|(configured, _)| configured == key) {
+                continue;
+            }
+            let value = match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            pairs.push((key.clone(), value));
+        }
+    });
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clients_set_only_allowed_unconfigured_keys() {
+        let config = SessionContextConfig {
+            values: vec![("tenant_id".into(), "7".into())],
+            client_keys: vec!["tenant_id".into(), "user_id".into()],
+        };
+        assert_eq!(pairs(&config), config.values);
+
+        let client = serde_json::json!({"tenant_id": "8", "user_id": 42, "role": "admin"});
+        let Value::Object(client) = client else {
+            unreachable!()
+        };
+        let pairs = scope(client, async { pairs(&config) }).await;
+        assert_eq!(
+            pairs,
+            vec![
+                ("tenant_id".to_string(), "7".to_string()),
+                ("user_id".to_string(), "42".to_string()),
+            ]
+        );
+    }
+}
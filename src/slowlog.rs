@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use rmcp::model::{CallToolResult, JsonObject};
+use rmcp::ErrorData;
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::audit;
+use crate::config::SlowQueryConfig;
+
+/// Arguments holding the statement a tool ran, in order of preference.
+const STATEMENT_ARGUMENTS: &[&str] = &["query", "filter"];
+
+/// The statement a call ran (a SQL, KQL, Gremlin or CQL query, a table
+/// filter), if it has one.
+fn statement(arguments: Option<&JsonObject>) -> Option<&str> {
+    STATEMENT_ARGUMENTS
+        .iter()
+        .find_map(|name| arguments?.get(*name)?.as_str())
+}
+
+/// The entry recording one slow call: `{correlation_id, time, tool,
+/// duration_ms, threshold_ms, statement, arguments, outcome, metadata}`,
+/// where `metadata` is the result's Cosmos DB request charge, activity ids
+/// and (when requested) query metrics.
+fn entry(
+    correlation_id: &str,
+    tool: &str,
+    arguments: Option<&JsonObject>,
+    duration: Duration,
+    threshold: Duration,
+    result: &Result<CallToolResult, ErrorData>,
+) -> Value {
+    let outcome = match result {
+        Ok(result) if result.is_error != Some(true) => "ok",
+        _ => "error",
+    };
+    let metadata = result
+        .as_ref()
+        .ok()
+        .and_then(|r| r.structured_content.as_ref())
+        .map(|s| s["metadata"].clone())
+        .filter(Value::is_object);
+    serde_json::json!({
+        "correlation_id": correlation_id,
+        "time": chrono::Utc::now().to_rfc3339(),
+        "tool": tool,
+        "duration_ms": duration.as_millis() as u64,
+        "threshold_ms": threshold.as_millis() as u64,
+        "statement": statement(arguments),
+        "arguments": arguments,
+        "outcome": outcome,
+        "metadata": metadata,
+    })
+}
+
+/// Tool calls that took longer than `SLOW_QUERY_MS`.
+pub struct SlowQueryLog {
+    config: Option<SlowQueryConfig>,
+    /// Serialises appends to the file.
+    file: Mutex<()>,
+}
+
+impl SlowQueryLog {
+    pub fn new(config: Option<SlowQueryConfig>) -> Self {
+        Self {
+            config,
+            file: Mutex::new(()),
+        }
+    }
+
+    /// Log a call of `tool` that took `duration` as a warning when it
+    /// reached the threshold, and append it to `SLOW_QUERY_LOG_FILE` when
+    /// set.  A failed write is logged as an error.
+    pub async fn record(
+        &self,
+        correlation_id: &str,
+        tool: &str,
+        arguments: Option<&JsonObject>,
+        duration: Duration,
+        result: &Result<CallToolResult, ErrorData>,
+    ) {
+        let Some(config) = &self.config else {
+            return;
+        };
+        if duration < config.threshold {
+            return;
+        }
+        let entry = entry(
+            correlation_id,
+            tool,
+            arguments,
+            duration,
+            config.threshold,
+            result,
+        );
+        tracing::warn!(
+            "Slow tool call: {tool} took {} ms (threshold {} ms): {}",
+            entry["duration_ms"],
+            entry["threshold_ms"],
+            statement(arguments).unwrap_or("no statement"),
+        );
+        if let Some(path) = &config.file {
+            if let Err(e) = audit::append_line(&self.file, path, &entry).await {
+                tracing::error!("Failed to write the slow query log {path}: {e:#}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn only_calls_over_the_threshold_are_written() {
+        let path = std::env::temp_dir().join(format!("slow-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = SlowQueryLog::new(Some(SlowQueryConfig {
+            threshold: Duration::from_millis(500),
+            file: Some(path.to_string_lossy().into_owned()),
+        }));
+        let arguments = JsonObject::from_iter([(
+            "query".to_string(),
+            Value::from("SELECT * FROM c WHERE c.city = 'Oslo'"),
+        )]);
+        let result = Ok(CallToolResult::structured(serde_json::json!({
+            "items": [],
+            "metadata": { "request_charge": 812.4, "page_count": 3 },
+        })));
+        for ms in [120, 2300] {
+            let duration = Duration::from_millis(ms);
+            log.record(
+                "id",
+                "cosmos_query_items",
+                Some(&arguments),
+                duration,
+                &result,
+            )
+            .await;
+        }
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let entries: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["duration_ms"], 2300);
+        assert_eq!(entries[0]["threshold_ms"], 500);
+        assert_eq!(
+            entries[0]["statement"],
+            "SELECT * FROM c WHERE c.city = 'Oslo'"
+        );
+        assert_eq!(entries[0]["metadata"]["request_charge"], 812.4);
+        assert_eq!(entries[0]["outcome"], "ok");
+    }
+}
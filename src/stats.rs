@@ -0,0 +1,321 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmcp::model::CallToolResult;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::annotations::RECORD_FIELDS;
+use crate::budget::json_size;
+use crate::health::Backend;
+
+/// Hit and miss counts of a cache.
+#[derive(Default)]
+pub struct CacheCounter {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CacheCounter {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `{hits, misses, hit_rate}`, where `hit_rate` is `null` before the
+    /// first lookup.
+    pub fn report(&self) -> Value {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        serde_json::json!({
+            "hits": hits,
+            "misses": misses,
+            "hit_rate": rate(hits, hits + misses),
+        })
+    }
+}
+
+/// `part / total`, or `None` when `total` is zero.
+fn rate(part: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| part as f64 / total as f64)
+}
+
+/// The Cosmos DB request units a result reports it was charged:
+/// `metadata.request_charge` of queries and change feed reads, and
+/// `request_charge` of writes and Gremlin traversals.
+pub fn request_charge(result: &CallToolResult) -> f64 {
+    let Some(structured) = &result.structured_content else {
+        return 0.0;
+    };
+    structured["metadata"]["request_charge"]
+        .as_f64()
+        .or(structured["request_charge"].as_f64())
+        .unwrap_or(0.0)
+}
+
+/// Whether a call of `tool` counts against the session quotas: the tool
+/// reaches a backend, or is `fetch_next_page`, which continues a query.
+pub fn is_query(tool: &str) -> bool {
+    Backend::of_tool(tool).is_some() || tool == "fetch_next_page"
+}
+
+/// The records a result holds and the approximate bytes of its JSON:
+/// `(rows, bytes)`.
+pub fn result_size(result: &CallToolResult) -> (u64, u64) {
+    let Some(structured) = &result.structured_content else {
+        let bytes = result
+            .content
+            .iter()
+            .filter_map(|c| c.as_text())
+            .map(|t| t.text.len())
+            .sum::<usize>();
+        return (0, bytes as u64);
+    };
+    let rows = RECORD_FIELDS
+        .iter()
+        .find_map(|f| structured.get(*f)?.as_array().map(Vec::len))
+        .unwrap_or(0);
+    (rows as u64, json_size(structured) as u64)
+}
+
+/// What one MCP session has used, checked against the session quotas.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct SessionUsage {
+    /// Calls of tools reaching a backend (see [`is_query`]).
+    pub queries: u64,
+    /// Records returned.
+    pub rows: u64,
+    /// Approximate bytes of JSON returned.
+    pub bytes: u64,
+}
+
+/// Calls of one tool.
+#[derive(Default)]
+struct ToolCounts {
+    calls: u64,
+    errors: u64,
+    total: Duration,
+    max: Duration,
+    request_units: f64,
+}
+
+/// Runtime statistics of the server, reported by `server_stats`.
+pub struct Stats {
+    started: Instant,
+    started_at: chrono::DateTime<chrono::Utc>,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+    tools: Mutex<BTreeMap<String, ToolCounts>>,
+    /// Request units charged per MCP session.
+    request_units: Mutex<BTreeMap<String, f64>>,
+    /// Queries, rows and bytes per MCP session.
+    usage: Mutex<BTreeMap<String, SessionUsage>>,
+}
+
+/// A tool call in progress; counted in flight until dropped.
+pub struct InFlight<'a>(&'a Stats);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            started_at: chrono::Utc::now(),
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+            tools: Mutex::default(),
+            request_units: Mutex::default(),
+            usage: Mutex::default(),
+        }
+    }
+}
+
+impl Stats {
+    /// Count a tool call as in flight until the returned guard is dropped.
+    pub fn begin(&self) -> InFlight<'_> {
+        let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::Relaxed);
+        InFlight(self)
+    }
+
+    /// Record a finished call of `tool` in `session` that was charged
+    /// `request_units`.
+    pub fn record(
+        &self,
+        session: &str,
+        tool: &str,
+        duration: Duration,
+        failed: bool,
+        request_units: f64,
+    ) {
+        let mut tools = self.tools.lock().unwrap();
+        let counts = tools.entry(tool.to_string()).or_default();
+        counts.calls += 1;
+        counts.errors += u64::from(failed);
+        counts.total += duration;
+        counts.max = counts.max.max(duration);
+        counts.request_units += request_units;
+        if request_units > 0.0 {
+            *self
+                .request_units
+                .lock()
+                .unwrap()
+                .entry(session.to_string())
+                .or_default() += request_units;
+        }
+    }
+
+    /// The request units charged so far in `session`.
+    pub fn session_request_units(&self, session: &str) -> f64 {
+        let request_units = self.request_units.lock().unwrap();
+        request_units.get(session).copied().unwrap_or(0.0)
+    }
+
+    /// Add a finished query returning `rows` records in about `bytes` bytes
+    /// to the usage of `session`.
+    pub fn record_query(&self, session: &str, rows: u64, bytes: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        let usage = usage.entry(session.to_string()).or_default();
+        usage.queries += 1;
+        usage.rows += rows;
+        usage.bytes += bytes;
+    }
+
+    /// The queries, rows and bytes of `session` so far.
+    pub fn session_usage(&self, session: &str) -> SessionUsage {
+        let usage = self.usage.lock().unwrap();
+        usage.get(session).copied().unwrap_or_default()
+    }
+
+    /// `{uptime_seconds, started_at, in_flight, peak_in_flight, calls, errors,
+    /// tools, request_units}`, where `tools` maps each tool called so far to
+    /// `{calls, errors, error_rate, mean_ms, max_ms, request_units}` and
+    /// `request_units` holds the `total` and the charge per session
+    /// (`sessions`), and `usage` maps each session to its [`SessionUsage`].
+    pub fn report(&self) -> Value {
+        let tools = self.tools.lock().unwrap();
+        let (calls, errors) = tools
+            .values()
+            .fold((0, 0), |(c, e), t| (c + t.calls, e + t.errors));
+        let per_tool: serde_json::Map<String, Value> = tools
+            .iter()
+            .map(|(name, t)| {
+                let stats = serde_json::json!({
+                    "calls": t.calls,
+                    "errors": t.errors,
+                    "error_rate": rate(t.errors, t.calls),
+                    "mean_ms": (t.total / t.calls.max(1) as u32).as_millis() as u64,
+                    "max_ms": t.max.as_millis() as u64,
+                    "request_units": t.request_units,
+                });
+                (name.clone(), stats)
+            })
+            .collect();
+        let sessions = self.request_units.lock().unwrap().clone();
+        let usage = self.usage.lock().unwrap().clone();
+        serde_json::json!({
+            "uptime_seconds": self.started.elapsed().as_secs(),
+            "started_at": self.started_at.to_rfc3339(),
+            "in_flight": self.in_flight.load(Ordering::Relaxed),
+            "peak_in_flight": self.peak_in_flight.load(Ordering::Relaxed),
+            "calls": calls,
+            "errors": errors,
+            "tools": per_tool,
+            "request_units": {
+                "total": sessions.values().sum::<f64>(),
+                "sessions": sessions,
+            },
+            "usage": usage,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calls_are_counted_per_tool() {
+        let stats = Stats::default();
+        {
+            let _a = stats.begin();
+            let _b = stats.begin();
+            assert_eq!(stats.report()["in_flight"], 2);
+        }
+        let query = CallToolResult::structured(serde_json::json!({
+            "items": [],
+            "metadata": { "request_charge": 2.5 },
+        }));
+        assert_eq!(request_charge(&query), 2.5);
+        stats.record(
+            "a",
+            "mssql_execute_query",
+            Duration::from_millis(10),
+            false,
+            0.0,
+        );
+        stats.record(
+            "a",
+            "mssql_execute_query",
+            Duration::from_millis(30),
+            true,
+            0.0,
+        );
+        stats.record(
+            "a",
+            "cosmos_query_items",
+            Duration::from_millis(5),
+            false,
+            2.5,
+        );
+        stats.record(
+            "b",
+            "cosmos_query_items",
+            Duration::from_millis(5),
+            false,
+            4.0,
+        );
+
+        let report = stats.report();
+        assert_eq!(report["in_flight"], 0);
+        assert_eq!(report["peak_in_flight"], 2);
+        assert_eq!(report["calls"], 4);
+        assert_eq!(report["errors"], 1);
+        let mssql = &report["tools"]["mssql_execute_query"];
+        assert_eq!(mssql["calls"], 2);
+        assert_eq!(mssql["error_rate"], 0.5);
+        assert_eq!(mssql["mean_ms"], 20);
+        assert_eq!(mssql["max_ms"], 30);
+        assert_eq!(report["tools"]["cosmos_query_items"]["request_units"], 6.5);
+        assert_eq!(report["request_units"]["total"], 6.5);
+        assert_eq!(report["request_units"]["sessions"]["b"], 4.0);
+        assert_eq!(stats.session_request_units("a"), 2.5);
+
+        let (rows, bytes) = result_size(&query);
+        assert_eq!(rows, 0);
+        stats.record_query("a", 3, 120);
+        stats.record_query("a", 2, bytes);
+        let usage = stats.session_usage("a");
+        assert_eq!((usage.queries, usage.rows), (2, 5));
+        assert_eq!(stats.report()["usage"]["a"]["bytes"], 120 + bytes);
+        assert!(is_query("fetch_next_page") && !is_query("server_stats"));
+
+        let cache = CacheCounter::default();
+        assert_eq!(cache.report()["hit_rate"], Value::Null);
+        cache.hit();
+        cache.hit();
+        cache.hit();
+        cache.miss();
+        assert_eq!(cache.report()["hit_rate"], 0.75);
+    }
+}
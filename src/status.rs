@@ -0,0 +1,321 @@
+use serde_json::{Map, Value};
+
+use crate::config::{Config, CredentialConfig, Recording};
+use crate::cosmos::DEFAULT_MAX_ITEMS;
+use crate::health::Backend;
+use crate::mssql::DEFAULT_MAX_ROWS;
+use crate::results::{MAX_INLINE_BYTES, RESULT_TTL};
+
+/// Connection string keywords that are safe to report.  Everything else —
+/// passwords, account keys, shared access keys — is left out.
+const SAFE_KEYWORDS: &[&str] = &[
+    "server",
+    "data source",
+    "address",
+    "database",
+    "initial catalog",
+    "authentication",
+    "encrypt",
+    "trustservercertificate",
+    "application name",
+    "connect timeout",
+];
+
+/// A tool (or an option of one) refused unless an environment flag is set.
+struct Policy {
+    flag: &'static str,
+    tools: &'static [&'static str],
+    effect: &'static str,
+    enabled: fn(&Config) -> bool,
+}
+
+const POLICIES: &[Policy] = &[
+    Policy {
+        flag: "MSSQL_ALLOW_WRITES",
+        tools: &["mssql_execute_query"],
+        effect: "statements that may modify data are refused",
+        enabled: |c| c.mssql.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "SYNAPSE_ALLOW_WRITES",
+        tools: &["synapse_execute_query"],
+        effect: "statements that may modify data are refused",
+        enabled: |c| c.synapse.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "FABRIC_ALLOW_WRITES",
+        tools: &["fabric_execute_query"],
+        effect: "statements that may modify data are refused",
+        enabled: |c| c.fabric.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "COSMOS_ALLOW_WRITES",
+        tools: &[
+            "cosmos_patch_item",
+            "cosmos_execute_stored_procedure",
+            "cosmos_set_item_ttl",
+            "cosmos_bulk_import",
+        ],
+        effect: "refused",
+        enabled: |c| c.cosmos.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "COSMOS_ALLOW_MANAGEMENT",
+        tools: &[
+            "cosmos_create_database",
+            "cosmos_create_container",
+            "cosmos_set_container_ttl",
+        ],
+        effect: "refused",
+        enabled: |c| c.cosmos.as_ref().is_some_and(|c| c.allow_management),
+    },
+    Policy {
+        flag: "GREMLIN_ALLOW_WRITES",
+        tools: &["gremlin_query"],
+        effect: "traversals that change the graph are refused",
+        enabled: |c| c.gremlin.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "STORAGE_ALLOW_WRITES",
+        tools: &["blob_write"],
+        effect: "refused",
+        enabled: |c| c.storage.as_ref().is_some_and(|c| c.allow_writes),
+    },
+    Policy {
+        flag: "STORAGE_ALLOW_SHARE",
+        tools: &["blob_share_url"],
+        effect: "refused",
+        enabled: |c| c.storage.as_ref().is_some_and(|c| c.allow_share),
+    },
+    Policy {
+        flag: "SERVICEBUS_ALLOW_SEND",
+        tools: &["servicebus_send"],
+        effect: "refused",
+        enabled: |c| c.servicebus.as_ref().is_some_and(|c| c.allow_send),
+    },
+    Policy {
+        flag: "EVENTGRID_ALLOW_PUBLISH",
+        tools: &["eventgrid_publish"],
+        effect: "refused",
+        enabled: |c| c.eventgrid.as_ref().is_some_and(|c| c.allow_publish),
+    },
+    Policy {
+        flag: "KEYVAULT_ALLOW_SECRET_VALUES",
+        tools: &["keyvault_get_secret_metadata"],
+        effect: "include_value is refused",
+        enabled: |c| c.keyvault.as_ref().is_some_and(|c| c.allow_secret_values),
+    },
+];
+
+/// The safe keywords of an ADO.NET connection string, lowercased.
+fn connection_summary(connection_string: &str) -> Value {
+    let pairs: Map<String, Value> = connection_string
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_lowercase(), value.trim()))
+        .filter(|(key, _)| SAFE_KEYWORDS.contains(&key.as_str()))
+        .map(|(key, value)| (key, Value::from(value)))
+        .collect();
+    Value::Object(pairs)
+}
+
+/// The effective configuration without secrets: per backend, `null` when it
+/// is not configured, else its endpoints, defaults and flags, with secrets
+/// reduced to whether they are set.
+pub fn configuration(config: &Config) -> Value {
+    let credential = config.credential.as_ref().map(|c| match c {
+        CredentialConfig::ClientSecret {
+            authority_host,
+            tenant_id,
+            client_id,
+            ..
+        } => serde_json::json!({
+            "kind": "client_secret",
+            "authority_host": authority_host,
+            "tenant_id": tenant_id,
+            "client_id": client_id,
+        }),
+        CredentialConfig::ManagedIdentity { client_id } => serde_json::json!({
+            "kind": "managed_identity",
+            "client_id": client_id,
+        }),
+    });
+    serde_json::json!({
+        "mssql": config.mssql.as_ref().map(|c| connection_summary(&c.connection_string)),
+        "synapse": config.synapse.as_ref().map(|c| connection_summary(&c.connection_string)),
+        "fabric": config.fabric.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "database": c.database,
+        })),
+        "cosmos": config.cosmos.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "key_set": c.key.is_some(),
+            "default_database": c.default_database,
+            "allow_writes": c.allow_writes,
+            "allow_management": c.allow_management,
+            "preferred_regions": c.preferred_regions,
+            "require_filter": c.query_policy.require_filter,
+            "require_partition_key": c.query_policy.require_partition_key,
+            "analytical": c.analytical.as_ref().map(|a| connection_summary(&a.connection_string)),
+            "import_dir": c.import_dir,
+        })),
+        "gremlin": config.gremlin.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "database": c.database,
+            "graph": c.graph,
+            "allow_writes": c.allow_writes,
+        })),
+        "cassandra": config.cassandra.as_ref().map(|c| serde_json::json!({
+            "contact_point": c.contact_point,
+            "port": c.port,
+            "username": c.username,
+            "default_keyspace": c.default_keyspace,
+        })),
+        "storage": config.storage.as_ref().map(|c| serde_json::json!({
+            "account": c.account,
+            "key_set": c.key.is_some(),
+            "endpoint_suffix": c.endpoint_suffix,
+            "table_endpoint": c.table_endpoint,
+            "blob_endpoint": c.blob_endpoint,
+            "allow_writes": c.allow_writes,
+            "allow_share": c.allow_share,
+            "share_container": c.share_container,
+        })),
+        "servicebus": config.servicebus.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "key_name": c.key_name,
+            "allow_send": c.allow_send,
+        })),
+        "eventhubs": config.eventhubs.as_ref().map(|c| serde_json::json!({
+            "host": c.host,
+            "consumer_group": c.consumer_group,
+        })),
+        "eventgrid": config.eventgrid.as_ref().map(|c| serde_json::json!({
+            "topics": c.topics.iter().map(|t| serde_json::json!({
+                "name": t.name,
+                "endpoint": t.endpoint,
+                "key_set": t.key.is_some(),
+            })).collect::<Vec<_>>(),
+            "allow_publish": c.allow_publish,
+        })),
+        "credential": credential,
+        "monitor": {
+            "default_workspace": config.monitor.default_workspace,
+            "default_app": config.monitor.default_app,
+        },
+        "kusto": config.kusto.as_ref().map(|c| serde_json::json!({
+            "cluster_url": c.cluster_url,
+            "default_database": c.default_database,
+        })),
+        "keyvault": config.keyvault.as_ref().map(|c| serde_json::json!({
+            "url": c.url,
+            "allow_secret_values": c.allow_secret_values,
+        })),
+        "openai": config.openai.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "deployment": c.deployment,
+            "api_key_set": c.api_key.is_some(),
+            "dimensions": c.dimensions,
+            "api_version": c.api_version,
+        })),
+        "appconfig": config.appconfig.as_ref().map(|c| serde_json::json!({
+            "endpoint": c.endpoint,
+            "access_key_set": c.access_key.is_some(),
+        })),
+        "subscription_id": config.subscription_id,
+        "audit": {
+            "file": config.audit.file,
+            "cosmos_container": config.audit.cosmos_container
+                .as_ref()
+                .map(|(database, container)| format!("{database}/{container}")),
+        },
+        "customization": {
+            "instructions": config.customization.instructions.is_some(),
+            "tool_descriptions": config.customization.tool_descriptions.len(),
+        },
+        "role": {
+            "name": config.role.name,
+            "tools": config.role.tools,
+        },
+        "query_denylist": config.query_denylist,
+        "mock_fixtures": config.mock.as_ref().map(|m| m.fixtures.display().to_string()),
+        "backend_recording": match &config.recording {
+            Some(Recording::Record(path)) => serde_json::json!({ "record": path.display().to_string() }),
+            Some(Recording::Replay(path)) => serde_json::json!({ "replay": path.display().to_string() }),
+            None => Value::Null,
+        },
+    })
+}
+
+/// The flags that refuse tools (or options of them) unless set:
+/// `[{flag, enabled, tools, effect}]`.
+pub fn policies(config: &Config) -> Value {
+    POLICIES
+        .iter()
+        .map(|policy| {
+            serde_json::json!({
+                "flag": policy.flag,
+                "enabled": (policy.enabled)(config),
+                "tools": policy.tools,
+                "effect": policy.effect,
+            })
+        })
+        .collect()
+}
+
+/// The limits applied to tool calls.
+pub fn limits(config: &Config) -> Value {
+    let cosmos = config.cosmos.as_ref();
+    let plan_limits = config.mssql.as_ref().and_then(|c| c.plan_limits);
+    serde_json::json!({
+        "sql_default_max_rows": DEFAULT_MAX_ROWS,
+        "cosmos_default_max_items": DEFAULT_MAX_ITEMS,
+        "cosmos_max_request_units": cosmos.and_then(|c| c.max_request_units),
+        "cosmos_session_max_request_units": cosmos.and_then(|c| c.session_max_request_units),
+        "mssql_max_estimated_rows": plan_limits.and_then(|l| l.max_rows),
+        "mssql_max_estimated_cost": plan_limits.and_then(|l| l.max_cost),
+        "max_cell_chars": config.max_cell_chars,
+        "slow_query_ms": config
+            .slow_queries
+            .as_ref()
+            .map(|s| s.threshold.as_millis() as u64),
+        "retry_max_attempts": config.retry.max_attempts,
+        "retry_max_wait_ms": config.retry.max_wait.as_millis() as u64,
+        "max_result_bytes": config.max_result_bytes,
+        "session_max_queries": config.quotas.max_queries,
+        "session_max_rows": config.quotas.max_rows,
+        "session_max_result_bytes": config.quotas.max_bytes,
+        "max_concurrent_queries": Backend::ALL
+            .into_iter()
+            .filter(|backend| backend.configured(config))
+            .filter_map(|backend| {
+                let limit = config.concurrency.limit(backend)?;
+                Some((backend.name().to_string(), Value::from(limit)))
+            })
+            .collect::<Map<_, _>>(),
+        "max_inline_result_bytes": MAX_INLINE_BYTES,
+        "stored_result_ttl_seconds": RESULT_TTL.as_secs(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_strings_lose_their_secrets() {
+        let summary = connection_summary(
+            "Server=tcp:db.database.windows.net,1433;Database=sales;\
+             User Id=app;Password=hunter2;Encrypt=true",
+        );
+        assert_eq!(
+            summary,
+            serde_json::json!({
+                "server": "tcp:db.database.windows.net,1433",
+                "database": "sales",
+                "encrypt": "true",
+            })
+        );
+        assert!(!summary.to_string().contains("hunter2"));
+    }
+}
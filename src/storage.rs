@@ -0,0 +1,522 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::{header::HeaderMap, Method, StatusCode};
+use sha2::Sha256;
+
+use crate::config::StorageConfig;
+use crate::recording;
+
+/// REST API version sent with every request.
+const API_VERSION: &str = "2023-11-03";
+
+/// An Azure Storage service of the account, each with its own endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Service {
+    Blob,
+    Table,
+    /// The Data Lake Storage Gen2 (hierarchical namespace) endpoint.
+    Dfs,
+    /// Azure Files shares.
+    File,
+}
+
+impl Service {
+    fn subdomain(self) -> &'static str {
+        match self {
+            Service::Blob => "blob",
+            Service::Table => "table",
+            Service::Dfs => "dfs",
+            Service::File => "file",
+        }
+    }
+}
+
+/// A raw Storage REST response.
+#[derive(Debug)]
+pub struct StorageResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl StorageResponse {
+    /// Return a response header as a string, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|v| v.to_str().ok())
+    }
+}
+
+/// Minimal signed client for the Azure Storage REST APIs.
+///
+/// Requests are authorised with the account key using the Shared Key scheme,
+/// so no SDK is needed; the same client serves every storage service of the
+/// account.
+pub struct StorageClient {
+    http: reqwest::Client,
+    account: String,
+    key: Vec<u8>,
+    endpoint_suffix: String,
+    table_endpoint: Option<String>,
+    blob_endpoint: Option<String>,
+}
+
+impl StorageClient {
+    /// Build a client from the supplied configuration.
+    pub fn new(cfg: &StorageConfig) -> Result<Self> {
+        let Some(key) = &cfg.key else {
+            bail!("Azure Storage authentication requires STORAGE_KEY to be set.");
+        };
+        let key = BASE64
+            .decode(key.trim())
+            .context("STORAGE_KEY is not a valid base64 account key")?;
+
+        Ok(Self {
+            http: reqwest::Client::new(),
+            account: cfg.account.clone(),
+            key,
+            endpoint_suffix: cfg.endpoint_suffix.clone(),
+            table_endpoint: cfg
+                .table_endpoint
+                .as_deref()
+                .map(|e| e.trim_end_matches('/').to_string()),
+            blob_endpoint: cfg
+                .blob_endpoint
+                .as_deref()
+                .map(|e| e.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// The endpoint of `service`: `https://{account}.{service}.{suffix}`, or
+    /// the configured Table endpoint (e.g. a Cosmos DB Table API account) or
+    /// Blob endpoint (e.g. Azurite).
+    fn endpoint(&self, service: Service) -> String {
+        match (service, &self.table_endpoint, &self.blob_endpoint) {
+            (Service::Table, Some(endpoint), _) | (Service::Blob, _, Some(endpoint)) => {
+                endpoint.clone()
+            }
+            _ => format!(
+                "https://{}.{}.{}",
+                self.account,
+                service.subdomain(),
+                self.endpoint_suffix
+            ),
+        }
+    }
+
+    /// Send a signed request to `service`.
+    ///
+    /// `path` is the URL path below the service endpoint, already
+    /// percent-encoded (see [`encode_path`]); `query` holds the unencoded
+    /// query parameters and `headers` any request headers beyond the date and
+    /// version, which are added here.  Non-success status codes become errors
+    /// carrying the service's message.
+    pub async fn send(
+        &self,
+        service: Service,
+        method: Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<StorageResponse> {
+        let date = chrono::Utc::now()
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        let length = body.len().to_string();
+        let mut all_headers = vec![("x-ms-date", date.as_str()), ("x-ms-version", API_VERSION)];
+        all_headers.extend_from_slice(headers);
+        if !body.is_empty() {
+            all_headers.push(("content-length", &length));
+        }
+
+        // A path-style endpoint such as Azurite's `/devstoreaccount1` is
+        // part of the signed resource.
+        let endpoint = self.endpoint(service);
+        let base = reqwest::Url::parse(&endpoint)
+            .map(|url| url.path().trim_end_matches('/').to_string())
+            .unwrap_or_default();
+        let resource = format!("{base}/{path}");
+        let string_to_sign = match service {
+            Service::Table => self.table_string_to_sign(&method, &resource, query, &all_headers),
+            Service::Blob | Service::Dfs | Service::File => {
+                self.string_to_sign(&method, &resource, query, &all_headers)
+            }
+        };
+        let auth = format!("SharedKey {}:{}", self.account, self.sign(&string_to_sign));
+
+        let url = format!("{endpoint}/{path}");
+        let mut request = self
+            .http
+            .request(method, url)
+            .query(query)
+            .header("authorization", auth)
+            .body(body);
+        for (name, value) in all_headers {
+            request = request.header(name, value);
+        }
+
+        let response = recording::send(request)
+            .await
+            .context("Azure Storage request failed")?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .context("Failed to read Azure Storage response body")?
+            .to_vec();
+
+        if !status.is_success() {
+            let text = String::from_utf8_lossy(&body);
+            bail!("Azure Storage returned {status}: {}", error_message(&text));
+        }
+        Ok(StorageResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Build the Shared Key string-to-sign for the Table service, which signs
+    /// fewer headers and only the `comp` query parameter.
+    fn table_string_to_sign(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+    ) -> String {
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map_or("", |(_, v)| *v)
+        };
+        let comp = query
+            .iter()
+            .find(|(n, _)| *n == "comp")
+            .map(|(_, v)| format!("?comp={v}"))
+            .unwrap_or_default();
+        format!(
+            "{}\n{}\n{}\n{}\n/{}{path}{comp}",
+            method.as_str(),
+            header("content-md5"),
+            header("content-type"),
+            header("x-ms-date"),
+            self.account
+        )
+    }
+
+    /// Build the Shared Key string-to-sign for the Blob, Data Lake, Queue and
+    /// File services.
+    fn string_to_sign(
+        &self,
+        method: &Method,
+        path: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+    ) -> String {
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map_or("", |(_, v)| *v)
+        };
+        let standard = [
+            "content-encoding",
+            "content-language",
+            "content-length",
+            "content-md5",
+            "content-type",
+            "date",
+            "if-modified-since",
+            "if-match",
+            "if-none-match",
+            "if-unmodified-since",
+            "range",
+        ]
+        .map(header)
+        .join("\n");
+
+        let mut canonical_headers: Vec<(String, &str)> = headers
+            .iter()
+            .filter(|(n, _)| n.to_ascii_lowercase().starts_with("x-ms-"))
+            .map(|(n, v)| (n.to_ascii_lowercase(), v.trim()))
+            .collect();
+        canonical_headers.sort();
+        let canonical_headers: String = canonical_headers
+            .iter()
+            .map(|(n, v)| format!("{n}:{v}\n"))
+            .collect();
+
+        let mut canonical_query: Vec<(String, &str)> = query
+            .iter()
+            .map(|(n, v)| (n.to_ascii_lowercase(), *v))
+            .collect();
+        canonical_query.sort();
+        let canonical_query: String = canonical_query
+            .iter()
+            .map(|(n, v)| format!("\n{n}:{v}"))
+            .collect();
+
+        format!(
+            "{}\n{standard}\n{canonical_headers}/{}{path}{canonical_query}",
+            method.as_str(),
+            self.account
+        )
+    }
+
+    /// A URL granting read access to the blob at `container`/`path` over
+    /// HTTPS until `expiry` (`YYYY-MM-DDThh:mm:ssZ`), signed as a service
+    /// SAS with the account key.
+    pub fn blob_read_url(&self, container: &str, path: &str, expiry: &str) -> String {
+        let signature = self.sign(&self.blob_sas_string_to_sign(container, path, expiry));
+        let query = [
+            ("sv", API_VERSION),
+            ("sr", "b"),
+            ("sp", "r"),
+            ("se", expiry),
+            ("spr", "https"),
+            ("sig", &signature),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{name}={}", urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+        format!(
+            "{}/{}?{query}",
+            self.endpoint(Service::Blob),
+            encode_path(&format!("{container}/{path}"))
+        )
+    }
+
+    /// The string a read-only blob service SAS signs: permissions, start,
+    /// expiry, resource, identifier, IP range, protocol, version, resource
+    /// type, snapshot, encryption scope and the five response header
+    /// overrides, one per line.
+    fn blob_sas_string_to_sign(&self, container: &str, path: &str, expiry: &str) -> String {
+        let resource = format!("/blob/{}/{container}/{path}", self.account);
+        [
+            "r",
+            "",
+            expiry,
+            &resource,
+            "",
+            "",
+            "https",
+            API_VERSION,
+            "b",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+        ]
+        .join("\n")
+    }
+
+    /// HMAC-SHA256 `payload` with the account key.
+    fn sign(&self, payload: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        BASE64.encode(mac.finalize().into_bytes())
+    }
+
+    /// Check that the account is reachable and accepts the key by listing at
+    /// most one blob container.
+    pub async fn ping(&self) -> Result<()> {
+        self.send(
+            Service::Blob,
+            Method::GET,
+            "",
+            &[("comp", "list"), ("maxresults", "1")],
+            &[],
+            Vec::new(),
+        )
+        .await
+        .map(|_| ())
+    }
+}
+
+/// Percent-encode each `/`-separated segment of a storage path, keeping the
+/// separators.
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The message of a Storage error body: the XML `<Message>` of the Blob and
+/// File services, the JSON `odata.error` of the Table service or the JSON
+/// `error` of the Data Lake service.
+fn error_message(body: &str) -> String {
+    if let Some(message) = xml_element(body, "Message") {
+        return message.trim().to_string();
+    }
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| {
+            v["odata.error"]["message"]["value"]
+                .as_str()
+                .or(v["error"]["message"].as_str())
+                .map(|m| m.lines().next().unwrap_or_default().to_string())
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// The text of the first `<name>` element of an XML document.  Storage error
+/// bodies and listings are simple enough not to need a full XML parser.
+pub fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{name}>"))? + name.len() + 2;
+    let end = xml[start..].find(&format!("</{name}>"))? + start;
+    Some(&xml[start..end])
+}
+
+/// The text of every `<name>` element of an XML document, in order.
+pub fn xml_elements<'a>(xml: &'a str, name: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{name}>"), format!("</{name}>"));
+    let mut elements = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let body = &rest[start + open.len()..];
+        let Some(end) = body.find(&close) else {
+            break;
+        };
+        elements.push(&body[..end]);
+        rest = &body[end + close.len()..];
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_to_sign_follows_the_shared_key_layout() {
+        let client = StorageClient {
+            http: reqwest::Client::new(),
+            account: "myaccount".into(),
+            key: b"testkey".to_vec(),
+            endpoint_suffix: "core.windows.net".into(),
+            table_endpoint: None,
+            blob_endpoint: None,
+        };
+        let payload = client.string_to_sign(
+            &Method::GET,
+            "/logs/2026/app%20log.txt",
+            &[("timeout", "30")],
+            &[
+                ("x-ms-version", API_VERSION),
+                ("x-ms-date", "Thu, 01 Jan 2026 00:00:00 GMT"),
+                ("x-ms-range", "bytes=0-99"),
+            ],
+        );
+        assert_eq!(
+            payload,
+            "GET\n\n\n\n\n\n\n\n\n\n\n\n\
+             x-ms-date:Thu, 01 Jan 2026 00:00:00 GMT\n\
+             x-ms-range:bytes=0-99\n\
+             x-ms-version:2023-11-03\n\
+             /myaccount/logs/2026/app%20log.txt\n\
+             timeout:30"
+        );
+    }
+
+    #[test]
+    fn blob_sas_signs_a_read_only_https_grant() {
+        let client = StorageClient {
+            http: reqwest::Client::new(),
+            account: "myaccount".into(),
+            key: b"testkey".to_vec(),
+            endpoint_suffix: "core.windows.net".into(),
+            table_endpoint: None,
+            blob_endpoint: None,
+        };
+        assert_eq!(
+            client.blob_sas_string_to_sign("exports", "2026/orders.csv", "2026-01-01T01:00:00Z"),
+            "r\n\n2026-01-01T01:00:00Z\n/blob/myaccount/exports/2026/orders.csv\n\n\nhttps\n\
+             2023-11-03\nb\n\n\n\n\n\n\n"
+        );
+        let url = client.blob_read_url("exports", "2026/q1 orders.csv", "2026-01-01T01:00:00Z");
+        assert!(url.starts_with(
+            "https://myaccount.blob.core.windows.net/exports/2026/q1%20orders.csv?sv=2023-11-03\
+             &sr=b&sp=r&se=2026-01-01T01%3A00%3A00Z&spr=https&sig="
+        ));
+    }
+
+    #[test]
+    fn table_string_to_sign_only_signs_comp() {
+        let client = StorageClient {
+            http: reqwest::Client::new(),
+            account: "myaccount".into(),
+            key: b"testkey".to_vec(),
+            endpoint_suffix: "core.windows.net".into(),
+            table_endpoint: Some("https://myaccount.table.cosmos.azure.com".into()),
+            blob_endpoint: Some("http://localhost:10000/devstoreaccount1".into()),
+        };
+        let payload = client.table_string_to_sign(
+            &Method::GET,
+            "/orders()",
+            &[("$top", "10")],
+            &[("x-ms-date", "Thu, 01 Jan 2026 00:00:00 GMT")],
+        );
+        assert_eq!(
+            payload,
+            "GET\n\n\nThu, 01 Jan 2026 00:00:00 GMT\n/myaccount/orders()"
+        );
+        assert_eq!(
+            client.endpoint(Service::Table),
+            "https://myaccount.table.cosmos.azure.com"
+        );
+        assert_eq!(
+            client.endpoint(Service::Blob),
+            "http://localhost:10000/devstoreaccount1"
+        );
+        assert_eq!(
+            client.endpoint(Service::File),
+            "https://myaccount.file.core.windows.net"
+        );
+        assert_eq!(
+            error_message(
+                r#"{"odata.error":{"code":"TableNotFound","message":{"value":"No table"}}}"#
+            ),
+            "No table"
+        );
+        assert_eq!(
+            error_message(
+                r#"{"error":{"code":"PathNotFound","message":"The specified path does not exist.\nRequestId:1"}}"#
+            ),
+            "The specified path does not exist."
+        );
+    }
+
+    #[test]
+    fn encode_path_and_xml_element() {
+        assert_eq!(
+            encode_path("logs/2026/app log.txt"),
+            "logs/2026/app%20log.txt"
+        );
+        assert_eq!(
+            xml_element(
+                "<Error><Code>BlobNotFound</Code><Message>Gone</Message></Error>",
+                "Message"
+            ),
+            Some("Gone")
+        );
+        assert_eq!(xml_element("<Error/>", "Message"), None);
+        assert_eq!(
+            xml_elements(
+                "<Entries><File><Name>a.csv</Name></File><FileId>1</FileId>\
+                 <File><Name>b.csv</Name></File></Entries>",
+                "File"
+            ),
+            vec!["<Name>a.csv</Name>", "<Name>b.csv</Name>"]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::resources::ResourceUri;
+
+/// How often subscribed resources are re-read to detect changes.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Resource subscriptions of the connected client, with the fingerprint of
+/// what each resource looked like when last read.
+#[derive(Default)]
+pub struct Subscriptions {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Fingerprint of each subscribed resource; `None` when it could not be
+    /// read (e.g. the table was dropped).
+    resources: HashMap<String, Option<u64>>,
+    /// Fingerprint of the set of published resources.
+    listing: Option<u64>,
+    /// Whether a task is currently polling the subscriptions.
+    watching: bool,
+}
+
+/// Fingerprint the schema-relevant part of a resource body.
+///
+/// A container's sampled document shape counts how often each JSON type was
+/// seen, which changes with every write; only the types per property path are
+/// kept, so that notifications mean the shape itself changed.
+fn fingerprint(body: &Value) -> u64 {
+    let mut body = body.clone();
+    if let Value::Object(fields) = &mut body {
+        fields.remove("sampled_documents");
+        if let Some(Value::Object(shape)) = fields.get_mut("document_shape") {
+            for types in shape.values_mut() {
+                if let Value::Object(counts) = types {
+                    *types = counts.keys().cloned().collect();
+                }
+            }
+        }
+    }
+    let mut hasher = DefaultHasher::new();
+    body.to_string().hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Subscriptions {
+    /// Whether `uri` can be subscribed to.  Only schema resources (table
+    /// columns and container metadata) are watched; row and document
+    /// resources are not.
+    pub fn supports(uri: &ResourceUri) -> bool {
+        matches!(
+            uri,
+            ResourceUri::MssqlSchema { .. } | ResourceUri::CosmosContainer { .. }
+        )
+    }
+
+    /// Subscribe to `uri`, whose current body is `body`.
+    ///
+    /// Returns `true` when no task is polling yet and the caller must start
+    /// one.
+    pub fn subscribe(&self, uri: String, body: Option<&Value>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.resources.insert(uri, body.map(fingerprint));
+        !std::mem::replace(&mut state.watching, true)
+    }
+
+    /// Stop watching `uri`.
+    pub fn unsubscribe(&self, uri: &str) {
+        self.state.lock().unwrap().resources.remove(uri);
+    }
+
+    /// The subscribed URIs.  When there are none, the polling task is
+    /// considered stopped and should exit.
+    pub fn watched(&self) -> Vec<String> {
+        let mut state = self.state.lock().unwrap();
+        if state.resources.is_empty() {
+            state.watching = false;
+            state.listing = None;
+        }
+        state.resources.keys().cloned().collect()
+    }
+
+    /// Record the latest body of `uri` and report whether it changed since it
+    /// was last read.  Unsubscribed URIs never report a change.
+    pub fn update(&self, uri: &str, body: Option<&Value>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.resources.get_mut(uri) {
+            Some(previous) => {
+                let current = body.map(fingerprint);
+                std::mem::replace(previous, current) != current
+            }
+            None => false,
+        }
+    }
+
+    /// Record the URIs currently published and report whether resources were
+    /// added or removed since the previous call.  The first call only records
+    /// a baseline.
+    pub fn update_listing(&self, uris: &[String]) -> bool {
+        let mut sorted = uris.to_vec();
+        sorted.sort();
+        let mut hasher = DefaultHasher::new();
+        sorted.hash(&mut hasher);
+        let current = hasher.finish();
+
+        let mut state = self.state.lock().unwrap();
+        state
+            .listing
+            .replace(current)
+            .is_some_and(|previous| previous != current)
+    }
+
+    /// Drop every subscription, e.g. when the client has gone away.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = State::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_are_reported_once() {
+        let subscriptions = Subscriptions::default();
+        let uri = "mssql://schema/dbo/orders".to_string();
+        let before = serde_json::json!({ "columns": [{ "name": "id" }] });
+        let after = serde_json::json!({ "columns": [{ "name": "id" }, { "name": "total" }] });
+
+        assert!(subscriptions.subscribe(uri.clone(), Some(&before)));
+        assert!(!subscriptions.subscribe(uri.clone(), Some(&before)));
+        assert!(!subscriptions.update(&uri, Some(&before)));
+        assert!(subscriptions.update(&uri, Some(&after)));
+        assert!(!subscriptions.update(&uri, Some(&after)));
+        assert!(subscriptions.update(&uri, None));
+
+        subscriptions.unsubscribe(&uri);
+        assert!(!subscriptions.update(&uri, Some(&before)));
+        assert!(subscriptions.watched().is_empty());
+        assert!(subscriptions.subscribe(uri, None));
+    }
+
+    #[test]
+    fn listing_changes_after_baseline() {
+        let subscriptions = Subscriptions::default();
+        let a = "mssql://schema/dbo/a".to_string();
+        let b = "mssql://schema/dbo/b".to_string();
+        assert!(!subscriptions.update_listing(&[a.clone(), b.clone()]));
+        assert!(!subscriptions.update_listing(&[b.clone(), a.clone()]));
+        assert!(subscriptions.update_listing(&[a]));
+    }
+
+    #[test]
+    fn sample_counts_do_not_change_the_fingerprint() {
+        let shape = |count: u64| {
+            serde_json::json!({
+                "partition_key": { "paths": ["/tenant"] },
+                "sampled_documents": count,
+                "document_shape": { "/id": { "string": count } },
+            })
+        };
+        assert_eq!(fingerprint(&shape(3)), fingerprint(&shape(20)));
+
+        let mut changed = shape(3);
+        changed["document_shape"]["/id"] = serde_json::json!({ "number": 3 });
+        assert_ne!(fingerprint(&shape(3)), fingerprint(&changed));
+    }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context, Result};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::storage::{Service, StorageClient};
+
+/// Entities returned per `tables_query` page when the caller gives no `top`.
+pub const DEFAULT_TOP: u32 = 100;
+
+/// Upper bound on `top`; the Table service never returns more per request.
+pub const MAX_TOP: u32 = 1_000;
+
+/// Headers asking the Table service for plain JSON entities.
+const JSON_HEADERS: &[(&str, &str)] = &[
+    ("accept", "application/json;odata=nometadata"),
+    ("dataserviceversion", "3.0;NetFx"),
+    ("maxdataserviceversion", "3.0;NetFx"),
+];
+
+/// Where the next page of a table query starts, from the service's
+/// continuation headers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TableResume {
+    pub partition_key: String,
+    pub row_key: Option<String>,
+}
+
+/// List the names of every table in the account, following continuation
+/// tokens.
+pub async fn list_tables(client: &StorageClient) -> Result<Vec<String>> {
+    let mut tables = Vec::new();
+    let mut next: Option<String> = None;
+    loop {
+        let mut query = Vec::new();
+        if let Some(name) = &next {
+            query.push(("NextTableName", name.as_str()));
+        }
+        let response = client
+            .send(
+                Service::Table,
+                Method::GET,
+                "Tables",
+                &query,
+                JSON_HEADERS,
+                Vec::new(),
+            )
+            .await
+            .context("Failed to list tables")?;
+        let body: Value = serde_json::from_slice(&response.body)
+            .context("Table service returned invalid JSON")?;
+        tables.extend(
+            body["value"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|t| t["TableName"].as_str().map(str::to_string)),
+        );
+
+        next = response
+            .header("x-ms-continuation-nexttablename")
+            .map(str::to_string);
+        if next.is_none() {
+            break;
+        }
+    }
+    Ok(tables)
+}
+
+/// Query up to `top` entities of `table`, optionally filtered with an OData
+/// `filter` (e.g. `PartitionKey eq 'eu' and Amount gt 100`) and reduced to
+/// the `select`ed properties, starting from `resume` when given.
+///
+/// Returns the entities and, when more match, where the next page starts.
+pub async fn query_entities(
+    client: &StorageClient,
+    table: &str,
+    filter: Option<&str>,
+    select: &[String],
+    top: u32,
+    resume: Option<&TableResume>,
+) -> Result<(Vec<Value>, Option<TableResume>)> {
+    let top = top.clamp(1, MAX_TOP).to_string();
+    let select = select.join(",");
+    let mut query = vec![("$top", top.as_str())];
+    if let Some(filter) = filter {
+        query.push(("$filter", filter));
+    }
+    if !select.is_empty() {
+        query.push(("$select", &select));
+    }
+    if let Some(resume) = resume {
+        query.push(("NextPartitionKey", &resume.partition_key));
+        if let Some(row_key) = &resume.row_key {
+            query.push(("NextRowKey", row_key));
+        }
+    }
+
+    let response = client
+        .send(
+            Service::Table,
+            Method::GET,
+            &format!("{}()", urlencoding::encode(table)),
+            &query,
+            JSON_HEADERS,
+            Vec::new(),
+        )
+        .await
+        .with_context(|| format!("Failed to query table {table}"))?;
+    let mut body: Value =
+        serde_json::from_slice(&response.body).context("Table service returned invalid JSON")?;
+    let entities = match body["value"].take() {
+        Value::Array(entities) => entities,
+        _ => Vec::new(),
+    };
+
+    let resume = response
+        .header("x-ms-continuation-nextpartitionkey")
+        .map(|partition_key| TableResume {
+            partition_key: partition_key.to_string(),
+            row_key: response
+                .header("x-ms-continuation-nextrowkey")
+                .map(str::to_string),
+        });
+    Ok((entities, resume))
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde_json::{Map, Value};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{pki_types::ServerName, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+use crate::aad::TokenProvider;
+use crate::config::{Config, MssqlConfig};
+use crate::health::Backend;
+use crate::mssql;
+use crate::redact::redact;
+
+/// How long checking one connection may take.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The transport settings of an ADO.NET connection string, with tiberius'
+/// defaults for missing keys.
+#[derive(Debug, PartialEq)]
+struct SqlSettings {
+    /// `Encrypt=true`: the whole session is encrypted.  Otherwise only the
+    /// login is, unless the server requires encryption.
+    encrypt: bool,
+    /// `TrustServerCertificate=true`: the certificate is not validated.
+    trust_server_certificate: bool,
+}
+
+impl SqlSettings {
+    fn parse(connection_string: &str) -> Self {
+        let flag = |name: &str| {
+            connection_string.split(';').any(|pair| {
+                pair.split_once('=').is_some_and(|(key, value)| {
+                    key.trim().eq_ignore_ascii_case(name)
+                        && matches!(value.trim().to_ascii_lowercase().as_str(), "true" | "yes")
+                })
+            })
+        };
+        SqlSettings {
+            encrypt: flag("encrypt"),
+            trust_server_certificate: flag("trustservercertificate"),
+        }
+    }
+
+    /// What is wrong with the settings, before connecting.
+    fn issues(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !self.encrypt {
+            issues.push(
+                "Encrypt is not true: only the login is encrypted unless the server requires \
+                 encryption"
+                    .to_string(),
+            );
+        }
+        if self.trust_server_certificate {
+            issues.push(
+                "TrustServerCertificate is true: the server certificate is not validated, so \
+                 a man in the middle can read the traffic"
+                    .to_string(),
+            );
+        }
+        issues
+    }
+}
+
+/// The endpoint of a SQL connection string, without credentials.
+fn sql_target(connection_string: &str) -> String {
+    connection_string
+        .split(';')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| {
+            matches!(
+                key.trim().to_ascii_lowercase().as_str(),
+                "server" | "data source" | "address"
+            )
+        })
+        .map(|(_, value)| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// One connection's report: `{backend, target, encrypted,
+/// certificate_validated, negotiated, issues, ok}`.
+fn connection(
+    backend: &str,
+    target: &str,
+    encrypted: bool,
+    certificate_validated: bool,
+    negotiated: Map<String, Value>,
+    issues: Vec<String>,
+) -> Value {
+    serde_json::json!({
+        "backend": backend,
+        "target": target,
+        "encrypted": encrypted,
+        "certificate_validated": certificate_validated,
+        "negotiated": negotiated,
+        "ok": issues.is_empty(),
+        "issues": issues,
+    })
+}
+
+/// Check a SQL endpoint: its connection string settings, then what the
+/// server reports for a new connection.
+async fn check_sql(backend: &str, cfg: &MssqlConfig) -> Value {
+    let settings = SqlSettings::parse(&cfg.connection_string);
+    let mut issues = settings.issues();
+    let negotiated = match mssql::connection_properties(cfg).await {
+        Ok(properties) => properties,
+        Err(e) => {
+            issues.push(format!("Could not verify the connection: {e:#}"));
+            Map::new()
+        }
+    };
+    let encrypted = match negotiated.get("encrypt_option").and_then(Value::as_str) {
+        Some(option) => option.eq_ignore_ascii_case("TRUE"),
+        None => settings.encrypt,
+    };
+    if encrypted {
+        // The server enforces encryption even though the client does not.
+        issues.retain(|issue| !issue.starts_with("Encrypt is not true"));
+    } else if !negotiated.is_empty() {
+        issues.push("The server reports the session as unencrypted".to_string());
+    }
+    let target = sql_target(&cfg.connection_string);
+    connection(
+        backend,
+        &target,
+        encrypted,
+        !settings.trust_server_certificate,
+        negotiated,
+        issues,
+    )
+}
+
+/// Check an HTTPS endpoint: its scheme, then a TLS handshake validating the
+/// certificate against the same roots the HTTP client trusts.
+async fn check_https(backend: &str, endpoint: &str) -> Value {
+    let mut negotiated = Map::new();
+    let mut issues = Vec::new();
+    let url = match reqwest::Url::parse(endpoint) {
+        Ok(url) => url,
+        Err(e) => {
+            issues.push(format!("Invalid endpoint: {e}"));
+            return connection(backend, endpoint, false, false, negotiated, issues);
+        }
+    };
+    if url.scheme() != "https" {
+        issues.push(format!(
+            "The endpoint uses {}, not https: requests and keys travel in clear text",
+            url.scheme()
+        ));
+        return connection(backend, endpoint, false, false, negotiated, issues);
+    }
+    let validated = match handshake(&url).await {
+        Ok((version, cipher_suite)) => {
+            negotiated.insert("tls_version".into(), version.into());
+            negotiated.insert("cipher_suite".into(), cipher_suite.into());
+            true
+        }
+        Err(e) => {
+            issues.push(format!("TLS handshake failed: {e:#}"));
+            false
+        }
+    };
+    connection(backend, endpoint, true, validated, negotiated, issues)
+}
+
+/// Open a TLS connection to `url`'s host, validating its certificate, and
+/// return the negotiated protocol version and cipher suite.
+async fn handshake(url: &reqwest::Url) -> Result<(String, String)> {
+    let host = url.host_str().context("The endpoint has no host")?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let tcp = TcpStream::connect((host, port))
+        .await
+        .with_context(|| format!("Failed to connect to {host}:{port}"))?;
+    let roots = RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let tls = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let stream = TlsConnector::from(Arc::new(tls))
+        .connect(ServerName::try_from(host.to_string())?, tcp)
+        .await?;
+    let (_, session) = stream.get_ref();
+    let version = session
+        .protocol_version()
+        .map_or("unknown".into(), |v| format!("{v:?}"));
+    let cipher_suite = session
+        .negotiated_cipher_suite()
+        .map_or("unknown".into(), |s| format!("{:?}", s.suite()));
+    Ok((version, cipher_suite))
+}
+
+/// `{ok, connections}`: how every configured SQL endpoint and Cosmos DB
+/// endpoint protects data in transit, checked concurrently; `ok` when none
+/// has an issue.
+pub async fn check(config: &Config) -> Value {
+    let mut checks: Vec<(&str, String, BoxFuture<'_, Value>)> = Vec::new();
+    let sql = [
+        (Backend::Mssql, config.mssql.as_ref()),
+        (Backend::Synapse, config.synapse.as_ref()),
+        (
+            Backend::Analytical,
+            config.cosmos.as_ref().and_then(|c| c.analytical.as_ref()),
+        ),
+    ];
+    for (backend, cfg) in sql {
+        if let Some(cfg) = cfg {
+            let target = sql_target(&cfg.connection_string);
+            checks.push((
+                backend.name(),
+                target,
+                Box::pin(check_sql(backend.name(), cfg)),
+            ));
+        }
+    }
+    if let Some(fabric) = &config.fabric {
+        let name = Backend::Fabric.name();
+        let check = Box::pin(async move {
+            let token = async {
+                TokenProvider::new(config.require_credential()?)
+                    .token(mssql::SQL_RESOURCE)
+                    .await
+            };
+            match token.await {
+                Ok(token) => check_sql(name, &fabric.connection(token)).await,
+                Err(e) => connection(
+                    name,
+                    &fabric.endpoint,
+                    false,
+                    false,
+                    Map::new(),
+                    vec![format!("Could not verify the connection: {e:#}")],
+                ),
+            }
+        });
+        checks.push((name, fabric.endpoint.clone(), check));
+    }
+    if let Some(cosmos) = &config.cosmos {
+        let endpoints = [
+            (Backend::Cosmos.name(), Some(&cosmos.endpoint)),
+            (
+                "cosmos_gateway",
+                cosmos.integrated_cache.as_ref().map(|c| &c.endpoint),
+            ),
+        ];
+        for (name, endpoint) in endpoints {
+            if let Some(endpoint) = endpoint {
+                checks.push((
+                    name,
+                    endpoint.clone(),
+                    Box::pin(check_https(name, endpoint)),
+                ));
+            }
+        }
+    }
+    let checks = checks.into_iter().map(|(name, target, check)| async move {
+        match tokio::time::timeout(CHECK_TIMEOUT, check).await {
+            Ok(report) => report,
+            Err(_) => connection(
+                name,
+                &target,
+                false,
+                false,
+                Map::new(),
+                vec![format!("No answer within {} s", CHECK_TIMEOUT.as_secs())],
+            ),
+        }
+    });
+    let mut connections = futures::future::join_all(checks).await;
+    for connection in &mut connections {
+        if let Some(Value::Array(issues)) = connection.get_mut("issues") {
+            for issue in issues.iter_mut() {
+                if let Some(text) = issue.as_str() {
+                    *issue = redact(text).into_owned().into();
+                }
+            }
+        }
+    }
+    serde_json::json!({
+        "ok": connections.iter().all(|c| c["ok"] == true),
+        "connections": connections,
+    })
+}
+
+/// Warn at startup about SQL connection strings and Cosmos DB endpoints
+/// that leave data in transit unprotected, without connecting.
+pub fn warn_insecure(config: &Config) {
+    let sql = [
+        (Backend::Mssql, config.mssql.as_ref()),
+        (Backend::Synapse, config.synapse.as_ref()),
+        (
+            Backend::Analytical,
+            config.cosmos.as_ref().and_then(|c| c.analytical.as_ref()),
+        ),
+    ];
+    for (backend, cfg) in sql {
+        let Some(cfg) = cfg else {
+            continue;
+        };
+        for issue in SqlSettings::parse(&cfg.connection_string).issues() {
+            tracing::warn!("{}: {issue}", backend.name());
+        }
+    }
+    if let Some(cosmos) = &config.cosmos {
+        if !cosmos.endpoint.starts_with("https://") {
+            tracing::warn!("COSMOS_ENDPOINT does not use https: requests travel in clear text");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_settings_follow_the_connection_string() {
+        let secure = SqlSettings::parse(
+            "server=tcp:db.database.windows.net,1433;Encrypt=True;TrustServerCertificate=false",
+        );
+        assert_eq!(
+            secure,
+            SqlSettings {
+                encrypt: true,
+                trust_server_certificate: false,
+            }
+        );
+        assert!(secure.issues().is_empty());
+
+        let insecure =
+            SqlSettings::parse("server=tcp:localhost,1433;TrustServerCertificate=yes;pwd=x");
+        assert!(!insecure.encrypt);
+        assert_eq!(insecure.issues().len(), 2);
+        assert_eq!(
+            sql_target("Data Source=tcp:localhost,1433;pwd=x"),
+            "tcp:localhost,1433"
+        );
+    }
+
+    #[tokio::test]
+    async fn plain_http_endpoints_fail() {
+        let report = check_https("cosmos", "http://localhost:8081/").await;
+        assert_eq!(report["ok"], false);
+        assert_eq!(report["encrypted"], false);
+        assert!(report["issues"][0].as_str().unwrap().contains("not https"));
+    }
+}